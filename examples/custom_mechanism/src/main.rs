@@ -47,7 +47,7 @@ impl Authentication for CustomMechanism {
 
 const MECHNAME: &'static Mechname = &Mechname::const_new_unchecked(b"X-CUSTOMMECH");
 
-use rsasl::registry::{Mechanism, MECHANISMS};
+use rsasl::registry::{Mechanism, MechanismSecurityFactors, PropertyRequirements, MECHANISMS};
 
 #[linkme::distributed_slice(MECHANISMS)]
 pub static CUSTOMMECH: Mechanism = Mechanism {
@@ -59,6 +59,14 @@ pub static CUSTOMMECH: Mechanism = Mechanism {
     client: Some(CustomMechanism::new_client),
     server: Some(CustomMechanism::new_server),
     first: Side::Client,
+    // The mechanism doesn't need any data from the application
+    client_properties: PropertyRequirements::NONE,
+    server_properties: PropertyRequirements::NONE,
+    description: "Custom example mechanism",
+    specification: None,
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors::NONE,
 };
 
 pub fn main() {
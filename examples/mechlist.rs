@@ -1,7 +1,7 @@
 use rsasl::mechanism::Authentication;
 
 use rsasl::mechname::Mechname;
-use rsasl::registry::Mechanism;
+use rsasl::registry::{Mechanism, MechanismSecurityFactors, PropertyRequirements};
 use rsasl::session::{SessionData, Side, StepResult};
 use rsasl::SASL;
 
@@ -25,6 +25,13 @@ const TEST: Mechanism = Mechanism {
     client: Some(|_sasl| Ok(Box::new(Test))),
     server: None,
     first: Side::Client,
+    client_properties: PropertyRequirements::NONE,
+    server_properties: PropertyRequirements::NONE,
+    description: "Test mechanism",
+    specification: None,
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors::NONE,
 };
 
 pub fn main() {
@@ -42,12 +49,12 @@ pub fn main() {
 
     println!("List of enabled CLIENT mechanisms:");
     for m in client_mechlist {
-        println!(" - {}", m);
+        println!(" - {}: {}", m, m.description);
     }
 
     println!("\n\nList of enabled SERVER mechanisms:");
     for m in server_mechlist {
-        println!(" - {}", m);
+        println!(" - {}: {}", m, m.description);
     }
 
     println!("\n\nLet's check if we support specific mechanisms:");
@@ -1,7 +1,8 @@
 use rsasl::mechanisms::scram::client::ScramClient;
 use rsasl::mechname::Mechname;
+use rsasl::property::properties::{AUTHID, AUTHZID, PASSWORD};
 use rsasl::property::{AuthId, Password};
-use rsasl::registry::Mechanism;
+use rsasl::registry::{Mechanism, MechanismSecurityFactors, PropertyRequirements};
 use rsasl::session::Side;
 use rsasl::session::Step::{Done, NeedsMore};
 use rsasl::SASL;
@@ -18,6 +19,13 @@ pub fn main() {
         client: Some(|_sasl| Ok(Box::new(ScramClient::<18>::new()))),
         server: None,
         first: Side::Client,
+        client_properties: PropertyRequirements::new(&[AUTHID, PASSWORD], &[AUTHZID]),
+        server_properties: PropertyRequirements::NONE,
+        description: "SCRAM-SHA-256 client using a non-standard name",
+        specification: Some("RFC 7677"),
+        channel_binding: false,
+        security_layer: false,
+        security: MechanismSecurityFactors::NONE,
     };
     sasl.register(&M);
 
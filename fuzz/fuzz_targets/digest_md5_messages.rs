@@ -0,0 +1,33 @@
+#![no_main]
+//! Structure-aware variant of `digest_md5_parser` generating lists of plausible directives.
+
+use libfuzzer_sys::fuzz_target;
+
+use rsasl::mechanisms::digest_md5::free::{
+    digest_md5_free_challenge, digest_md5_free_finish, digest_md5_free_response,
+};
+use rsasl::mechanisms::digest_md5::parser::{
+    digest_md5_challenge, digest_md5_finish, digest_md5_parse_challenge,
+    digest_md5_parse_finish, digest_md5_parse_response, digest_md5_response,
+};
+use rsasl_fuzz::DigestMessage;
+
+fuzz_target!(|message: DigestMessage| {
+    let mut buf = message.to_bytes();
+    let len = buf.len();
+    buf.push(0);
+    let ptr = buf.as_ptr().cast();
+    unsafe {
+        let mut c: digest_md5_challenge = std::mem::zeroed();
+        digest_md5_parse_challenge(ptr, len, &mut c);
+        digest_md5_free_challenge(&mut c);
+
+        let mut r: digest_md5_response = std::mem::zeroed();
+        digest_md5_parse_response(ptr, len, &mut r);
+        digest_md5_free_response(&mut r);
+
+        let mut f: digest_md5_finish = std::mem::zeroed();
+        digest_md5_parse_finish(ptr, len, &mut f);
+        digest_md5_free_finish(&mut f);
+    }
+});
@@ -0,0 +1,32 @@
+#![no_main]
+//! Feeds arbitrary bytes into the DIGEST-MD5 challenge, response and finish parsers.
+
+use libfuzzer_sys::fuzz_target;
+
+use rsasl::mechanisms::digest_md5::free::{
+    digest_md5_free_challenge, digest_md5_free_finish, digest_md5_free_response,
+};
+use rsasl::mechanisms::digest_md5::parser::{
+    digest_md5_challenge, digest_md5_finish, digest_md5_parse_challenge,
+    digest_md5_parse_finish, digest_md5_parse_response, digest_md5_response,
+};
+
+fuzz_target!(|data: &[u8]| {
+    // The parsers copy their input with `strndup` so it must be NUL-terminated
+    let mut buf = data.to_vec();
+    buf.push(0);
+    let ptr = buf.as_ptr().cast();
+    unsafe {
+        let mut c: digest_md5_challenge = std::mem::zeroed();
+        digest_md5_parse_challenge(ptr, data.len(), &mut c);
+        digest_md5_free_challenge(&mut c);
+
+        let mut r: digest_md5_response = std::mem::zeroed();
+        digest_md5_parse_response(ptr, data.len(), &mut r);
+        digest_md5_free_response(&mut r);
+
+        let mut f: digest_md5_finish = std::mem::zeroed();
+        digest_md5_parse_finish(ptr, data.len(), &mut f);
+        digest_md5_free_finish(&mut f);
+    }
+});
@@ -0,0 +1,54 @@
+#![no_main]
+//! Feeds arbitrary bytes into every SCRAM message parser, both the Rust ones and the legacy
+//! C-style ones still used by the SCRAM mechanisms.
+
+use libfuzzer_sys::fuzz_target;
+
+use rsasl::mechanisms::gs2_header::{parse_header, CbFlag, SaslName};
+use rsasl::mechanisms::scram::client::{scram_client_final, scram_client_first};
+use rsasl::mechanisms::scram::parser::{
+    scram_parse_client_final, scram_parse_client_first, scram_parse_server_final,
+    scram_parse_server_first, ClientFinal, ClientFirstMessage, ServerFinal, ServerFirst,
+};
+use rsasl::mechanisms::scram::server::{scram_server_final, scram_server_first};
+use rsasl::mechanisms::scram::tokens::{
+    scram_free_client_final, scram_free_client_first, scram_free_server_final,
+    scram_free_server_first,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = CbFlag::parse(data);
+    let _ = parse_header(data);
+    let _ = ClientFirstMessage::parse(data);
+    let _ = ServerFirst::parse(data);
+    let _ = ClientFinal::parse(data);
+    let _ = ServerFinal::parse(data);
+
+    if let Ok(s) = std::str::from_utf8(data) {
+        if let Ok(name) = SaslName::new(s) {
+            let _ = name.unescape();
+        }
+    }
+
+    // The C parsers expect a NUL-terminated buffer even though they're given a length
+    let mut buf = data.to_vec();
+    buf.push(0);
+    let ptr = buf.as_ptr().cast();
+    unsafe {
+        let mut cf: scram_client_first = std::mem::zeroed();
+        scram_parse_client_first(ptr, data.len(), &mut cf);
+        scram_free_client_first(&mut cf);
+
+        let mut sf: scram_server_first = std::mem::zeroed();
+        scram_parse_server_first(ptr, data.len(), &mut sf);
+        scram_free_server_first(&mut sf);
+
+        let mut cl: scram_client_final = std::mem::zeroed();
+        scram_parse_client_final(ptr, data.len(), &mut cl);
+        scram_free_client_final(&mut cl);
+
+        let mut sl: scram_server_final = std::mem::zeroed();
+        scram_parse_server_final(ptr, data.len(), &mut sl);
+        scram_free_server_final(&mut sl);
+    }
+});
@@ -0,0 +1,43 @@
+#![no_main]
+//! Runs every server mechanism registered with `SASL` on attacker-controlled input.
+//!
+//! The exchange picks a mechanism, optionally channel binding data, and a sequence of inputs
+//! that are either raw bytes or generated to look like messages of one of the built-in
+//! mechanisms. Any panic or abort is a bug, errors returned from `step` are expected.
+
+use libfuzzer_sys::fuzz_target;
+use std::sync::Arc;
+
+use rsasl::session::Step;
+use rsasl::SASL;
+use rsasl_fuzz::{FuzzCallback, ServerExchange};
+
+fuzz_target!(|exchange: ServerExchange| {
+    let mut sasl = SASL::new();
+    sasl.init();
+    sasl.install_callback(Arc::new(FuzzCallback {
+        accept: exchange.accept,
+    }));
+
+    let mechanisms: Vec<_> = sasl.server_mech_list().into_iter().collect();
+    if mechanisms.is_empty() {
+        return;
+    }
+    let mechanism = mechanisms[exchange.mechanism as usize % mechanisms.len()];
+
+    let mut session = match sasl.server_start(mechanism.mechanism) {
+        Ok(session) => session,
+        Err(_) => return,
+    };
+    if let Some(cbdata) = exchange.channel_binding {
+        session.set_channel_binding_data("tls-unique", cbdata.into_boxed_slice());
+    }
+
+    for input in exchange.inputs.iter() {
+        let mut out = Vec::new();
+        match session.step(input.to_bytes(), &mut out) {
+            Ok(Step::NeedsMore(_)) => continue,
+            Ok(Step::Done(_)) | Err(_) => break,
+        }
+    }
+});
@@ -111,6 +111,11 @@ pub enum SessionError {
     NoProperty {
         property: Property,
     },
+
+    #[cfg(feature = "session_state")]
+    State {
+        source: crate::state::StateError,
+    },
 }
 impl SessionError {
     pub fn no_property<P: PropertyQ>() -> Self {
@@ -158,6 +163,9 @@ impl Display for SessionError {
             }
             Self::NoProperty { property } => write!(f, "required property {} is not set", property),
             SessionError::AuthenticationFailure => f.write_str("authentication failed"),
+
+            #[cfg(feature = "session_state")]
+            Self::State { source } => write!(f, "failed to export or resume session: {}", source),
         }
     }
 }
@@ -191,6 +199,13 @@ impl From<base64::DecodeError> for SessionError {
     }
 }
 
+#[cfg(feature = "session_state")]
+impl From<crate::state::StateError> for SessionError {
+    fn from(source: crate::state::StateError) -> Self {
+        Self::State { source }
+    }
+}
+
 impl<T: MechanismError + 'static> From<T> for SessionError {
     fn from(e: T) -> Self {
         Self::MechanismError(Box::new(e))
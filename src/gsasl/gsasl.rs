@@ -4,6 +4,8 @@ use crate::gsasl::consts::{GSASL_NEEDS_MORE, GSASL_OK, GSASL_UNKNOWN_MECHANISM};
 use crate::mechanism::Authentication;
 use crate::session::Step::{Done, NeedsMore};
 use crate::session::{SessionData, StepResult};
+#[cfg(feature = "session_state")]
+use crate::state::{StateError, StateWriter};
 use crate::{SASLError, Shared};
 use libc::{c_char, size_t};
 use std::fmt::{Debug, Formatter};
@@ -73,10 +75,16 @@ impl Debug for MechanismVTable {
 pub(crate) struct CMechanismStateKeeper {
     mech_data: Option<NonNull<()>>,
     vtable: MechanismVTable,
+    #[cfg(feature = "session_state")]
+    export: Gsasl_export_function,
 }
 
 impl CMechanismStateKeeper {
     pub fn build(vtable: MechanismVTable) -> Result<Box<dyn Authentication>, SASLError> {
+        Ok(Box::new(Self::start(vtable)?))
+    }
+
+    fn start(vtable: MechanismVTable) -> Result<Self, SASLError> {
         if vtable.init.is_some() {
             panic!("Initialization of C Mechanism at a global level is not implemented")
         }
@@ -90,7 +98,40 @@ impl CMechanismStateKeeper {
             }
         }
 
-        Ok(Box::new(CMechanismStateKeeper { mech_data, vtable }))
+        Ok(CMechanismStateKeeper {
+            mech_data,
+            vtable,
+            #[cfg(feature = "session_state")]
+            export: None,
+        })
+    }
+}
+
+#[cfg(feature = "session_state")]
+impl CMechanismStateKeeper {
+    /// Build a mechanism whose state can be exported with the given function
+    pub fn build_exportable(
+        vtable: MechanismVTable,
+        export: Gsasl_export_function,
+    ) -> Result<Box<dyn Authentication>, SASLError> {
+        let mut keeper = Self::start(vtable)?;
+        keeper.export = export;
+        Ok(Box::new(keeper))
+    }
+
+    /// Resume a mechanism from state previously written by `export`
+    ///
+    /// `start` is not called; `mech_data` must be allocated the same way it would have.
+    pub unsafe fn resume(
+        vtable: MechanismVTable,
+        mech_data: NonNull<()>,
+        export: Gsasl_export_function,
+    ) -> Box<dyn Authentication> {
+        Box::new(CMechanismStateKeeper {
+            mech_data: Some(mech_data),
+            vtable,
+            export,
+        })
     }
 }
 
@@ -126,26 +167,45 @@ impl Authentication for CMechanismStateKeeper {
             let mut output: *mut c_char = std::ptr::null_mut();
             let mut outlen: size_t = 0;
 
+            // Like gsasl the C mechanisms expect their input to be NUL-terminated and will
+            // happily `strlen` past the end of it otherwise.
+            let input = input.map(|input| {
+                let mut buf = Vec::with_capacity(input.len() + 1);
+                buf.extend_from_slice(input);
+                buf.push(0);
+                buf
+            });
+
             unsafe {
                 let res = step(
                     session,
                     self.mech_data.clone(),
-                    input,
+                    input.as_ref().map(|buf| &buf[..buf.len() - 1]),
                     &mut output,
                     &mut outlen,
                 );
-                if res == GSASL_OK as libc::c_int {
-                    Ok(Done(write_output(writer, output, outlen)?))
+                let result = if res == GSASL_OK as libc::c_int {
+                    write_output(writer, output, outlen).map(Done)
                 } else if res == GSASL_NEEDS_MORE as libc::c_int {
-                    Ok(NeedsMore(write_output(writer, output, outlen)?))
+                    write_output(writer, output, outlen).map(NeedsMore)
                 } else {
                     Err(Gsasl(res).into())
-                }
+                };
+                libc::free(output.cast());
+                result
             }
         } else {
             Err(Gsasl(GSASL_UNKNOWN_MECHANISM as i32).into())
         }
     }
+
+    #[cfg(feature = "session_state")]
+    fn export_state(&self, writer: &mut StateWriter) -> Result<(), StateError> {
+        match (self.export, self.mech_data) {
+            (Some(export), Some(mech_data)) => unsafe { export(mech_data, writer) },
+            _ => Err(StateError::Unsupported),
+        }
+    }
 }
 
 impl Drop for CMechanismStateKeeper {
@@ -182,5 +242,9 @@ pub(crate) type Gsasl_step_function = Option<
 
 pub(crate) type Gsasl_finish_function = Option<unsafe fn(_: Option<NonNull<()>>) -> ()>;
 
+#[cfg(feature = "session_state")]
+pub(crate) type Gsasl_export_function =
+    Option<unsafe fn(_: NonNull<()>, _: &mut StateWriter) -> Result<(), StateError>>;
+
 pub(crate) type Gsasl_init_function = Option<unsafe fn() -> libc::c_int>;
 pub(crate) type Gsasl_done_function = Option<unsafe fn() -> ()>;
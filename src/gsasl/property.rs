@@ -29,13 +29,20 @@ pub unsafe fn gsasl_property_set_raw(
     mut data: *const libc::c_char,
     mut len: size_t,
 ) -> libc::c_int {
+    // As in gsasl setting a property to NULL clears it
+    if data.is_null() {
+        sctx.clear_property_raw(prop);
+        return GSASL_OK as libc::c_int;
+    }
     let bytes = std::slice::from_raw_parts(data as *const u8, len);
     let mut vec = Vec::with_capacity(len);
     vec.extend_from_slice(bytes);
-    let cstring = CString::new(vec)
-        .expect("gsasl_property_set_raw called with NULL-containing string")
-        .into_string()
-        .expect("gsasl_propery_set_raw called with non-UTF8 string");
+    // Mechanisms set properties from data sent by the other party, so neither interior NULs nor
+    // invalid UTF-8 must be able to take down the process.
+    let cstring = match CString::new(vec).map(CString::into_string) {
+        Ok(Ok(string)) => string,
+        _ => return GSASL_MECHANISM_PARSE_ERROR as libc::c_int,
+    };
     sctx.set_property_raw(prop, Arc::new(cstring));
 
     return GSASL_OK as libc::c_int;
@@ -61,29 +68,17 @@ pub unsafe fn gsasl_property_set_raw(
  **/
 unsafe fn gsasl_property_fast(sctx: &mut SessionData, prop: Gsasl_property) -> *const libc::c_char {
     if GSASL_OPENID20_OUTCOME_DATA == prop {
-        if let Some(prop) = sctx.get_property::<OpenID20OutcomeData>() {
-            prop.as_ptr()
-        } else {
-            std::ptr::null()
-        }
+        sctx.get_property_c_str::<OpenID20OutcomeData>()
+            .unwrap_or(std::ptr::null())
     } else if GSASL_OPENID20_REDIRECT_URL == prop {
-        if let Some(prop) = sctx.get_property::<OpenID20RedirectUrl>() {
-            prop.as_ptr()
-        } else {
-            std::ptr::null()
-        }
+        sctx.get_property_c_str::<OpenID20RedirectUrl>()
+            .unwrap_or(std::ptr::null())
     } else if GSASL_SAML20_REDIRECT_URL == prop {
-        if let Some(prop) = sctx.get_property::<SAML20RedirectUrl>() {
-            prop.as_ptr()
-        } else {
-            std::ptr::null()
-        }
+        sctx.get_property_c_str::<SAML20RedirectUrl>()
+            .unwrap_or(std::ptr::null())
     } else if GSASL_SAML20_IDP_IDENTIFIER == prop {
-        if let Some(prop) = sctx.get_property::<SAML20IDPIdentifier>() {
-            prop.as_ptr()
-        } else {
-            std::ptr::null()
-        }
+        sctx.get_property_c_str::<SAML20IDPIdentifier>()
+            .unwrap_or(std::ptr::null())
     } else if GSASL_CB_TLS_UNIQUE == prop {
         if let Some(prop) = sctx.get_property::<CBTlsUnique>() {
             prop.as_ptr()
@@ -147,23 +142,14 @@ unsafe fn gsasl_property_fast(sctx: &mut SessionData, prop: Gsasl_property) -> *
             std::ptr::null()
         }
     } else if GSASL_PIN == prop {
-        if let Some(prop) = sctx.get_property::<Pin>() {
-            prop.as_ptr()
-        } else {
-            std::ptr::null()
-        }
+        sctx.get_property_c_str::<Pin>()
+            .unwrap_or(std::ptr::null())
     } else if GSASL_SUGGESTED_PIN == prop {
-        if let Some(prop) = sctx.get_property::<SuggestedPin>() {
-            prop.as_ptr()
-        } else {
-            std::ptr::null()
-        }
+        sctx.get_property_c_str::<SuggestedPin>()
+            .unwrap_or(std::ptr::null())
     } else if GSASL_PASSCODE == prop {
-        if let Some(prop) = sctx.get_property::<Passcode>() {
-            prop.as_ptr()
-        } else {
-            std::ptr::null()
-        }
+        sctx.get_property_c_str::<Passcode>()
+            .unwrap_or(std::ptr::null())
     } else if GSASL_GSSAPI_DISPLAY_NAME == prop {
         if let Some(prop) = sctx.get_property::<GssapiDisplayName>() {
             prop.as_ptr()
@@ -183,33 +169,17 @@ unsafe fn gsasl_property_fast(sctx: &mut SessionData, prop: Gsasl_property) -> *
             std::ptr::null()
         }
     } else if GSASL_ANONYMOUS_TOKEN == prop {
-        if let Some(prop) = sctx.get_property::<AnonymousToken>() {
-            let cstr = Box::leak(Box::new(CString::new(prop.as_bytes().to_owned()).unwrap()));
-            cstr.as_ptr()
-        } else {
-            std::ptr::null()
-        }
+        sctx.get_property_c_str::<AnonymousToken>()
+            .unwrap_or(std::ptr::null())
     } else if GSASL_PASSWORD == prop {
-        if let Some(prop) = sctx.get_property::<Password>() {
-            let cstr = Box::leak(Box::new(CString::new(prop.as_bytes().to_owned()).unwrap()));
-            cstr.as_ptr()
-        } else {
-            std::ptr::null()
-        }
+        sctx.get_property_c_str::<Password>()
+            .unwrap_or(std::ptr::null())
     } else if GSASL_AUTHZID == prop {
-        if let Some(prop) = sctx.get_property::<AuthzId>() {
-            let cstr = Box::leak(Box::new(CString::new(prop.as_bytes().to_owned()).unwrap()));
-            cstr.as_ptr()
-        } else {
-            std::ptr::null()
-        }
+        sctx.get_property_c_str::<AuthzId>()
+            .unwrap_or(std::ptr::null())
     } else if GSASL_AUTHID == prop {
-        if let Some(prop) = sctx.get_property::<AuthId>() {
-            let cstr = Box::leak(Box::new(CString::new(prop.as_bytes().to_owned()).unwrap()));
-            (*cstr).as_ptr()
-        } else {
-            std::ptr::null()
-        }
+        sctx.get_property_c_str::<AuthId>()
+            .unwrap_or(std::ptr::null())
     } else {
         std::ptr::null()
     }
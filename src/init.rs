@@ -43,7 +43,30 @@ pub(crate) fn register_builtin(_ctx: &mut SASL) {
         _ctx.register(_m);
     }
 
-    /* USE_NTLM */
+    #[cfg(feature = "otp")]
+    {
+        let _m = &crate::mechanisms::otp::mechinfo::OTP;
+        let _n = &crate::mechanisms::otp::mechinfo::X_OATH;
+        #[cfg(all(feature = "registry_dynamic", not(feature = "registry_static")))]
+        {
+            _ctx.register(_m);
+            _ctx.register(_n);
+        }
+    }
+
+    #[cfg(feature = "ntlm")]
+    {
+        let _m = &crate::mechanisms::ntlm::mechinfo::NTLM;
+        #[cfg(all(feature = "registry_dynamic", not(feature = "registry_static")))]
+        _ctx.register(_m);
+    }
+
+    #[cfg(feature = "srp")]
+    {
+        let _m = &crate::mechanisms::srp::mechinfo::SRP;
+        #[cfg(all(feature = "registry_dynamic", not(feature = "registry_static")))]
+        _ctx.register(_m);
+    }
 
     #[cfg(feature = "digest-md5")]
     {
@@ -81,6 +104,21 @@ pub(crate) fn register_builtin(_ctx: &mut SASL) {
         }
     }
 
+    #[cfg(feature = "ht-sha-256")]
+    {
+        let _m = &crate::mechanisms::ht::mechinfo::HT_SHA_256_NONE;
+        let _n = &crate::mechanisms::ht::mechinfo::HT_SHA_256_UNIQ;
+        let _o = &crate::mechanisms::ht::mechinfo::HT_SHA_256_ENDP;
+        let _p = &crate::mechanisms::ht::mechinfo::HT_SHA_256_EXPR;
+        #[cfg(all(feature = "registry_dynamic", not(feature = "registry_static")))]
+        {
+            _ctx.register(_m);
+            _ctx.register(_n);
+            _ctx.register(_o);
+            _ctx.register(_p);
+        }
+    }
+
     #[cfg(feature = "openid20")]
     {
         let _m = &crate::mechanisms::openid20::mechinfo::OPENID20;
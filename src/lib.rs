@@ -38,6 +38,10 @@
 //! may lead to a situation where users can't use any mechanisms since they only depend on
 //! rsasl via a transient dependency that has no mechanism features enabled.
 //!
+//! For a number of common protocols the framing of the authentication exchange is already
+//! implemented in the `protocols` module, gated behind the respective `protocol_*` feature, e.g.
+//! `protocol_imap`.
+//!
 //! TODO: How to handle EXTERNAL?
 //! TODO:
 //!     Bonus minus points: sasl.wrap(data) and sasl.unwrap(data) for security layers. Prefer to
@@ -108,13 +112,19 @@ pub mod channel_bindings;
 pub mod property;
 pub mod validate;
 
+#[cfg(feature = "session_state")]
+pub mod state;
+
+#[cfg(feature = "protocols")]
+pub mod protocols;
+
 mod vectored_io;
 
 use crate::callback::Callback;
 use crate::error::SASLError;
 use crate::mechanism::Authentication;
 use crate::mechname::Mechname;
-use crate::registry::{Mechanism, MECHANISMS};
+use crate::registry::{Mechanism, PropertyRequirements, MECHANISMS};
 use crate::session::{Session, Side};
 pub use property::{Property, PropertyQ};
 
@@ -136,6 +146,7 @@ pub struct SASL {
     static_mechs: &'static [Mechanism],
 
     sort_fn: fn(a: &&Mechanism, b: &&Mechanism) -> Ordering,
+    filter_fn: fn(mechanism: &Mechanism) -> bool,
 }
 
 impl Debug for SASL {
@@ -163,6 +174,7 @@ impl SASL {
     /// An interactive client "logging in" to some server application would use this method. The
     /// server application would use [`SASL::server_mech_list()`].
     pub fn client_mech_list(&self) -> impl IntoIterator<Item = &'static Mechanism> + '_ {
+        let filter_fn = self.filter_fn;
         #[cfg(feature = "registry_static")]
         {
             #[cfg(feature = "registry_dynamic")]
@@ -170,18 +182,21 @@ impl SASL {
                 MECHANISMS
                     .into_iter()
                     .chain(self.dynamic_mechs.iter().map(|m| *m))
-                    .filter(|mechanism| mechanism.client.is_some())
+                    .filter(move |mechanism| mechanism.client.is_some() && filter_fn(mechanism))
             }
             #[cfg(not(feature = "registry_dynamic"))]
             {
                 MECHANISMS
                     .into_iter()
-                    .filter(|mechanism| mechanism.client.is_some())
+                    .filter(move |mechanism| mechanism.client.is_some() && filter_fn(mechanism))
             }
         }
         #[cfg(all(not(feature = "registry_static"), feature = "registry_dynamic"))]
         {
-            self.dynamic_mechs.iter().map(|m| *m)
+            self.dynamic_mechs
+                .iter()
+                .map(|m| *m)
+                .filter(move |mechanism| mechanism.client.is_some() && filter_fn(mechanism))
         }
         #[cfg(not(any(feature = "registry_static", feature = "registry_dynamic")))]
         {
@@ -214,9 +229,10 @@ impl SASL {
                 (&[]).iter()
             }
         };
+        let filter_fn = self.filter_fn;
         statics
             .chain(dynamics)
-            .filter(|mechanism| mechanism.server.is_some())
+            .filter(move |mechanism| mechanism.server.is_some() && filter_fn(mechanism))
     }
 
     pub fn client_start_suggested<'a>(
@@ -242,6 +258,26 @@ impl SASL {
             .ok_or(SASLError::NoSharedMechanism)
     }
 
+    /// Start a session like [`SASL::client_start_suggested`], additionally recording the
+    /// advertised mechanisms and channel binding types on the session
+    ///
+    /// Mechanisms implementing downgrade protection (i.e. the `SCRAM-*` family) use them to
+    /// detect an attacker having stripped mechanisms or channel binding types from the server's
+    /// advertisement. The exchange then fails with servers not implementing downgrade
+    /// protection. `channel_bindings` should be empty if the server did not advertise any.
+    /// See [`AdvertisedMechanisms`](property::AdvertisedMechanisms) for details.
+    pub fn client_start_advertised<'a>(
+        &self,
+        mechs: impl IntoIterator<Item = &'a Mechname>,
+        channel_bindings: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Session, SASLError> {
+        let mechs: Vec<&Mechname> = mechs.into_iter().collect();
+        let mut session = self.client_start_suggested(mechs.iter().copied())?;
+        let advertised = property::Advertised::new(mechs, channel_bindings);
+        session.set_property::<property::AdvertisedMechanisms>(Arc::new(advertised));
+        Ok(session)
+    }
+
     pub fn server_start_suggested<'a>(
         &self,
         mechs: impl IntoIterator<Item = &'a Mechname>,
@@ -282,6 +318,65 @@ impl SASL {
         self.server_start(mech).is_ok()
     }
 
+    /// Returns the properties the client side of the given mechanisms queries
+    ///
+    /// Mechanisms without client support are skipped, the others are returned in the given
+    /// order. Interactive clients can use this to ask the user for all data the offered
+    /// mechanisms may need before connecting, instead of from
+    /// [`Callback::provide_prop`](callback::Callback::provide_prop) during the exchange.
+    ///
+    /// ```
+    /// # use rsasl::SASL;
+    /// use rsasl::mechname::Mechname;
+    /// use rsasl::property::properties::{AUTHID, PASSCODE, PASSWORD, PIN};
+    ///
+    /// let sasl = SASL::new();
+    /// let offered = [
+    ///     Mechname::new(b"PLAIN").unwrap(),
+    ///     Mechname::new(b"X-UNKNOWN").unwrap(),
+    ///     Mechname::new(b"SECURID").unwrap(),
+    /// ];
+    /// let needed = sasl.client_properties(offered);
+    /// assert_eq!(needed.len(), 2);
+    /// assert_eq!(needed[0].0.as_str(), "PLAIN");
+    /// assert_eq!(needed[0].1.required, &[AUTHID, PASSWORD]);
+    /// assert!(needed[1].1.required.contains(&PASSCODE));
+    /// assert!(needed[1].1.optional.contains(&PIN));
+    /// ```
+    pub fn client_properties<'a>(
+        &self,
+        mechs: impl IntoIterator<Item = &'a Mechname>,
+    ) -> Vec<(&'static Mechname, PropertyRequirements)> {
+        mechs
+            .into_iter()
+            .filter_map(|name| {
+                self.client_mech_list()
+                    .into_iter()
+                    .find(|mech| mech.mechanism == name)
+                    .map(|mech| (mech.mechanism, mech.client_properties))
+            })
+            .collect()
+    }
+
+    /// Returns the properties the server side of the given mechanisms queries
+    ///
+    /// Mechanisms without server support are skipped, the others are returned in the given
+    /// order. Properties only read during validation are not included.
+    pub fn server_properties<'a>(
+        &self,
+        mechs: impl IntoIterator<Item = &'a Mechname>,
+    ) -> Vec<(&'static Mechname, PropertyRequirements)> {
+        mechs
+            .into_iter()
+            .filter_map(|name| {
+                self.server_mech_list()
+                    .into_iter()
+                    .find(|mech| mech.mechanism == name)
+                    .map(|mech| (mech.mechanism, mech.server_properties))
+            })
+            .collect()
+    }
+
     /// Start a new session with the given [`Authentication`] implementation
     ///
     /// This function should rarely be necessary, see [`SASL::client_start`] and
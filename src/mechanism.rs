@@ -1,6 +1,8 @@
 use crate::error::SessionError;
 use crate::error::SessionError::NoSecurityLayer;
 use crate::session::{SessionData, StepResult};
+#[cfg(feature = "session_state")]
+use crate::state::{StateError, StateWriter};
 use std::io::Write;
 
 /// Trait implemented to be one party in an authentication exchange
@@ -57,4 +59,19 @@ pub trait Authentication {
     fn decode(&mut self, _input: &[u8]) -> Result<Box<[u8]>, SessionError> {
         Err(NoSecurityLayer)
     }
+
+    /// Returns true if the exchange negotiated a security layer that `encode` and `decode` apply
+    fn has_security_layer(&self) -> bool {
+        false
+    }
+
+    /// Write the state of the exchange in progress so it can be resumed elsewhere
+    ///
+    /// *requires feature `session_state`*
+    ///
+    /// Only the built-in mechanisms can currently be resumed, see [`crate::state`].
+    #[cfg(feature = "session_state")]
+    fn export_state(&self, _writer: &mut StateWriter) -> Result<(), StateError> {
+        Err(StateError::Unsupported)
+    }
 }
@@ -1,4 +1,6 @@
 use crate::mechanisms::anonymous::{client, server};
+use crate::property::properties::{ANONYMOUS_ACCESS, ANONYMOUS_TOKEN};
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
 use crate::Side;
 use crate::{Mechanism, Mechname};
 
@@ -12,4 +14,16 @@ pub static ANONYMOUS: Mechanism = Mechanism {
     client: Some(|_sasl| Ok(Box::new(client::Anonymous))),
     server: Some(|_sasl| Ok(Box::new(server::Anonymous))),
     first: Side::Client,
+    client_properties: PropertyRequirements::new(&[], &[ANONYMOUS_TOKEN]),
+    server_properties: PropertyRequirements::new(&[], &[ANONYMOUS_ACCESS]),
+    description: "Anonymous access with an optional trace token",
+    specification: Some("RFC 4505"),
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: false,
+        weak: false,
+    },
 };
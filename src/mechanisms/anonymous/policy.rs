@@ -0,0 +1,185 @@
+//! Enforcing an [`AnonymousPolicy`] on the server side
+
+use crate::property::{
+    AnonymousAttempt, AnonymousAttempts, AnonymousOutcome, AnonymousPolicy, TraceRequirement,
+};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Maximum number of trace tokens whose attempts are counted separately
+pub const MAX_TRACKED_TRACES: usize = 4096;
+
+/// A syntactically valid `ANONYMOUS` message
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(super) enum Trace<'a> {
+    None,
+    Email(&'a str),
+    Opaque(&'a str),
+}
+
+impl<'a> Trace<'a> {
+    pub(super) fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Self::None => None,
+            Self::Email(trace) | Self::Opaque(trace) => Some(trace),
+        }
+    }
+}
+
+impl AnonymousPolicy {
+    /// A policy allowing every trace token RFC 4505 allows, without rate limit or audit hook
+    pub fn new() -> Self {
+        Self {
+            requirement: TraceRequirement::Optional,
+            rate_limit: None,
+            attempts: Mutex::default(),
+            audit: None,
+        }
+    }
+
+    pub fn require(mut self, requirement: TraceRequirement) -> Self {
+        self.requirement = requirement;
+        self
+    }
+
+    /// Allow at most `attempts` authentications per trace token within `period`
+    ///
+    /// Attempts without a trace token share one limit. At most [`MAX_TRACKED_TRACES`] trace
+    /// tokens are counted separately within a period; attempts with any further ones share a
+    /// single limit as well until the counters of past periods are dropped, which happens once
+    /// per `period`.
+    pub fn rate_limit(mut self, attempts: u32, period: Duration) -> Self {
+        self.rate_limit = Some((attempts, period));
+        self
+    }
+
+    /// Call `hook` for every attempt, including the ones that failed
+    pub fn audit(mut self, hook: impl Fn(&AnonymousAttempt<'_>) + Send + Sync + 'static) -> Self {
+        self.audit = Some(Box::new(hook));
+        self
+    }
+
+    pub(super) fn permits(&self, trace: Trace<'_>) -> bool {
+        match (self.requirement, trace) {
+            (TraceRequirement::Optional, _) => true,
+            (TraceRequirement::Required, trace) => trace != Trace::None,
+            (TraceRequirement::Email, Trace::Email(_)) => true,
+            (TraceRequirement::Opaque, Trace::Opaque(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Count an attempt, returning `false` if the rate limit for the trace token is exceeded
+    pub(super) fn count_attempt(&self, trace: Option<&str>) -> bool {
+        let (limit, period) = match self.rate_limit {
+            Some(rate_limit) => rate_limit,
+            None => return true,
+        };
+
+        // The audit hook is never called while the counters are locked. Should counting itself
+        // panic the counters are still usable, at worst missing that attempt.
+        let mut attempts = self
+            .attempts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        attempts.count(trace.unwrap_or_default(), period) <= limit
+    }
+
+    pub(super) fn record(&self, trace: Option<&str>, outcome: AnonymousOutcome) {
+        if let Some(audit) = &self.audit {
+            audit(&AnonymousAttempt { trace, outcome });
+        }
+    }
+}
+
+impl AnonymousAttempts {
+    /// Count an attempt, returning the number of attempts for the trace token in this period
+    fn count(&mut self, trace: &str, period: Duration) -> u32 {
+        let now = Instant::now();
+        if !matches!(self.next_prune, Some(next_prune) if now < next_prune) {
+            self.counters
+                .retain(|_, (start, _)| now.duration_since(*start) < period);
+            self.next_prune = Some(now + period);
+        }
+
+        let tracked = self.counters.len() < MAX_TRACKED_TRACES || self.counters.contains_key(trace);
+        let (start, count) = if tracked {
+            self.counters.entry(trace.to_string()).or_insert((now, 0))
+        } else {
+            self.overflow.get_or_insert((now, 0))
+        };
+        if now.duration_since(*start) >= period {
+            *start = now;
+            *count = 0;
+        }
+        *count = count.saturating_add(1);
+        *count
+    }
+}
+
+impl Default for AnonymousPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit() {
+        let policy = AnonymousPolicy::new().rate_limit(2, Duration::from_secs(3600));
+        assert!(policy.count_attempt(Some("spam@example.org")));
+        assert!(policy.count_attempt(Some("spam@example.org")));
+        assert!(!policy.count_attempt(Some("spam@example.org")));
+        assert!(policy.count_attempt(Some("other@example.org")));
+        assert!(policy.count_attempt(None));
+
+        let policy = AnonymousPolicy::new().rate_limit(1, Duration::from_millis(0));
+        assert!(policy.count_attempt(None));
+        assert!(policy.count_attempt(None));
+    }
+
+    #[test]
+    fn untracked_traces_share_a_limit() {
+        let policy = AnonymousPolicy::new().rate_limit(2, Duration::from_secs(3600));
+        for i in 0..MAX_TRACKED_TRACES {
+            assert!(policy.count_attempt(Some(&i.to_string())));
+        }
+        assert_eq!(
+            policy.attempts.lock().unwrap().counters.len(),
+            MAX_TRACKED_TRACES
+        );
+
+        assert!(policy.count_attempt(Some("spam1@example.org")));
+        assert!(policy.count_attempt(Some("spam2@example.org")));
+        assert!(!policy.count_attempt(Some("spam3@example.org")));
+        // Tokens already tracked keep their own limit
+        assert!(policy.count_attempt(Some("0")));
+        assert_eq!(
+            policy.attempts.lock().unwrap().counters.len(),
+            MAX_TRACKED_TRACES
+        );
+    }
+
+    #[test]
+    fn requirements() {
+        let traces = [
+            Trace::None,
+            Trace::Email("user@example.org"),
+            Trace::Opaque("user"),
+        ];
+        let permitted = |requirement| {
+            let policy = AnonymousPolicy::new().require(requirement);
+            traces
+                .iter()
+                .map(|trace| policy.permits(*trace))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(permitted(TraceRequirement::Optional), [true, true, true]);
+        assert_eq!(permitted(TraceRequirement::Required), [false, true, true]);
+        assert_eq!(permitted(TraceRequirement::Email), [false, true, false]);
+        assert_eq!(permitted(TraceRequirement::Opaque), [false, false, true]);
+    }
+}
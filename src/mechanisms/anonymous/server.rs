@@ -1,5 +1,6 @@
-use crate::error::{MechanismError, MechanismErrorKind};
-use crate::property::AnonymousToken;
+use crate::error::{MechanismError, MechanismErrorKind, SessionError};
+use crate::mechanisms::anonymous::policy::Trace;
+use crate::property::{AnonymousAccess, AnonymousOutcome, AnonymousToken};
 use crate::session::Step::{Done, NeedsMore};
 use crate::session::{SessionData, StepResult};
 use crate::validate::validations::ANONYMOUS;
@@ -12,7 +13,10 @@ use std::sync::Arc;
 pub struct ParseError;
 impl Display for ParseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str("the given anonymous token is invalid UTF-8 or longer than 255 chars")
+        f.write_str(
+            "the given anonymous token is invalid UTF-8, longer than 255 chars or contains \
+             control characters",
+        )
     }
 }
 impl MechanismError for ParseError {
@@ -21,6 +25,34 @@ impl MechanismError for ParseError {
     }
 }
 
+/// Parse the optional trace information (RFC 4505, section 3)
+fn parse(input: &[u8]) -> Result<Trace<'_>, ParseError> {
+    let input = std::str::from_utf8(input).map_err(|_| ParseError)?;
+    /* token       = 1*255TCHAR
+    The <token> production is restricted to 255 UTF-8 encoded Unicode
+    characters.   As the encoding of a characters uses a sequence of 1
+    to 4 octets, a token may be long as 1020 octets. */
+    if input.chars().count() > 255 || input.chars().any(char::is_control) {
+        return Err(ParseError);
+    }
+
+    // A token can't contain '@', so anything containing one has to be an email address
+    match input.rsplit_once('@') {
+        None if input.is_empty() => Ok(Trace::None),
+        None => Ok(Trace::Opaque(input)),
+        Some((local, domain))
+            if !local.is_empty() && !domain.is_empty() && !input.contains(' ') =>
+        {
+            Ok(Trace::Email(input))
+        }
+        Some(_) => Err(ParseError),
+    }
+}
+
+/// Server side of `ANONYMOUS`
+///
+/// Accepts the optional trace token and validates with [`ANONYMOUS`] after applying the
+/// [`AnonymousPolicy`](crate::property::AnonymousPolicy) queried with [`AnonymousAccess`].
 #[derive(Copy, Clone, Debug)]
 pub struct Anonymous;
 
@@ -37,21 +69,69 @@ impl Authentication for Anonymous {
             return Ok(NeedsMore(None));
         };
 
-        if let Ok(input) = std::str::from_utf8(input) {
-            /* token       = 1*255TCHAR
-            The <token> production is restricted to 255 UTF-8 encoded Unicode
-            characters.   As the encoding of a characters uses a sequence of 1
-            to 4 octets, a token may be long as 1020 octets. */
-            if input.len() == 0 || input.len() > 255 {
-                return Err(ParseError.into());
+        let policy = session.get_property_or_callback::<AnonymousAccess>()?;
+        let trace = match parse(input) {
+            Ok(trace) => trace,
+            Err(e) => {
+                if let Some(policy) = policy {
+                    let trace = String::from_utf8_lossy(input);
+                    policy.record(Some(&trace), AnonymousOutcome::Malformed);
+                }
+                return Err(e.into());
             }
+        };
 
-            session.set_property::<AnonymousToken>(Arc::new(input.to_string()));
-            session.validate(ANONYMOUS)?;
+        if let Some(policy) = &policy {
+            if !policy.permits(trace) {
+                policy.record(trace.as_str(), AnonymousOutcome::Disallowed);
+                return Err(SessionError::AuthenticationFailure);
+            }
+            if !policy.count_attempt(trace.as_str()) {
+                policy.record(trace.as_str(), AnonymousOutcome::RateLimited);
+                return Err(SessionError::AuthenticationFailure);
+            }
+        }
 
-            Ok(Done(None))
-        } else {
-            Err(ParseError.into())
+        match trace.as_str() {
+            Some(token) => {
+                session.set_property::<AnonymousToken>(Arc::new(token.to_string()));
+            }
+            None => session.clear_property::<AnonymousToken>(),
+        }
+        let validated = session.validate(ANONYMOUS);
+        if let Some(policy) = &policy {
+            let outcome = match validated {
+                Ok(()) => AnonymousOutcome::Accepted,
+                Err(_) => AnonymousOutcome::Rejected,
+            };
+            policy.record(trace.as_str(), outcome);
         }
+        validated?;
+
+        Ok(Done(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_tokens() {
+        assert_eq!(parse(b""), Ok(Trace::None));
+        assert_eq!(parse(b"sirhc"), Ok(Trace::Opaque("sirhc")));
+        assert_eq!(
+            parse(b"sirhc@example.org"),
+            Ok(Trace::Email("sirhc@example.org"))
+        );
+        let long = "\u{1F4E7}".repeat(255);
+        assert_eq!(parse(long.as_bytes()), Ok(Trace::Opaque(&long)));
+
+        assert!(parse("x".repeat(256).as_bytes()).is_err());
+        assert!(parse(b"@example.org").is_err());
+        assert!(parse(b"user@").is_err());
+        assert!(parse(b"us er@example.org").is_err());
+        assert!(parse(b"line\nbreak").is_err());
+        assert!(parse(b"\xff").is_err());
     }
 }
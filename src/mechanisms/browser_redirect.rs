@@ -0,0 +1,187 @@
+//! Browser authentication for `SAML20` and `OPENID20` clients. *Requires feature
+//! `browser_redirect`*
+//!
+//! Both mechanisms have the user authenticate to their IdP in a web browser and expect the
+//! callback to only return once that completed. [`BrowserRedirect`] does so by listening on a
+//! loopback port: the IdP (or the service it hands the user back to) redirects the browser to
+//! [`BrowserRedirect::url`] at the end of the login, which completes the step.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use std::time::Duration;
+//! use rsasl::mechanisms::browser_redirect::BrowserRedirect;
+//! # fn main() -> std::io::Result<()> {
+//! let redirect = BrowserRedirect::bind(|url| {
+//!     std::process::Command::new("xdg-open").arg(url).spawn().map(|_| ())
+//! })?
+//! .with_timeout(Duration::from_secs(300));
+//! // Register `redirect.url()` as return URL with the IdP, then use `redirect` as callback
+//! let mut sasl = rsasl::SASL::new();
+//! sasl.install_callback(Arc::new(redirect));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::callback::Callback;
+use crate::error::SessionError;
+use crate::property::{properties, OpenID20RedirectUrl, SAML20RedirectUrl};
+use crate::session::SessionData;
+use crate::Property;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+/// How long to wait for a connected browser to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often to check for a new connection if a timeout is set
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const RESPONSE_BODY: &str = "<!DOCTYPE html>\n<html><head><title>Authentication complete</title>\
+                             </head><body><p>Authentication complete. You can close this window \
+                             and return to the application.</p></body></html>\n";
+
+/// Loopback HTTP listener completing browser authentication
+///
+/// Answers [`Saml20AuthenticateInBrowser`] and [`OpenID20AuthenticateInBrowser`] by calling
+/// `open` with the URL the server sent and then blocking until the browser requests
+/// [`url`](BrowserRedirect::url). Applications with their own [`Callback`] can delegate these
+/// two properties to [`authenticate`](BrowserRedirect::authenticate).
+///
+/// [`Saml20AuthenticateInBrowser`]: crate::property::Saml20AuthenticateInBrowser
+/// [`OpenID20AuthenticateInBrowser`]: crate::property::OpenID20AuthenticateInBrowser
+#[derive(Debug)]
+pub struct BrowserRedirect<F> {
+    listener: TcpListener,
+    url: String,
+    open: F,
+    timeout: Option<Duration>,
+}
+
+impl<F: Fn(&str) -> io::Result<()>> BrowserRedirect<F> {
+    /// Listen on a random port on `127.0.0.1`
+    ///
+    /// `open` is called with the URL the user has to authenticate at, usually it opens that URL
+    /// in the system's web browser.
+    pub fn bind(open: F) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let url = format!("http://{}/", listener.local_addr()?);
+        Ok(Self {
+            listener,
+            url,
+            open,
+            timeout: None,
+        })
+    }
+
+    /// Fail the step if the browser wasn't redirected back within `timeout`
+    ///
+    /// Without a timeout the step blocks until the redirect happens.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The URL the browser has to be redirected to after the user authenticated
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Open the redirect URL of the mechanism and wait for the browser to come back
+    ///
+    /// Returns [`SessionError::NoCallback`] for all properties other than
+    /// [`SAML20_AUTHENTICATE_IN_BROWSER`](properties::SAML20_AUTHENTICATE_IN_BROWSER) and
+    /// [`OPENID20_AUTHENTICATE_IN_BROWSER`](properties::OPENID20_AUTHENTICATE_IN_BROWSER).
+    pub fn authenticate(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        let redirect = match property {
+            properties::SAML20_AUTHENTICATE_IN_BROWSER => session
+                .get_property::<SAML20RedirectUrl>()
+                .ok_or(SessionError::no_property::<SAML20RedirectUrl>())?,
+            properties::OPENID20_AUTHENTICATE_IN_BROWSER => session
+                .get_property::<OpenID20RedirectUrl>()
+                .ok_or(SessionError::no_property::<OpenID20RedirectUrl>())?,
+            _ => return Err(SessionError::NoCallback { property }),
+        };
+
+        (self.open)(&redirect)?;
+        self.wait()?;
+        Ok(())
+    }
+
+    fn wait(&self) -> io::Result<()> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        self.listener.set_nonblocking(deadline.is_some())?;
+        loop {
+            let stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return Err(io::Error::new(
+                            ErrorKind::TimedOut,
+                            "browser was not redirected back in time",
+                        ));
+                    }
+                    std::thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            // Connections that fail or request something else, e.g. a favicon, are not the
+            // redirect we are waiting for.
+            if let Ok(true) = respond(stream) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<F: Fn(&str) -> io::Result<()>> Callback for BrowserRedirect<F> {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        self.authenticate(session, property)
+    }
+}
+
+/// Answer a single request, returning whether it was the redirect
+fn respond(stream: TcpStream) -> io::Result<bool> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers, the request has no body
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let is_redirect = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => target.split('?').next() == Some("/"),
+        _ => false,
+    };
+
+    let mut stream = reader.into_inner();
+    if is_redirect {
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            RESPONSE_BODY.len(),
+            RESPONSE_BODY
+        )?;
+    } else {
+        stream.write_all(
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        )?;
+    }
+    stream.flush()?;
+    Ok(is_redirect)
+}
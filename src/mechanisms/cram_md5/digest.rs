@@ -1,98 +1,172 @@
-use crate::gsasl::gl::gc_gnulib::gc_hmac_md5;
-use ::libc;
-use libc::{size_t, strlen};
-
-/* gc.h --- Header file for implementation agnostic crypto wrapper API.
- * Copyright (C) 2002-2005, 2007-2008, 2011-2021 Free Software Foundation, Inc.
- *
- * This file is free software: you can redistribute it and/or modify
- * it under the terms of the GNU Lesser General Public License as
- * published by the Free Software Foundation; either version 2.1 of the
- * License, or (at your option) any later version.
- *
- * This file is distributed in the hope that it will be useful,
- * but WITHOUT ANY WARRANTY; without even the implied warranty of
- * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
- * GNU Lesser General Public License for more details.
- *
- * You should have received a copy of the GNU Lesser General Public License
- * along with this program.  If not, see <https://www.gnu.org/licenses/>.
- *
- */
-/* digest.h --- Generate a CRAM-MD5 hex encoded HMAC-MD5 response string.
- * Copyright (C) 2002-2021 Simon Josefsson
- *
- * This file is part of GNU SASL Library.
- *
- * GNU SASL Library is free software; you can redistribute it and/or
- * modify it under the terms of the GNU Lesser General Public License
- * as published by the Free Software Foundation; either version 2.1 of
- * the License, or (at your option) any later version.
- *
- * GNU SASL Library is distributed in the hope that it will be useful,
- * but WITHOUT ANY WARRANTY; without even the implied warranty of
- * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
- * Lesser General Public License for more details.
- *
- * You should have received a copy of the GNU Lesser General Public
- * License along with GNU SASL Library; if not, write to the Free
- * Free Software Foundation, Inc., 51 Franklin Street, Fifth Floor,
- * Boston, MA 02110-1301, USA.
- *
- */
-/* Get size_t. */
-/* Compute hex encoded HMAC-MD5 on the CHALLENGELEN long string
-CHALLENGE, keyed with SECRET of length SECRETLEN.  Use a
-CHALLENGELEN or SECRETLEN of 0 to indicate that CHALLENGE or
-SECRET, respectively, is zero terminated.  The RESPONSE buffer must
-be allocated by the caller, and must have room for
-CRAM_MD5_DIGEST_LEN characters.*/
-
-pub unsafe fn cram_md5_digest(
-    challenge: *const libc::c_char,
-    challengelen: size_t,
-    secret: *const libc::c_char,
-    secretlen: size_t,
-    mut response: *mut libc::c_char,
-) {
-    let mut hash: [libc::c_char; 16] = [0; 16];
-    gc_hmac_md5(
-        secret as *const libc::c_void,
-        if secretlen != 0 {
-            secretlen
-        } else {
-            strlen(secret)
-        },
-        challenge as *const libc::c_void,
-        if challengelen != 0 {
-            challengelen
-        } else {
-            strlen(challenge)
-        },
-        hash.as_mut_ptr(),
-    );
-    let mut i = 0i32;
-    while i < 16 {
-        let fresh0 = response;
-        response = response.offset(1);
-        *fresh0 = if hash[i as usize] as libc::c_int >> 4 as libc::c_int & 0xf as libc::c_int
-            > 9 as libc::c_int
-        {
-            ('a' as i32
-                + (hash[i as usize] as libc::c_int >> 4 as libc::c_int & 0xf as libc::c_int))
-                - 10 as libc::c_int
-        } else {
-            ('0' as i32)
-                + (hash[i as usize] as libc::c_int >> 4 as libc::c_int & 0xf as libc::c_int)
-        } as libc::c_char;
-        let fresh1 = response;
-        response = response.offset(1);
-        *fresh1 = if hash[i as usize] as libc::c_int & 0xf as libc::c_int > 9 as libc::c_int {
-            ('a' as i32 + (hash[i as usize] as libc::c_int & 0xf as libc::c_int))
-                - 10 as libc::c_int
+//! HMAC-MD5 computation for both sides
+//!
+//! Besides the plain HMAC keyed with the password this can continue from a precomputed
+//! [`HmacMd5Context`], which requires access to the MD5 state the `md-5` crate does not expose.
+//! The few lines of MD5 needed for that are implemented here.
+
+use crate::property::HmacMd5Context;
+use hmac::{Hmac, Mac};
+use md5::{Digest, Md5};
+use std::convert::TryInto;
+
+type HmacMd5 = Hmac<Md5>;
+
+/// Length of the hex-encoded digest in a response
+pub(super) const DIGEST_LEN: usize = 32;
+
+const BLOCK_LEN: usize = 64;
+const IV: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+#[rustfmt::skip]
+const SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+    6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+#[rustfmt::skip]
+const CONSTANTS: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+/// The MD5 compression function (RFC 1321, section 3.4)
+fn compress(state: &mut [u32; 4], block: &[u8]) {
+    let mut words = [0u32; 16];
+    for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+
+    let [mut a, mut b, mut c, mut d] = *state;
+    for i in 0..64 {
+        let (f, g) = match i / 16 {
+            0 => ((b & c) | (!b & d), i),
+            1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+            2 => (b ^ c ^ d, (3 * i + 5) % 16),
+            _ => (c ^ (b | !d), (7 * i) % 16),
+        };
+        let rotated = a
+            .wrapping_add(f)
+            .wrapping_add(CONSTANTS[i])
+            .wrapping_add(words[g])
+            .rotate_left(SHIFTS[i]);
+        a = d;
+        d = c;
+        c = b;
+        b = b.wrapping_add(rotated);
+    }
+
+    for (word, value) in state.iter_mut().zip([a, b, c, d].iter()) {
+        *word = word.wrapping_add(*value);
+    }
+}
+
+/// Finish an MD5 hash over `data` from the state after exactly one block was hashed
+fn finish_after_block(mut state: [u32; 4], data: &[u8]) -> [u8; 16] {
+    let bits = ((BLOCK_LEN + data.len()) as u64).wrapping_mul(8);
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % BLOCK_LEN != BLOCK_LEN - 8 {
+        message.push(0);
+    }
+    message.extend_from_slice(&bits.to_le_bytes());
+    for block in message.chunks_exact(BLOCK_LEN) {
+        compress(&mut state, block);
+    }
+
+    let mut digest = [0u8; 16];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state.iter()) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+impl HmacMd5Context {
+    /// Precompute the context for a password
+    ///
+    /// The password should have been prepared with SASLprep already, as the `CRAM-MD5` client
+    /// does before computing its response.
+    pub fn from_password(password: &[u8]) -> Self {
+        let mut key = [0u8; BLOCK_LEN];
+        if password.len() > BLOCK_LEN {
+            // Keys longer than a block are hashed first (RFC 2104, section 2)
+            key[..16].copy_from_slice(&Md5::digest(password));
         } else {
-            ('0' as i32) + (hash[i as usize] as libc::c_int & 0xf as libc::c_int)
-        } as libc::c_char;
-        i = i.wrapping_add(1)
+            key[..password.len()].copy_from_slice(password);
+        }
+
+        let pad = |byte: u8| {
+            let mut state = IV;
+            let block: Vec<u8> = key.iter().map(|k| k ^ byte).collect();
+            compress(&mut state, &block);
+            state
+        };
+        Self {
+            inner: pad(0x36),
+            outer: pad(0x5c),
+        }
+    }
+
+    /// Compute HMAC-MD5 of the message with the password this context was computed from
+    pub fn digest(&self, message: &[u8]) -> [u8; 16] {
+        let inner = finish_after_block(self.inner, message);
+        finish_after_block(self.outer, &inner)
+    }
+}
+
+/// HMAC-MD5 of the challenge keyed with the password
+pub(super) fn hmac_md5(password: &[u8], challenge: &[u8]) -> [u8; 16] {
+    let mut mac = HmacMd5::new_from_slice(password).expect("HMAC can take keys of any size");
+    mac.update(challenge);
+    mac.finalize().into_bytes().into()
+}
+
+pub(super) fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc2195_example() {
+        let challenge = b"<1896.697170952@postoffice.reston.mci.net>";
+        let digest = hmac_md5(b"tanstaaftanstaaf", challenge);
+        assert_eq!(to_hex(&digest), "b913a602c7eda7a495b4e6e7334d3890");
+
+        let context = HmacMd5Context::from_password(b"tanstaaftanstaaf");
+        assert_eq!(context.digest(challenge), digest);
+    }
+
+    #[test]
+    fn context_matches_hmac() {
+        let long = [b'x'; 100];
+        for password in [&b""[..], b"secret", &long[..BLOCK_LEN], &long[..]].iter() {
+            for message in [&b""[..], b"<1.2@localhost>", &long[..]].iter() {
+                let context = HmacMd5Context::from_password(password);
+                assert_eq!(context.digest(message), hmac_md5(password, message));
+            }
+        }
+    }
+
+    #[test]
+    fn stored_formats() {
+        let context = HmacMd5Context::from_password(b"secret");
+        let dovecot = context.to_dovecot();
+        assert!(dovecot.starts_with("{CRAM-MD5}"));
+        assert_eq!(HmacMd5Context::from_dovecot(&dovecot), Some(context));
+        assert_eq!(HmacMd5Context::from_dovecot(&dovecot[10..]), Some(context));
+        assert_eq!(
+            HmacMd5Context::from_cyrus(&context.to_cyrus()),
+            Some(context)
+        );
     }
 }
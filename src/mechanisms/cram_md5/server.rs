@@ -1,149 +1,143 @@
-use crate::gsasl::consts::{
-    GSASL_AUTHENTICATION_ERROR, GSASL_AUTHID, GSASL_CRYPTO_ERROR, GSASL_MALLOC_ERROR,
-    GSASL_MECHANISM_PARSE_ERROR, GSASL_NEEDS_MORE, GSASL_NO_PASSWORD, GSASL_OK,
-};
-use crate::gsasl::gl::free::rpl_free;
-use crate::gsasl::property::gsasl_property_set;
-use crate::gsasl::saslprep::{gsasl_saslprep, Gsasl_saslprep_flags};
-use crate::mechanisms::cram_md5::challenge::cram_md5_challenge;
-use crate::mechanisms::cram_md5::digest::cram_md5_digest;
-use crate::property::Password;
-use crate::session::SessionData;
-use crate::Shared;
-use ::libc;
-use libc::{c_char, calloc, malloc, memcmp, memcpy, size_t, strdup, strlen};
-use std::ffi::CString;
-use std::ptr::NonNull;
+use crate::error::{MechanismError, MechanismErrorKind, SessionError};
+use crate::mechanism::Authentication;
+use crate::mechanisms::cram_md5::digest::{hmac_md5, to_hex, DIGEST_LEN};
+use crate::property::{AuthId, CramMd5Secret, Hostname, Password};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+#[cfg(feature = "session_state")]
+use crate::state::{StateError, StateWriter};
+use rand::Rng;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::str::Utf8Error;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub(crate) unsafe fn _gsasl_cram_md5_server_start(
-    _ctx: &Shared,
-    mech_data: &mut Option<NonNull<()>>,
-) -> libc::c_int {
-    let challenge;
-    let rc;
-    challenge = malloc(35) as *mut libc::c_char;
-    if challenge.is_null() {
-        return GSASL_MALLOC_ERROR as libc::c_int;
-    }
-    rc = cram_md5_challenge(challenge);
-    if rc != 0 {
-        return GSASL_CRYPTO_ERROR as libc::c_int;
+#[derive(Debug)]
+pub(super) enum CramMd5Error {
+    BadFormat,
+    BadUsername(Utf8Error),
+    Saslprep(stringprep::Error),
+}
+
+impl Display for CramMd5Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadFormat => f.write_str(
+                "invalid format, expected a username and a hex digest separated by a space",
+            ),
+            Self::BadUsername(e) => write!(f, "username is invalid UTF-8: {}", e),
+            Self::Saslprep(e) => write!(f, "saslprep failed: {}", e),
+        }
     }
-    *mech_data = NonNull::new(challenge as *mut ());
-    return GSASL_OK as libc::c_int;
 }
 
-pub unsafe fn _gsasl_cram_md5_server_step(
-    sctx: &mut SessionData,
-    mech_data: Option<NonNull<()>>,
-    input: Option<&[u8]>,
-    output: *mut *mut libc::c_char,
-    output_len: *mut size_t,
-) -> libc::c_int {
-    let mech_data = mech_data
-        .map(|ptr| ptr.as_ptr())
-        .unwrap_or_else(std::ptr::null_mut);
+impl MechanismError for CramMd5Error {
+    fn kind(&self) -> MechanismErrorKind {
+        MechanismErrorKind::Parse
+    }
+}
 
-    let input_len = input.map(|i| i.len()).unwrap_or(0);
-    let input: *const libc::c_char = input.map(|i| i.as_ptr().cast()).unwrap_or(std::ptr::null());
+/// Server side of `CRAM-MD5`
+///
+/// The challenge has the form `<random.timestamp@hostname>` with the hostname taken from the
+/// [`Hostname`] property, defaulting to `localhost`. Responses are verified against the
+/// [`CramMd5Secret`] property if the callback provides one, and against the [`Password`]
+/// otherwise.
+#[derive(Debug, Default)]
+pub struct CramMd5 {
+    challenge: Option<String>,
+}
 
-    let challenge: *mut libc::c_char = mech_data as *mut libc::c_char;
-    let mut hash: [libc::c_char; 32] = [0; 32];
-    let username;
-    let mut res;
-    let mut normkey: *mut libc::c_char = 0 as *mut libc::c_char;
-    if input_len == 0 {
-        *output_len = strlen(challenge) as usize;
-        *output = strdup(challenge);
-        return GSASL_NEEDS_MORE as libc::c_int;
-    }
-    if input_len <= (16 * 2) {
-        return GSASL_MECHANISM_PARSE_ERROR as libc::c_int;
+impl CramMd5 {
+    pub fn new() -> Self {
+        Self::default()
     }
-    if *input.offset(input_len.wrapping_sub(16 * 2).wrapping_sub(1) as isize) != ' ' as c_char {
-        return GSASL_MECHANISM_PARSE_ERROR as libc::c_int;
+
+    #[cfg(feature = "session_state")]
+    pub(super) fn with_challenge(challenge: String) -> Self {
+        Self {
+            challenge: Some(challenge),
+        }
     }
-    username = calloc(1, input_len.wrapping_sub(16 * 2)) as *mut libc::c_char;
-    if username.is_null() {
-        return GSASL_MALLOC_ERROR as libc::c_int;
+
+    fn generate_challenge(session: &mut SessionData) -> Result<String, SessionError> {
+        let hostname = session
+            .get_property_or_callback::<Hostname>()?
+            .map(|hostname| hostname.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "localhost".to_string());
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+        let random: u64 = rand::thread_rng().gen();
+        Ok(format!("<{}.{}@{}>", random, timestamp, hostname))
     }
-    memcpy(
-        username as *mut libc::c_void,
-        input as *const libc::c_void,
-        input_len.wrapping_sub(16 * 2).wrapping_sub(1),
-    );
-    res = gsasl_property_set(sctx, GSASL_AUTHID, username);
-    rpl_free(username as *mut libc::c_void);
-    if res != GSASL_OK as libc::c_int {
-        return res;
+
+    fn expected_digest(
+        session: &mut SessionData,
+        challenge: &[u8],
+    ) -> Result<[u8; 16], SessionError> {
+        if let Some(secret) = session.get_property_or_callback::<CramMd5Secret>()? {
+            return Ok(secret.digest(challenge));
+        }
+        let password = session
+            .get_property_or_callback::<Password>()?
+            .ok_or(SessionError::no_property::<Password>())?;
+        let password = stringprep::saslprep(&password).map_err(CramMd5Error::Saslprep)?;
+        Ok(hmac_md5(password.as_bytes(), challenge))
     }
+}
 
-    if let Ok(Some(password)) = sctx.get_property_or_callback::<Password>() {
-        let cstr = CString::new(password.as_bytes().to_owned()).unwrap();
-        /* FIXME: Use SASLprep here?  Treat string as storage string?
-        Specification is unclear. */
-        res = gsasl_saslprep(
-            cstr.as_ptr(),
-            0 as Gsasl_saslprep_flags,
-            &mut normkey,
-            0 as *mut libc::c_int,
-        );
-        if res != GSASL_OK as libc::c_int {
-            return res;
+impl Authentication for CramMd5 {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        let response = match (input, &self.challenge) {
+            (Some(response), Some(_)) if !response.is_empty() => response,
+            (Some(response), None) if !response.is_empty() => {
+                return Err(CramMd5Error::BadFormat.into())
+            }
+            _ => {
+                if self.challenge.is_none() {
+                    self.challenge = Some(Self::generate_challenge(session)?);
+                }
+                let challenge = self.challenge.as_ref().unwrap();
+                writer.write_all(challenge.as_bytes())?;
+                return Ok(NeedsMore(Some(challenge.len())));
+            }
+        };
+        let challenge = self.challenge.take().unwrap();
+
+        if response.len() <= DIGEST_LEN || response[response.len() - DIGEST_LEN - 1] != b' ' {
+            return Err(CramMd5Error::BadFormat.into());
+        }
+        let (username, digest) = response.split_at(response.len() - DIGEST_LEN - 1);
+        let username = std::str::from_utf8(username).map_err(CramMd5Error::BadUsername)?;
+        session.set_property::<AuthId>(Arc::new(username.to_string()));
+
+        let expected = to_hex(&Self::expected_digest(session, challenge.as_bytes())?);
+        // Compare without short-circuiting to not leak how much of the digest matched
+        let difference = expected
+            .bytes()
+            .zip(digest[1..].iter())
+            .fold(0, |acc, (a, b)| acc | (a ^ b.to_ascii_lowercase()));
+        if difference != 0 {
+            return Err(SessionError::AuthenticationFailure);
         }
-    } else {
-        return GSASL_NO_PASSWORD as libc::c_int;
+        Ok(Done(None))
     }
 
-    cram_md5_digest(
-        challenge,
-        strlen(challenge) as usize,
-        normkey,
-        strlen(normkey) as usize,
-        hash.as_mut_ptr(),
-    );
-    rpl_free(normkey as *mut libc::c_void);
-    if memcmp(
-        &*input.offset(input_len.wrapping_sub(32) as isize) as *const libc::c_char
-            as *const libc::c_void,
-        hash.as_mut_ptr() as *const libc::c_void,
-        2 * 16,
-    ) == 0
-    {
-        res = GSASL_OK as libc::c_int
-    } else {
-        res = GSASL_AUTHENTICATION_ERROR as libc::c_int
+    #[cfg(feature = "session_state")]
+    fn export_state(&self, writer: &mut StateWriter) -> Result<(), StateError> {
+        writer.put_optional(
+            self.challenge
+                .as_ref()
+                .map(|challenge| challenge.as_bytes()),
+        );
+        Ok(())
     }
-    *output_len = 0 as libc::c_int as size_t;
-    *output = 0 as *mut libc::c_char;
-    return res;
-}
-/* cram-md5.h --- Prototypes for CRAM-MD5 mechanism as defined in RFC 2195.
- * Copyright (C) 2002-2021 Simon Josefsson
- *
- * This file is part of GNU SASL Library.
- *
- * GNU SASL Library is free software; you can redistribute it and/or
- * modify it under the terms of the GNU Lesser General Public License
- * as published by the Free Software Foundation; either version 2.1 of
- * the License, or (at your option) any later version.
- *
- * GNU SASL Library is distributed in the hope that it will be useful,
- * but WITHOUT ANY WARRANTY; without even the implied warranty of
- * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
- * Lesser General Public License for more details.
- *
- * You should have received a copy of the GNU Lesser General Public
- * License along with GNU SASL Library; if not, write to the Free
- * Free Software Foundation, Inc., 51 Franklin Street, Fifth Floor,
- * Boston, MA 02110-1301, USA.
- *
- */
-#[no_mangle]
-pub unsafe fn _gsasl_cram_md5_server_finish(mech_data: Option<NonNull<()>>) {
-    let mech_data = mech_data
-        .map(|ptr| ptr.as_ptr())
-        .unwrap_or_else(std::ptr::null_mut);
-    let challenge: *mut libc::c_char = mech_data as *mut libc::c_char;
-    rpl_free(challenge as *mut libc::c_void);
 }
@@ -0,0 +1,18 @@
+//! Resuming the state of the server side, see [`crate::state`]
+
+use crate::mechanism::Authentication;
+use crate::mechanisms::cram_md5::server::CramMd5;
+use crate::state::{StateError, StateReader};
+
+pub(crate) fn import_server(
+    reader: &mut StateReader,
+) -> Result<Box<dyn Authentication>, StateError> {
+    // The challenge is all the state there is
+    match reader.get_optional()? {
+        Some(challenge) => {
+            let challenge = std::str::from_utf8(challenge).map_err(|_| StateError::Invalid)?;
+            Ok(Box::new(CramMd5::with_challenge(challenge.to_string())))
+        }
+        None => Ok(Box::new(CramMd5::new())),
+    }
+}
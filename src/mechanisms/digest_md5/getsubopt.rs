@@ -1,5 +1,5 @@
 use ::libc;
-use libc::{memchr, memcmp, size_t};
+use libc::{memchr, size_t, strncmp};
 
 /* getsubopt.c --- Parse comma separate list into words, DIGEST-MD5 style.
  * Copyright (C) 2002-2021 Simon Josefsson
@@ -72,9 +72,10 @@ pub unsafe fn digest_md5_getsubopt(
     one of the TOKENS.  */
     cnt = 0 as libc::c_int;
     while !(*tokens.offset(cnt as isize)).is_null() {
-        if memcmp(
-            *optionp as *const libc::c_void,
-            *tokens.offset(cnt as isize) as *const libc::c_void,
+        // strncmp instead of memcmp since the option may be longer than the token
+        if strncmp(
+            *optionp,
+            *tokens.offset(cnt as isize),
             vstart.offset_from(*optionp) as size_t,
         ) == 0
             && *(*tokens.offset(cnt as isize))
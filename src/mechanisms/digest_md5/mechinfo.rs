@@ -7,6 +7,10 @@ use crate::mechanisms::digest_md5::server::{
     _gsasl_digest_md5_server_decode, _gsasl_digest_md5_server_encode,
     _gsasl_digest_md5_server_finish, _gsasl_digest_md5_server_start, _gsasl_digest_md5_server_step,
 };
+use crate::property::properties::{
+    AUTHID, AUTHZID, DIGEST_MD5_HASHED_PASSWORD, HOSTNAME, PASSWORD, QOP, QOPS, REALM, SERVICE,
+};
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
 use crate::{Mechanism, Mechname, Side};
 
 #[cfg(feature = "registry_static")]
@@ -38,4 +42,23 @@ pub static DIGEST_MD5: Mechanism = Mechanism {
         })
     }),
     first: Side::Server,
+    client_properties: PropertyRequirements::new(
+        &[AUTHID, PASSWORD, SERVICE, HOSTNAME],
+        &[AUTHZID, QOP, REALM],
+    ),
+    server_properties: PropertyRequirements::new(
+        &[PASSWORD],
+        &[DIGEST_MD5_HASHED_PASSWORD, REALM, QOPS],
+    ),
+    description: "HTTP Digest challenge-response",
+    specification: Some("RFC 2831"),
+    channel_binding: false,
+    // The C integrity and confidentiality layers are not exposed through `CMechanismStateKeeper`
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: true,
+        weak: true,
+    },
 };
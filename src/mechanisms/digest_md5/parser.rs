@@ -224,6 +224,8 @@ unsafe fn parse_challenge(
     }
     while *challenge as libc::c_int != '\u{0}' as i32 {
         match digest_md5_getsubopt(&mut challenge, digest_challenge_opts.as_ptr(), &mut value) {
+            // All known directives require a value
+            n if n >= 0 && value.is_null() => return -(1 as libc::c_int),
             0 => {
                 let tmp;
                 (*out).nrealms = (*out).nrealms.wrapping_add(1);
@@ -423,6 +425,8 @@ unsafe fn parse_response(
     }
     while *response as libc::c_int != '\u{0}' as i32 {
         match digest_md5_getsubopt(&mut response, digest_response_opts.as_ptr(), &mut value) {
+            // All known directives require a value
+            n if n >= 0 && value.is_null() => return -(1 as libc::c_int),
             0 => {
                 /* This directive is required and MUST be present exactly
                 once; otherwise, authentication fails. */
@@ -629,6 +633,8 @@ unsafe fn parse_finish(
     }
     while *finish as libc::c_int != '\u{0}' as i32 {
         match digest_md5_getsubopt(&mut finish, digest_responseauth_opts.as_ptr(), &mut value) {
+            // All known directives require a value
+            n if n >= 0 && value.is_null() => return -(1 as libc::c_int),
             0 => {
                 if *(*out).rspauth.as_mut_ptr() != 0 {
                     return -(1 as libc::c_int);
@@ -654,10 +660,12 @@ pub unsafe fn digest_md5_parse_challenge(
     len: size_t,
     out: *mut digest_md5_challenge,
 ) -> libc::c_int {
+    // Input comes from a Rust slice, so it's neither NUL-terminated nor is the pointer valid if
+    // `len` is zero.
     let subopts: *mut libc::c_char = if len != 0 {
         strndup(challenge, len)
     } else {
-        strdup(challenge)
+        strdup(b"\x00" as *const u8 as *const libc::c_char)
     };
     let rc;
     if subopts.is_null() {
@@ -673,10 +681,12 @@ pub unsafe fn digest_md5_parse_response(
     len: size_t,
     out: *mut digest_md5_response,
 ) -> libc::c_int {
+    // Input comes from a Rust slice, so it's neither NUL-terminated nor is the pointer valid if
+    // `len` is zero.
     let subopts: *mut libc::c_char = if len != 0 {
         strndup(response, len)
     } else {
-        strdup(response)
+        strdup(b"\x00" as *const u8 as *const libc::c_char)
     };
     let rc;
     if subopts.is_null() {
@@ -692,10 +702,12 @@ pub unsafe fn digest_md5_parse_finish(
     len: size_t,
     out: *mut digest_md5_finish,
 ) -> libc::c_int {
+    // Input comes from a Rust slice, so it's neither NUL-terminated nor is the pointer valid if
+    // `len` is zero.
     let subopts: *mut libc::c_char = if len != 0 {
         strndup(finish, len)
     } else {
-        strdup(finish)
+        strdup(b"\x00" as *const u8 as *const libc::c_char)
     };
     let rc;
     if subopts.is_null() {
@@ -1,9 +1,13 @@
 use crate::mechanism::Authentication;
-use crate::property::AuthId;
+use crate::property::AuthzId;
 use crate::session::Step::Done;
 use crate::session::{SessionData, StepResult};
 use std::io::Write;
 
+/// Client side of `EXTERNAL`
+///
+/// The message is the [`AuthzId`] to act as, or empty to use the identity the server derives
+/// from the external authentication.
 #[derive(Copy, Clone, Debug)]
 pub struct External;
 
@@ -14,12 +18,12 @@ impl Authentication for External {
         _input: Option<&[u8]>,
         writer: &mut dyn Write,
     ) -> StepResult {
-        if let Some(authid) = session.get_property_or_callback::<AuthId>()? {
-            let buf = authid.as_bytes();
+        if let Some(authzid) = session.get_property_or_callback::<AuthzId>()? {
+            let buf = authzid.as_bytes();
             writer.write_all(buf)?;
             Ok(Done(Some(buf.len())))
         } else {
-            Ok(Done(None))
+            Ok(Done(Some(0)))
         }
     }
 }
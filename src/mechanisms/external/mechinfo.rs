@@ -1,4 +1,6 @@
 use crate::mechanisms::external::{client, server};
+use crate::property::properties::AUTHZID;
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
 use crate::{Mechanism, Mechname, Side};
 
 #[cfg(feature = "registry_static")]
@@ -10,4 +12,16 @@ pub static EXTERNAL: Mechanism = Mechanism {
     client: Some(|_sasl| Ok(Box::new(client::External))),
     server: Some(|_sasl| Ok(Box::new(server::External))),
     first: Side::Client,
+    client_properties: PropertyRequirements::new(&[], &[AUTHZID]),
+    server_properties: PropertyRequirements::NONE,
+    description: "Authentication established outside of SASL, e.g. with a TLS client certificate",
+    specification: Some("RFC 4422"),
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: false,
+        weak: false,
+    },
 };
@@ -0,0 +1,73 @@
+//! Reading the credentials of the peer of a Unix domain socket
+
+use crate::property::UnixCredentials;
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+impl UnixCredentials {
+    /// Credentials of the process connected to the Unix domain socket `socket`
+    ///
+    /// Uses `SO_PEERCRED` on Linux and Android, and `getpeereid` on macOS and the BSDs which
+    /// don't report the process ID.
+    ///
+    /// ```no_run
+    /// # use std::os::unix::net::UnixListener;
+    /// # use std::sync::Arc;
+    /// # use rsasl::mechname::Mechname;
+    /// use rsasl::property::{PeerCredentials, UnixCredentials};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let listener = UnixListener::bind("/run/admin.sock")?;
+    /// # let sasl = rsasl::SASL::new();
+    /// let (stream, _) = listener.accept()?;
+    /// let mut session = sasl.server_start(Mechname::new(b"EXTERNAL").unwrap()).unwrap();
+    /// session.set_property::<PeerCredentials>(Arc::new(UnixCredentials::of(&stream)?));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn of(socket: &impl AsRawFd) -> io::Result<Self> {
+        peer_credentials(socket.as_raw_fd())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_credentials(fd: libc::c_int) -> io::Result<UnixCredentials> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: `cred` is valid for writes of `len` bytes for the duration of the call
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(UnixCredentials {
+        uid: cred.uid,
+        gid: cred.gid,
+        pid: Some(cred.pid),
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_credentials(fd: libc::c_int) -> io::Result<UnixCredentials> {
+    let mut uid = 0;
+    let mut gid = 0;
+    // SAFETY: both pointers are valid for writes for the duration of the call
+    if unsafe { libc::getpeereid(fd, &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(UnixCredentials {
+        uid,
+        gid,
+        pid: None,
+    })
+}
@@ -1,7 +1,7 @@
 use crate::error::{MechanismError, MechanismErrorKind};
 use crate::mechanism::Authentication;
-use crate::property::AuthId;
-use crate::session::Step::Done;
+use crate::property::AuthzId;
+use crate::session::Step::{Done, NeedsMore};
 use crate::session::{SessionData, StepResult};
 use crate::validate::validations::EXTERNAL;
 use std::fmt::{Display, Formatter};
@@ -12,7 +12,7 @@ use std::sync::Arc;
 pub struct ParseError;
 impl Display for ParseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str("the given external token is invalid UTF-8")
+        f.write_str("the given authorization identity is invalid UTF-8")
     }
 }
 impl MechanismError for ParseError {
@@ -21,6 +21,10 @@ impl MechanismError for ParseError {
     }
 }
 
+/// Server side of `EXTERNAL`
+///
+/// Sets the [`AuthzId`] the client requested, if any, and validates with [`EXTERNAL`]. See its
+/// documentation for how to make the external authentication available to the validation.
 #[derive(Copy, Clone, Debug)]
 pub struct External;
 
@@ -31,12 +35,18 @@ impl Authentication for External {
         input: Option<&[u8]>,
         _writer: &mut dyn Write,
     ) -> StepResult {
-        if let Some(input) = input {
-            if let Ok(authid) = std::str::from_utf8(input) {
-                session.set_property::<AuthId>(Arc::new(authid.to_string()));
-            } else {
-                return Err(ParseError.into());
-            }
+        let input = if let Some(buf) = input {
+            buf
+        } else {
+            return Ok(NeedsMore(None));
+        };
+
+        if input.is_empty() {
+            session.clear_property::<AuthzId>();
+        } else if let Ok(authzid) = std::str::from_utf8(input) {
+            session.set_property::<AuthzId>(Arc::new(authzid.to_string()));
+        } else {
+            return Err(ParseError.into());
         }
 
         session.validate(EXTERNAL)?;
@@ -0,0 +1,125 @@
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::gs2::context::{GssContext, GssMechanism, GssStep};
+use crate::mechanisms::gs2::protocol::{strip_header, Gs2Error};
+use crate::mechanisms::gs2_header::{self, CbFlag};
+use crate::property::AuthzId;
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use std::fmt::{Debug, Formatter};
+use std::io::Write;
+
+/// Client side of a `GS2-*` mechanism
+///
+/// Prefixes the initial context token with the GS2 header, requesting the [`AuthzId`] if one is
+/// provided. The `-PLUS` variant binds the context to the channel binding data set on the session
+/// and fails without. Otherwise channel binding data on the session only signals that the client
+/// supports channel binding.
+pub struct Gs2<M: GssMechanism> {
+    plus: bool,
+    context: Option<M::Client>,
+    completed: bool,
+}
+
+impl<M: GssMechanism> Debug for Gs2<M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Gs2")
+            .field("plus", &self.plus)
+            .field("started", &self.context.is_some())
+            .field("completed", &self.completed)
+            .finish()
+    }
+}
+
+impl<M: GssMechanism> Gs2<M> {
+    pub fn new(plus: bool) -> Self {
+        Self {
+            plus,
+            context: None,
+            completed: false,
+        }
+    }
+
+    fn start(
+        &mut self,
+        session: &mut SessionData,
+        writer: &mut dyn Write,
+    ) -> Result<(bool, Option<usize>), SessionError> {
+        let cb = session
+            .get_cb_data()
+            .map(|(name, data)| (name, data.to_vec()));
+        let cbflag = match (self.plus, cb.as_ref()) {
+            (true, Some((name, _))) => CbFlag::Used(name),
+            (true, None) => return Err(Gs2Error::NoChannelBinding.into()),
+            (false, Some(_)) => CbFlag::SupportedNotUsed,
+            (false, None) => CbFlag::NotSupported,
+        };
+        let authzid = session.get_property_or_callback::<AuthzId>()?;
+        let header = gs2_header::encode(cbflag, authzid.as_deref().map(String::as_str))?;
+
+        let mut channel_bindings = header.clone().into_bytes();
+        if let (CbFlag::Used(_), Some((_, data))) = (cbflag, cb.as_ref()) {
+            channel_bindings.extend_from_slice(data);
+        }
+        let mut context = M::client(session, &channel_bindings)?;
+        let step = context.step(session, None)?;
+        self.context = Some(context);
+
+        let (continues, token) = match &step {
+            GssStep::Continue(token) => (true, token),
+            GssStep::Complete(Some(token)) => (false, token),
+            GssStep::Complete(None) => return Err(Gs2Error::MissingToken.into()),
+        };
+        let mut written = 0;
+        let token = match strip_header(M::OID, token) {
+            Some(inner) => inner,
+            None => {
+                writer.write_all(b"F,")?;
+                written += 2;
+                &token[..]
+            }
+        };
+        writer.write_all(header.as_bytes())?;
+        writer.write_all(token)?;
+        written += header.len() + token.len();
+        Ok((continues, Some(written)))
+    }
+}
+
+impl<M: GssMechanism> Authentication for Gs2<M> {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        if self.completed {
+            return Err(Gs2Error::Completed.into());
+        }
+        // Any error ends the exchange
+        self.completed = true;
+        let (continues, written) = match self.context.as_mut() {
+            None => self.start(session, writer)?,
+            Some(context) => {
+                let input = input.ok_or(SessionError::InputDataRequired)?;
+                match context.step(session, Some(input))? {
+                    GssStep::Continue(token) => {
+                        writer.write_all(&token)?;
+                        (true, Some(token.len()))
+                    }
+                    GssStep::Complete(Some(token)) => {
+                        writer.write_all(&token)?;
+                        (false, Some(token.len()))
+                    }
+                    GssStep::Complete(None) => (false, None),
+                }
+            }
+        };
+        if continues {
+            self.completed = false;
+            Ok(NeedsMore(written))
+        } else {
+            Ok(Done(written))
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! The interface to GSS-API mechanisms
+//!
+//! A GSS-API mechanism is plugged into GS2 by implementing [`GssMechanism`] with one
+//! [`GssContext`] for each side, usually wrapping `GSS_Init_sec_context` and
+//! `GSS_Accept_sec_context` of a GSS-API library or a native implementation of the mechanism.
+
+use crate::error::SessionError;
+use crate::session::SessionData;
+
+/// Outcome of a single call establishing a security context
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum GssStep {
+    /// `GSS_S_CONTINUE_NEEDED`, the token has to be sent and another one is expected from the peer
+    Continue(Vec<u8>),
+    /// `GSS_S_COMPLETE`, the context is established. A final token has to be sent if there is one.
+    Complete(Option<Vec<u8>>),
+}
+
+/// One side of a security context being established
+pub trait GssContext {
+    /// Process the token received from the peer
+    ///
+    /// `token` is `None` only for the first call of the initiator. Initial context tokens are
+    /// passed to and returned from this method with the standard header of
+    /// [RFC 2743, section 3.1](https://www.rfc-editor.org/rfc/rfc2743#section-3.1) if the
+    /// mechanism uses it, the GS2 framing is handled by the mechanism. Errors abort the exchange,
+    /// a failure to authenticate the peer should be reported as
+    /// [`SessionError::AuthenticationFailure`].
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        token: Option<&[u8]>,
+    ) -> Result<GssStep, SessionError>;
+}
+
+/// A GSS-API mechanism to be offered as `GS2-*` SASL mechanisms
+///
+/// Register it with [`register`](super::mechinfo::register).
+pub trait GssMechanism: 'static {
+    /// The OID of the mechanism, DER encoded without tag and length
+    ///
+    /// These are the bytes of the `elements` of a `gss_OID`, e.g. `b"\x2b\x06\x01\x05\x05\x01\x01"`
+    /// for `1.3.6.1.5.5.1.1`.
+    const OID: &'static [u8];
+
+    type Client: GssContext + 'static;
+    type Server: GssContext + 'static;
+
+    /// Start establishing a context as initiator
+    ///
+    /// The context MUST request mutual authentication and MUST NOT request delegation.
+    /// `channel_bindings` is the application data of the channel bindings, both initiator and
+    /// acceptor address are empty.
+    fn client(
+        session: &mut SessionData,
+        channel_bindings: &[u8],
+    ) -> Result<Self::Client, SessionError>;
+
+    /// Start establishing a context as acceptor
+    ///
+    /// `channel_bindings` is the application data the initiator's channel bindings must match.
+    /// [`AuthzId`](crate::property::AuthzId) is already set if the client requested one. The
+    /// context should set the properties naming the initiator before it completes, which are then
+    /// checked with the [`GS2`](crate::validate::validations::GS2) validation.
+    fn server(
+        session: &mut SessionData,
+        channel_bindings: &[u8],
+    ) -> Result<Self::Server, SessionError>;
+}
@@ -0,0 +1,99 @@
+use crate::mechanism::Authentication;
+use crate::mechanisms::gs2::context::GssMechanism;
+use crate::mechanisms::gs2::protocol;
+use crate::mechanisms::gs2::{client, server};
+use crate::mechname::Mechname;
+use crate::property::properties::AUTHZID;
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
+use crate::{Mechanism, SASLError, Side, SASL};
+
+/// Priority of `GS2-*`, above the password based mechanisms
+pub const GS2_PRIORITY: usize = 800;
+/// Priority of `GS2-*-PLUS`
+pub const GS2_PLUS_PRIORITY: usize = 900;
+
+/// The SASL mechanism name of a GSS-API mechanism, without the `-PLUS` suffix
+///
+/// Computed from the OID as described in
+/// [RFC 5801, section 3.1](https://www.rfc-editor.org/rfc/rfc5801#section-3.1), with `oid` as
+/// in [`GssMechanism::OID`].
+///
+/// ```
+/// # use rsasl::mechanisms::gs2::mechinfo::mechanism_name;
+/// assert_eq!(mechanism_name(b"\x2b\x06\x01\x05\x05\x01\x01"), "GS2-DT4PIK22T6A");
+/// ```
+pub fn mechanism_name(oid: &[u8]) -> String {
+    protocol::mechanism_name(oid)
+}
+
+/// Properties of the client side, those of the GSS-API mechanism itself aren't known
+const PROPERTIES: PropertyRequirements = PropertyRequirements::new(&[], &[AUTHZID]);
+
+/// GS2 always requests mutual authentication and doesn't use the security layers of GSS-API
+const SECURITY: MechanismSecurityFactors = MechanismSecurityFactors {
+    max_ssf: 0,
+    noplain: true,
+    mutual: true,
+    weak: false,
+};
+
+fn client<M: GssMechanism>(_sasl: &SASL) -> Result<Box<dyn Authentication>, SASLError> {
+    Ok(Box::new(client::Gs2::<M>::new(false)))
+}
+
+fn client_plus<M: GssMechanism>(_sasl: &SASL) -> Result<Box<dyn Authentication>, SASLError> {
+    Ok(Box::new(client::Gs2::<M>::new(true)))
+}
+
+fn server<M: GssMechanism>(_sasl: &SASL) -> Result<Box<dyn Authentication>, SASLError> {
+    Ok(Box::new(server::Gs2::<M>::new(false)))
+}
+
+fn server_plus<M: GssMechanism>(_sasl: &SASL) -> Result<Box<dyn Authentication>, SASLError> {
+    Ok(Box::new(server::Gs2::<M>::new(true)))
+}
+
+/// Register `GS2-*` and `GS2-*-PLUS` for a GSS-API mechanism
+///
+/// The mechanism names are computed with [`mechanism_name`]. Registering allocates the names and
+/// mechanism descriptions for the lifetime of the program, so a mechanism should be registered
+/// once per [`SASL`] and not for each session.
+pub fn register<M: GssMechanism>(sasl: &mut SASL) {
+    let name = mechanism_name(M::OID);
+    let plus = format!("{}-PLUS", name);
+    let gs2 = Mechanism {
+        mechanism: leak_name(name),
+        priority: GS2_PRIORITY,
+        client: Some(client::<M>),
+        server: Some(server::<M>),
+        first: Side::Client,
+        client_properties: PROPERTIES,
+        server_properties: PropertyRequirements::NONE,
+        description: "GSS-API mechanism used through the GS2 bridge",
+        specification: Some("RFC 5801"),
+        channel_binding: false,
+        security_layer: false,
+        security: SECURITY,
+    };
+    let gs2_plus = Mechanism {
+        mechanism: leak_name(plus),
+        priority: GS2_PLUS_PRIORITY,
+        client: Some(client_plus::<M>),
+        server: Some(server_plus::<M>),
+        first: Side::Client,
+        client_properties: PROPERTIES,
+        server_properties: PropertyRequirements::NONE,
+        description: "GSS-API mechanism used through the GS2 bridge, bound to the TLS connection",
+        specification: Some("RFC 5801"),
+        channel_binding: true,
+        security_layer: false,
+        security: SECURITY,
+    };
+    sasl.register(Box::leak(Box::new(gs2)));
+    sasl.register(Box::leak(Box::new(gs2_plus)));
+}
+
+fn leak_name(name: String) -> &'static Mechname {
+    // The name is `GS2-`, 11 base32 characters and at most `-PLUS`, so always valid
+    Mechname::new_unchecked(Box::leak(name.into_boxed_str()))
+}
@@ -0,0 +1,164 @@
+//! Framing of context tokens and errors shared by both sides of `GS2-*`
+
+use crate::error::{MechanismError, MechanismErrorKind};
+use std::fmt::{Display, Formatter};
+
+/// Tag of the `[APPLICATION 0]` initial context token header
+const INITIAL_CONTEXT_TOKEN: u8 = 0x60;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+
+#[derive(Debug)]
+pub(super) enum Gs2Error {
+    /// The initiator didn't produce an initial context token
+    MissingToken,
+    /// `-PLUS` was selected but there is no channel binding data
+    NoChannelBinding,
+    /// The client requested a channel binding type that isn't available
+    ChannelBindingType,
+    /// The client requested channel binding with a mechanism not supporting it
+    ChannelBindingRequested,
+    /// The client selected `-PLUS` without channel binding
+    ChannelBindingRequired,
+    /// The client supports channel binding but thinks the server doesn't, while it does
+    DowngradeDetected,
+    Completed,
+}
+
+impl Display for Gs2Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingToken => f.write_str("GSS-API mechanism produced no initial token"),
+            Self::NoChannelBinding => f.write_str("no channel binding data available"),
+            Self::ChannelBindingType => f.write_str("requested channel binding type unavailable"),
+            Self::ChannelBindingRequested => {
+                f.write_str("channel binding requested but not supported by the mechanism")
+            }
+            Self::ChannelBindingRequired => f.write_str("channel binding required but not used"),
+            Self::DowngradeDetected => {
+                f.write_str("client thinks the server doesn't support channel binding")
+            }
+            Self::Completed => f.write_str("the exchange is already complete"),
+        }
+    }
+}
+
+impl MechanismError for Gs2Error {
+    fn kind(&self) -> MechanismErrorKind {
+        MechanismErrorKind::Protocol
+    }
+}
+
+/// The mechanism name of the OID, `GS2-` followed by the first 55 bits of the SHA-1 hash of the
+/// DER encoded OID in base32
+pub(super) fn mechanism_name(oid: &[u8]) -> String {
+    use sha1::{Digest, Sha1};
+    const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    let hash = Sha1::new()
+        .chain_update([OBJECT_IDENTIFIER])
+        .chain_update(der_length(oid.len()))
+        .chain_update(oid)
+        .finalize();
+    let mut bits = [0; 8];
+    bits[1..].copy_from_slice(&hash[..7]);
+    let bits = u64::from_be_bytes(bits) >> 1;
+    let mut name = String::from("GS2-");
+    for i in (0..11).rev() {
+        name.push(BASE32[(bits >> (5 * i)) as usize & 0x1f] as char);
+    }
+    name
+}
+
+fn der_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        return vec![len as u8];
+    }
+    let bytes = len.to_be_bytes();
+    let skip = bytes.iter().take_while(|byte| **byte == 0).count();
+    let mut out = vec![0x80 | (bytes.len() - skip) as u8];
+    out.extend_from_slice(&bytes[skip..]);
+    out
+}
+
+/// Parse a DER length, returning it and the remaining input
+fn parse_der_length(input: &[u8]) -> Option<(usize, &[u8])> {
+    let (&first, rest) = input.split_first()?;
+    if first < 0x80 {
+        return Some((first as usize, rest));
+    }
+    let count = (first & 0x7f) as usize;
+    if count == 0 || count > std::mem::size_of::<usize>() || rest.len() < count {
+        return None;
+    }
+    let (bytes, rest) = rest.split_at(count);
+    let len = bytes
+        .iter()
+        .fold(0usize, |len, byte| (len << 8) | *byte as usize);
+    Some((len, rest))
+}
+
+/// Remove the standard header of an initial context token for the mechanism `oid`
+///
+/// Returns `None` if the token doesn't have the header, in which case it is sent as is with the
+/// `F` flag set.
+pub(super) fn strip_header<'a>(oid: &[u8], token: &'a [u8]) -> Option<&'a [u8]> {
+    let inner = token.strip_prefix(&[INITIAL_CONTEXT_TOKEN])?;
+    let (len, inner) = parse_der_length(inner)?;
+    if inner.len() != len {
+        return None;
+    }
+    let inner = inner.strip_prefix(&[OBJECT_IDENTIFIER])?;
+    let (len, inner) = parse_der_length(inner)?;
+    if len != oid.len() {
+        return None;
+    }
+    inner.strip_prefix(oid)
+}
+
+/// Restore the standard header of an initial context token for the mechanism `oid`
+pub(super) fn add_header(oid: &[u8], inner: &[u8]) -> Vec<u8> {
+    let mut body = vec![OBJECT_IDENTIFIER];
+    body.extend(der_length(oid.len()));
+    body.extend_from_slice(oid);
+    body.extend_from_slice(inner);
+
+    let mut token = vec![INITIAL_CONTEXT_TOKEN];
+    token.extend(der_length(body.len()));
+    token.extend(body);
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPKM1: &[u8] = b"\x2b\x06\x01\x05\x05\x01\x01";
+    const KRB5: &[u8] = b"\x2a\x86\x48\x86\xf7\x12\x01\x02\x02";
+
+    #[test]
+    fn mechanism_names() {
+        // The example of RFC 5801, section 3.1
+        assert_eq!(mechanism_name(SPKM1), "GS2-DT4PIK22T6A");
+        assert_eq!(mechanism_name(KRB5), "GS2-QLJHGJLWNPL");
+    }
+
+    #[test]
+    fn framing() {
+        for len in [0, 10, 200, 70000].iter() {
+            let inner = vec![0xab; *len];
+            let token = add_header(KRB5, &inner);
+            assert_eq!(strip_header(KRB5, &token), Some(&inner[..]));
+            assert_eq!(strip_header(SPKM1, &token), None);
+        }
+        assert_eq!(
+            add_header(SPKM1, b"x"),
+            b"\x60\x0a\x06\x07\x2b\x06\x01\x05\x05\x01\x01x"
+        );
+        assert_eq!(
+            strip_header(SPKM1, b"\x60\x0b\x06\x07\x2b\x06\x01\x05\x05\x01\x01x"),
+            None
+        );
+        assert_eq!(strip_header(SPKM1, b"token"), None);
+        assert_eq!(strip_header(SPKM1, b""), None);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::gs2::context::{GssContext, GssMechanism, GssStep};
+use crate::mechanisms::gs2::protocol::{add_header, Gs2Error};
+use crate::mechanisms::gs2_header::{parse_header, CbFlag};
+use crate::property::AuthzId;
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use crate::validate::validations::GS2;
+use std::fmt::{Debug, Formatter};
+use std::io::Write;
+use std::sync::Arc;
+
+/// Server side of a `GS2-*` mechanism
+///
+/// Sets [`AuthzId`] from the GS2 header before starting the context and issues the
+/// [`GS2`](crate::validate::validations::GS2) validation once it is established. The `-PLUS`
+/// variant requires the client to bind to the channel binding data set on the session. Without
+/// `-PLUS` a client signalling channel binding support is rejected if the session has channel
+/// binding data, as the server would then have offered `-PLUS` too.
+pub struct Gs2<M: GssMechanism> {
+    plus: bool,
+    context: Option<M::Server>,
+    completed: bool,
+}
+
+impl<M: GssMechanism> Debug for Gs2<M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Gs2")
+            .field("plus", &self.plus)
+            .field("started", &self.context.is_some())
+            .field("completed", &self.completed)
+            .finish()
+    }
+}
+
+impl<M: GssMechanism> Gs2<M> {
+    pub fn new(plus: bool) -> Self {
+        Self {
+            plus,
+            context: None,
+            completed: false,
+        }
+    }
+
+    fn start(&mut self, session: &mut SessionData, input: &[u8]) -> Result<GssStep, SessionError> {
+        let (header, token) = parse_header(input)?;
+        if token.is_empty() {
+            return Err(Gs2Error::MissingToken.into());
+        }
+
+        let mut channel_bindings = header.raw.to_vec();
+        match (self.plus, header.cbflag, session.get_cb_data()) {
+            (true, CbFlag::Used(name), Some((cb_name, data))) if name == cb_name => {
+                channel_bindings.extend_from_slice(data);
+            }
+            (true, CbFlag::Used(_), _) => return Err(Gs2Error::ChannelBindingType.into()),
+            (true, _, _) => return Err(Gs2Error::ChannelBindingRequired.into()),
+            (false, CbFlag::Used(_), _) => return Err(Gs2Error::ChannelBindingRequested.into()),
+            (false, CbFlag::SupportedNotUsed, Some(_)) => {
+                return Err(Gs2Error::DowngradeDetected.into())
+            }
+            (false, _, _) => {}
+        }
+
+        match header.authzid() {
+            Some(authzid) => {
+                session.set_property::<AuthzId>(Arc::new(authzid.into_owned()));
+            }
+            None => session.clear_property::<AuthzId>(),
+        }
+
+        let token = if header.nonstd {
+            token.to_vec()
+        } else {
+            add_header(M::OID, token)
+        };
+        let mut context = M::server(session, &channel_bindings)?;
+        let step = context.step(session, Some(&token))?;
+        self.context = Some(context);
+        Ok(step)
+    }
+}
+
+impl<M: GssMechanism> Authentication for Gs2<M> {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        if self.completed {
+            return Err(Gs2Error::Completed.into());
+        }
+        let step = match (self.context.as_mut(), input) {
+            (None, None) | (None, Some(&[])) => return Ok(NeedsMore(None)),
+            // Any error ends the exchange
+            (None, Some(input)) => {
+                self.completed = true;
+                self.start(session, input)?
+            }
+            (Some(context), input) => {
+                self.completed = true;
+                let input = input.ok_or(SessionError::InputDataRequired)?;
+                context.step(session, Some(input))?
+            }
+        };
+        match step {
+            GssStep::Continue(token) => {
+                writer.write_all(&token)?;
+                self.completed = false;
+                Ok(NeedsMore(Some(token.len())))
+            }
+            GssStep::Complete(token) => {
+                session.validate(GS2)?;
+                match token {
+                    Some(token) => {
+                        writer.write_all(&token)?;
+                        Ok(Done(Some(token.len())))
+                    }
+                    None => Ok(Done(None)),
+                }
+            }
+        }
+    }
+}
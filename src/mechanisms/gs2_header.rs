@@ -0,0 +1,413 @@
+//! The GS2 header prefixing the initial client message of GS2 style mechanisms
+//!
+//! `SCRAM-*`, `GS2-*`, `SAML20` and `OPENID20` all start the exchange with a GS2 header as
+//! described in [RFC 5801, section 4](https://www.rfc-editor.org/rfc/rfc5801#section-4):
+//!
+//! ```text
+//! [F,] (n | y | p=cb-name) , [a=saslname] ,
+//! ```
+//!
+//! It carries the channel binding flag and an optional authorization identity, escaped as a
+//! [`SaslName`]. [`parse_header`] and [`encode`] handle the full header, [`parse`] and [`write`]
+//! are shortcuts for mechanisms without a `-PLUS` variant.
+//!
+//! **Note: This module is only public with the feature `unstable_custom_mechanism`**
+//!
+//! ```
+//! # #[cfg(feature = "unstable_custom_mechanism")] {
+//! # use rsasl::mechanisms::gs2_header::{encode, parse_header, CbFlag};
+//! let header = encode(CbFlag::Used("tls-exporter"), Some("admin,ops")).unwrap();
+//! assert_eq!(header, "p=tls-exporter,a=admin=2Cops,");
+//!
+//! let message = format!("{}client-first-message", header);
+//! let (parsed, rest) = parse_header(message.as_bytes()).unwrap();
+//! assert_eq!(parsed.cbflag, CbFlag::Used("tls-exporter"));
+//! assert_eq!(parsed.authzid().as_deref(), Some("admin,ops"));
+//! assert_eq!(rest, b"client-first-message");
+//! # }
+//! ```
+
+use crate::error::{MechanismError, MechanismErrorKind, SessionError};
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::str::Utf8Error;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Gs2HeaderError {
+    /// The header is not of the form `[F,] cbflag , [a=saslname] ,`
+    BadFormat,
+    /// A saslname contains a `=` that doesn't start `=2C` or `=3D`
+    BadEscape,
+    /// A saslname contains a NUL or an unescaped `,`
+    BadChar(u8),
+    BadUtf8(Utf8Error),
+    /// The client requested channel binding from a mechanism that doesn't support it
+    ChannelBindingRequested,
+}
+
+impl Display for Gs2HeaderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadFormat => f.write_str("invalid GS2 header"),
+            Self::BadEscape => f.write_str("saslname contains an invalid escape sequence"),
+            Self::BadChar(c) => write!(f, "saslname contains invalid character {:#04x}", c),
+            Self::BadUtf8(e) => write!(f, "saslname is invalid UTF-8: {}", e),
+            Self::ChannelBindingRequested => {
+                f.write_str("channel binding requested but not supported by the mechanism")
+            }
+        }
+    }
+}
+
+impl MechanismError for Gs2HeaderError {
+    fn kind(&self) -> MechanismErrorKind {
+        match self {
+            Self::ChannelBindingRequested => MechanismErrorKind::Protocol,
+            _ => MechanismErrorKind::Parse,
+        }
+    }
+}
+
+/// The channel binding flag of a GS2 header
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum CbFlag<'a> {
+    /// `n`, the client doesn't support channel binding
+    NotSupported,
+    /// `y`, the client supports channel binding but thinks the server doesn't
+    SupportedNotUsed,
+    /// `p=`, the client uses channel binding of the named type
+    Used(&'a str),
+}
+
+impl<'a> CbFlag<'a> {
+    /// Parse a channel binding flag, without the trailing `,`
+    pub fn parse(input: &'a [u8]) -> Result<Self, Gs2HeaderError> {
+        match input {
+            b"n" => Ok(Self::NotSupported),
+            b"y" => Ok(Self::SupportedNotUsed),
+            _ => match input.strip_prefix(b"p=") {
+                Some(name) if is_cb_name(name) => {
+                    // Only ASCII was accepted by `is_cb_name`
+                    let name = std::str::from_utf8(name).map_err(Gs2HeaderError::BadUtf8)?;
+                    Ok(Self::Used(name))
+                }
+                _ => Err(Gs2HeaderError::BadFormat),
+            },
+        }
+    }
+
+    /// The flag as slices to be written out in order
+    pub fn to_ioslices(&self) -> [&'a [u8]; 2] {
+        match self {
+            Self::NotSupported => [b"n", &[]],
+            Self::SupportedNotUsed => [b"y", &[]],
+            Self::Used(name) => [b"p=", name.as_bytes()],
+        }
+    }
+}
+
+impl Display for CbFlag<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotSupported => f.write_str("n"),
+            Self::SupportedNotUsed => f.write_str("y"),
+            Self::Used(name) => write!(f, "p={}", name),
+        }
+    }
+}
+
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[repr(transparent)]
+/// Escaped saslname
+///
+/// A saslname can't contain NUL or `,`, and `=` is only used in the escape sequences `=2C` for
+/// `,` and `=3D` for `=`. Used for the authorization identity of the GS2 header and the username
+/// of `SCRAM-*`.
+pub struct SaslName(str);
+
+impl SaslName {
+    /// Construct a saslname from an already escaped str
+    ///
+    /// This function will *fail* if the given name is not a valid saslname. To escape an
+    /// arbitrary string into a valid saslname use [`SaslName::escape`].
+    pub fn new(input: &str) -> Result<&Self, Gs2HeaderError> {
+        Self::verify(input)?;
+        // SAFE because SaslName is a transparent wrapper around str
+        let this = unsafe { &*(input as *const str as *const SaslName) };
+        Ok(this)
+    }
+
+    /// Construct an owned saslname from an already escaped str
+    pub fn from_boxed_str(input: Box<str>) -> Result<Box<Self>, Gs2HeaderError> {
+        Self::verify(&input)?;
+        // SAFE because SaslName is a transparent wrapper around str
+        let this = unsafe { Box::from_raw(Box::into_raw(input) as *mut SaslName) };
+        Ok(this)
+    }
+
+    /// Escape an arbitrary string into an owned saslname
+    pub fn from_unescaped(input: &str) -> Result<Box<Self>, Gs2HeaderError> {
+        Self::from_boxed_str(Self::escape(input)?.into_owned().into_boxed_str())
+    }
+
+    /// Check that `input` contains no NUL or ',' and only uses the escapes `=2C` and `=3D`
+    fn verify(input: &str) -> Result<(), Gs2HeaderError> {
+        let bytes = input.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'=' => match bytes.get(i + 1..i + 3) {
+                    Some(b"2C") | Some(b"3D") => i += 3,
+                    _ => return Err(Gs2HeaderError::BadEscape),
+                },
+                b @ (b'\0' | b',') => return Err(Gs2HeaderError::BadChar(b)),
+                _ => i += 1,
+            }
+        }
+        Ok(())
+    }
+
+    /// The escaped name
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Escape `,` and `=` in an arbitrary string, failing if it contains NUL
+    pub fn escape(input: &str) -> Result<Cow<'_, str>, Gs2HeaderError> {
+        if input.contains('\0') {
+            return Err(Gs2HeaderError::BadChar(0));
+        }
+
+        if input.contains([',', '=']) {
+            // '=' has to be escaped first, otherwise the escape sequence for ',' is mangled
+            Ok(input.replace('=', "=3D").replace(',', "=2C").into())
+        } else {
+            Ok(input.into())
+        }
+    }
+
+    /// The name with all escape sequences replaced
+    pub fn unescape(&self) -> Cow<'_, str> {
+        let mut rest = &self.0;
+        if !rest.contains('=') {
+            return Cow::Borrowed(rest);
+        }
+
+        let mut out = String::with_capacity(rest.len());
+        while let Some(escape) = rest.find('=') {
+            out.push_str(&rest[..escape]);
+            // Only the two escape sequences pass `verify`
+            out.push(if &rest[escape + 1..escape + 3] == "2C" {
+                ','
+            } else {
+                '='
+            });
+            // Both bytes of the escape sequence are ASCII so this is always a char boundary
+            rest = &rest[escape + 3..];
+        }
+        out.push_str(rest);
+        Cow::Owned(out)
+    }
+}
+
+/// A GS2 header as sent by the client
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Header<'a> {
+    /// The `F` flag, set if the mechanism's initial context token doesn't have the standard header
+    pub nonstd: bool,
+    pub cbflag: CbFlag<'a>,
+    /// The escaped authorization identity, if the client requested one
+    pub authzid: Option<&'a SaslName>,
+    /// The header without the `F` flag, as covered by channel bindings
+    pub raw: &'a [u8],
+}
+
+impl<'a> Header<'a> {
+    /// The unescaped authorization identity, if the client requested one
+    pub fn authzid(&self) -> Option<Cow<'a, str>> {
+        self.authzid.map(SaslName::unescape)
+    }
+}
+
+/// Parse a GS2 header, returning it and the remainder of the message
+pub fn parse_header(input: &[u8]) -> Result<(Header<'_>, &[u8]), Gs2HeaderError> {
+    let (nonstd, input) = match input.strip_prefix(b"F,") {
+        Some(input) => (true, input),
+        None => (false, input),
+    };
+    let mut fields = input.splitn(3, |byte| *byte == b',');
+    let (cbflag, authzid, rest) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(cbflag), Some(authzid), Some(rest)) => (cbflag, authzid, rest),
+        _ => return Err(Gs2HeaderError::BadFormat),
+    };
+    let raw = &input[..input.len() - rest.len()];
+
+    let cbflag = CbFlag::parse(cbflag)?;
+    let authzid = match authzid {
+        b"" => None,
+        _ => match authzid.strip_prefix(b"a=") {
+            Some(name) if !name.is_empty() => {
+                let name = std::str::from_utf8(name).map_err(Gs2HeaderError::BadUtf8)?;
+                Some(SaslName::new(name)?)
+            }
+            _ => return Err(Gs2HeaderError::BadFormat),
+        },
+    };
+
+    let header = Header {
+        nonstd,
+        cbflag,
+        authzid,
+        raw,
+    };
+    Ok((header, rest))
+}
+
+/// Parse the GS2 header of a mechanism without `-PLUS` variant, returning the unescaped authzid
+/// if one was given and the remainder of the message
+pub fn parse(input: &[u8]) -> Result<(Option<String>, &[u8]), Gs2HeaderError> {
+    let (header, rest) = parse_header(input)?;
+    if header.nonstd {
+        return Err(Gs2HeaderError::BadFormat);
+    }
+    if let CbFlag::Used(_) = header.cbflag {
+        return Err(Gs2HeaderError::ChannelBindingRequested);
+    }
+    Ok((header.authzid().map(Cow::into_owned), rest))
+}
+
+/// Encode a header, without the `F` flag, escaping the authzid
+pub fn encode(cbflag: CbFlag<'_>, authzid: Option<&str>) -> Result<String, Gs2HeaderError> {
+    match authzid {
+        Some(authzid) => Ok(format!("{},a={},", cbflag, SaslName::escape(authzid)?)),
+        None => Ok(format!("{},,", cbflag)),
+    }
+}
+
+/// Write the header for a client not supporting channel binding, returning the bytes written
+pub fn write(authzid: Option<&str>, writer: &mut dyn Write) -> Result<usize, SessionError> {
+    let header = encode(CbFlag::NotSupported, authzid)?;
+    writer.write_all(header.as_bytes())?;
+    Ok(header.len())
+}
+
+/// `cb-name = 1*(ALPHA / DIGIT / "." / "-")`, see
+/// [RFC 5056, section 7](https://www.rfc-editor.org/rfc/rfc5056#section-7)
+fn is_cb_name(name: &[u8]) -> bool {
+    !name.is_empty()
+        && name
+            .iter()
+            .all(|byte| byte.is_ascii_alphanumeric() || *byte == b'.' || *byte == b'-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        for authzid in [None, Some("user"), Some("a,b=c")].iter() {
+            let mut header = Vec::new();
+            let len = write(*authzid, &mut header).unwrap();
+            assert_eq!(len, header.len());
+            header.extend_from_slice(b"rest,of=message");
+            let (parsed, rest) = parse(&header).unwrap();
+            assert_eq!(parsed.as_deref(), *authzid);
+            assert_eq!(rest, b"rest,of=message");
+        }
+        assert!(encode(CbFlag::NotSupported, Some("a\0b")).is_err());
+    }
+
+    #[test]
+    fn invalid_headers() {
+        assert!(parse(b"n,").is_err());
+        assert!(parse(b"x,,rest").is_err());
+        assert!(parse(b"n,a=,rest").is_err());
+        assert!(parse(b"n,u=user,rest").is_err());
+        assert!(parse(b"n,a=us=er,rest").is_err());
+        assert!(matches!(
+            parse(b"p=tls-unique,,rest"),
+            Err(Gs2HeaderError::ChannelBindingRequested)
+        ));
+        assert_eq!(parse(b"y,,").unwrap(), (None, &b""[..]));
+        assert!(parse(b"F,n,,rest").is_err());
+    }
+
+    #[test]
+    fn channel_binding_headers() {
+        let header = encode(CbFlag::Used("tls-exporter"), Some("admin")).unwrap();
+        assert_eq!(header, "p=tls-exporter,a=admin,");
+        let message = format!("F,{}token", header);
+        let (parsed, rest) = parse_header(message.as_bytes()).unwrap();
+        assert!(parsed.nonstd);
+        assert_eq!(parsed.cbflag, CbFlag::Used("tls-exporter"));
+        assert_eq!(parsed.authzid().as_deref(), Some("admin"));
+        assert_eq!(parsed.raw, header.as_bytes());
+        assert_eq!(rest, b"token");
+
+        let (parsed, _) = parse_header(b"y,,token").unwrap();
+        assert!(!parsed.nonstd);
+        assert_eq!(parsed.cbflag, CbFlag::SupportedNotUsed);
+        assert_eq!(parsed.raw, b"y,,");
+
+        assert!(parse_header(b"p=,,token").is_err());
+        assert!(parse_header(b"p=tls_unique,,token").is_err());
+    }
+
+    #[test]
+    fn cbflags() {
+        let valid: [(&[u8], CbFlag); 7] = [
+            (b"n", CbFlag::NotSupported),
+            (b"y", CbFlag::SupportedNotUsed),
+            (b"p=tls-unique", CbFlag::Used("tls-unique")),
+            (b"p=.", CbFlag::Used(".")),
+            (b"p=-", CbFlag::Used("-")),
+            (b"p=a", CbFlag::Used("a")),
+            (
+                b"p=a-very-long-cb-name.indeed",
+                CbFlag::Used("a-very-long-cb-name.indeed"),
+            ),
+        ];
+
+        for (input, output) in valid.iter() {
+            assert_eq!(CbFlag::parse(input), Ok(*output));
+            assert_eq!(output.to_string().as_bytes(), *input);
+        }
+        assert_eq!(CbFlag::parse(b"p"), Err(Gs2HeaderError::BadFormat));
+        assert_eq!(CbFlag::parse(b"N"), Err(Gs2HeaderError::BadFormat));
+    }
+
+    #[test]
+    fn saslname_escaping() {
+        let valid: [(&str, &str); 5] = [
+            ("user", "user"),
+            ("a,b", "a=2Cb"),
+            ("a=b", "a=3Db"),
+            ("=,=", "=3D=2C=3D"),
+            ("ü=2C", "ü=3D2C"),
+        ];
+
+        for (input, escaped) in valid.iter() {
+            assert_eq!(SaslName::escape(input).unwrap(), *escaped);
+            let name = SaslName::from_unescaped(input).unwrap();
+            assert_eq!(name.as_str(), *escaped);
+            assert_eq!(name.unescape(), *input);
+        }
+
+        for bad in ["=", "=2", "a=2D", "=3"].iter() {
+            assert_eq!(
+                SaslName::new(bad).map(|_| ()),
+                Err(Gs2HeaderError::BadEscape)
+            );
+        }
+        assert_eq!(
+            SaslName::new("a,b").map(|_| ()),
+            Err(Gs2HeaderError::BadChar(b','))
+        );
+        assert_eq!(
+            SaslName::escape("a\0b").map(|_| ()),
+            Err(Gs2HeaderError::BadChar(0))
+        );
+    }
+}
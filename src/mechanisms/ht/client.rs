@@ -0,0 +1,71 @@
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::ht::token::{hashed_token, ChannelBinding, HmacSha256, HtError};
+use crate::mechanisms::ht::token::{INITIATOR, RESPONDER};
+use crate::property::{AuthId, HtToken};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use crate::vectored_io::VectoredWriter;
+use hmac::Mac;
+use std::io::Write;
+
+enum State {
+    Initial,
+    /// The initial message was sent, waiting for the server to prove knowledge of the token
+    Sent(Box<HmacSha256>),
+    Finished,
+}
+
+pub struct HtSha256 {
+    channel_binding: ChannelBinding,
+    state: State,
+}
+
+impl HtSha256 {
+    pub fn new(channel_binding: ChannelBinding) -> Self {
+        Self {
+            channel_binding,
+            state: State::Initial,
+        }
+    }
+}
+
+impl Authentication for HtSha256 {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        match std::mem::replace(&mut self.state, State::Finished) {
+            State::Initial => {
+                let authid = session
+                    .get_property_or_callback::<AuthId>()?
+                    .ok_or(SessionError::no_property::<AuthId>())?;
+                let token = session
+                    .get_property_or_callback::<HtToken>()?
+                    .ok_or(SessionError::no_property::<HtToken>())?;
+                let cb_data = self.channel_binding.data(session)?;
+
+                let initiator = hashed_token(token.as_bytes(), INITIATOR, cb_data)
+                    .finalize()
+                    .into_bytes();
+                let responder = hashed_token(token.as_bytes(), RESPONDER, cb_data);
+
+                let data: [&[u8]; 3] = [authid.as_bytes(), &[0], &initiator];
+                let written = VectoredWriter::new(data).write_all_vectored(writer)?;
+
+                self.state = State::Sent(Box::new(responder));
+                Ok(NeedsMore(Some(written)))
+            }
+            State::Sent(responder) => {
+                let input = input.ok_or(SessionError::InputDataRequired)?;
+                responder
+                    .verify_slice(input)
+                    .map_err(|_| HtError::InvalidServerToken)?;
+                Ok(Done(None))
+            }
+            State::Finished => Err(HtError::Completed.into()),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::mechanisms::ht::token::ChannelBinding;
+use crate::mechanisms::ht::{client, server};
+use crate::property::properties::{AUTHID, HT_TOKEN};
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
+use crate::{Mechanism, Mechname, Side};
+
+#[cfg(feature = "registry_static")]
+use crate::registry::{distributed_slice, MECHANISMS};
+#[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
+pub static HT_SHA_256_NONE: Mechanism = Mechanism {
+    mechanism: Mechname::const_new_unchecked(b"HT-SHA-256-NONE"),
+    priority: 100,
+    client: Some(|_sasl| Ok(Box::new(client::HtSha256::new(ChannelBinding::None)))),
+    server: Some(|_sasl| Ok(Box::new(server::HtSha256::new(ChannelBinding::None)))),
+    first: Side::Client,
+    client_properties: PropertyRequirements::new(&[AUTHID, HT_TOKEN], &[]),
+    server_properties: PropertyRequirements::NONE,
+    description: "Hashed token issued by the server",
+    specification: Some("draft-schmaus-kitten-sasl-ht"),
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: true,
+        weak: false,
+    },
+};
+
+#[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
+pub static HT_SHA_256_UNIQ: Mechanism = Mechanism {
+    mechanism: Mechname::const_new_unchecked(b"HT-SHA-256-UNIQ"),
+    priority: 110,
+    client: Some(|_sasl| Ok(Box::new(client::HtSha256::new(ChannelBinding::Unique)))),
+    server: Some(|_sasl| Ok(Box::new(server::HtSha256::new(ChannelBinding::Unique)))),
+    first: Side::Client,
+    client_properties: PropertyRequirements::new(&[AUTHID, HT_TOKEN], &[]),
+    server_properties: PropertyRequirements::NONE,
+    description: "Hashed token issued by the server, bound to the TLS connection (tls-unique)",
+    specification: Some("draft-schmaus-kitten-sasl-ht"),
+    channel_binding: true,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: true,
+        weak: false,
+    },
+};
+
+#[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
+pub static HT_SHA_256_ENDP: Mechanism = Mechanism {
+    mechanism: Mechname::const_new_unchecked(b"HT-SHA-256-ENDP"),
+    priority: 110,
+    client: Some(|_sasl| Ok(Box::new(client::HtSha256::new(ChannelBinding::EndPoint)))),
+    server: Some(|_sasl| Ok(Box::new(server::HtSha256::new(ChannelBinding::EndPoint)))),
+    first: Side::Client,
+    client_properties: PropertyRequirements::new(&[AUTHID, HT_TOKEN], &[]),
+    server_properties: PropertyRequirements::NONE,
+    description: "Hashed token issued by the server, bound to the TLS server certificate",
+    specification: Some("draft-schmaus-kitten-sasl-ht"),
+    channel_binding: true,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: true,
+        weak: false,
+    },
+};
+
+#[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
+pub static HT_SHA_256_EXPR: Mechanism = Mechanism {
+    mechanism: Mechname::const_new_unchecked(b"HT-SHA-256-EXPR"),
+    priority: 120,
+    client: Some(|_sasl| Ok(Box::new(client::HtSha256::new(ChannelBinding::Exporter)))),
+    server: Some(|_sasl| Ok(Box::new(server::HtSha256::new(ChannelBinding::Exporter)))),
+    first: Side::Client,
+    client_properties: PropertyRequirements::new(&[AUTHID, HT_TOKEN], &[]),
+    server_properties: PropertyRequirements::NONE,
+    description: "Hashed token issued by the server, bound to the TLS connection (tls-exporter)",
+    specification: Some("draft-schmaus-kitten-sasl-ht"),
+    channel_binding: true,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: true,
+        weak: false,
+    },
+};
@@ -0,0 +1,66 @@
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::ht::token::{hashed_token, ChannelBinding, HtError};
+use crate::mechanisms::ht::token::{INITIATOR, RESPONDER};
+use crate::property::{AuthId, HtToken};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use crate::validate::validations::HT;
+use hmac::Mac;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Length of a HMAC-SHA-256 output
+const HASH_LEN: usize = 32;
+
+pub struct HtSha256 {
+    channel_binding: ChannelBinding,
+}
+
+impl HtSha256 {
+    pub fn new(channel_binding: ChannelBinding) -> Self {
+        Self { channel_binding }
+    }
+}
+
+impl Authentication for HtSha256 {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        let input = match input {
+            Some(input) if !input.is_empty() => input,
+            _ => return Ok(NeedsMore(None)),
+        };
+
+        let nul = input
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or(HtError::BadFormat)?;
+        let (authcid, hash) = (&input[..nul], &input[nul + 1..]);
+        if hash.len() != HASH_LEN {
+            return Err(HtError::BadFormat.into());
+        }
+        let authcid = std::str::from_utf8(authcid).map_err(HtError::BadAuthcid)?;
+        session.set_property::<AuthId>(Arc::new(authcid.to_string()));
+
+        // The callback looks up the token issued to the user for this mechanism
+        session.validate(HT)?;
+        let token = session
+            .get_property::<HtToken>()
+            .ok_or(SessionError::no_property::<HtToken>())?;
+        let cb_data = self.channel_binding.data(session)?;
+
+        hashed_token(token.as_bytes(), INITIATOR, cb_data)
+            .verify_slice(hash)
+            .map_err(|_| HtError::InvalidToken)?;
+
+        let responder = hashed_token(token.as_bytes(), RESPONDER, cb_data)
+            .finalize()
+            .into_bytes();
+        writer.write_all(&responder)?;
+        Ok(Done(Some(responder.len())))
+    }
+}
@@ -0,0 +1,107 @@
+//! Parts shared between the client and server side of the `HT-*` mechanisms
+//!
+//! Both sides prove knowledge of a token previously issued by the server by sending a HMAC over a
+//! fixed label and the channel binding data, keyed with the token. The client (initiator) sends
+//! its HMAC along with the authcid, the server (responder) verifies it and sends its own HMAC as
+//! additional data with the success indication.
+
+use crate::error::{MechanismError, MechanismErrorKind};
+use crate::session::SessionData;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt::{Display, Formatter};
+use std::str::Utf8Error;
+
+pub(super) type HmacSha256 = Hmac<Sha256>;
+
+pub(super) const INITIATOR: &[u8] = b"Initiator";
+pub(super) const RESPONDER: &[u8] = b"Responder";
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+/// The channel binding type named by the last part of the mechanism name
+pub enum ChannelBinding {
+    /// `-NONE`, no channel binding
+    None,
+    /// `-UNIQ`, `tls-unique` ([RFC 5929](https://tools.ietf.org/html/rfc5929#section-3))
+    Unique,
+    /// `-ENDP`, `tls-server-end-point` ([RFC 5929](https://tools.ietf.org/html/rfc5929#section-4))
+    EndPoint,
+    /// `-EXPR`, `tls-exporter` ([RFC 9266](https://tools.ietf.org/html/rfc9266))
+    Exporter,
+}
+
+impl ChannelBinding {
+    /// The name of the channel binding type as used with [`Session::set_channel_binding_data`]
+    ///
+    /// [`Session::set_channel_binding_data`]: crate::session::Session::set_channel_binding_data
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Unique => Some("tls-unique"),
+            Self::EndPoint => Some("tls-server-end-point"),
+            Self::Exporter => Some("tls-exporter"),
+        }
+    }
+
+    pub(super) fn data<'a>(&self, session: &'a SessionData) -> Result<&'a [u8], HtError> {
+        let name = match self.name() {
+            Some(name) => name,
+            None => return Ok(&[]),
+        };
+        match session.get_cb_data() {
+            Some((cbname, data)) if cbname == name => Ok(data),
+            _ => Err(HtError::MissingChannelBinding(name)),
+        }
+    }
+}
+
+pub(super) fn hashed_token(token: &[u8], label: &[u8], cb_data: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(token).expect("HMAC can take keys of any size");
+    mac.update(label);
+    mac.update(cb_data);
+    mac
+}
+
+#[derive(Debug)]
+pub enum HtError {
+    BadFormat,
+    BadAuthcid(Utf8Error),
+    /// The channel binding data of the given type was not set on the session
+    MissingChannelBinding(&'static str),
+    /// The hashed token sent by the client does not match the token issued to the user
+    InvalidToken,
+    /// The hashed token sent by the server does not match, i.e. the server could not prove that
+    /// it knows the token
+    InvalidServerToken,
+    /// The exchange has already completed
+    Completed,
+}
+
+impl Display for HtError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadFormat => f.write_str(
+                "invalid format, expected an authcid and a hashed token separated by a NULL-byte",
+            ),
+            Self::BadAuthcid(e) => write!(f, "authcid is invalid UTF-8: {}", e),
+            Self::MissingChannelBinding(name) => {
+                write!(f, "channel binding data of type {} is not available", name)
+            }
+            Self::InvalidToken => f.write_str("the hashed token sent by the client is invalid"),
+            Self::InvalidServerToken => {
+                f.write_str("the hashed token sent by the server is invalid")
+            }
+            Self::Completed => f.write_str("the exchange has already completed"),
+        }
+    }
+}
+
+impl MechanismError for HtError {
+    fn kind(&self) -> MechanismErrorKind {
+        match self {
+            Self::BadFormat | Self::BadAuthcid(_) => MechanismErrorKind::Parse,
+            Self::MissingChannelBinding(_) | Self::Completed => MechanismErrorKind::Protocol,
+            Self::InvalidToken | Self::InvalidServerToken => MechanismErrorKind::Outcome,
+        }
+    }
+}
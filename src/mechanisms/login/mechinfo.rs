@@ -1,39 +1,27 @@
-use crate::gsasl::gsasl::{CMechanismStateKeeper, MechanismVTable};
-use crate::mechanisms::login::client::{
-    _gsasl_login_client_finish, _gsasl_login_client_start, _gsasl_login_client_step,
-};
-use crate::mechanisms::login::server::{
-    _gsasl_login_server_finish, _gsasl_login_server_start, _gsasl_login_server_step,
-};
+use crate::mechanisms::login::{client, server};
+use crate::property::properties::{AUTHID, LOGIN_PROMPTS, PASSWORD};
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
 use crate::{Mechanism, Mechname, Side};
 
 #[cfg(feature = "registry_static")]
 use crate::registry::{distributed_slice, MECHANISMS};
 #[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
 pub static LOGIN: Mechanism = Mechanism {
-    mechanism: &Mechname::const_new_unchecked(b"LOGIN"),
+    mechanism: Mechname::const_new_unchecked(b"LOGIN"),
     priority: 200,
-    client: Some(|_sasl| {
-        CMechanismStateKeeper::build(MechanismVTable {
-            init: None,
-            done: None,
-            start: Some(_gsasl_login_client_start),
-            step: Some(_gsasl_login_client_step),
-            finish: Some(_gsasl_login_client_finish),
-            encode: None,
-            decode: None,
-        })
-    }),
-    server: Some(|_sasl| {
-        CMechanismStateKeeper::build(MechanismVTable {
-            init: None,
-            done: None,
-            start: Some(_gsasl_login_server_start),
-            step: Some(_gsasl_login_server_step),
-            finish: Some(_gsasl_login_server_finish),
-            encode: None,
-            decode: None,
-        })
-    }),
+    client: Some(|_sasl| Ok(Box::new(client::Login::new()))),
+    server: Some(|_sasl| Ok(Box::new(server::Login::new()))),
     first: Side::Server,
+    client_properties: PropertyRequirements::new(&[AUTHID, PASSWORD], &[]),
+    server_properties: PropertyRequirements::new(&[], &[LOGIN_PROMPTS]),
+    description: "Username and password sent in plain text in answer to server prompts",
+    specification: Some("draft-murchison-sasl-login"),
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: false,
+        mutual: false,
+        weak: true,
+    },
 };
@@ -0,0 +1,136 @@
+//! The prompts of the `LOGIN` exchange
+
+use crate::property::LoginPromptText;
+use std::fmt::{Display, Formatter};
+
+const USERNAME: &str = "Username:";
+const PASSWORD: &str = "Password:";
+
+/// Reasons [`LoginPromptText::new`] rejects a prompt
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PromptError {
+    Empty,
+    /// Prompts can't contain control characters, which would break line-based protocols
+    ControlCharacter,
+    /// Both prompts are the same, so clients can't tell them apart
+    Ambiguous,
+}
+
+impl Display for PromptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => f.write_str("LOGIN prompts can't be empty"),
+            Self::ControlCharacter => f.write_str("LOGIN prompts can't contain control characters"),
+            Self::Ambiguous => f.write_str("the LOGIN username and password prompts are the same"),
+        }
+    }
+}
+
+impl std::error::Error for PromptError {}
+
+impl LoginPromptText {
+    /// Prompts to ask for the username and the password with
+    pub fn new(username: impl ToString, password: impl ToString) -> Result<Self, PromptError> {
+        let username = username.to_string();
+        let password = password.to_string();
+        for prompt in [&username, &password] {
+            if prompt.is_empty() {
+                return Err(PromptError::Empty);
+            }
+            if prompt.chars().any(char::is_control) {
+                return Err(PromptError::ControlCharacter);
+            }
+        }
+        if username.eq_ignore_ascii_case(&password) {
+            return Err(PromptError::Ambiguous);
+        }
+        Ok(Self { username, password })
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+}
+
+impl Default for LoginPromptText {
+    fn default() -> Self {
+        Self {
+            username: USERNAME.to_string(),
+            password: PASSWORD.to_string(),
+        }
+    }
+}
+
+/// What a prompt sent by a server asks for
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(super) enum Prompt {
+    Username,
+    Password,
+}
+
+impl Prompt {
+    /// Interpret the wording of a prompt, returning `None` for prompts not recognized
+    ///
+    /// Matches `Username:` and `Password:` as well as the variations servers send instead, e.g.
+    /// `User Name`, `login:` or `Passphrase`.
+    pub(super) fn classify(prompt: &[u8]) -> Option<Self> {
+        let prompt = String::from_utf8_lossy(prompt).to_ascii_lowercase();
+        if prompt.contains("pass") {
+            Some(Self::Password)
+        } else if ["user", "name", "login", "account", "mail"]
+            .iter()
+            .any(|word| prompt.contains(word))
+        {
+            Some(Self::Username)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify() {
+        for prompt in [&b"Username:"[..], b"User Name", b"login:", b"E-Mail"] {
+            assert_eq!(Prompt::classify(prompt), Some(Prompt::Username));
+        }
+        for prompt in [
+            &b"Password:"[..],
+            b"PASSWORD",
+            b"Passphrase",
+            b"User password",
+        ] {
+            assert_eq!(Prompt::classify(prompt), Some(Prompt::Password));
+        }
+        assert_eq!(Prompt::classify(b""), None);
+        assert_eq!(Prompt::classify(b"\xff\xfe"), None);
+        assert_eq!(Prompt::classify(b"Wer bist du?"), None);
+    }
+
+    #[test]
+    fn configured_prompts() {
+        let prompts = LoginPromptText::new("Benutzername:", "Passwort:").unwrap();
+        assert_eq!(prompts.username(), "Benutzername:");
+        assert_eq!(prompts.password(), "Passwort:");
+
+        assert_eq!(
+            LoginPromptText::new("", "Password:"),
+            Err(PromptError::Empty)
+        );
+        assert_eq!(
+            LoginPromptText::new("Username:\r\n", "Password:"),
+            Err(PromptError::ControlCharacter)
+        );
+        assert_eq!(
+            LoginPromptText::new("Login:", "login:"),
+            Err(PromptError::Ambiguous)
+        );
+    }
+}
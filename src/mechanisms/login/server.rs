@@ -1,179 +1,162 @@
-use crate::gsasl::callback::gsasl_callback;
-use crate::gsasl::consts::{
-    GSASL_AUTHENTICATION_ERROR, GSASL_AUTHID, GSASL_MALLOC_ERROR,
-    GSASL_MECHANISM_CALLED_TOO_MANY_TIMES, GSASL_MECHANISM_PARSE_ERROR, GSASL_NEEDS_MORE,
-    GSASL_NO_CALLBACK, GSASL_OK, GSASL_PASSWORD, GSASL_VALIDATE_SIMPLE,
-};
-use crate::gsasl::gl::free::rpl_free;
-use crate::gsasl::property::{gsasl_property_get, gsasl_property_set};
-use crate::session::SessionData;
-use crate::Shared;
-use ::libc;
-use libc::{calloc, size_t, strcmp, strdup, strlen, strndup};
-use std::ptr::NonNull;
+use crate::error::{MechanismError, MechanismErrorKind, SessionError};
+use crate::mechanism::Authentication;
+use crate::mechanisms::login::prompts::Prompt;
+use crate::property::{AuthId, LoginPromptText, LoginPrompts, Password};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+#[cfg(feature = "session_state")]
+use crate::state::{StateError, StateWriter};
+use crate::validate::validations::SIMPLE;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::str::Utf8Error;
+use std::sync::Arc;
 
-/* server.c --- Non-standard SASL mechanism LOGIN, server side.
- * Copyright (C) 2002-2021 Simon Josefsson
- *
- * This file is part of GNU SASL Library.
- *
- * GNU SASL Library is free software; you can redistribute it and/or
- * modify it under the terms of the GNU Lesser General Public License
- * as published by the Free Software Foundation; either version 2.1 of
- * the License, or (at your option) any later version.
- *
- * GNU SASL Library is distributed in the hope that it will be useful,
- * but WITHOUT ANY WARRANTY; without even the implied warranty of
- * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
- * Lesser General Public License for more details.
- *
- * You should have received a copy of the GNU Lesser General Public
- * License along with GNU SASL Library; if not, write to the Free
- * Free Software Foundation, Inc., 51 Franklin Street, Fifth Floor,
- * Boston, MA 02110-1301, USA.
- *
- */
-/* Get malloc, free. */
-/* Get strdup, strlen. */
-/* Get specification. */
-#[derive(Copy, Clone)]
-#[repr(C)]
-pub struct _Gsasl_login_server_state {
-    pub step: libc::c_int,
-    pub username: *mut libc::c_char,
-    pub password: *mut libc::c_char,
+#[derive(Debug)]
+pub(super) enum LoginError {
+    Empty(Prompt),
+    BadUtf8(Prompt, Utf8Error),
+    Saslprep(Prompt, stringprep::Error),
+    /// The server prompted for something the client already sent
+    Repeated(Prompt),
+    Completed,
 }
 
-pub(crate) unsafe fn _gsasl_login_server_start(
-    _sctx: &Shared,
-    mech_data: &mut Option<NonNull<()>>,
-) -> libc::c_int {
-    let state;
-    state = calloc(1, ::std::mem::size_of::<_Gsasl_login_server_state>())
-        as *mut _Gsasl_login_server_state;
-    if state.is_null() {
-        return GSASL_MALLOC_ERROR as libc::c_int;
+impl Display for LoginError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = |prompt: &Prompt| match prompt {
+            Prompt::Username => "username",
+            Prompt::Password => "password",
+        };
+        match self {
+            Self::Empty(prompt) => write!(f, "the {} is empty", name(prompt)),
+            Self::BadUtf8(prompt, e) => write!(f, "the {} is invalid UTF-8: {}", name(prompt), e),
+            Self::Saslprep(prompt, e) => {
+                write!(f, "saslprep of the {} failed: {}", name(prompt), e)
+            }
+            Self::Repeated(prompt) => write!(f, "server asked for the {} again", name(prompt)),
+            Self::Completed => f.write_str("the exchange is already complete"),
+        }
     }
-    *mech_data = NonNull::new(state as *mut ());
-    return GSASL_OK as libc::c_int;
 }
 
-pub unsafe fn _gsasl_login_server_step(
-    sctx: &mut SessionData,
-    mech_data: Option<NonNull<()>>,
-    input: Option<&[u8]>,
-    output: *mut *mut libc::c_char,
-    output_len: *mut size_t,
-) -> libc::c_int {
-    let mech_data = mech_data
-        .map(|ptr| ptr.as_ptr())
-        .unwrap_or_else(std::ptr::null_mut);
+impl MechanismError for LoginError {
+    fn kind(&self) -> MechanismErrorKind {
+        match self {
+            Self::Repeated(_) | Self::Completed => MechanismErrorKind::Protocol,
+            Self::Empty(_) | Self::BadUtf8(..) | Self::Saslprep(..) => MechanismErrorKind::Parse,
+        }
+    }
+}
 
-    let input_len = input.map(|i| i.len()).unwrap_or(0);
-    let input: *const libc::c_char = input.map(|i| i.as_ptr().cast()).unwrap_or(std::ptr::null());
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+enum State {
+    #[default]
+    Initial,
+    Username,
+    Password,
+    Completed,
+}
 
-    let mut state: *mut _Gsasl_login_server_state = mech_data as *mut _Gsasl_login_server_state;
-    let mut res;
-    match (*state).step {
-        0 => {
-            *output = strdup(b"User Name\x00" as *const u8 as *const libc::c_char);
-            if (*output).is_null() {
-                return GSASL_MALLOC_ERROR as libc::c_int;
-            }
-            *output_len = strlen(b"User Name\x00" as *const u8 as *const libc::c_char);
-            (*state).step += 1;
-            res = GSASL_NEEDS_MORE as libc::c_int
-        }
-        1 => {
-            if input_len == 0 {
-                return GSASL_MECHANISM_PARSE_ERROR as libc::c_int;
-            }
-            (*state).username = strndup(input, input_len);
-            if (*state).username.is_null() {
-                return GSASL_MALLOC_ERROR as libc::c_int;
-            }
-            if input_len != strlen((*state).username) {
-                return GSASL_MECHANISM_PARSE_ERROR as libc::c_int;
-            }
-            *output = strdup(b"Password\x00" as *const u8 as *const libc::c_char);
-            if (*output).is_null() {
-                return GSASL_MALLOC_ERROR as libc::c_int;
-            }
-            *output_len = strlen(b"Password\x00" as *const u8 as *const libc::c_char);
-            (*state).step += 1;
-            res = GSASL_NEEDS_MORE as libc::c_int
-        }
-        2 => {
-            if input_len == 0 {
-                return GSASL_MECHANISM_PARSE_ERROR as libc::c_int;
-            }
-            (*state).password = strndup(input, input_len);
-            if (*state).password.is_null() {
-                return GSASL_MALLOC_ERROR as libc::c_int;
-            }
-            if input_len != strlen((*state).password) {
-                return GSASL_MECHANISM_PARSE_ERROR as libc::c_int;
-            }
-            res = gsasl_property_set(sctx, GSASL_AUTHID, (*state).username);
-            if res != GSASL_OK as libc::c_int {
-                return res;
+/// Server side of `LOGIN`
+///
+/// Prompts for the username and password with the prompts set by the [`LoginPrompts`]
+/// property, `Username:` and `Password:` by default, and validates them with [`SIMPLE`]. A
+/// username sent as initial response is accepted, in which case the server only prompts for the
+/// password.
+#[derive(Debug, Default)]
+pub struct Login {
+    state: State,
+    username: Option<String>,
+}
+
+impl Login {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(feature = "session_state")]
+    pub(super) fn resume(step: u8, username: Option<String>) -> Result<Self, StateError> {
+        let state = match (step, &username) {
+            (0, _) => State::Initial,
+            (1, _) => State::Username,
+            // Waiting for the password requires the username
+            (2, Some(_)) => State::Password,
+            _ => return Err(StateError::Invalid),
+        };
+        Ok(Self { state, username })
+    }
+
+    fn prompt(
+        session: &mut SessionData,
+        prompt: Prompt,
+        writer: &mut dyn Write,
+    ) -> Result<usize, SessionError> {
+        let prompts = session
+            .get_property_or_callback::<LoginPrompts>()?
+            .unwrap_or_else(|| Arc::new(LoginPromptText::default()));
+        let prompt = match prompt {
+            Prompt::Username => prompts.username(),
+            Prompt::Password => prompts.password(),
+        };
+        writer.write_all(prompt.as_bytes())?;
+        Ok(prompt.len())
+    }
+}
+
+fn parse(input: Option<&[u8]>, prompt: Prompt) -> Result<String, LoginError> {
+    let input = match input {
+        Some(input) if !input.is_empty() => input,
+        _ => return Err(LoginError::Empty(prompt)),
+    };
+    let input = std::str::from_utf8(input).map_err(|e| LoginError::BadUtf8(prompt, e))?;
+    let prepped = stringprep::saslprep(input).map_err(|e| LoginError::Saslprep(prompt, e))?;
+    Ok(prepped.into_owned())
+}
+
+impl Authentication for Login {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        match self.state {
+            State::Initial if input.unwrap_or_default().is_empty() => {
+                let len = Self::prompt(session, Prompt::Username, writer)?;
+                self.state = State::Username;
+                Ok(NeedsMore(Some(len)))
             }
-            res = gsasl_property_set(sctx, GSASL_PASSWORD, (*state).password);
-            if res != GSASL_OK as libc::c_int {
-                return res;
+            State::Initial | State::Username => {
+                self.username = Some(parse(input, Prompt::Username)?);
+                let len = Self::prompt(session, Prompt::Password, writer)?;
+                self.state = State::Password;
+                Ok(NeedsMore(Some(len)))
             }
-            res = gsasl_callback(0 as *mut Shared, sctx, GSASL_VALIDATE_SIMPLE);
-            if res == GSASL_NO_CALLBACK as libc::c_int {
-                let key;
-                key = gsasl_property_get(sctx, GSASL_PASSWORD);
-                if !key.is_null()
-                    && strlen((*state).password) == strlen(key)
-                    && strcmp((*state).password, key) == 0 as libc::c_int
-                {
-                    res = GSASL_OK as libc::c_int
-                } else {
-                    res = GSASL_AUTHENTICATION_ERROR as libc::c_int
-                }
+            State::Password => {
+                let password = parse(input, Prompt::Password)?;
+                self.state = State::Completed;
+                let username = self.username.take().unwrap_or_default();
+                session.set_property::<AuthId>(Arc::new(username));
+                session.set_property::<Password>(Arc::new(password));
+                session.validate(SIMPLE)?;
+                Ok(Done(None))
             }
-            *output_len = 0;
-            *output = 0 as *mut libc::c_char;
-            (*state).step += 1
+            State::Completed => Err(LoginError::Completed.into()),
         }
-        _ => res = GSASL_MECHANISM_CALLED_TOO_MANY_TIMES as libc::c_int,
     }
-    return res;
-}
-/* login.h --- Prototypes for non-standard SASL mechanism LOGIN.
- * Copyright (C) 2002-2021 Simon Josefsson
- *
- * This file is part of GNU SASL Library.
- *
- * GNU SASL Library is free software; you can redistribute it and/or
- * modify it under the terms of the GNU Lesser General Public License
- * as published by the Free Software Foundation; either version 2.1 of
- * the License, or (at your option) any later version.
- *
- * GNU SASL Library is distributed in the hope that it will be useful,
- * but WITHOUT ANY WARRANTY; without even the implied warranty of
- * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
- * Lesser General Public License for more details.
- *
- * You should have received a copy of the GNU Lesser General Public
- * License along with GNU SASL Library; if not, write to the Free
- * Free Software Foundation, Inc., 51 Franklin Street, Fifth Floor,
- * Boston, MA 02110-1301, USA.
- *
- */
-pub unsafe fn _gsasl_login_server_finish(mech_data: Option<NonNull<()>>) {
-    let mech_data = mech_data
-        .map(|ptr| ptr.as_ptr())
-        .unwrap_or_else(std::ptr::null_mut);
 
-    let state: *mut _Gsasl_login_server_state = mech_data as *mut _Gsasl_login_server_state;
-    if state.is_null() {
-        return;
+    #[cfg(feature = "session_state")]
+    fn export_state(&self, writer: &mut StateWriter) -> Result<(), StateError> {
+        let step = match self.state {
+            State::Initial => 0,
+            State::Username => 1,
+            State::Password => 2,
+            // The exchange is over once the password was received
+            State::Completed => return Err(StateError::Unsupported),
+        };
+        writer.put_u8(step);
+        writer.put_optional(self.username.as_ref().map(|username| username.as_bytes()));
+        Ok(())
     }
-    rpl_free((*state).username as *mut libc::c_void);
-    rpl_free((*state).password as *mut libc::c_void);
-    rpl_free(state as *mut libc::c_void);
 }
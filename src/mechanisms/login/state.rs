@@ -0,0 +1,20 @@
+//! Resuming the state of the server side, see [`crate::state`]
+
+use crate::mechanism::Authentication;
+use crate::mechanisms::login::server::Login;
+use crate::state::{StateError, StateReader};
+
+pub(crate) fn import_server(
+    reader: &mut StateReader,
+) -> Result<Box<dyn Authentication>, StateError> {
+    let step = reader.get_u8()?;
+    let username = match reader.get_optional()? {
+        Some(username) => Some(
+            std::str::from_utf8(username)
+                .map_err(|_| StateError::Invalid)?
+                .to_string(),
+        ),
+        None => None,
+    };
+    Ok(Box::new(Login::resume(step, username)?))
+}
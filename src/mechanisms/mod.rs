@@ -27,19 +27,27 @@
 #[cfg(feature = "anonymous")]
 pub mod anonymous {
     //! `ANONYMOUS` *mechanism. Requires feature "anonymous"*
+    //!
+    //! Servers can restrict the trace tokens they accept, rate-limit and audit anonymous logins
+    //! with an [`AnonymousPolicy`](crate::property::AnonymousPolicy).
     pub mod client;
     pub mod mechinfo;
+    pub mod policy;
     pub mod server;
 }
 
+#[cfg(feature = "browser_redirect")]
+pub mod browser_redirect;
+
 #[cfg(feature = "cram-md5")]
 pub mod cram_md5 {
     //! `CRAM_MD5` *mechanism. Requires feature `cram-md5`*
-    pub mod challenge;
     pub mod client;
     pub mod digest;
     pub mod mechinfo;
     pub mod server;
+    #[cfg(feature = "session_state")]
+    pub(crate) mod state;
 }
 
 #[cfg(feature = "digest-md5")]
@@ -62,27 +70,188 @@ pub mod digest_md5 {
 #[cfg(feature = "external")]
 pub mod external {
     //! `EXTERNAL` *mechanism. Requires feature `external`*
+    //!
+    //! Authentication established outside of SASL, e.g. with a TLS client certificate or by
+    //! connecting to a Unix domain socket ([RFC 4422, Appendix A](https://www.rfc-editor.org/rfc/rfc4422#appendix-A)).
+    //! The client only sends the [`AuthzId`](crate::property::AuthzId) it wants to act as.
+    //!
+    //! Servers attach what they know about the peer to the session before the exchange, so the
+    //! [`EXTERNAL`](crate::validate::validations::EXTERNAL) validation can decide on it:
+    //! ```
+    //! # use std::sync::Arc;
+    //! # use rsasl::mechname::Mechname;
+    //! use rsasl::property::{CertificateIdentity, SubjectAltName, TlsClientIdentity};
+    //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    //! # let sasl = rsasl::SASL::new();
+    //! let mut session = sasl.server_start(Mechname::new(b"EXTERNAL").unwrap()).unwrap();
+    //! // Taken from the certificate the TLS library verified
+    //! session.set_property::<TlsClientIdentity>(Arc::new(CertificateIdentity {
+    //!     subject: "CN=backup,O=Example".to_string(),
+    //!     subject_alt_names: vec![SubjectAltName::Dns("backup.example.org".to_string())],
+    //! }));
+    //! # Ok(())
+    //! # }
+    //! ```
+    //! On Unix domain sockets [`UnixCredentials::of`](crate::property::UnixCredentials::of)
+    //! reads the credentials to set as [`PeerCredentials`](crate::property::PeerCredentials).
+    pub mod client;
+    pub mod mechinfo;
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    ))]
+    pub mod peercred;
+    pub mod server;
+}
+
+#[cfg(feature = "gs2")]
+pub mod gs2 {
+    //! `GS2-*` *mechanisms. Requires feature `gs2`*
+    //!
+    //! [RFC 5801](https://www.rfc-editor.org/rfc/rfc5801) turns any GSS-API mechanism into the SASL
+    //! mechanisms `GS2-<hash>` and `GS2-<hash>-PLUS`, the latter binding the context to the
+    //! channel binding data set on the session. Implement
+    //! [`GssMechanism`](context::GssMechanism) for the GSS-API mechanism and register both
+    //! variants with [`register`](mechinfo::register):
+    //! ```
+    //! # use rsasl::error::SessionError;
+    //! # use rsasl::session::SessionData;
+    //! use rsasl::mechanisms::gs2::context::{GssContext, GssMechanism, GssStep};
+    //! use rsasl::mechanisms::gs2::mechinfo::register;
+    //! # struct Initiator;
+    //! # impl GssContext for Initiator {
+    //! #     fn step(&mut self, _: &mut SessionData, _: Option<&[u8]>) -> Result<GssStep, SessionError> {
+    //! #         unimplemented!()
+    //! #     }
+    //! # }
+    //! # type Acceptor = Initiator;
+    //!
+    //! struct Spkm1;
+    //! impl GssMechanism for Spkm1 {
+    //!     const OID: &'static [u8] = b"\x2b\x06\x01\x05\x05\x01\x01";
+    //!     type Client = Initiator;
+    //!     type Server = Acceptor;
+    //!     fn client(_: &mut SessionData, _channel_bindings: &[u8]) -> Result<Initiator, SessionError> {
+    //!         Ok(Initiator)
+    //!     }
+    //!     fn server(_: &mut SessionData, _channel_bindings: &[u8]) -> Result<Acceptor, SessionError> {
+    //!         Ok(Initiator)
+    //!     }
+    //! }
+    //!
+    //! let mut sasl = rsasl::SASL::new();
+    //! register::<Spkm1>(&mut sasl);
+    //! assert!(sasl.client_supports(rsasl::mechname::Mechname::new(b"GS2-DT4PIK22T6A-PLUS").unwrap()));
+    //! ```
+    pub mod client;
+    pub mod context;
+    pub mod mechinfo;
+    mod protocol;
+    pub mod server;
+}
+
+#[cfg(feature = "unstable_custom_mechanism")]
+pub mod gs2_header;
+#[cfg(all(
+    not(feature = "unstable_custom_mechanism"),
+    any(
+        feature = "scram-sha-1",
+        feature = "scram-sha-2",
+        feature = "saml20",
+        feature = "openid20",
+        feature = "gs2"
+    )
+))]
+#[allow(dead_code)]
+mod gs2_header;
+
+#[cfg(feature = "ht-sha-256")]
+pub mod ht {
+    //! `HT-SHA-256-*` *mechanisms. Requires feature `ht-sha-256`*
+    //!
+    //! The hashed token mechanisms ([draft-schmaus-kitten-sasl-ht](https://datatracker.ietf.org/doc/draft-schmaus-kitten-sasl-ht/))
+    //! authenticate using a token previously issued by the server, e.g. with XMPP FAST
+    //! ([XEP-0484](https://xmpp.org/extensions/xep-0484.html)). Clients provide the token with the
+    //! [`HtToken`](crate::property::HtToken) property, servers look it up during the
+    //! [`HT`](crate::validate::validations::HT) validation. The `-UNIQ`, `-ENDP` and `-EXPR`
+    //! variants bind the exchange to the channel binding data set on the session.
     pub mod client;
     pub mod mechinfo;
     pub mod server;
+    pub mod token;
 }
 
 #[cfg(feature = "login")]
 pub mod login {
     //! `LOGIN` *mechanism. Requires feature `login`*
+    //!
+    //! The client answers the prompts of the server by their wording, the server sends the prompts
+    //! set with the [`LoginPrompts`](crate::property::LoginPrompts) property.
     pub mod client;
     pub mod mechinfo;
+    pub mod prompts;
+    pub mod server;
+    #[cfg(feature = "session_state")]
+    pub(crate) mod state;
+}
+
+#[cfg(feature = "ntlm")]
+pub mod ntlm {
+    //! `NTLM` *mechanism. Requires feature `ntlm`*
+    //!
+    //! NTLMv2 authentication as offered by Microsoft Exchange and other Windows services. Servers
+    //! verify responses with the NT hash provided by the [`NtlmSecret`](crate::property::NtlmSecret)
+    //! property or computed from the [`Password`](crate::property::Password).
+    //!
+    //! NTLM is only provided for compatibility, its security factors are
+    //! [`NTLM_SECURITY`](mechinfo::NTLM_SECURITY). It is not enabled by the default features.
+    pub mod client;
+    mod message;
+    pub mod mechinfo;
     pub mod server;
 }
 
 #[cfg(feature = "openid20")]
 pub mod openid20 {
     //! `OPENID20` *mechanism. Requires feature `openid20`*
+    //!
+    //! OpenID authentication of [RFC 6616](https://www.rfc-editor.org/rfc/rfc6616). Clients have
+    //! to open the URL the server sends in a web browser when queried for
+    //! [`OpenID20AuthenticateInBrowser`](crate::property::OpenID20AuthenticateInBrowser), servers
+    //! provide that URL with [`OpenID20RedirectUrl`](crate::property::OpenID20RedirectUrl) and
+    //! check the outcome during the [`OPENID20`](crate::validate::validations::OPENID20)
+    //! validation.
     pub mod client;
     pub mod mechinfo;
     pub mod server;
 }
 
+#[cfg(feature = "otp")]
+pub mod otp {
+    //! `OTP` and `X-OATH` *mechanisms. Requires feature `otp`*
+    //!
+    //! One-time passwords of [RFC 2289](https://www.rfc-editor.org/rfc/rfc2289) (S/KEY) as used
+    //! by [RFC 2444](https://www.rfc-editor.org/rfc/rfc2444). Clients use the
+    //! [`Password`](crate::property::Password) as pass phrase, servers look up the sequence of
+    //! the user with the [`OtpState`](crate::property::OtpState) property and store the updated
+    //! one during the [`OTP`](crate::validate::validations::OTP) validation.
+    //!
+    //! HOTP and TOTP secrets are instead used with the non-standard `X-OATH` mechanism, see
+    //! [`oath`]. Its server looks up the secret with [`OathState`](crate::property::OathState).
+    pub mod client;
+    mod dictionary;
+    pub mod mechinfo;
+    pub mod oath;
+    pub mod password;
+    pub mod server;
+}
+
 #[cfg(feature = "plain")]
 pub mod plain {
     //! `PLAIN` *mechanism. Requires feature `plain`*
@@ -94,6 +263,13 @@ pub mod plain {
 #[cfg(feature = "saml20")]
 pub mod saml20 {
     //! `SAML20` *mechanism. Requires feature `saml20`*
+    //!
+    //! SAML authentication of [RFC 6595](https://www.rfc-editor.org/rfc/rfc6595). Clients have to
+    //! open the URL the server sends in a web browser when queried for
+    //! [`Saml20AuthenticateInBrowser`](crate::property::Saml20AuthenticateInBrowser), servers
+    //! provide that URL with [`SAML20RedirectUrl`](crate::property::SAML20RedirectUrl) and check
+    //! the assertion the IdP issued during the [`SAML20`](crate::validate::validations::SAML20)
+    //! validation.
     pub mod client;
     pub mod mechinfo;
     pub mod server;
@@ -108,6 +284,9 @@ pub mod scram {
     pub mod parser;
     pub mod printer;
     pub mod server;
+    pub mod ssdp;
+    #[cfg(feature = "session_state")]
+    pub(crate) mod state;
     pub mod tokens;
     pub mod tools;
     pub mod validate;
@@ -120,3 +299,25 @@ pub mod securid {
     pub mod mechinfo;
     pub mod server;
 }
+
+#[cfg(feature = "srp")]
+pub mod srp {
+    //! `SRP` *mechanism. Requires feature `srp`*
+    //!
+    //! Password authentication with the Secure Remote Password protocol. The server stores a
+    //! [`SrpVerifier`](crate::property::SrpVerifier) instead of the password, which can't be used
+    //! to authenticate as the user, and both sides prove knowing their secret. Verifiers are
+    //! created with [`SrpVerifier::new`](crate::property::SrpVerifier::new) and provided to the
+    //! server as the [`SrpSecret`](crate::property::SrpSecret) property.
+    //!
+    //! An integrity or confidentiality layer can be requested with the
+    //! [`SrpSecurityLayer`](crate::property::SrpSecurityLayer) property. It is not enabled by the
+    //! default features.
+    pub mod client;
+    mod groups;
+    mod layer;
+    pub mod mechinfo;
+    mod protocol;
+    pub mod server;
+    mod verifier;
+}
@@ -0,0 +1,117 @@
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::ntlm::message::{
+    channel_bindings_hash, hmac_md5, mic, negotiate, ntlmv2_response, ntowf_v2, set_mic, timestamp,
+    Authenticate, Challenge, NtlmError, CLIENT_FLAGS,
+};
+use crate::property::{AuthId, NtHash, Password, Realm};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use std::io::Write;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+enum State {
+    #[default]
+    Initial,
+    Negotiated,
+    Completed,
+}
+
+/// Client side of `NTLM`
+///
+/// Authenticates with an NTLMv2 response to the server's challenge. The domain is taken from the
+/// [`Realm`] property or, if that isn't provided, from an [`AuthId`] of the form `DOMAIN\user`.
+/// Channel binding data set on the session is included in the response.
+#[derive(Debug, Default)]
+pub struct Ntlm {
+    state: State,
+    negotiate: Vec<u8>,
+}
+
+impl Ntlm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The user and domain to authenticate as
+    fn identity(session: &mut SessionData) -> Result<(String, String), SessionError> {
+        let authid = session
+            .get_property_or_callback::<AuthId>()?
+            .ok_or(SessionError::no_property::<AuthId>())?;
+        if let Some(realm) = session.get_property_or_callback::<Realm>()? {
+            return Ok((authid.to_string(), realm.to_string_lossy().into_owned()));
+        }
+        match authid.split_once('\\') {
+            Some((domain, user)) => Ok((user.to_string(), domain.to_string())),
+            None => Ok((authid.to_string(), String::new())),
+        }
+    }
+}
+
+impl Authentication for Ntlm {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        match self.state {
+            State::Initial => {
+                self.negotiate = negotiate();
+                writer.write_all(&self.negotiate)?;
+                self.state = State::Negotiated;
+                Ok(NeedsMore(Some(self.negotiate.len())))
+            }
+            State::Negotiated => {
+                let input = input.ok_or(SessionError::InputDataRequired)?;
+                let challenge = Challenge::parse(input)?;
+
+                let (user, domain) = Self::identity(session)?;
+                let password = session
+                    .get_property_or_callback::<Password>()?
+                    .ok_or(SessionError::no_property::<Password>())?;
+                let key = ntowf_v2(&NtHash::from_password(&password), &user, &domain);
+
+                let client_challenge: [u8; 8] = rand::random();
+                let server_timestamp = challenge.target_info.timestamp();
+                let target_info = challenge
+                    .target_info
+                    .for_client(channel_bindings_hash(session.get_cb_data()));
+                let (nt_response, session_key) = ntlmv2_response(
+                    &key,
+                    &challenge.server_challenge,
+                    &client_challenge,
+                    server_timestamp.unwrap_or_else(timestamp),
+                    &target_info,
+                );
+                // The LMv2 response must be empty if the server sent a timestamp
+                let lm_response = match server_timestamp {
+                    Some(_) => vec![0; 24],
+                    None => {
+                        let proof =
+                            hmac_md5(&key, &[&challenge.server_challenge, &client_challenge]);
+                        [&proof[..], &client_challenge].concat()
+                    }
+                };
+
+                let mut authenticate = Authenticate {
+                    flags: challenge.flags & CLIENT_FLAGS,
+                    lm_response,
+                    nt_response,
+                    domain,
+                    user,
+                    workstation: String::new(),
+                    mic: [0; 16],
+                }
+                .encode();
+                let mic = mic(&session_key, &self.negotiate, input, &authenticate);
+                set_mic(&mut authenticate, &mic);
+
+                writer.write_all(&authenticate)?;
+                self.state = State::Completed;
+                Ok(Done(Some(authenticate.len())))
+            }
+            State::Completed => Err(NtlmError::Completed.into()),
+        }
+    }
+}
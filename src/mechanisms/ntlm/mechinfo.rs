@@ -0,0 +1,31 @@
+use crate::mechanisms::ntlm::{client, server};
+use crate::property::properties::{AUTHID, HOSTNAME, NTLM_SECRET, PASSWORD, REALM};
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
+use crate::{Mechanism, Mechname, Side};
+
+#[cfg(feature = "registry_static")]
+use crate::registry::{distributed_slice, MECHANISMS};
+#[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
+pub static NTLM: Mechanism = Mechanism {
+    mechanism: Mechname::const_new_unchecked(b"NTLM"),
+    priority: 50,
+    client: Some(|_sasl| Ok(Box::new(client::Ntlm::new()))),
+    server: Some(|_sasl| Ok(Box::new(server::Ntlm::new()))),
+    first: Side::Client,
+    client_properties: PropertyRequirements::new(&[AUTHID, PASSWORD], &[REALM]),
+    server_properties: PropertyRequirements::new(&[PASSWORD], &[NTLM_SECRET, HOSTNAME, REALM]),
+    description: "Legacy NTLMv2 challenge-response of Windows services",
+    specification: Some("MS-NLMP"),
+    channel_binding: false,
+    security_layer: false,
+    security: NTLM_SECURITY,
+};
+
+/// NTLM doesn't send the password but MD4 and HMAC-MD5 over low-entropy secrets are easily brute
+/// forced from a captured exchange and the server can't be authenticated.
+pub const NTLM_SECURITY: MechanismSecurityFactors = MechanismSecurityFactors {
+    max_ssf: 0,
+    noplain: true,
+    mutual: false,
+    weak: true,
+};
@@ -0,0 +1,629 @@
+//! Encoding and decoding of the NTLM messages and the NTLMv2 computations
+//!
+//! See [MS-NLMP](https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-nlmp/) for the
+//! message layout. Only the parts required for NTLMv2 authentication without a security layer
+//! are implemented, i.e. neither key exchange nor signing and sealing are negotiated.
+
+use crate::error::{MechanismError, MechanismErrorKind};
+use crate::property::NtHash;
+use hmac::{Hmac, Mac};
+use md4::{Digest, Md4};
+use md5::Md5;
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+type HmacMd5 = Hmac<Md5>;
+
+const SIGNATURE: &[u8] = b"NTLMSSP\0";
+const NEGOTIATE: u32 = 1;
+const CHALLENGE: u32 = 2;
+const AUTHENTICATE: u32 = 3;
+
+pub(super) const NEGOTIATE_UNICODE: u32 = 0x0000_0001;
+const REQUEST_TARGET: u32 = 0x0000_0004;
+pub(super) const NEGOTIATE_NTLM: u32 = 0x0000_0200;
+const NEGOTIATE_ALWAYS_SIGN: u32 = 0x0000_8000;
+const TARGET_TYPE_DOMAIN: u32 = 0x0001_0000;
+const NEGOTIATE_EXTENDED_SESSIONSECURITY: u32 = 0x0008_0000;
+const NEGOTIATE_TARGET_INFO: u32 = 0x0080_0000;
+const NEGOTIATE_VERSION: u32 = 0x0200_0000;
+const NEGOTIATE_128: u32 = 0x2000_0000;
+const NEGOTIATE_56: u32 = 0x8000_0000;
+
+/// Flags the client sends in its `NEGOTIATE` message
+pub(super) const CLIENT_FLAGS: u32 = NEGOTIATE_UNICODE
+    | REQUEST_TARGET
+    | NEGOTIATE_NTLM
+    | NEGOTIATE_ALWAYS_SIGN
+    | NEGOTIATE_EXTENDED_SESSIONSECURITY
+    | NEGOTIATE_TARGET_INFO
+    | NEGOTIATE_VERSION
+    | NEGOTIATE_128
+    | NEGOTIATE_56;
+
+/// Flags of the client the server echoes in its `CHALLENGE` message
+const SERVER_FLAGS: u32 = NEGOTIATE_UNICODE
+    | NEGOTIATE_NTLM
+    | NEGOTIATE_ALWAYS_SIGN
+    | NEGOTIATE_EXTENDED_SESSIONSECURITY
+    | NEGOTIATE_VERSION
+    | NEGOTIATE_128
+    | NEGOTIATE_56;
+
+/// Windows 10, NTLMSSP revision 15
+const VERSION: [u8; 8] = [10, 0, 0x61, 0x4a, 0, 0, 0, 15];
+
+const AV_EOL: u16 = 0;
+const AV_NB_COMPUTER_NAME: u16 = 1;
+const AV_NB_DOMAIN_NAME: u16 = 2;
+const AV_FLAGS: u16 = 6;
+const AV_TIMESTAMP: u16 = 7;
+const AV_CHANNEL_BINDINGS: u16 = 0x0a;
+
+/// `MsvAvFlags` bit indicating the `AUTHENTICATE` message contains a MIC
+const AV_FLAG_MIC: u32 = 0x2;
+
+/// Length of the fixed part of an `AUTHENTICATE` message including version and MIC
+const AUTHENTICATE_LEN: usize = 88;
+const MIC_OFFSET: usize = 72;
+/// Offset of the AV pairs in an NTLMv2 response, after the `NTProofStr` and the fixed part of
+/// the client challenge structure
+const RESPONSE_AV_OFFSET: usize = 44;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(super) enum NtlmError {
+    BadFormat,
+    BadSignature,
+    UnexpectedType(u32),
+    BadUtf16,
+    /// The peer did not negotiate the Unicode encoding or NTLM authentication
+    Unsupported,
+    /// Only NTLMv2 responses are accepted
+    NtlmV1,
+    /// The message integrity code of the exchange didn't match
+    BadMic,
+    /// The client's channel bindings do not match the ones set on the session
+    ChannelBindingMismatch,
+    Completed,
+}
+
+impl Display for NtlmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadFormat => f.write_str("invalid format of the NTLM message"),
+            Self::BadSignature => f.write_str("NTLM message is missing the NTLMSSP signature"),
+            Self::UnexpectedType(t) => write!(f, "unexpected NTLM message type {}", t),
+            Self::BadUtf16 => f.write_str("NTLM message contains invalid UTF-16"),
+            Self::Unsupported => f.write_str("peer does not support Unicode NTLM authentication"),
+            Self::NtlmV1 => f.write_str("NTLMv1 responses are not supported"),
+            Self::BadMic => f.write_str("message integrity code of the NTLM exchange is invalid"),
+            Self::ChannelBindingMismatch => f.write_str("NTLM channel bindings do not match"),
+            Self::Completed => f.write_str("the exchange is already complete"),
+        }
+    }
+}
+
+impl MechanismError for NtlmError {
+    fn kind(&self) -> MechanismErrorKind {
+        match self {
+            Self::BadFormat | Self::BadSignature | Self::UnexpectedType(_) | Self::BadUtf16 => {
+                MechanismErrorKind::Parse
+            }
+            Self::BadMic | Self::ChannelBindingMismatch => MechanismErrorKind::Outcome,
+            Self::Unsupported | Self::NtlmV1 | Self::Completed => MechanismErrorKind::Protocol,
+        }
+    }
+}
+
+impl NtHash {
+    /// The NT hash of a password, i.e. MD4 over its UTF-16LE encoding
+    pub fn from_password(password: &str) -> Self {
+        let hash = Md4::digest(utf16le(password));
+        Self(hash.into())
+    }
+}
+
+pub(super) fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn from_utf16le(bytes: &[u8]) -> Result<String, NtlmError> {
+    let chunks = bytes.chunks_exact(2);
+    if !chunks.remainder().is_empty() {
+        return Err(NtlmError::BadUtf16);
+    }
+    let units: Vec<u16> = chunks
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect();
+    String::from_utf16(&units).map_err(|_| NtlmError::BadUtf16)
+}
+
+pub(super) fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> [u8; 16] {
+    let mut mac = HmacMd5::new_from_slice(key).expect("HMAC can take keys of any size");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/// `NTOWFv2`, the key of the NTLMv2 responses
+pub(super) fn ntowf_v2(hash: &NtHash, user: &str, domain: &str) -> [u8; 16] {
+    let identity = utf16le(&(user.to_uppercase() + domain));
+    hmac_md5(&hash.0, &[&identity])
+}
+
+/// The current time as Windows `FILETIME`, i.e. in 100ns intervals since 1601-01-01
+pub(super) fn timestamp() -> [u8; 8] {
+    const EPOCH_DIFFERENCE: u64 = 11_644_473_600;
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let intervals =
+        (time.as_secs() + EPOCH_DIFFERENCE) * 10_000_000 + time.subsec_nanos() as u64 / 100;
+    intervals.to_le_bytes()
+}
+
+/// The `MsvAvChannelBindings` value, i.e. the MD5 hash of a `gss_channel_bindings_struct` with
+/// empty addresses and the channel binding data as application data
+pub(super) fn channel_bindings_hash(cb: Option<(&str, &[u8])>) -> [u8; 16] {
+    let (name, data) = match cb {
+        Some(cb) => cb,
+        None => return [0; 16],
+    };
+    let application_data = [name.as_bytes(), b":", data].concat();
+    let mut md5 = Md5::new();
+    md5.update([0; 16]);
+    md5.update((application_data.len() as u32).to_le_bytes());
+    md5.update(&application_data);
+    md5.finalize().into()
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> Result<u16, NtlmError> {
+    buf.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or(NtlmError::BadFormat)
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> Result<u32, NtlmError> {
+    buf.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(NtlmError::BadFormat)
+}
+
+/// Read the payload field described by the length and offset at `offset`
+fn field_at(buf: &[u8], offset: usize) -> Result<&[u8], NtlmError> {
+    let len = u16_at(buf, offset)? as usize;
+    if len == 0 {
+        return Ok(&[]);
+    }
+    let start = u32_at(buf, offset + 4)? as usize;
+    let end = start.checked_add(len).ok_or(NtlmError::BadFormat)?;
+    buf.get(start..end).ok_or(NtlmError::BadFormat)
+}
+
+/// Check the signature and type of a message
+fn check_header(buf: &[u8], expected: u32) -> Result<(), NtlmError> {
+    if !buf.starts_with(SIGNATURE) {
+        return Err(NtlmError::BadSignature);
+    }
+    match u32_at(buf, 8)? {
+        t if t == expected => Ok(()),
+        t => Err(NtlmError::UnexpectedType(t)),
+    }
+}
+
+/// Builds a message from its fixed part and the fields stored in the payload after it
+struct MessageBuilder {
+    fixed: Vec<u8>,
+    payload: Vec<u8>,
+    len: usize,
+}
+
+impl MessageBuilder {
+    fn new(message_type: u32, len: usize) -> Self {
+        let mut fixed = Vec::with_capacity(len);
+        fixed.extend_from_slice(SIGNATURE);
+        fixed.extend_from_slice(&message_type.to_le_bytes());
+        Self {
+            fixed,
+            payload: Vec::new(),
+            len,
+        }
+    }
+
+    fn field(&mut self, value: &[u8]) -> &mut Self {
+        let len = value.len() as u16;
+        self.fixed.extend_from_slice(&len.to_le_bytes());
+        self.fixed.extend_from_slice(&len.to_le_bytes());
+        let offset = (self.len + self.payload.len()) as u32;
+        self.fixed.extend_from_slice(&offset.to_le_bytes());
+        self.payload.extend_from_slice(value);
+        self
+    }
+
+    fn bytes(&mut self, value: &[u8]) -> &mut Self {
+        self.fixed.extend_from_slice(value);
+        self
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        debug_assert_eq!(self.fixed.len(), self.len);
+        let mut message = std::mem::take(&mut self.fixed);
+        message.append(&mut self.payload);
+        message
+    }
+}
+
+/// The AV pairs of a target information block, without the terminating `MsvAvEOL`
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub(super) struct AvPairs(Vec<(u16, Vec<u8>)>);
+
+impl AvPairs {
+    pub(super) fn parse(mut buf: &[u8]) -> Result<Self, NtlmError> {
+        let mut pairs = Vec::new();
+        loop {
+            let id = u16_at(buf, 0)?;
+            let len = u16_at(buf, 2)? as usize;
+            let value = buf.get(4..4 + len).ok_or(NtlmError::BadFormat)?;
+            if id == AV_EOL {
+                return Ok(Self(pairs));
+            }
+            pairs.push((id, value.to_vec()));
+            buf = &buf[4 + len..];
+        }
+    }
+
+    pub(super) fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for (id, value) in self.0.iter() {
+            buf.extend_from_slice(&id.to_le_bytes());
+            buf.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            buf.extend_from_slice(value);
+        }
+        buf.extend_from_slice(&[0; 4]);
+        buf
+    }
+
+    fn get(&self, id: u16) -> Option<&[u8]> {
+        self.0
+            .iter()
+            .find(|(pair, _)| *pair == id)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    fn set(&mut self, id: u16, value: Vec<u8>) {
+        match self.0.iter_mut().find(|(pair, _)| *pair == id) {
+            Some((_, old)) => *old = value,
+            None => self.0.push((id, value)),
+        }
+    }
+
+    pub(super) fn timestamp(&self) -> Option<[u8; 8]> {
+        self.get(AV_TIMESTAMP)?.try_into().ok()
+    }
+
+    pub(super) fn channel_bindings(&self) -> Option<&[u8]> {
+        self.get(AV_CHANNEL_BINDINGS)
+    }
+
+    pub(super) fn has_mic(&self) -> bool {
+        self.get(AV_FLAGS)
+            .and_then(|flags| flags.try_into().ok())
+            .map(u32::from_le_bytes)
+            .is_some_and(|flags| flags & AV_FLAG_MIC != 0)
+    }
+
+    /// The target information the server sends
+    pub(super) fn for_server(domain: &str, computer: &str, timestamp: [u8; 8]) -> Self {
+        Self(vec![
+            (AV_NB_DOMAIN_NAME, utf16le(domain)),
+            (AV_NB_COMPUTER_NAME, utf16le(computer)),
+            (AV_TIMESTAMP, timestamp.to_vec()),
+        ])
+    }
+
+    /// The target information the client returns, adding its channel bindings and announcing
+    /// the MIC
+    pub(super) fn for_client(mut self, channel_bindings: [u8; 16]) -> Self {
+        let flags = self
+            .get(AV_FLAGS)
+            .and_then(|flags| flags.try_into().ok())
+            .map(u32::from_le_bytes)
+            .unwrap_or(0);
+        self.set(AV_FLAGS, (flags | AV_FLAG_MIC).to_le_bytes().to_vec());
+        self.set(AV_CHANNEL_BINDINGS, channel_bindings.to_vec());
+        self
+    }
+}
+
+pub(super) fn negotiate() -> Vec<u8> {
+    MessageBuilder::new(NEGOTIATE, 40)
+        .bytes(&CLIENT_FLAGS.to_le_bytes())
+        .field(&[])
+        .field(&[])
+        .bytes(&VERSION)
+        .finish()
+}
+
+/// Parse a `NEGOTIATE` message, returning the flags of the client
+pub(super) fn parse_negotiate(buf: &[u8]) -> Result<u32, NtlmError> {
+    check_header(buf, NEGOTIATE)?;
+    u32_at(buf, 12)
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(super) struct Challenge {
+    pub(super) flags: u32,
+    pub(super) server_challenge: [u8; 8],
+    pub(super) target_name: String,
+    pub(super) target_info: AvPairs,
+}
+
+impl Challenge {
+    /// The challenge a server answers a `NEGOTIATE` message with `flags` with
+    pub(super) fn new(client_flags: u32, domain: &str, target_info: AvPairs) -> Self {
+        Self {
+            flags: (client_flags & SERVER_FLAGS)
+                | REQUEST_TARGET
+                | TARGET_TYPE_DOMAIN
+                | NEGOTIATE_TARGET_INFO,
+            server_challenge: rand::random(),
+            target_name: domain.to_string(),
+            target_info,
+        }
+    }
+
+    pub(super) fn encode(&self) -> Vec<u8> {
+        MessageBuilder::new(CHALLENGE, 56)
+            .field(&utf16le(&self.target_name))
+            .bytes(&self.flags.to_le_bytes())
+            .bytes(&self.server_challenge)
+            .bytes(&[0; 8])
+            .field(&self.target_info.encode())
+            .bytes(&VERSION)
+            .finish()
+    }
+
+    pub(super) fn parse(buf: &[u8]) -> Result<Self, NtlmError> {
+        check_header(buf, CHALLENGE)?;
+        let flags = u32_at(buf, 20)?;
+        if flags & NEGOTIATE_UNICODE == 0 || flags & NEGOTIATE_NTLM == 0 {
+            return Err(NtlmError::Unsupported);
+        }
+        let target_name = from_utf16le(field_at(buf, 12)?)?;
+        let server_challenge = buf
+            .get(24..32)
+            .ok_or(NtlmError::BadFormat)?
+            .try_into()
+            .unwrap();
+        let target_info = match field_at(buf, 40)? {
+            [] => AvPairs::default(),
+            target_info => AvPairs::parse(target_info)?,
+        };
+        Ok(Self {
+            flags,
+            server_challenge,
+            target_name,
+            target_info,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(super) struct Authenticate {
+    pub(super) flags: u32,
+    pub(super) lm_response: Vec<u8>,
+    pub(super) nt_response: Vec<u8>,
+    pub(super) domain: String,
+    pub(super) user: String,
+    pub(super) workstation: String,
+    pub(super) mic: [u8; 16],
+}
+
+impl Authenticate {
+    /// Encode the message with the MIC set to zero, see [`set_mic`]
+    pub(super) fn encode(&self) -> Vec<u8> {
+        MessageBuilder::new(AUTHENTICATE, AUTHENTICATE_LEN)
+            .field(&self.lm_response)
+            .field(&self.nt_response)
+            .field(&utf16le(&self.domain))
+            .field(&utf16le(&self.user))
+            .field(&utf16le(&self.workstation))
+            .field(&[])
+            .bytes(&self.flags.to_le_bytes())
+            .bytes(&VERSION)
+            .bytes(&[0; 16])
+            .finish()
+    }
+
+    pub(super) fn parse(buf: &[u8]) -> Result<Self, NtlmError> {
+        check_header(buf, AUTHENTICATE)?;
+        let flags = u32_at(buf, 60)?;
+        if flags & NEGOTIATE_UNICODE == 0 {
+            return Err(NtlmError::Unsupported);
+        }
+        // Messages without a MIC may end before it, it's only checked if announced
+        let mic = buf
+            .get(MIC_OFFSET..AUTHENTICATE_LEN)
+            .map(|mic| mic.try_into().unwrap())
+            .unwrap_or_default();
+        Ok(Self {
+            flags,
+            lm_response: field_at(buf, 12)?.to_vec(),
+            nt_response: field_at(buf, 20)?.to_vec(),
+            domain: from_utf16le(field_at(buf, 28)?)?,
+            user: from_utf16le(field_at(buf, 36)?)?,
+            workstation: from_utf16le(field_at(buf, 44)?)?,
+            mic,
+        })
+    }
+
+    /// The AV pairs contained in an NTLMv2 response
+    pub(super) fn target_info(&self) -> Result<AvPairs, NtlmError> {
+        if self.nt_response.len() <= RESPONSE_AV_OFFSET {
+            return Err(NtlmError::NtlmV1);
+        }
+        AvPairs::parse(&self.nt_response[RESPONSE_AV_OFFSET..])
+    }
+}
+
+/// The message integrity code over all three messages of the exchange
+///
+/// `authenticate` is the encoded `AUTHENTICATE` message with the MIC set to zero.
+pub(super) fn mic(
+    session_key: &[u8; 16],
+    negotiate: &[u8],
+    challenge: &[u8],
+    authenticate: &[u8],
+) -> [u8; 16] {
+    hmac_md5(session_key, &[negotiate, challenge, authenticate])
+}
+
+/// Set the MIC in an encoded `AUTHENTICATE` message
+pub(super) fn set_mic(authenticate: &mut [u8], mic: &[u8; 16]) {
+    authenticate[MIC_OFFSET..AUTHENTICATE_LEN].copy_from_slice(mic);
+}
+
+/// Zero the MIC in a received `AUTHENTICATE` message, if it contains one
+pub(super) fn clear_mic(authenticate: &[u8]) -> Result<Vec<u8>, NtlmError> {
+    let mut authenticate = authenticate.to_vec();
+    authenticate
+        .get_mut(MIC_OFFSET..AUTHENTICATE_LEN)
+        .ok_or(NtlmError::BadFormat)?
+        .fill(0);
+    Ok(authenticate)
+}
+
+/// The NTLMv2 response to a challenge, i.e. `NTProofStr` followed by the client challenge
+/// structure, and the session base key derived from it
+pub(super) fn ntlmv2_response(
+    response_key: &[u8; 16],
+    server_challenge: &[u8; 8],
+    client_challenge: &[u8; 8],
+    timestamp: [u8; 8],
+    target_info: &AvPairs,
+) -> (Vec<u8>, [u8; 16]) {
+    let mut temp = vec![1, 1, 0, 0, 0, 0, 0, 0];
+    temp.extend_from_slice(&timestamp);
+    temp.extend_from_slice(client_challenge);
+    temp.extend_from_slice(&[0; 4]);
+    temp.extend_from_slice(&target_info.encode());
+    temp.extend_from_slice(&[0; 4]);
+    let proof = hmac_md5(response_key, &[server_challenge, &temp]);
+    let session_key = hmac_md5(response_key, &[&proof]);
+    ([&proof[..], &temp].concat(), session_key)
+}
+
+/// Verify an NTLMv2 response, returning the session base key if it is valid
+pub(super) fn verify_ntlmv2_response(
+    response_key: &[u8; 16],
+    server_challenge: &[u8; 8],
+    nt_response: &[u8],
+) -> Option<[u8; 16]> {
+    if nt_response.len() <= RESPONSE_AV_OFFSET {
+        return None;
+    }
+    let (proof, temp) = nt_response.split_at(16);
+    let expected = hmac_md5(response_key, &[server_challenge, temp]);
+    // Compare without short-circuiting to not leak how much of the proof matched
+    let difference = expected
+        .iter()
+        .zip(proof)
+        .fold(0, |acc, (a, b)| acc | (a ^ b));
+    if difference != 0 {
+        return None;
+    }
+    Some(hmac_md5(response_key, &[proof]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// The NTLMv2 example of MS-NLMP section 4.2.4
+    #[test]
+    fn ntlmv2_example() {
+        let hash = NtHash::from_password("Password");
+        assert_eq!(hash.0.to_vec(), hex("a4f49c406510bdcab6824ee7c30fd852"));
+        let key = ntowf_v2(&hash, "User", "Domain");
+        assert_eq!(key.to_vec(), hex("0c868a403bfd7a93a3001ef22ef02e3f"));
+
+        let target_info = AvPairs(vec![
+            (AV_NB_DOMAIN_NAME, utf16le("Domain")),
+            (AV_NB_COMPUTER_NAME, utf16le("Server")),
+        ]);
+        let server_challenge = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+        let (response, session_key) =
+            ntlmv2_response(&key, &server_challenge, &[0xaa; 8], [0; 8], &target_info);
+        assert_eq!(response[..16], hex("68cd0ab851e51c96aabc927bebef6a1c")[..]);
+        assert_eq!(
+            session_key.to_vec(),
+            hex("8de40ccadbc14a82f15cb0ad0de95ca3")
+        );
+
+        assert_eq!(
+            verify_ntlmv2_response(&key, &server_challenge, &response),
+            Some(session_key)
+        );
+        let mut tampered = response.clone();
+        tampered[20] ^= 1;
+        assert_eq!(
+            verify_ntlmv2_response(&key, &server_challenge, &tampered),
+            None
+        );
+    }
+
+    #[test]
+    fn messages() {
+        let negotiate = negotiate();
+        assert_eq!(parse_negotiate(&negotiate), Ok(CLIENT_FLAGS));
+        assert_eq!(parse_negotiate(&negotiate[..12]), Err(NtlmError::BadFormat));
+
+        let target_info = AvPairs::for_server("EXAMPLE", "MAIL", timestamp());
+        let challenge = Challenge::new(CLIENT_FLAGS, "EXAMPLE", target_info);
+        let encoded = challenge.encode();
+        assert_eq!(Challenge::parse(&encoded), Ok(challenge));
+        assert_eq!(
+            parse_negotiate(&encoded),
+            Err(NtlmError::UnexpectedType(CHALLENGE))
+        );
+
+        let authenticate = Authenticate {
+            flags: CLIENT_FLAGS,
+            lm_response: vec![0; 24],
+            nt_response: vec![7; 64],
+            domain: "EXAMPLE".to_string(),
+            user: "Jürgen".to_string(),
+            workstation: String::new(),
+            mic: [0; 16],
+        };
+        assert_eq!(
+            Authenticate::parse(&authenticate.encode()),
+            Ok(authenticate)
+        );
+    }
+
+    #[test]
+    fn av_pairs() {
+        let pairs = AvPairs::for_server("EXAMPLE", "MAIL", [1; 8]);
+        assert_eq!(AvPairs::parse(&pairs.encode()), Ok(pairs.clone()));
+        assert_eq!(pairs.timestamp(), Some([1; 8]));
+        assert!(!pairs.has_mic());
+
+        let pairs = pairs.for_client([2; 16]);
+        assert!(pairs.has_mic());
+        assert_eq!(pairs.channel_bindings(), Some(&[2; 16][..]));
+
+        // Missing the terminating MsvAvEOL
+        let encoded = pairs.encode();
+        assert!(AvPairs::parse(&encoded[..encoded.len() - 4]).is_err());
+    }
+}
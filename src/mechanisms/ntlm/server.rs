@@ -0,0 +1,150 @@
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::ntlm::message::{
+    channel_bindings_hash, clear_mic, mic, ntowf_v2, parse_negotiate, timestamp,
+    verify_ntlmv2_response, Authenticate, AvPairs, Challenge, NtlmError, NEGOTIATE_NTLM,
+    NEGOTIATE_UNICODE,
+};
+use crate::property::{AuthId, Hostname, NtHash, NtlmSecret, Password, Realm};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use std::ffi::CString;
+use std::io::Write;
+use std::sync::Arc;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+enum State {
+    #[default]
+    Initial,
+    Challenged,
+    Completed,
+}
+
+/// Server side of `NTLM`
+///
+/// Sends a challenge naming the domain from the [`Realm`] property, `WORKGROUP` by default, and
+/// verifies the client's NTLMv2 response against the [`NtlmSecret`] property if the callback
+/// provides one, and against the [`Password`] otherwise. The user and domain the client
+/// authenticated as are set as [`AuthId`] and [`Realm`] before the secret is queried.
+///
+/// If channel binding data is set on the session the client must have included matching
+/// channel bindings. NTLMv1 responses are rejected.
+#[derive(Debug, Default)]
+pub struct Ntlm {
+    state: State,
+    negotiate: Vec<u8>,
+    challenge: Vec<u8>,
+    server_challenge: [u8; 8],
+}
+
+impl Ntlm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn challenge(
+        &mut self,
+        session: &mut SessionData,
+        negotiate: &[u8],
+    ) -> Result<(), SessionError> {
+        let flags = parse_negotiate(negotiate)?;
+        if flags & NEGOTIATE_UNICODE == 0 || flags & NEGOTIATE_NTLM == 0 {
+            return Err(NtlmError::Unsupported.into());
+        }
+        let domain = session
+            .get_property_or_callback::<Realm>()?
+            .map(|realm| realm.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "WORKGROUP".to_string());
+        let computer = session
+            .get_property_or_callback::<Hostname>()?
+            .map(|hostname| hostname.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "localhost".to_string());
+
+        let target_info = AvPairs::for_server(&domain, &computer, timestamp());
+        let challenge = Challenge::new(flags, &domain, target_info);
+        self.server_challenge = challenge.server_challenge;
+        self.negotiate = negotiate.to_vec();
+        self.challenge = challenge.encode();
+        Ok(())
+    }
+
+    fn verify(&self, session: &mut SessionData, input: &[u8]) -> Result<(), SessionError> {
+        let authenticate = Authenticate::parse(input)?;
+        let target_info = authenticate.target_info()?;
+        // Anonymous authentication isn't supported
+        if authenticate.user.is_empty() {
+            return Err(SessionError::AuthenticationFailure);
+        }
+
+        session.set_property::<AuthId>(Arc::new(authenticate.user.clone()));
+        if authenticate.domain.is_empty() {
+            session.clear_property::<Realm>();
+        } else {
+            let domain =
+                CString::new(authenticate.domain.as_str()).map_err(|_| NtlmError::BadFormat)?;
+            session.set_property::<Realm>(Arc::new(domain));
+        }
+        let hash = match session.get_property_or_callback::<NtlmSecret>()? {
+            Some(hash) => *hash,
+            None => {
+                let password = session
+                    .get_property_or_callback::<Password>()?
+                    .ok_or(SessionError::no_property::<Password>())?;
+                NtHash::from_password(&password)
+            }
+        };
+
+        let key = ntowf_v2(&hash, &authenticate.user, &authenticate.domain);
+        let session_key =
+            verify_ntlmv2_response(&key, &self.server_challenge, &authenticate.nt_response)
+                .ok_or(SessionError::AuthenticationFailure)?;
+
+        if let Some(cb) = session.get_cb_data() {
+            if target_info.channel_bindings() != Some(&channel_bindings_hash(Some(cb))[..]) {
+                return Err(NtlmError::ChannelBindingMismatch.into());
+            }
+        }
+        if target_info.has_mic() {
+            let expected = mic(
+                &session_key,
+                &self.negotiate,
+                &self.challenge,
+                &clear_mic(input)?,
+            );
+            let difference = expected
+                .iter()
+                .zip(authenticate.mic.iter())
+                .fold(0, |acc, (a, b)| acc | (a ^ b));
+            if difference != 0 {
+                return Err(NtlmError::BadMic.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Authentication for Ntlm {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        match (self.state, input) {
+            (State::Initial, None) | (State::Initial, Some(&[])) => Ok(NeedsMore(None)),
+            (State::Initial, Some(negotiate)) => {
+                self.challenge(session, negotiate)?;
+                writer.write_all(&self.challenge)?;
+                self.state = State::Challenged;
+                Ok(NeedsMore(Some(self.challenge.len())))
+            }
+            (State::Challenged, input) => {
+                let input = input.ok_or(SessionError::InputDataRequired)?;
+                self.state = State::Completed;
+                self.verify(session, input)?;
+                Ok(Done(None))
+            }
+            (State::Completed, _) => Err(NtlmError::Completed.into()),
+        }
+    }
+}
@@ -1,39 +1,35 @@
-use crate::gsasl::gsasl::{CMechanismStateKeeper, MechanismVTable};
-use crate::mechanisms::openid20::client::{
-    _gsasl_openid20_client_finish, _gsasl_openid20_client_start, _gsasl_openid20_client_step,
-};
-use crate::mechanisms::openid20::server::{
-    _gsasl_openid20_server_finish, _gsasl_openid20_server_start, _gsasl_openid20_server_step,
+use crate::mechanisms::openid20::{client, server};
+use crate::property::properties::{
+    AUTHID, AUTHZID, OPENID20_AUTHENTICATE_IN_BROWSER, OPENID20_OUTCOME_DATA, OPENID20_REDIRECT_URL,
 };
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
 use crate::{Mechanism, Mechname, Side};
 
 #[cfg(feature = "registry_static")]
 use crate::registry::{distributed_slice, MECHANISMS};
 #[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
 pub static OPENID20: Mechanism = Mechanism {
-    mechanism: &Mechname::const_new_unchecked(b"OPENID20"),
+    mechanism: Mechname::const_new_unchecked(b"OPENID20"),
     priority: 1000,
-    client: Some(|_sasl| {
-        CMechanismStateKeeper::build(MechanismVTable {
-            init: None,
-            done: None,
-            start: Some(_gsasl_openid20_client_start),
-            step: Some(_gsasl_openid20_client_step),
-            finish: Some(_gsasl_openid20_client_finish),
-            encode: None,
-            decode: None,
-        })
-    }),
-    server: Some(|_sasl| {
-        CMechanismStateKeeper::build(MechanismVTable {
-            init: None,
-            done: None,
-            start: Some(_gsasl_openid20_server_start),
-            step: Some(_gsasl_openid20_server_step),
-            finish: Some(_gsasl_openid20_server_finish),
-            encode: None,
-            decode: None,
-        })
-    }),
+    client: Some(|_sasl| Ok(Box::new(client::OpenID20::new()))),
+    server: Some(|_sasl| Ok(Box::new(server::OpenID20::new()))),
     first: Side::Client,
+    client_properties: PropertyRequirements::new(
+        &[AUTHID, OPENID20_AUTHENTICATE_IN_BROWSER],
+        &[AUTHZID],
+    ),
+    server_properties: PropertyRequirements::new(
+        &[OPENID20_REDIRECT_URL],
+        &[OPENID20_OUTCOME_DATA],
+    ),
+    description: "OpenID 2.0 authentication in a web browser",
+    specification: Some("RFC 6616"),
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: false,
+        weak: false,
+    },
 };
@@ -1,187 +1,141 @@
-use crate::gsasl::callback::gsasl_callback;
-use crate::gsasl::consts::{
-    GSASL_AUTHENTICATION_ERROR, GSASL_AUTHID, GSASL_AUTHZID, GSASL_MALLOC_ERROR,
-    GSASL_MECHANISM_CALLED_TOO_MANY_TIMES, GSASL_MECHANISM_PARSE_ERROR, GSASL_NEEDS_MORE,
-    GSASL_NO_OPENID20_REDIRECT_URL, GSASL_OK, GSASL_OPENID20_OUTCOME_DATA,
-    GSASL_OPENID20_REDIRECT_URL, GSASL_VALIDATE_OPENID20,
-};
-use crate::gsasl::gl::free::rpl_free;
-use crate::gsasl::mechtools::_gsasl_parse_gs2_header;
-use crate::gsasl::property::{gsasl_property_get, gsasl_property_set, gsasl_property_set_raw};
-use crate::session::SessionData;
-use crate::Shared;
-use ::libc;
-use libc::{calloc, malloc, memcpy, size_t, strdup, strlen};
-use std::ptr::NonNull;
+use crate::error::{MechanismError, MechanismErrorKind, SessionError};
+use crate::mechanism::Authentication;
+use crate::mechanisms::gs2_header;
+use crate::property::{AuthId, AuthzId, OpenID20OutcomeData, OpenID20RedirectUrl};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use crate::validate::validations::OPENID20;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::str::Utf8Error;
+use std::sync::Arc;
 
-/* Get specification. */
-/* Get strdup, strlen. */
-/* Get calloc, free. */
-/* Get _gsasl_parse_gs2_header. */
-#[derive(Copy, Clone)]
-#[repr(C)]
-pub struct openid20_server_state {
-    pub step: libc::c_int,
-    pub allow_error_step: libc::c_int,
+/// Prefix of the additional challenge sent if authentication failed
+pub(super) const ERROR_PREFIX: &str = "openid.error=";
+
+#[derive(Debug)]
+pub(super) enum OpenID20Error {
+    BadUtf8(Utf8Error),
+    EmptyIdentifier,
+    ExpectedCompletion,
+    Completed,
 }
 
-pub(crate) unsafe fn _gsasl_openid20_server_start(
-    _sctx: &Shared,
-    mech_data: &mut Option<NonNull<()>>,
-) -> libc::c_int {
-    let state;
-    state = calloc(::std::mem::size_of::<openid20_server_state>(), 1) as *mut openid20_server_state;
-    if state.is_null() {
-        return GSASL_MALLOC_ERROR as libc::c_int;
+impl Display for OpenID20Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadUtf8(e) => write!(f, "message is invalid UTF-8: {}", e),
+            Self::EmptyIdentifier => f.write_str("OpenID identifier is empty"),
+            Self::ExpectedCompletion => f.write_str("expected '=' signalling completion"),
+            Self::Completed => f.write_str("step called after the exchange completed"),
+        }
     }
-    *mech_data = NonNull::new(state as *mut ());
-    return GSASL_OK as libc::c_int;
 }
 
-pub unsafe fn _gsasl_openid20_server_step(
-    sctx: &mut SessionData,
-    mech_data: Option<NonNull<()>>,
-    input: Option<&[u8]>,
-    output: *mut *mut libc::c_char,
-    output_len: *mut size_t,
-) -> libc::c_int {
-    let mech_data = mech_data
-        .map(|ptr| ptr.as_ptr())
-        .unwrap_or_else(std::ptr::null_mut);
+impl MechanismError for OpenID20Error {
+    fn kind(&self) -> MechanismErrorKind {
+        match self {
+            Self::Completed => MechanismErrorKind::Protocol,
+            _ => MechanismErrorKind::Parse,
+        }
+    }
+}
 
-    let mut input_len = input.map(|i| i.len()).unwrap_or(0);
-    let mut input: *const libc::c_char =
-        input.map(|i| i.as_ptr().cast()).unwrap_or(std::ptr::null());
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+enum State {
+    #[default]
+    Initial,
+    Redirected,
+    Failed,
+    Completed,
+}
 
-    let mut state: *mut openid20_server_state = mech_data as *mut openid20_server_state;
-    let mut res: libc::c_int = GSASL_MECHANISM_CALLED_TOO_MANY_TIMES as libc::c_int;
-    *output_len = 0 as libc::c_int as size_t;
-    *output = 0 as *mut libc::c_char;
-    match (*state).step {
-        0 => {
-            let p;
-            let mut authzid: *mut libc::c_char = 0 as *mut libc::c_char;
-            let mut headerlen: size_t = 0;
-            if input_len == 0 {
-                return GSASL_NEEDS_MORE as libc::c_int;
-            }
-            res = _gsasl_parse_gs2_header(input, input_len, &mut authzid, &mut headerlen);
-            if res != GSASL_OK as libc::c_int {
-                return res;
-            }
-            if !authzid.is_null() {
-                res = gsasl_property_set(sctx, GSASL_AUTHZID, authzid);
-                rpl_free(authzid as *mut libc::c_void);
-                if res != GSASL_OK as libc::c_int {
-                    return res;
+/// Server side of `OPENID20`
+///
+/// The OpenID identifier of the client is set as [`AuthId`] before the [`OpenID20RedirectUrl`]
+/// to send it to is queried. Once the client signals that it completed authentication in its
+/// browser the exchange is validated with [`OPENID20`]. If set, [`OpenID20OutcomeData`] is sent
+/// to the client as additional data with the success.
+///
+/// A failed validation is reported to the client with an `openid.error` challenge first, as
+/// [RFC 6616](https://www.rfc-editor.org/rfc/rfc6616#section-3.2) requires.
+#[derive(Debug, Default)]
+pub struct OpenID20 {
+    state: State,
+}
+
+impl OpenID20 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Authentication for OpenID20 {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        match self.state {
+            State::Initial => {
+                let input = match input {
+                    Some(input) if !input.is_empty() => input,
+                    _ => return Ok(NeedsMore(None)),
+                };
+
+                let (authzid, identifier) = gs2_header::parse(input)?;
+                let identifier = std::str::from_utf8(identifier).map_err(OpenID20Error::BadUtf8)?;
+                if identifier.is_empty() {
+                    return Err(OpenID20Error::EmptyIdentifier.into());
                 }
-            }
-            input = input.offset(headerlen as isize);
-            input_len = input_len.wrapping_sub(headerlen);
-            res = gsasl_property_set_raw(sctx, GSASL_AUTHID, input, input_len);
-            if res != GSASL_OK as libc::c_int {
-                return res;
-            }
-            p = gsasl_property_get(sctx, GSASL_OPENID20_REDIRECT_URL);
-            if p.is_null() || *p == 0 {
-                return GSASL_NO_OPENID20_REDIRECT_URL as libc::c_int;
-            }
-            *output_len = strlen(p);
-            *output = malloc(*output_len) as *mut libc::c_char;
-            if (*output).is_null() {
-                return GSASL_MALLOC_ERROR as libc::c_int;
-            }
-            memcpy(
-                *output as *mut libc::c_void,
-                p as *const libc::c_void,
-                *output_len,
-            );
-            res = GSASL_NEEDS_MORE as libc::c_int;
-            (*state).step += 1
-        }
-        1 => {
-            let outcome_data;
-            if !(input_len == 1 && *input as libc::c_int == '=' as i32) {
-                return GSASL_MECHANISM_PARSE_ERROR as libc::c_int;
-            }
-            res = gsasl_callback(0 as *mut Shared, sctx, GSASL_VALIDATE_OPENID20);
-            if res != GSASL_OK as libc::c_int {
-                *output = strdup(b"openid.error=fail\x00" as *const u8 as *const libc::c_char);
-                if (*output).is_null() {
-                    return GSASL_MALLOC_ERROR as libc::c_int;
+                match authzid {
+                    Some(authzid) => {
+                        session.set_property::<AuthzId>(Arc::new(authzid));
+                    }
+                    None => session.clear_property::<AuthzId>(),
                 }
-                *output_len = strlen(*output);
-                /* [RFC4422] Section 3.6 explicitly prohibits additional
-                information in an unsuccessful authentication outcome.
-                Therefore, the openid.error and openid.error_code are
-                to be sent as an additional challenge in the event of
-                an unsuccessful outcome.  In this case, as the protocol
-                is lock step, the client will follow with an additional
-                exchange containing "=", after which the server will
-                respond with an application-level outcome. */
-                (*state).allow_error_step = 1 as libc::c_int;
-                (*state).step += 1;
-                return GSASL_NEEDS_MORE as libc::c_int;
+                session.set_property::<AuthId>(Arc::new(identifier.to_string()));
+
+                let redirect = session
+                    .get_property_or_callback::<OpenID20RedirectUrl>()?
+                    .filter(|url| !url.is_empty())
+                    .ok_or(SessionError::no_property::<OpenID20RedirectUrl>())?;
+                writer.write_all(redirect.as_bytes())?;
+                self.state = State::Redirected;
+                Ok(NeedsMore(Some(redirect.len())))
             }
-            outcome_data = gsasl_property_get(sctx, GSASL_OPENID20_OUTCOME_DATA);
-            if !outcome_data.is_null() {
-                *output = strdup(outcome_data);
-                if (*output).is_null() {
-                    return GSASL_MALLOC_ERROR as libc::c_int;
+            State::Redirected => {
+                if input != Some(b"=") {
+                    return Err(OpenID20Error::ExpectedCompletion.into());
+                }
+
+                if session.validate(OPENID20).is_err() {
+                    // RFC 4422 prohibits additional data with a failure, so the error is sent
+                    // as additional challenge the client has to answer with "=" first.
+                    let error = format!("{}fail", ERROR_PREFIX);
+                    writer.write_all(error.as_bytes())?;
+                    self.state = State::Failed;
+                    return Ok(NeedsMore(Some(error.len())));
+                }
+
+                self.state = State::Completed;
+                match session.get_property::<OpenID20OutcomeData>() {
+                    Some(outcome) => {
+                        writer.write_all(outcome.as_bytes())?;
+                        Ok(Done(Some(outcome.len())))
+                    }
+                    None => Ok(Done(None)),
                 }
-                *output_len = strlen(*output) as usize
-            } else {
-                *output = 0 as *mut libc::c_char;
-                *output_len = 0 as libc::c_int as size_t
-            }
-            res = GSASL_OK as libc::c_int;
-            (*state).step += 1
-        }
-        2 => {
-            /* We only get here when the previous step signalled an error
-            to the client.  */
-            if (*state).allow_error_step == 0 {
-                return GSASL_MECHANISM_CALLED_TOO_MANY_TIMES as libc::c_int;
             }
-            if !(input_len == 1 && *input as libc::c_int == '=' as i32) {
-                return GSASL_MECHANISM_PARSE_ERROR as libc::c_int;
+            State::Failed => {
+                if input != Some(b"=") {
+                    return Err(OpenID20Error::ExpectedCompletion.into());
+                }
+                self.state = State::Completed;
+                Err(SessionError::AuthenticationFailure)
             }
-            res = GSASL_AUTHENTICATION_ERROR as libc::c_int;
-            (*state).step += 1
+            State::Completed => Err(OpenID20Error::Completed.into()),
         }
-        _ => {}
-    }
-    return res;
-}
-/* openid20.h --- Prototypes for OPENID20.
- * Copyright (C) 2011-2021 Simon Josefsson
- *
- * This file is part of GNU SASL Library.
- *
- * GNU SASL Library is free software; you can redistribute it and/or
- * modify it under the terms of the GNU Lesser General Public License
- * as published by the Free Software Foundation; either version 2.1 of
- * the License, or (at your option) any later version.
- *
- * GNU SASL Library is distributed in the hope that it will be useful,
- * but WITHOUT ANY WARRANTY; without even the implied warranty of
- * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
- * Lesser General Public License for more details.
- *
- * You should have received a copy of the GNU Lesser General Public
- * License along with GNU SASL Library; if not, write to the Free
- * Free Software Foundation, Inc., 51 Franklin Street, Fifth Floor,
- * Boston, MA 02110-1301, USA.
- *
- */
-pub unsafe fn _gsasl_openid20_server_finish(mech_data: Option<NonNull<()>>) {
-    let mech_data = mech_data
-        .map(|ptr| ptr.as_ptr())
-        .unwrap_or_else(std::ptr::null_mut);
-
-    let state: *mut openid20_server_state = mech_data as *mut openid20_server_state;
-    if state.is_null() {
-        return;
     }
-    rpl_free(state as *mut libc::c_void);
 }
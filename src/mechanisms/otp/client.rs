@@ -0,0 +1,124 @@
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::otp::oath::OathChallenge;
+use crate::mechanisms::otp::password::{generate, Challenge, OtpError, Response};
+use crate::property::{AuthId, AuthzId, OtpEntry, OtpInit, Passcode, Password};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use crate::vectored_io::VectoredWriter;
+use std::io::Write;
+
+/// Client side of `OTP`
+///
+/// Computes the one-time password from the [`Password`] used as pass phrase. If the callback
+/// provides [`OtpInit`] the sequence is re-initialized with these parameters.
+#[derive(Debug, Default)]
+pub struct Otp {
+    sent_authid: bool,
+}
+
+impl Otp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Write the first message of the client, the authorization and authentication id
+fn write_authid(session: &mut SessionData, writer: &mut dyn Write) -> Result<usize, SessionError> {
+    let authzid = session.get_property_or_callback::<AuthzId>()?;
+    let authid = session
+        .get_property_or_callback::<AuthId>()?
+        .ok_or(SessionError::no_property::<AuthId>())?;
+    let authzid = authzid.as_deref().map(String::as_bytes).unwrap_or(&[]);
+    let data: [&[u8]; 3] = [authzid, &[0], authid.as_bytes()];
+    let written = VectoredWriter::new(data).write_all_vectored(writer)?;
+    Ok(written)
+}
+
+impl Authentication for Otp {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        if !self.sent_authid {
+            let written = write_authid(session, writer)?;
+            self.sent_authid = true;
+            return Ok(NeedsMore(Some(written)));
+        }
+
+        let challenge = input.ok_or(OtpError::BadChallenge)?;
+        let challenge = std::str::from_utf8(challenge).map_err(OtpError::BadUtf8)?;
+        let challenge = Challenge::parse(challenge)?;
+
+        let password = session
+            .get_property_or_callback::<Password>()?
+            .ok_or(SessionError::no_property::<Password>())?;
+        let otp = generate(
+            challenge.algorithm,
+            &password,
+            challenge.seed,
+            challenge.sequence,
+        );
+
+        let init = if challenge.extended {
+            session.get_property_or_callback::<OtpInit>()?
+        } else {
+            None
+        };
+        let response = match init {
+            Some(init) => {
+                let passphrase = init.passphrase.as_deref().unwrap_or(&password);
+                let new = OtpEntry::new(init.algorithm, passphrase, &init.seed, init.sequence);
+                Response::Init { otp, new }
+            }
+            None => Response::Otp(otp),
+        }
+        .to_string();
+
+        writer.write_all(response.as_bytes())?;
+        Ok(Done(Some(response.len())))
+    }
+}
+
+/// Client side of `X-OATH`
+///
+/// Answers the HOTP or TOTP challenge with the [`Passcode`], which an application holding the
+/// secret can compute with [`hotp`](super::oath::hotp) or [`totp`](super::oath::totp).
+#[derive(Debug, Default)]
+pub struct Oath {
+    sent_authid: bool,
+}
+
+impl Oath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Authentication for Oath {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        if !self.sent_authid {
+            let written = write_authid(session, writer)?;
+            self.sent_authid = true;
+            return Ok(NeedsMore(Some(written)));
+        }
+
+        let challenge = input.ok_or(OtpError::BadChallenge)?;
+        let challenge = std::str::from_utf8(challenge).map_err(OtpError::BadUtf8)?;
+        OathChallenge::parse(challenge)?.ok_or(OtpError::BadChallenge)?;
+
+        let passcode = session
+            .get_property_or_callback::<Passcode>()?
+            .ok_or(SessionError::no_property::<Passcode>())?;
+        let response = format!("oath:{}", passcode);
+        writer.write_all(response.as_bytes())?;
+        Ok(Done(Some(response.len())))
+    }
+}
@@ -0,0 +1,51 @@
+use crate::mechanisms::otp::{client, server};
+use crate::property::properties::{
+    AUTHID, AUTHZID, OATH_STATE, OTP_INIT, OTP_STATE, PASSCODE, PASSWORD,
+};
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
+use crate::{Mechanism, Mechname, Side};
+
+#[cfg(feature = "registry_static")]
+use crate::registry::{distributed_slice, MECHANISMS};
+#[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
+pub static OTP: Mechanism = Mechanism {
+    mechanism: Mechname::const_new_unchecked(b"OTP"),
+    priority: 300,
+    client: Some(|_sasl| Ok(Box::new(client::Otp::new()))),
+    server: Some(|_sasl| Ok(Box::new(server::Otp::new()))),
+    first: Side::Client,
+    client_properties: PropertyRequirements::new(&[AUTHID, PASSWORD], &[AUTHZID, OTP_INIT]),
+    server_properties: PropertyRequirements::new(&[OTP_STATE], &[]),
+    description: "One-time passwords (S/KEY)",
+    specification: Some("RFC 2444"),
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: false,
+        weak: false,
+    },
+};
+
+/// HOTP and TOTP one-time passwords in the shape of `OTP`, see [`oath`](super::oath)
+#[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
+pub static X_OATH: Mechanism = Mechanism {
+    mechanism: Mechname::const_new_unchecked(b"X-OATH"),
+    priority: 250,
+    client: Some(|_sasl| Ok(Box::new(client::Oath::new()))),
+    server: Some(|_sasl| Ok(Box::new(server::Oath::new()))),
+    first: Side::Client,
+    client_properties: PropertyRequirements::new(&[AUTHID, PASSCODE], &[AUTHZID]),
+    server_properties: PropertyRequirements::new(&[OATH_STATE], &[]),
+    description: "HOTP and TOTP one-time passwords (non-standard)",
+    specification: None,
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: false,
+        weak: false,
+    },
+};
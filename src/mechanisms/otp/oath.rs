@@ -0,0 +1,281 @@
+//! HOTP (RFC 4226) and TOTP (RFC 6238) one-time passwords
+//!
+//! RFC 2444 only specifies S/KEY sequences, so these are used with the `X-OATH` mechanism that is
+//! only understood by rsasl. Its exchange has the shape of `OTP`: after the client sent the
+//! authorization and authentication id the server challenges with `otp-hotp <counter>` or
+//! `otp-totp <time step>` and the client responds with `oath:<password>`.
+
+use crate::mechanisms::otp::password::OtpError;
+use crate::property::{OathAlgorithm, OathCounter, OathEntry};
+use hmac::{Hmac, Mac};
+use std::fmt::{Display, Formatter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of HOTP passwords after the expected one accepted to resynchronize the counter
+pub const HOTP_LOOK_AHEAD: u64 = 10;
+
+/// Number of TOTP time steps before and after the current one accepted to allow for clock skew
+/// and transmission delay (RFC 6238, section 5.2)
+pub const TOTP_DELAY: u64 = 1;
+
+fn mac<M: Mac + hmac::digest::KeyInit>(secret: &[u8], counter: u64) -> Vec<u8> {
+    let mut mac = <M as Mac>::new_from_slice(secret).expect("HMAC can take a key of any size");
+    mac.update(&counter.to_be_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Compute the HOTP password for the given counter
+pub fn hotp(algorithm: OathAlgorithm, secret: &[u8], digits: u32, counter: u64) -> u32 {
+    let hash = match algorithm {
+        OathAlgorithm::Sha1 => mac::<Hmac<sha1::Sha1>>(secret, counter),
+        OathAlgorithm::Sha256 => mac::<Hmac<sha2::Sha256>>(secret, counter),
+        OathAlgorithm::Sha512 => mac::<Hmac<sha2::Sha512>>(secret, counter),
+    };
+    // Dynamic truncation (RFC 4226, section 5.3)
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let mut truncated = [0u8; 4];
+    truncated.copy_from_slice(&hash[offset..offset + 4]);
+    let binary = u32::from_be_bytes(truncated) & 0x7fff_ffff;
+    binary % 10u32.pow(digits)
+}
+
+/// The TOTP time step `time` falls into
+pub fn time_step(period: u64, time: SystemTime) -> u64 {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0);
+    seconds / period.max(1)
+}
+
+/// Compute the TOTP password for the given time
+pub fn totp(
+    algorithm: OathAlgorithm,
+    secret: &[u8],
+    digits: u32,
+    period: u64,
+    time: SystemTime,
+) -> u32 {
+    hotp(algorithm, secret, digits, time_step(period, time))
+}
+
+/// Format a password with leading zeros
+pub fn to_digits(password: u32, digits: u32) -> String {
+    format!("{:0width$}", password, width = digits as usize)
+}
+
+impl OathEntry {
+    /// Create an entry for a new HOTP secret
+    pub fn hotp(algorithm: OathAlgorithm, secret: &[u8], digits: u32) -> Self {
+        Self {
+            algorithm,
+            secret: secret.to_vec(),
+            digits,
+            counter: OathCounter::Hotp { next: 0 },
+        }
+    }
+
+    /// Create an entry for a new TOTP secret with a time step of `period` seconds
+    pub fn totp(algorithm: OathAlgorithm, secret: &[u8], digits: u32, period: u64) -> Self {
+        Self {
+            algorithm,
+            secret: secret.to_vec(),
+            digits,
+            counter: OathCounter::Totp { period, last: None },
+        }
+    }
+
+    /// The challenge asking for the next password at the given time
+    pub fn challenge(&self, time: SystemTime) -> OathChallenge {
+        match self.counter {
+            OathCounter::Hotp { next } => OathChallenge::Hotp(next),
+            OathCounter::Totp { period, .. } => OathChallenge::Totp(time_step(period, time)),
+        }
+    }
+
+    /// Check the password at the given time, returning the entry to store if it's valid
+    pub fn verify(&self, password: &str, time: SystemTime) -> Option<Self> {
+        if password.len() != self.digits as usize {
+            return None;
+        }
+        let password: u32 = password.parse().ok()?;
+        let matches =
+            |counter| hotp(self.algorithm, &self.secret, self.digits, counter) == password;
+
+        let counter = match self.counter {
+            OathCounter::Hotp { next } => {
+                let counter = (next..next.saturating_add(HOTP_LOOK_AHEAD)).find(|c| matches(*c))?;
+                OathCounter::Hotp { next: counter + 1 }
+            }
+            OathCounter::Totp { period, last } => {
+                let current = time_step(period, time);
+                let first = match last {
+                    Some(last) => current.saturating_sub(TOTP_DELAY).max(last + 1),
+                    None => current.saturating_sub(TOTP_DELAY),
+                };
+                let step = (first..=current + TOTP_DELAY).find(|step| matches(*step))?;
+                OathCounter::Totp {
+                    period,
+                    last: Some(step),
+                }
+            }
+        };
+        Some(Self {
+            counter,
+            ..self.clone()
+        })
+    }
+}
+
+/// A challenge of the form `otp-hotp <counter>` or `otp-totp <time step>`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OathChallenge {
+    Hotp(u64),
+    Totp(u64),
+}
+
+impl OathChallenge {
+    /// Parse the challenge, returning `None` if it is not for HOTP or TOTP
+    pub fn parse(challenge: &str) -> Result<Option<Self>, OtpError> {
+        let mut parts = challenge.split_whitespace();
+        let constructor = match parts.next() {
+            Some("otp-hotp") => Self::Hotp,
+            Some("otp-totp") => Self::Totp,
+            _ => return Ok(None),
+        };
+        let counter = parts
+            .next()
+            .and_then(|counter| counter.parse().ok())
+            .ok_or(OtpError::BadChallenge)?;
+        Ok(Some(constructor(counter)))
+    }
+}
+
+impl Display for OathChallenge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Hotp(counter) => write!(f, "otp-hotp {}", counter),
+            Self::Totp(step) => write!(f, "otp-totp {}", step),
+        }
+    }
+}
+
+/// Parse an `oath:` response, returning the password
+pub fn parse_response(response: &str) -> Result<Option<&str>, OtpError> {
+    let password = match response.split_once(':') {
+        Some((kind, password)) if kind.eq_ignore_ascii_case("oath") => password.trim(),
+        _ => return Ok(None),
+    };
+    if (6..=8).contains(&password.len()) && password.bytes().all(|byte| byte.is_ascii_digit()) {
+        Ok(Some(password))
+    } else {
+        Err(OtpError::BadResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Test vectors of RFC 4226, appendix D
+    #[test]
+    fn rfc4226_vectors() {
+        let expected = [
+            755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+        ];
+        for (counter, password) in expected.iter().enumerate() {
+            let hotp = hotp(
+                OathAlgorithm::Sha1,
+                b"12345678901234567890",
+                6,
+                counter as u64,
+            );
+            assert_eq!(hotp, *password);
+        }
+    }
+
+    /// Test vectors of RFC 6238, appendix B
+    #[test]
+    fn rfc6238_vectors() {
+        let sha1 = b"12345678901234567890";
+        let sha256 = b"12345678901234567890123456789012";
+        let sha512 = b"1234567890123456789012345678901234567890123456789012345678901234";
+        let vectors: [(u64, [u32; 3]); 6] = [
+            (59, [94287082, 46119246, 90693936]),
+            (1111111109, [7081804, 68084774, 25091201]),
+            (1111111111, [14050471, 67062674, 99943326]),
+            (1234567890, [89005924, 91819424, 93441116]),
+            (2000000000, [69279037, 90698825, 38618901]),
+            (20000000000, [65353130, 77737706, 47863826]),
+        ];
+        for (seconds, expected) in vectors.iter() {
+            let time = UNIX_EPOCH + Duration::from_secs(*seconds);
+            let passwords = [
+                totp(OathAlgorithm::Sha1, sha1, 8, 30, time),
+                totp(OathAlgorithm::Sha256, sha256, 8, 30, time),
+                totp(OathAlgorithm::Sha512, sha512, 8, 30, time),
+            ];
+            assert_eq!(passwords, *expected);
+        }
+    }
+
+    #[test]
+    fn hotp_resynchronizes() {
+        let entry = OathEntry::hotp(OathAlgorithm::Sha1, b"12345678901234567890", 6);
+        let updated = entry.verify("338314", UNIX_EPOCH).unwrap();
+        assert_eq!(updated.counter, OathCounter::Hotp { next: 5 });
+        // Passwords before the counter are no longer accepted
+        assert_eq!(updated.verify("969429", UNIX_EPOCH), None);
+        // Neither are those too far ahead
+        let ahead = hotp(
+            OathAlgorithm::Sha1,
+            b"12345678901234567890",
+            6,
+            HOTP_LOOK_AHEAD,
+        );
+        assert_eq!(entry.verify(&to_digits(ahead, 6), UNIX_EPOCH), None);
+        assert_eq!(entry.verify("0755224", UNIX_EPOCH), None);
+    }
+
+    #[test]
+    fn totp_rejects_replay() {
+        let entry = OathEntry::totp(OathAlgorithm::Sha1, b"12345678901234567890", 8, 30);
+        let time = UNIX_EPOCH + Duration::from_secs(1111111109);
+        let updated = entry.verify("07081804", time).unwrap();
+        assert_eq!(
+            updated.counter,
+            OathCounter::Totp {
+                period: 30,
+                last: Some(37037036)
+            }
+        );
+        assert_eq!(updated.verify("07081804", time), None);
+        // The password of the previous time step is still accepted after that one
+        let next_step = time + Duration::from_secs(30);
+        assert!(entry.verify("07081804", next_step).is_some());
+        assert_eq!(
+            entry.verify("07081804", next_step + Duration::from_secs(30)),
+            None
+        );
+    }
+
+    #[test]
+    fn challenges_and_responses() {
+        assert_eq!(
+            OathChallenge::parse("otp-totp 37037036").unwrap(),
+            Some(OathChallenge::Totp(37037036))
+        );
+        assert_eq!(OathChallenge::Hotp(5).to_string(), "otp-hotp 5");
+        assert_eq!(
+            OathChallenge::parse("otp-md5 499 ke1234 ext").unwrap(),
+            None
+        );
+        assert!(OathChallenge::parse("otp-hotp").is_err());
+
+        assert_eq!(parse_response("oath:755224").unwrap(), Some("755224"));
+        assert_eq!(parse_response("hex:9e876134d90499dd").unwrap(), None);
+        assert!(parse_response("oath:75522").is_err());
+        assert!(parse_response("oath:75522a").is_err());
+    }
+}
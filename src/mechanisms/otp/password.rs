@@ -0,0 +1,437 @@
+//! RFC 2289 one-time passwords and the formats of RFC 2243 used to exchange them
+
+use crate::error::{MechanismError, MechanismErrorKind};
+use crate::mechanisms::otp::dictionary::{FOUR_LETTER_OFFSET, WORDS};
+use crate::property::{OtpAlgorithm, OtpEntry};
+use md5::Digest;
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter};
+use std::str::Utf8Error;
+
+/// Highest sequence number accepted when re-initializing
+pub const MAX_SEQUENCE: u32 = 9999;
+
+#[derive(Debug)]
+pub enum OtpError {
+    BadFormat,
+    BadUtf8(Utf8Error),
+    BadChallenge,
+    BadResponse,
+    BadSeed,
+    BadInit,
+    UnknownAlgorithm,
+}
+
+impl Display for OtpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadFormat => {
+                f.write_str("invalid format, expected authzid and authcid separated by a NULL-byte")
+            }
+            Self::BadUtf8(e) => write!(f, "message is invalid UTF-8: {}", e),
+            Self::BadChallenge => f.write_str("invalid challenge"),
+            Self::BadResponse => f.write_str("invalid response"),
+            Self::BadSeed => f.write_str("seed must be 1 to 16 alphanumeric characters"),
+            Self::BadInit => f.write_str(
+                "re-initialization requires a new seed and a sequence number greater than zero",
+            ),
+            Self::UnknownAlgorithm => f.write_str("unknown hash algorithm"),
+        }
+    }
+}
+
+impl MechanismError for OtpError {
+    fn kind(&self) -> MechanismErrorKind {
+        MechanismErrorKind::Parse
+    }
+}
+
+/// Hash the input and fold the digest to 64 bits (RFC 2289, section 6 and appendix A)
+fn hash_fold(algorithm: OtpAlgorithm, input: &[u8]) -> [u8; 8] {
+    let mut folded = [0u8; 8];
+    match algorithm {
+        OtpAlgorithm::Md4 | OtpAlgorithm::Md5 => {
+            let digest = match algorithm {
+                OtpAlgorithm::Md4 => md4::Md4::digest(input),
+                _ => md5::Md5::digest(input),
+            };
+            for (i, byte) in folded.iter_mut().enumerate() {
+                *byte = digest[i] ^ digest[i + 8];
+            }
+        }
+        OtpAlgorithm::Sha1 => {
+            let digest = sha1::Sha1::digest(input);
+            let word = |i: usize| u32::from_be_bytes(digest[i * 4..i * 4 + 4].try_into().unwrap());
+            // The reference implementation outputs the folded words in little-endian order
+            let first = word(0) ^ word(2) ^ word(4);
+            let second = word(1) ^ word(3);
+            folded[..4].copy_from_slice(&first.to_le_bytes());
+            folded[4..].copy_from_slice(&second.to_le_bytes());
+        }
+    }
+    folded
+}
+
+/// Compute the one-time password with the given sequence number
+pub fn generate(algorithm: OtpAlgorithm, passphrase: &str, seed: &str, sequence: u32) -> [u8; 8] {
+    let mut input = seed.to_ascii_lowercase();
+    input.push_str(passphrase);
+    let mut otp = hash_fold(algorithm, input.as_bytes());
+    for _ in 0..sequence {
+        otp = hash_fold(algorithm, &otp);
+    }
+    otp
+}
+
+/// Check if `otp` is the password preceding `next` in a sequence
+pub fn verify(algorithm: OtpAlgorithm, otp: &[u8; 8], next: &[u8; 8]) -> bool {
+    &hash_fold(algorithm, otp) == next
+}
+
+impl OtpEntry {
+    /// Create an entry for a new sequence
+    pub fn new(algorithm: OtpAlgorithm, passphrase: &str, seed: &str, sequence: u32) -> Self {
+        Self {
+            algorithm,
+            sequence,
+            seed: seed.to_string(),
+            otp: generate(algorithm, passphrase, seed, sequence),
+        }
+    }
+}
+
+/// Seeds are 1 to 16 characters, restricted to alphanumerics by RFC 2289
+pub fn is_valid_seed(seed: &str) -> bool {
+    (1..=16).contains(&seed.len()) && seed.bytes().all(|byte| byte.is_ascii_alphanumeric())
+}
+
+pub fn to_hex(otp: &[u8; 8]) -> String {
+    otp.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Parse a hexadecimal password, ignoring any whitespace
+pub fn from_hex(hex: &str) -> Option<[u8; 8]> {
+    let digits: Vec<u8> = hex
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    if digits.len() != 16 {
+        return None;
+    }
+    let mut otp = [0u8; 8];
+    for (byte, pair) in otp.iter_mut().zip(digits.chunks_exact(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(otp)
+}
+
+/// Sum of all two-bit groups, appended to the 64 bits to make up six words of eleven bits
+fn checksum(value: u64) -> u64 {
+    (0..32).map(|i| (value >> (2 * i)) & 3).sum::<u64>() & 3
+}
+
+/// Encode the password as six words of the standard dictionary
+pub fn to_words(otp: &[u8; 8]) -> String {
+    let value = u64::from_be_bytes(*otp);
+    let bits = (u128::from(value) << 2) | u128::from(checksum(value));
+    (0..6)
+        .map(|i| WORDS[((bits >> (55 - 11 * i)) & 0x7ff) as usize])
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Parse six words of the standard dictionary, checking the checksum
+pub fn from_words(words: &str) -> Option<[u8; 8]> {
+    let mut bits: u128 = 0;
+    let mut count = 0;
+    for word in words.split_whitespace() {
+        let word = word.to_ascii_uppercase();
+        let range = if word.len() == 4 {
+            FOUR_LETTER_OFFSET..WORDS.len()
+        } else {
+            0..FOUR_LETTER_OFFSET
+        };
+        // Both parts of the dictionary are sorted alphabetically
+        let index = WORDS[range.clone()].binary_search(&word.as_str()).ok()?;
+        bits = (bits << 11) | (range.start + index) as u128;
+        count += 1;
+    }
+    if count != 6 {
+        return None;
+    }
+    let value = (bits >> 2) as u64;
+    if checksum(value) != (bits & 3) as u64 {
+        return None;
+    }
+    Some(value.to_be_bytes())
+}
+
+/// A challenge of the form `otp-<algorithm> <sequence> <seed> ext`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Challenge<'a> {
+    pub algorithm: OtpAlgorithm,
+    pub sequence: u32,
+    pub seed: &'a str,
+    /// The server accepts the extended responses of RFC 2243
+    pub extended: bool,
+}
+
+impl<'a> Challenge<'a> {
+    pub fn parse(challenge: &'a str) -> Result<Self, OtpError> {
+        let mut parts = challenge.split_whitespace();
+        let algorithm = parts
+            .next()
+            .and_then(|otp| otp.strip_prefix("otp-"))
+            .ok_or(OtpError::BadChallenge)?;
+        let algorithm = OtpAlgorithm::from_name(algorithm).ok_or(OtpError::UnknownAlgorithm)?;
+        let sequence = parts
+            .next()
+            .and_then(|sequence| sequence.parse().ok())
+            .ok_or(OtpError::BadChallenge)?;
+        let seed = parts.next().ok_or(OtpError::BadChallenge)?;
+        if !is_valid_seed(seed) {
+            return Err(OtpError::BadSeed);
+        }
+        let extended = parts.next() == Some("ext");
+        Ok(Self {
+            algorithm,
+            sequence,
+            seed,
+            extended,
+        })
+    }
+}
+
+impl Display for Challenge<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "otp-{} {} {}",
+            self.algorithm.name(),
+            self.sequence,
+            self.seed
+        )?;
+        if self.extended {
+            f.write_str(" ext")?;
+        }
+        Ok(())
+    }
+}
+
+/// A response to a challenge, either `hex:`/`word:` or `init-hex:`/`init-word:`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Response {
+    Otp([u8; 8]),
+    Init {
+        otp: [u8; 8],
+        /// The new sequence, with the password for its sequence number
+        new: OtpEntry,
+    },
+}
+
+impl Response {
+    pub fn parse(response: &str) -> Result<Self, OtpError> {
+        let (kind, rest) = response.split_once(':').ok_or(OtpError::BadResponse)?;
+        let decode = match kind.to_ascii_lowercase().as_str() {
+            "hex" | "init-hex" => from_hex,
+            "word" | "init-word" => from_words,
+            _ => return Err(OtpError::BadResponse),
+        };
+        if !kind.to_ascii_lowercase().starts_with("init-") {
+            return decode(rest).map(Self::Otp).ok_or(OtpError::BadResponse);
+        }
+
+        let mut fields = rest.splitn(3, ':');
+        let (otp, params, new_otp) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(otp), Some(params), Some(new_otp)) => (otp, params, new_otp),
+            _ => return Err(OtpError::BadResponse),
+        };
+        let otp = decode(otp).ok_or(OtpError::BadResponse)?;
+        let new_otp = decode(new_otp).ok_or(OtpError::BadResponse)?;
+
+        let mut params = params.split_whitespace();
+        let algorithm = params
+            .next()
+            .and_then(OtpAlgorithm::from_name)
+            .ok_or(OtpError::UnknownAlgorithm)?;
+        let sequence = params
+            .next()
+            .and_then(|sequence| sequence.parse().ok())
+            .filter(|sequence| *sequence <= MAX_SEQUENCE)
+            .ok_or(OtpError::BadResponse)?;
+        let seed = params.next().ok_or(OtpError::BadResponse)?;
+        if !is_valid_seed(seed) {
+            return Err(OtpError::BadSeed);
+        }
+        if params.next().is_some() {
+            return Err(OtpError::BadResponse);
+        }
+
+        Ok(Self::Init {
+            otp,
+            new: OtpEntry {
+                algorithm,
+                sequence,
+                seed: seed.to_string(),
+                otp: new_otp,
+            },
+        })
+    }
+}
+
+impl Display for Response {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Otp(otp) => write!(f, "hex:{}", to_hex(otp)),
+            Self::Init { otp, new } => write!(
+                f,
+                "init-hex:{}:{} {} {}:{}",
+                to_hex(otp),
+                new.algorithm.name(),
+                new.sequence,
+                new.seed,
+                to_hex(&new.otp)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSPHRASE: &str = "This is a test.";
+    const SEED: &str = "TeSt";
+
+    /// Test vectors of RFC 2289, appendix C
+    #[test]
+    fn rfc2289_vectors() {
+        let vectors = [
+            (
+                OtpAlgorithm::Md4,
+                0,
+                "d1854218ebbb0b51",
+                "ROME MUG FRED SCAN LIVE LACE",
+            ),
+            (
+                OtpAlgorithm::Md4,
+                1,
+                "63473ef01cd0b444",
+                "CARD SAD MINI RYE COL KIN",
+            ),
+            (
+                OtpAlgorithm::Md4,
+                99,
+                "c5e612776e6c237a",
+                "NOTE OUT IBIS SINK NAVE MODE",
+            ),
+            (
+                OtpAlgorithm::Md5,
+                0,
+                "9e876134d90499dd",
+                "INCH SEA ANNE LONG AHEM TOUR",
+            ),
+            (
+                OtpAlgorithm::Md5,
+                1,
+                "7965e05436f5029f",
+                "EASE OIL FUM CURE AWRY AVIS",
+            ),
+            (
+                OtpAlgorithm::Md5,
+                99,
+                "50fe1962c4965880",
+                "BAIL TUFT BITS GANG CHEF THY",
+            ),
+            (
+                OtpAlgorithm::Sha1,
+                0,
+                "bb9e6ae1979d8ff4",
+                "MILT VARY MAST OK SEES WENT",
+            ),
+            (
+                OtpAlgorithm::Sha1,
+                1,
+                "63d936639734385b",
+                "CART OTTO HIVE ODE VAT NUT",
+            ),
+            (
+                OtpAlgorithm::Sha1,
+                99,
+                "87fec7768b73ccf9",
+                "GAFF WAIT SKID GIG SKY EYED",
+            ),
+        ];
+        for (algorithm, sequence, hex, words) in vectors.iter() {
+            let otp = generate(*algorithm, PASSPHRASE, SEED, *sequence);
+            assert_eq!(to_hex(&otp), *hex);
+            assert_eq!(to_words(&otp), *words);
+            assert_eq!(from_hex(hex), Some(otp));
+            assert_eq!(from_words(&words.to_lowercase()), Some(otp));
+        }
+    }
+
+    #[test]
+    fn sequence_chain() {
+        let next = generate(OtpAlgorithm::Md5, PASSPHRASE, SEED, 100);
+        let otp = generate(OtpAlgorithm::Md5, PASSPHRASE, SEED, 99);
+        assert!(verify(OtpAlgorithm::Md5, &otp, &next));
+        assert!(!verify(OtpAlgorithm::Md5, &next, &otp));
+    }
+
+    #[test]
+    fn dictionary_is_sorted() {
+        for part in [&WORDS[..FOUR_LETTER_OFFSET], &WORDS[FOUR_LETTER_OFFSET..]].iter() {
+            assert!(part.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[test]
+    fn bad_words() {
+        assert_eq!(from_words("INCH SEA ANNE LONG AHEM"), None);
+        assert_eq!(from_words("INCH SEA ANNE LONG AHEM XYZZY"), None);
+        // Wrong checksum
+        assert_eq!(from_words("INCH SEA ANNE LONG AHEM TOUT"), None);
+    }
+
+    #[test]
+    fn challenges() {
+        let challenge = Challenge::parse("otp-md5 499 ke1234 ext").unwrap();
+        assert_eq!(challenge.algorithm, OtpAlgorithm::Md5);
+        assert_eq!(challenge.sequence, 499);
+        assert_eq!(challenge.seed, "ke1234");
+        assert!(challenge.extended);
+        assert_eq!(challenge.to_string(), "otp-md5 499 ke1234 ext");
+        assert!(Challenge::parse("otp-sha256 499 ke1234 ext").is_err());
+        assert!(Challenge::parse("otp-md5 499 ke_1234 ext").is_err());
+    }
+
+    #[test]
+    fn responses() {
+        let otp = generate(OtpAlgorithm::Md5, PASSPHRASE, SEED, 0);
+        assert_eq!(
+            Response::parse("hex:9E87 6134 D904 99DD").unwrap(),
+            Response::Otp(otp)
+        );
+        assert_eq!(
+            Response::parse("word:INCH SEA ANNE LONG AHEM TOUR").unwrap(),
+            Response::Otp(otp)
+        );
+
+        let init = Response::Init {
+            otp,
+            new: OtpEntry::new(OtpAlgorithm::Sha1, PASSPHRASE, "ke1235", 499),
+        };
+        assert_eq!(Response::parse(&init.to_string()).unwrap(), init);
+        let words = format!(
+            "init-word:{}:sha1 499 ke1235:{}",
+            to_words(&otp),
+            to_words(&generate(OtpAlgorithm::Sha1, PASSPHRASE, "ke1235", 499))
+        );
+        assert_eq!(Response::parse(&words).unwrap(), init);
+
+        assert!(Response::parse("9e876134d90499dd").is_err());
+        assert!(Response::parse("hex:9e876134d90499").is_err());
+        assert!(Response::parse("init-hex:9e876134d90499dd:md5 499:9e876134d90499dd").is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::otp::oath::parse_response;
+use crate::mechanisms::otp::password::{verify, Challenge, OtpError, Response};
+use crate::property::{AuthId, AuthzId, OathEntry, OathState, OtpEntry, OtpState};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use crate::validate::validations::OTP;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Server side of `OTP`
+///
+/// The challenge is built from the [`OtpState`] the callback provides for the user. A correct
+/// response, including one re-initializing the sequence, replaces the property with the updated
+/// entry before validating with [`OTP`].
+#[derive(Debug, Default)]
+pub struct Otp {
+    entry: Option<OtpEntry>,
+}
+
+impl Otp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Set the authorization and authentication id of the client's first message
+fn read_authid(session: &mut SessionData, input: &[u8]) -> Result<(), SessionError> {
+    let nul = input
+        .iter()
+        .position(|byte| *byte == 0)
+        .ok_or(OtpError::BadFormat)?;
+    let authzid = std::str::from_utf8(&input[..nul]).map_err(OtpError::BadUtf8)?;
+    let authcid = std::str::from_utf8(&input[nul + 1..]).map_err(OtpError::BadUtf8)?;
+    if authcid.is_empty() {
+        return Err(OtpError::BadFormat.into());
+    }
+    if !authzid.is_empty() {
+        session.set_property::<AuthzId>(Arc::new(authzid.to_string()));
+    }
+    session.set_property::<AuthId>(Arc::new(authcid.to_string()));
+    Ok(())
+}
+
+impl Authentication for Otp {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        // The client has yet to send its first message; an empty one is malformed though
+        let input = match input {
+            Some(input) => input,
+            None => return Ok(NeedsMore(None)),
+        };
+
+        let entry = match self.entry.take() {
+            Some(entry) => entry,
+            None => {
+                read_authid(session, input)?;
+                let entry = session
+                    .get_property_or_callback::<OtpState>()?
+                    .ok_or(SessionError::no_property::<OtpState>())?;
+                // A sequence counted down to zero has to be re-initialized out of band
+                if entry.sequence == 0 {
+                    return Err(SessionError::AuthenticationFailure);
+                }
+                let challenge = Challenge {
+                    algorithm: entry.algorithm,
+                    sequence: entry.sequence - 1,
+                    seed: &entry.seed,
+                    extended: true,
+                }
+                .to_string();
+                writer.write_all(challenge.as_bytes())?;
+                self.entry = Some(entry.as_ref().clone());
+                return Ok(NeedsMore(Some(challenge.len())));
+            }
+        };
+
+        let response = std::str::from_utf8(input).map_err(OtpError::BadUtf8)?;
+        let (otp, updated) = match Response::parse(response)? {
+            Response::Otp(otp) => {
+                let updated = OtpEntry {
+                    sequence: entry.sequence - 1,
+                    otp,
+                    ..entry.clone()
+                };
+                (otp, updated)
+            }
+            Response::Init { otp, new } => {
+                // The new sequence must not repeat the passwords of the current one
+                if new.sequence == 0 || new.seed.eq_ignore_ascii_case(&entry.seed) {
+                    return Err(OtpError::BadInit.into());
+                }
+                (otp, new)
+            }
+        };
+        if !verify(entry.algorithm, &otp, &entry.otp) {
+            return Err(SessionError::AuthenticationFailure);
+        }
+
+        // The callback has to store the updated entry so the password can't be used again
+        session.set_property::<OtpState>(Arc::new(updated));
+        session.validate(OTP)?;
+        Ok(Done(None))
+    }
+}
+
+/// Server side of `X-OATH`
+///
+/// The challenge is built from the [`OathState`] the callback provides for the user. A correct
+/// response replaces the property with the updated entry before validating with [`OTP`].
+#[derive(Debug, Default)]
+pub struct Oath {
+    entry: Option<OathEntry>,
+}
+
+impl Oath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Authentication for Oath {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        // The client has yet to send its first message; an empty one is malformed though
+        let input = match input {
+            Some(input) => input,
+            None => return Ok(NeedsMore(None)),
+        };
+
+        let entry = match self.entry.take() {
+            Some(entry) => entry,
+            None => {
+                read_authid(session, input)?;
+                let entry = session
+                    .get_property_or_callback::<OathState>()?
+                    .ok_or(SessionError::no_property::<OathState>())?;
+                let challenge = entry.challenge(SystemTime::now()).to_string();
+                writer.write_all(challenge.as_bytes())?;
+                self.entry = Some(entry.as_ref().clone());
+                return Ok(NeedsMore(Some(challenge.len())));
+            }
+        };
+
+        let response = std::str::from_utf8(input).map_err(OtpError::BadUtf8)?;
+        let password = parse_response(response)?.ok_or(OtpError::BadResponse)?;
+        let updated = entry
+            .verify(password, SystemTime::now())
+            .ok_or(SessionError::AuthenticationFailure)?;
+
+        // The callback has to store the updated entry so the password can't be used again
+        session.set_property::<OathState>(Arc::new(updated));
+        session.validate(OTP)?;
+        Ok(Done(None))
+    }
+}
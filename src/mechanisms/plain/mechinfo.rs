@@ -1,4 +1,6 @@
 use crate::mechanisms::plain::{client, server};
+use crate::property::properties::{AUTHID, AUTHZID, PASSWORD};
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
 use crate::{Mechanism, Mechname, Side};
 
 #[cfg(feature = "registry_static")]
@@ -10,4 +12,16 @@ pub static PLAIN: Mechanism = Mechanism {
     client: Some(|_sasl| Ok(Box::new(client::Plain))),
     server: Some(|_sasl| Ok(Box::new(server::Plain))),
     first: Side::Client,
+    client_properties: PropertyRequirements::new(&[AUTHID, PASSWORD], &[AUTHZID]),
+    server_properties: PropertyRequirements::NONE,
+    description: "Username and password sent in plain text",
+    specification: Some("RFC 4616"),
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: false,
+        mutual: false,
+        weak: false,
+    },
 };
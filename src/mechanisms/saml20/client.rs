@@ -1,154 +1,71 @@
-use crate::gsasl::callback::gsasl_callback;
-use crate::gsasl::consts::{
-    GSASL_AUTHZID, GSASL_MALLOC_ERROR, GSASL_MECHANISM_CALLED_TOO_MANY_TIMES, GSASL_NEEDS_MORE,
-    GSASL_NO_SAML20_IDP_IDENTIFIER, GSASL_OK, GSASL_SAML20_AUTHENTICATE_IN_BROWSER,
-    GSASL_SAML20_IDP_IDENTIFIER, GSASL_SAML20_REDIRECT_URL,
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::gs2_header;
+use crate::mechanisms::saml20::server::Saml20Error;
+use crate::property::{
+    AuthzId, SAML20IDPIdentifier, SAML20RedirectUrl, Saml20AuthenticateInBrowser,
 };
-use crate::gsasl::gl::free::rpl_free;
-use crate::gsasl::mechtools::_gsasl_gs2_generate_header;
-use crate::gsasl::property::{gsasl_property_get, gsasl_property_set_raw};
-use crate::session::SessionData;
-use crate::Shared;
-use ::libc;
-use libc::{calloc, size_t, strdup, strlen};
-use std::ptr::NonNull;
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use std::io::Write;
+use std::sync::Arc;
 
-/* client.c --- SAML20 mechanism, client side.
- * Copyright (C) 2010-2021 Simon Josefsson
- *
- * This file is part of GNU SASL Library.
- *
- * GNU SASL Library is free software; you can redistribute it and/or
- * modify it under the terms of the GNU Lesser General Public License
- * as published by the Free Software Foundation; either version 2.1 of
- * the License, or (at your option) any later version.
- *
- * GNU SASL Library is distributed in the hope that it will be useful,
- * but WITHOUT ANY WARRANTY; without even the implied warranty of
- * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
- * Lesser General Public License for more details.
- *
- * You should have received a copy of the GNU Lesser General Public
- * License along with GNU SASL Library; if not, write to the Free
- * Free Software Foundation, Inc., 51 Franklin Street, Fifth Floor,
- * Boston, MA 02110-1301, USA.
- *
- */
-/* Get specification. */
-/* Get strdup, strlen. */
-/* Get free. */
-/* Get bool. */
-/* Get _gsasl_gs2_generate_header. */
-#[derive(Copy, Clone)]
-#[repr(C)]
-pub struct saml20_client_state {
-    pub step: libc::c_int,
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+enum State {
+    #[default]
+    Initial,
+    Redirected,
+    Completed,
 }
 
-pub(crate) unsafe fn _gsasl_saml20_client_start(
-    _sctx: &Shared,
-    mech_data: &mut Option<NonNull<()>>,
-) -> libc::c_int {
-    let state;
-    state = calloc(::std::mem::size_of::<saml20_client_state>(), 1) as *mut saml20_client_state;
-    if state.is_null() {
-        return GSASL_MALLOC_ERROR as libc::c_int;
-    }
-    *mech_data = NonNull::new(state as *mut ());
-    return GSASL_OK as libc::c_int;
+/// Client side of `SAML20`
+///
+/// Sends the [`SAML20IDPIdentifier`] and an optional [`AuthzId`]. The URL the server answers
+/// with is stored in [`SAML20RedirectUrl`] before [`Saml20AuthenticateInBrowser`] is queried,
+/// the callback for it has to return once the user completed authentication in their browser.
+/// The `browser_redirect` helper (feature `browser_redirect`) can be used for that.
+#[derive(Debug, Default)]
+pub struct Saml20 {
+    state: State,
 }
 
-pub unsafe fn _gsasl_saml20_client_step(
-    sctx: &mut SessionData,
-    mech_data: Option<NonNull<()>>,
-    input: Option<&[u8]>,
-    output: *mut *mut libc::c_char,
-    output_len: *mut size_t,
-) -> libc::c_int {
-    let mech_data = mech_data
-        .map(|ptr| ptr.as_ptr())
-        .unwrap_or_else(std::ptr::null_mut);
+impl Saml20 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
 
-    let input_len = input.map(|i| i.len()).unwrap_or(0);
-    let input: *const libc::c_char = input.map(|i| i.as_ptr().cast()).unwrap_or(std::ptr::null());
+impl Authentication for Saml20 {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        match self.state {
+            State::Initial => {
+                let authzid = session.get_property_or_callback::<AuthzId>()?;
+                let idp = session
+                    .get_property_or_callback::<SAML20IDPIdentifier>()?
+                    .filter(|idp| !idp.is_empty())
+                    .ok_or(SessionError::no_property::<SAML20IDPIdentifier>())?;
 
-    let mut state: *mut saml20_client_state = mech_data as *mut saml20_client_state;
-    let mut res: libc::c_int = GSASL_MECHANISM_CALLED_TOO_MANY_TIMES as libc::c_int;
-    match (*state).step {
-        0 => {
-            let authzid: *const libc::c_char = gsasl_property_get(sctx, GSASL_AUTHZID);
-            let idp: *const libc::c_char =
-                gsasl_property_get(sctx, GSASL_SAML20_IDP_IDENTIFIER);
-            if idp.is_null() || *idp == 0 {
-                return GSASL_NO_SAML20_IDP_IDENTIFIER as libc::c_int;
-            }
-            res = _gsasl_gs2_generate_header(
-                0 as libc::c_int != 0,
-                'n' as i32 as libc::c_char,
-                0 as *const libc::c_char,
-                authzid,
-                strlen(idp),
-                idp,
-                output,
-                output_len,
-            );
-            if res != GSASL_OK as libc::c_int {
-                return res;
-            }
-            res = GSASL_NEEDS_MORE as libc::c_int;
-            (*state).step += 1
-        }
-        1 => {
-            res = gsasl_property_set_raw(sctx, GSASL_SAML20_REDIRECT_URL, input, input_len);
-            if res != GSASL_OK as libc::c_int {
-                return res;
+                let header_len = gs2_header::write(authzid.as_deref().map(String::as_str), writer)?;
+                writer.write_all(idp.as_bytes())?;
+                self.state = State::Redirected;
+                Ok(NeedsMore(Some(header_len + idp.len())))
             }
-            res = gsasl_callback(0 as *mut Shared, sctx, GSASL_SAML20_AUTHENTICATE_IN_BROWSER);
-            if res != GSASL_OK as libc::c_int {
-                return res;
-            }
-            *output_len = 1 as libc::c_int as size_t;
-            *output = strdup(b"=\x00" as *const u8 as *const libc::c_char);
-            if (*output).is_null() {
-                return GSASL_MALLOC_ERROR as libc::c_int;
+            State::Redirected => {
+                let input = input.ok_or(SessionError::InputDataRequired)?;
+                let redirect = std::str::from_utf8(input).map_err(Saml20Error::BadUtf8)?;
+                session.set_property::<SAML20RedirectUrl>(Arc::new(redirect.to_string()));
+                session.callback::<Saml20AuthenticateInBrowser>()?;
+
+                writer.write_all(b"=")?;
+                self.state = State::Completed;
+                Ok(Done(Some(1)))
             }
-            res = GSASL_OK as libc::c_int;
-            (*state).step += 1
+            State::Completed => Err(Saml20Error::Completed.into()),
         }
-        _ => {}
-    }
-    return res;
-}
-
-/* saml20.h --- Prototypes for SAML20.
- * Copyright (C) 2010-2021 Simon Josefsson
- *
- * This file is part of GNU SASL Library.
- *
- * GNU SASL Library is free software; you can redistribute it and/or
- * modify it under the terms of the GNU Lesser General Public License
- * as published by the Free Software Foundation; either version 2.1 of
- * the License, or (at your option) any later version.
- *
- * GNU SASL Library is distributed in the hope that it will be useful,
- * but WITHOUT ANY WARRANTY; without even the implied warranty of
- * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
- * Lesser General Public License for more details.
- *
- * You should have received a copy of the GNU Lesser General Public
- * License along with GNU SASL Library; if not, write to the Free
- * Free Software Foundation, Inc., 51 Franklin Street, Fifth Floor,
- * Boston, MA 02110-1301, USA.
- *
- */
-pub unsafe fn _gsasl_saml20_client_finish(mech_data: Option<NonNull<()>>) {
-    let mech_data = mech_data
-        .map(|ptr| ptr.as_ptr())
-        .unwrap_or_else(std::ptr::null_mut);
-
-    let state: *mut saml20_client_state = mech_data as *mut saml20_client_state;
-    if state.is_null() {
-        return;
     }
-    rpl_free(state as *mut libc::c_void);
 }
@@ -1,39 +1,32 @@
-use crate::gsasl::gsasl::{CMechanismStateKeeper, MechanismVTable};
-use crate::mechanisms::saml20::client::{
-    _gsasl_saml20_client_finish, _gsasl_saml20_client_start, _gsasl_saml20_client_step,
-};
-use crate::mechanisms::saml20::server::{
-    _gsasl_saml20_server_finish, _gsasl_saml20_server_start, _gsasl_saml20_server_step,
+use crate::mechanisms::saml20::{client, server};
+use crate::property::properties::{
+    AUTHZID, SAML20_AUTHENTICATE_IN_BROWSER, SAML20_IDP_IDENTIFIER, SAML20_REDIRECT_URL,
 };
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
 use crate::{Mechanism, Mechname, Side};
 
 #[cfg(feature = "registry_static")]
 use crate::registry::{distributed_slice, MECHANISMS};
 #[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
 pub static SAML20: Mechanism = Mechanism {
+    mechanism: Mechname::const_new_unchecked(b"SAML20"),
     priority: 1000,
-    mechanism: &Mechname::const_new_unchecked(b"SAML20"),
-    client: Some(|_sasl| {
-        CMechanismStateKeeper::build(MechanismVTable {
-            init: None,
-            done: None,
-            start: Some(_gsasl_saml20_client_start),
-            step: Some(_gsasl_saml20_client_step),
-            finish: Some(_gsasl_saml20_client_finish),
-            encode: None,
-            decode: None,
-        })
-    }),
-    server: Some(|_sasl| {
-        CMechanismStateKeeper::build(MechanismVTable {
-            init: None,
-            done: None,
-            start: Some(_gsasl_saml20_server_start),
-            step: Some(_gsasl_saml20_server_step),
-            finish: Some(_gsasl_saml20_server_finish),
-            encode: None,
-            decode: None,
-        })
-    }),
+    client: Some(|_sasl| Ok(Box::new(client::Saml20::new()))),
+    server: Some(|_sasl| Ok(Box::new(server::Saml20::new()))),
     first: Side::Client,
+    client_properties: PropertyRequirements::new(
+        &[SAML20_IDP_IDENTIFIER, SAML20_AUTHENTICATE_IN_BROWSER],
+        &[AUTHZID],
+    ),
+    server_properties: PropertyRequirements::new(&[SAML20_REDIRECT_URL], &[]),
+    description: "SAML 2.0 authentication in a web browser",
+    specification: Some("RFC 6595"),
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: false,
+        weak: false,
+    },
 };
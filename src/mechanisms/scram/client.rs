@@ -29,18 +29,21 @@ use crate::gsasl::mechtools::{
 };
 use crate::gsasl::property::{gsasl_property_get, gsasl_property_set};
 use crate::gsasl::saslprep::{gsasl_saslprep, GSASL_ALLOW_UNASSIGNED};
+use crate::mechanisms::gs2_header::{CbFlag, SaslName};
 use crate::mechanisms::scram::parser::{
     scram_parse_server_final, scram_parse_server_first, ClientFinal, ClientFirstMessage,
-    GS2CBindFlag, SaslName, ServerErrorValue, ServerFinal, ServerFirst,
+    ServerErrorValue, ServerFinal, ServerFirst,
 };
 use crate::mechanisms::scram::printer::{scram_print_client_final, scram_print_client_first};
 use crate::mechanisms::scram::server::{scram_server_final, scram_server_first};
+use crate::mechanisms::scram::ssdp;
 use crate::mechanisms::scram::tokens::{
     scram_free_client_final, scram_free_client_first, scram_free_server_final,
     scram_free_server_first,
 };
 use crate::mechanisms::scram::tools::{find_proofs, hash_password, set_saltedpassword};
-use crate::property::{AuthId, AuthzId, Password};
+use crate::mechname::Mechname;
+use crate::property::{Advertised, AuthId, AuthzId, Password};
 use crate::session::Step::NeedsMore;
 use crate::session::{SessionData, Step, StepResult};
 use crate::vectored_io::VectoredWriter;
@@ -86,15 +89,15 @@ impl<const N: usize> State<StateClientFirst<N>> {
     pub fn step(
         self,
         rng: &mut impl Rng,
-        authzid: Option<&str>,
+        authzid: Option<&SaslName>,
         username: Box<SaslName>,
         writer: impl Write,
         written: &mut usize,
     ) -> Result<State<WaitingServerFirst<N>>, SessionError> {
         let cbflag = if let Some((name, _)) = self.cbdata.as_ref() {
-            GS2CBindFlag::Used(name)
+            CbFlag::Used(name)
         } else {
-            GS2CBindFlag::NotSupported
+            CbFlag::NotSupported
         };
         let state = self
             .state
@@ -109,11 +112,19 @@ impl<const N: usize> State<StateClientFirst<N>> {
 impl<const N: usize> State<WaitingServerFirst<N>> {
     pub fn step(
         self,
+        hash: Gsasl_hash,
         password: &str,
+        advertised: Option<&Advertised>,
         server_first: &[u8],
         writer: impl Write,
         written: &mut usize,
     ) -> Result<State<WaitingServerFinal<32>>, SessionError> {
+        if let Some(advertised) = advertised {
+            if !ssdp::verify(hash, advertised, server_first) {
+                return Err(SCRAMError::Protocol(ProtocolError::DowngradeDetected).into());
+            }
+        }
+
         let cbdata = self.cbdata.map(|(_, b)| b);
         let state =
             self.state
@@ -160,8 +171,8 @@ impl<const N: usize> StateClientFirst<N> {
     pub fn send_client_first(
         self,
         rng: &mut impl Rng,
-        cbflag: GS2CBindFlag<'_>,
-        authzid: Option<&str>,
+        cbflag: CbFlag<'_>,
+        authzid: Option<&SaslName>,
         username: Box<SaslName>,
         writer: impl Write,
         written: &mut usize,
@@ -239,6 +250,7 @@ impl<const N: usize> WaitingServerFirst<N> {
             nonce,
             salt,
             iteration_count,
+            ..
         } = ServerFirst::parse(server_first).map_err(SCRAMError::ParseError)?;
 
         if !(nonce.len() > self.client_nonce.len() && nonce.starts_with(&self.client_nonce[..])) {
@@ -333,31 +345,25 @@ impl<const N: usize> Authentication for ScramClient<N> {
                 let (_cbflag, _cbdata) = if self.plus {
                     let (name, value) = session
                         .get_cb_data()
-                        // TODO: fix
-                        .expect("CB data required");
-                    (GS2CBindFlag::Used(name), Some(base64::encode(value)))
+                        .ok_or(SCRAMError::Protocol(ProtocolError::NoChannelBinding))?;
+                    (CbFlag::Used(name), Some(base64::encode(value)))
                 } else {
-                    (GS2CBindFlag::NotSupported, None)
+                    (CbFlag::NotSupported, None)
                 };
 
                 let authzid = session.get_property_or_callback::<AuthzId>()?;
                 let authid = session
                     .get_property_or_callback::<AuthId>()?
                     .ok_or(SessionError::no_property::<AuthId>())?;
-                let username_escaped = SaslName::escape(&authid).unwrap();
-                let username =
-                    SaslName::from_boxed_str(username_escaped.into_owned().into_boxed_str())
-                        .expect("escaped SaslName contained invalid chars");
+                let authzid = authzid
+                    .map(|authzid| SaslName::from_unescaped(&authzid))
+                    .transpose()?;
+                let username = SaslName::from_unescaped(&authid)?;
 
                 let mut rng = rand::thread_rng();
                 let mut written = 0;
-                let new_state = state.step(
-                    &mut rng,
-                    authzid.as_ref().map(|arc| arc.as_str()),
-                    username,
-                    writer,
-                    &mut written,
-                )?;
+                let new_state =
+                    state.step(&mut rng, authzid.as_deref(), username, writer, &mut written)?;
                 self.state = Some(ClientFirst(new_state));
 
                 Ok(NeedsMore(Some(written)))
@@ -369,8 +375,17 @@ impl<const N: usize> Authentication for ScramClient<N> {
                     .get_property_or_callback::<Password>()?
                     .ok_or(SessionError::no_property::<Password>())?;
 
+                let advertised = ssdp::advertised(session)?;
+
                 let mut written = 0;
-                let new_state = state.step(&password, server_first, writer, &mut written)?;
+                let new_state = state.step(
+                    mechanism_hash(session.get_mechname()),
+                    &password,
+                    advertised.as_deref(),
+                    server_first,
+                    writer,
+                    &mut written,
+                )?;
                 self.state = Some(ServerFirst(new_state));
 
                 Ok(NeedsMore(Some(written)))
@@ -380,17 +395,30 @@ impl<const N: usize> Authentication for ScramClient<N> {
                 state.step(server_final)?;
                 Ok(Step::Done(None))
             }
-            None => panic!("State machine in invalid state"),
+            // A previous step either completed the exchange or failed
+            None => Err(SCRAMError::Protocol(ProtocolError::Finished).into()),
         }
     }
 }
 
+/// The hash function of the selected `SCRAM-*` mechanism
+fn mechanism_hash(mechanism: &Mechname) -> Gsasl_hash {
+    if mechanism.as_str().starts_with("SCRAM-SHA-1") {
+        GSASL_HASH_SHA1
+    } else {
+        GSASL_HASH_SHA256
+    }
+}
+
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
 pub enum ProtocolError {
     InvalidNonce,
     IterationCountFormat,
     IterationCountZero,
     ServerSignatureMismatch,
+    DowngradeDetected,
+    NoChannelBinding,
+    Finished,
 }
 
 impl Display for ProtocolError {
@@ -402,6 +430,11 @@ impl Display for ProtocolError {
             ProtocolError::ServerSignatureMismatch => {
                 f.write_str("Calculated server MAC and received server MAC do not match")
             }
+            ProtocolError::DowngradeDetected => f.write_str(
+                "advertised mechanisms or channel bindings were altered, possible downgrade attack",
+            ),
+            ProtocolError::NoChannelBinding => f.write_str("no channel binding data available"),
+            ProtocolError::Finished => f.write_str("the exchange has already finished"),
         }
     }
 }
@@ -444,6 +477,8 @@ mod tests {
     use std::io::Cursor;
     use std::sync::Arc;
 
+    use crate::property::properties::{AUTHID, AUTHZID, PASSWORD};
+    use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
     use crate::{Mechanism, Mechname, Side, SASL};
 
     use super::*;
@@ -457,6 +492,13 @@ mod tests {
             client: Some(|_sasl| Ok(Box::new(ScramClient::<18>::new()))),
             server: None,
             first: Side::Client,
+            client_properties: PropertyRequirements::new(&[AUTHID, PASSWORD], &[AUTHZID]),
+            server_properties: PropertyRequirements::NONE,
+            description: "SCRAM test mechanism",
+            specification: None,
+            channel_binding: false,
+            security_layer: false,
+            security: MechanismSecurityFactors::NONE,
         };
         sasl.register(&M);
         let mut session = sasl.client_start(Mechname::new(b"SCRAM").unwrap()).unwrap();
@@ -475,6 +517,10 @@ mod tests {
 
         println!("({:?}): {}", stepout, std::str::from_utf8(sdata).unwrap());
         assert_eq!(stepout, Step::NeedsMore(Some(after - before)));
+
+        // No password was provided, and a failed exchange can't be continued
+        assert!(session.step(Some(b"r=abc,s=c2FsdA==,i=4096"), &mut out).is_err());
+        assert!(session.step(Some(b"v=c2ln"), &mut out).is_err());
     }
 }
 
@@ -783,6 +829,16 @@ pub unsafe fn _gsasl_scram_client_step(
             {
                 return GSASL_AUTHENTICATION_ERROR as libc::c_int;
             }
+            match ssdp::advertised(sctx) {
+                Ok(Some(advertised)) => {
+                    let server_first = std::slice::from_raw_parts(input.cast::<u8>(), input_len);
+                    if !ssdp::verify((*state).hash, &advertised, server_first) {
+                        return GSASL_AUTHENTICATION_ERROR as libc::c_int;
+                    }
+                }
+                Ok(None) => {}
+                Err(_) => return GSASL_AUTHENTICATION_ERROR as libc::c_int,
+            }
             (*state).cl.nonce = strdup((*state).sf.nonce);
             if (*state).cl.nonce.is_null() {
                 return GSASL_MALLOC_ERROR as libc::c_int;
@@ -1,4 +1,6 @@
-use crate::gsasl::gsasl::{CMechanismStateKeeper, MechanismVTable};
+use crate::error::SASLError;
+use crate::gsasl::gsasl::{CMechanismStateKeeper, Gsasl_start_function, MechanismVTable};
+use crate::mechanism::Authentication;
 use crate::mechanisms::scram::client::{
     _gsasl_scram_client_finish, _gsasl_scram_client_step, _gsasl_scram_sha1_client_start,
     _gsasl_scram_sha1_plus_client_start, _gsasl_scram_sha256_client_start,
@@ -9,8 +11,33 @@ use crate::mechanisms::scram::server::{
     _gsasl_scram_sha1_server_start, _gsasl_scram_sha256_plus_server_start,
     _gsasl_scram_sha256_server_start,
 };
+#[cfg(feature = "session_state")]
+use crate::mechanisms::scram::state::export_server;
+use crate::property::properties::{
+    ADVERTISED_MECHANISMS, AUTHID, AUTHZID, PASSWORD, SCRAM_ITER, SCRAM_SALT,
+    SCRAM_SALTED_PASSWORD, SCRAM_SERVERKEY, SCRAM_STOREDKEY,
+};
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
 use crate::{Mechanism, Mechname, Side};
 
+/// The client uses a salted password instead of the password if one is provided
+const CLIENT_PROPERTIES: PropertyRequirements = PropertyRequirements::new(
+    &[AUTHID, PASSWORD],
+    &[AUTHZID, SCRAM_SALTED_PASSWORD, ADVERTISED_MECHANISMS],
+);
+/// The server uses the stored and server key instead of the password if both are provided, they
+/// have to be derived using the provided salt and iteration count
+const SERVER_PROPERTIES: PropertyRequirements = PropertyRequirements::new(
+    &[PASSWORD],
+    &[
+        SCRAM_SALT,
+        SCRAM_ITER,
+        SCRAM_STOREDKEY,
+        SCRAM_SERVERKEY,
+        ADVERTISED_MECHANISMS,
+    ],
+);
+
 #[cfg(feature = "registry_static")]
 use crate::registry::{distributed_slice, MECHANISMS};
 #[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
@@ -28,18 +55,20 @@ pub static SCRAM_SHA1: Mechanism = Mechanism {
             decode: None,
         })
     }),
-    server: Some(|_sasl| {
-        CMechanismStateKeeper::build(MechanismVTable {
-            init: None,
-            done: None,
-            start: Some(_gsasl_scram_sha1_server_start),
-            step: Some(_gsasl_scram_server_step),
-            finish: Some(_gsasl_scram_server_finish),
-            encode: None,
-            decode: None,
-        })
-    }),
+    server: Some(|_sasl| server(Some(_gsasl_scram_sha1_server_start))),
     first: Side::Client,
+    client_properties: CLIENT_PROPERTIES,
+    server_properties: SERVER_PROPERTIES,
+    description: "Salted challenge-response using SHA-1",
+    specification: Some("RFC 5802"),
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: true,
+        weak: false,
+    },
 };
 
 #[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
@@ -57,18 +86,20 @@ pub static SCRAM_SHA1_PLUS: Mechanism = Mechanism {
             decode: None,
         })
     }),
-    server: Some(|_sasl| {
-        CMechanismStateKeeper::build(MechanismVTable {
-            init: None,
-            done: None,
-            start: Some(_gsasl_scram_sha1_plus_server_start),
-            step: Some(_gsasl_scram_server_step),
-            finish: Some(_gsasl_scram_server_finish),
-            encode: None,
-            decode: None,
-        })
-    }),
+    server: Some(|_sasl| server(Some(_gsasl_scram_sha1_plus_server_start))),
     first: Side::Client,
+    client_properties: CLIENT_PROPERTIES,
+    server_properties: SERVER_PROPERTIES,
+    description: "Salted challenge-response using SHA-1, bound to the TLS connection",
+    specification: Some("RFC 5802"),
+    channel_binding: true,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: true,
+        weak: false,
+    },
 };
 
 #[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
@@ -86,18 +117,20 @@ pub static SCRAM_SHA256: Mechanism = Mechanism {
             decode: None,
         })
     }),
-    server: Some(|_sasl| {
-        CMechanismStateKeeper::build(MechanismVTable {
-            init: None,
-            done: None,
-            start: Some(_gsasl_scram_sha256_server_start),
-            step: Some(_gsasl_scram_server_step),
-            finish: Some(_gsasl_scram_server_finish),
-            encode: None,
-            decode: None,
-        })
-    }),
+    server: Some(|_sasl| server(Some(_gsasl_scram_sha256_server_start))),
     first: Side::Client,
+    client_properties: CLIENT_PROPERTIES,
+    server_properties: SERVER_PROPERTIES,
+    description: "Salted challenge-response using SHA-256",
+    specification: Some("RFC 7677"),
+    channel_binding: false,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: true,
+        weak: false,
+    },
 };
 
 #[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
@@ -115,16 +148,41 @@ pub static SCRAM_SHA256_PLUS: Mechanism = Mechanism {
             decode: None,
         })
     }),
-    server: Some(|_sasl| {
-        CMechanismStateKeeper::build(MechanismVTable {
-            init: None,
-            done: None,
-            start: Some(_gsasl_scram_sha256_plus_server_start),
-            step: Some(_gsasl_scram_server_step),
-            finish: Some(_gsasl_scram_server_finish),
-            encode: None,
-            decode: None,
-        })
-    }),
+    server: Some(|_sasl| server(Some(_gsasl_scram_sha256_plus_server_start))),
     first: Side::Client,
+    client_properties: CLIENT_PROPERTIES,
+    server_properties: SERVER_PROPERTIES,
+    description: "Salted challenge-response using SHA-256, bound to the TLS connection",
+    specification: Some("RFC 7677"),
+    channel_binding: true,
+    security_layer: false,
+    security: MechanismSecurityFactors {
+        max_ssf: 0,
+        noplain: true,
+        mutual: true,
+        weak: false,
+    },
 };
+
+pub(super) fn server_vtable(start: Gsasl_start_function) -> MechanismVTable {
+    MechanismVTable {
+        init: None,
+        done: None,
+        start,
+        step: Some(_gsasl_scram_server_step),
+        finish: Some(_gsasl_scram_server_finish),
+        encode: None,
+        decode: None,
+    }
+}
+
+fn server(start: Gsasl_start_function) -> Result<Box<dyn Authentication>, SASLError> {
+    #[cfg(feature = "session_state")]
+    {
+        CMechanismStateKeeper::build_exportable(server_vtable(start), Some(export_server))
+    }
+    #[cfg(not(feature = "session_state"))]
+    {
+        CMechanismStateKeeper::build(server_vtable(start))
+    }
+}
@@ -1,3 +1,4 @@
+use crate::mechanisms::gs2_header::{parse_header, CbFlag, Gs2HeaderError, SaslName};
 use crate::mechanisms::scram::client::{scram_client_final, scram_client_first};
 use crate::mechanisms::scram::server::{scram_server_final, scram_server_first};
 use crate::mechanisms::scram::validate::{
@@ -5,95 +6,10 @@ use crate::mechanisms::scram::validate::{
     scram_valid_server_first,
 };
 use ::libc;
-use libc::{malloc, memchr, memcpy, size_t, strnlen};
-use std::borrow::Cow;
+use libc::{malloc, memchr, memcpy, size_t, strdup, strnlen};
+use std::ffi::CString;
 use std::fmt::{Display, Formatter};
 
-#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
-pub enum SaslNameError {
-    Empty,
-    InvalidUtf8,
-    InvalidChar(u8),
-    InvalidEscape,
-}
-
-#[repr(transparent)]
-/// Escaped saslname type
-pub struct SaslName(str);
-impl SaslName {
-    /// Construct a new saslname from a given str
-    ///
-    /// This function will *fail* if the given name is not a valid saslname. To escape an
-    /// arbitratry string into a valid saslname use [`SaslName::escape`].
-    pub fn from_str(input: &str) -> Result<&Self, SaslNameError> {
-        if let Some(b) = input.find(&['\0', ',', '=']) {
-            Err(SaslNameError::InvalidChar(b as u8))
-        } else {
-            let this = unsafe { &*(input as *const str as *const SaslName) };
-            Ok(this)
-        }
-    }
-
-    pub fn from_boxed_str(input: Box<str>) -> Result<Box<Self>, SaslNameError> {
-        if let Some(b) = input.find(&['\0', ',', '=']) {
-            Err(SaslNameError::InvalidChar(b as u8))
-        } else {
-            let this = unsafe { Box::from_raw(Box::into_raw(input) as *mut SaslName) };
-            Ok(this)
-        }
-    }
-
-    pub fn as_str(&self) -> &str {
-        &self.0
-    }
-
-    pub fn escape(input: &str) -> Result<Cow<'_, str>, SaslNameError> {
-        if input.contains('\0') {
-            return Err(SaslNameError::InvalidChar(0));
-        }
-
-        if input.contains(&[',', '=']) {
-            Ok(input.replace(',', "=2C").replace('=', "=3D").into())
-        } else {
-            Ok(input.into())
-        }
-    }
-
-    pub fn unescape(&self) -> Result<Cow<'_, str>, SaslNameError> {
-        let v = &self.0;
-        if v.is_empty() {
-            return Err(SaslNameError::Empty);
-        }
-        if let Some(c) = v.find(&['\0', ',']) {
-            return Err(SaslNameError::InvalidChar(c as u8));
-        }
-        if let Some(bad) = v.bytes().position(|b| matches!(b, b'=')) {
-            let mut out = String::with_capacity(v.len());
-            let good = std::str::from_utf8(&v.as_bytes()[..bad])
-                .map_err(|_| SaslNameError::InvalidUtf8)?;
-            out.push_str(good);
-            let mut v = &v[bad..];
-
-            while let Some(bad) = v.bytes().position(|b| matches!(b, b'=')) {
-                let good = std::str::from_utf8(&v.as_bytes()[..bad])
-                    .map_err(|_| SaslNameError::InvalidUtf8)?;
-                out.push_str(good);
-                let c = match &v.as_bytes()[bad + 1..bad + 3] {
-                    b"2C" => ',',
-                    b"3D" => '=',
-                    _ => return Err(SaslNameError::InvalidEscape),
-                };
-                out.push(c);
-                v = &v[bad..];
-            }
-
-            Ok(Cow::Owned(out))
-        } else {
-            Ok(Cow::Borrowed(&self.0))
-        }
-    }
-}
-
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
 pub enum ParseError {
     BadCBFlag,
@@ -114,55 +30,43 @@ impl Display for ParseError {
     }
 }
 
-#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
-pub enum GS2CBindFlag<'scram> {
-    SupportedNotUsed,
-    NotSupported,
-    Used(&'scram str),
-}
-impl<'scram> GS2CBindFlag<'scram> {
-    pub fn parse(input: &'scram [u8]) -> Result<Self, ParseError> {
-        match input {
-            b"n" => Ok(Self::NotSupported),
-            b"y" => Ok(Self::SupportedNotUsed),
-            _x if input.len() > 2 && input[0] == b'p' && input[1] == b'=' => {
-                let cbname = &input[2..];
-                if let Some(bad) = cbname.into_iter().find(|b|
-                          // According to [RFC5056 Section 7](https://www.rfc-editor.org/rfc/rfc5056#section-7)
-                          // valid cb names are only composed of ASCII alphanumeric, '.' and '-'
-                          !(matches!(b, b'.' | b'-' | b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z')))
-                {
-                    Err(ParseError::BadCBName(*bad))
-                } else {
-                    // SAFE because we just checked for a subset of ASCII which is always UTF-8
-                    let name = unsafe { std::str::from_utf8_unchecked(cbname) };
-                    Ok(Self::Used(name))
-                }
-            }
-            _ => Err(ParseError::BadCBFlag),
-        }
+/// Map an error in the GS2 header of a client-first-message to the matching [`ParseError`]
+fn header_error(input: &[u8], error: Gs2HeaderError) -> ParseError {
+    let cbflag = input.split(|b| *b == b',').next().unwrap_or(input);
+    match error {
+        Gs2HeaderError::BadUtf8(_) => ParseError::BadUtf8,
+        Gs2HeaderError::BadFormat if CbFlag::parse(cbflag).is_err() => {
+            let bad = cbflag.strip_prefix(b"p=").and_then(|name| {
+                name.iter()
+                    .find(|b| !(b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-')))
+            });
+            bad.map(|b| ParseError::BadCBName(*b))
+                .unwrap_or(ParseError::BadCBFlag)
+        }
+        _ => ParseError::BadGS2Header,
     }
+}
 
-    pub fn to_ioslices(&self) -> [&'scram [u8]; 2] {
-        match self {
-            Self::NotSupported => [b"n", &[]],
-            Self::SupportedNotUsed => [b"y", &[]],
-            Self::Used(name) => [b"p=", name.as_bytes()],
-        }
-    }
+/// Error for an attribute that isn't the one expected at this position
+///
+/// Empty attributes, e.g. from a trailing or doubled ',', are reported as missing.
+fn invalid_attribute(attr: &[u8]) -> ParseError {
+    attr.first()
+        .map(|b| ParseError::InvalidAttribute(*b))
+        .unwrap_or(ParseError::MissingAttributes)
 }
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
 pub struct ClientFirstMessage<'scram> {
-    pub cbflag: GS2CBindFlag<'scram>,
-    pub authzid: Option<&'scram str>,
+    pub cbflag: CbFlag<'scram>,
+    pub authzid: Option<&'scram SaslName>,
     pub username: &'scram str,
     pub nonce: &'scram [u8],
 }
 impl<'scram> ClientFirstMessage<'scram> {
     pub fn new(
-        cbflag: GS2CBindFlag<'scram>,
-        authzid: Option<&'scram str>,
+        cbflag: CbFlag<'scram>,
+        authzid: Option<&'scram SaslName>,
         username: &'scram SaslName,
         nonce: &'scram [u8],
     ) -> Self {
@@ -175,34 +79,31 @@ impl<'scram> ClientFirstMessage<'scram> {
     }
 
     pub fn parse(input: &'scram [u8]) -> Result<Self, ParseError> {
-        let mut partiter = input.split(|b| matches!(b, b','));
-
-        let first = partiter.next().ok_or(ParseError::BadCBFlag)?;
-        let cbflag = GS2CBindFlag::parse(first)?;
+        let (header, rest) = parse_header(input).map_err(|error| header_error(input, error))?;
+        // SCRAM has no use for the `F` flag, its header is only ever parsed as a channel binding
+        if header.nonstd {
+            return Err(ParseError::BadGS2Header);
+        }
+        let (cbflag, authzid) = (header.cbflag, header.authzid);
 
-        let authzid = partiter.next().ok_or(ParseError::BadGS2Header)?;
-        let authzid = if !authzid.is_empty() {
-            Some(std::str::from_utf8(authzid).map_err(|_| ParseError::BadUtf8)?)
-        } else {
-            None
-        };
+        let mut partiter = rest.split(|b| matches!(b, b','));
 
         let next = partiter.next().ok_or(ParseError::MissingAttributes)?;
-        if &next[0..2] == b"m=" {
+        if next.starts_with(b"m=") {
             return Err(ParseError::UnknownMandatoryExtensions);
         }
 
-        let username = if &next[0..2] == b"n=" {
+        let username = if next.starts_with(b"n=") {
             std::str::from_utf8(&next[2..]).map_err(|_| ParseError::BadUtf8)?
         } else {
-            return Err(ParseError::InvalidAttribute(next[0] as u8));
+            return Err(invalid_attribute(next));
         };
 
         let next = partiter.next().ok_or(ParseError::MissingAttributes)?;
-        let nonce = if &next[0..2] == b"r=" {
+        let nonce = if next.starts_with(b"r=") {
             &next[2..]
         } else {
-            return Err(ParseError::InvalidAttribute(next[0] as u8));
+            return Err(invalid_attribute(next));
         };
         if !nonce
             .into_iter()
@@ -223,7 +124,7 @@ impl<'scram> ClientFirstMessage<'scram> {
         let [cba, cbb] = self.cbflag.to_ioslices();
 
         let (prefix, authzid): (&[u8], &[u8]) = if let Some(authzid) = self.authzid {
-            (b",a=", authzid.as_bytes())
+            (b",a=", authzid.as_str().as_bytes())
         } else {
             (b",", &[])
         };
@@ -246,6 +147,8 @@ pub struct ServerFirst<'scram> {
     pub nonce: &'scram [u8],
     pub salt: &'scram [u8],
     pub iteration_count: &'scram [u8],
+    /// Value of the `d` extension attribute used for downgrade protection, if present
+    pub downgrade_protection: Option<&'scram [u8]>,
 }
 
 impl<'scram> ServerFirst<'scram> {
@@ -253,41 +156,45 @@ impl<'scram> ServerFirst<'scram> {
         let mut partiter = input.split(|b| matches!(b, b','));
 
         let next = partiter.next().ok_or(ParseError::MissingAttributes)?;
-        if next.len() < 2 {
-            println!("{:?}", input);
-        }
-        if &next[0..2] == b"m=" {
+        if next.starts_with(b"m=") {
             return Err(ParseError::UnknownMandatoryExtensions);
         }
 
-        let nonce = if &next[0..2] == b"r=" {
+        let nonce = if next.starts_with(b"r=") {
             &next[2..]
         } else {
-            return Err(ParseError::InvalidAttribute(next[0] as u8));
+            return Err(invalid_attribute(next));
         };
 
         let next = partiter.next().ok_or(ParseError::MissingAttributes)?;
-        let salt = if &next[0..2] == b"s=" {
+        let salt = if next.starts_with(b"s=") {
             &next[2..]
         } else {
-            return Err(ParseError::InvalidAttribute(next[0] as u8));
+            return Err(invalid_attribute(next));
         };
 
         let next = partiter.next().ok_or(ParseError::MissingAttributes)?;
-        let iteration_count = if &next[0..2] == b"i=" {
+        let iteration_count = if next.starts_with(b"i=") {
             &next[2..]
         } else {
-            return Err(ParseError::InvalidAttribute(next[0] as u8));
+            return Err(invalid_attribute(next));
         };
 
-        if let Some(next) = partiter.next() {
-            return Err(ParseError::InvalidAttribute(next[0]));
+        // Extensions are optional and unknown ones are ignored
+        let mut downgrade_protection = None;
+        for next in partiter {
+            match next {
+                [b'd', b'=', value @ ..] => downgrade_protection = Some(value),
+                [attr, b'=', ..] if attr.is_ascii_alphabetic() => {}
+                _ => return Err(invalid_attribute(next)),
+            }
         }
 
         Ok(Self {
             nonce,
             salt,
             iteration_count,
+            downgrade_protection,
         })
     }
 
@@ -322,26 +229,26 @@ impl<'scram> ClientFinal<'scram> {
         let mut partiter = input.split(|b| matches!(b, b','));
 
         let next = partiter.next().ok_or(ParseError::MissingAttributes)?;
-        let channel_binding = if &next[0..2] == b"c=" {
+        let channel_binding = if next.starts_with(b"c=") {
             &next[2..]
         } else {
-            return Err(ParseError::InvalidAttribute(next[0]));
+            return Err(invalid_attribute(next));
         };
         let next = partiter.next().ok_or(ParseError::MissingAttributes)?;
-        let nonce = if &next[0..2] == b"r=" {
+        let nonce = if next.starts_with(b"r=") {
             &next[2..]
         } else {
-            return Err(ParseError::InvalidAttribute(next[0]));
+            return Err(invalid_attribute(next));
         };
         let next = partiter.next().ok_or(ParseError::MissingAttributes)?;
-        let proof = if &next[0..2] == b"p=" {
+        let proof = if next.starts_with(b"p=") {
             &next[2..]
         } else {
-            return Err(ParseError::InvalidAttribute(next[0]));
+            return Err(invalid_attribute(next));
         };
 
         if let Some(next) = partiter.next() {
-            return Err(ParseError::InvalidAttribute(next[0]));
+            return Err(invalid_attribute(next));
         }
 
         Ok(Self {
@@ -423,9 +330,9 @@ pub enum ServerFinal<'scram> {
 
 impl<'scram> ServerFinal<'scram> {
     pub fn parse(input: &'scram [u8]) -> Result<Self, ParseError> {
-        if &input[0..2] == b"v=" {
+        if input.starts_with(b"v=") {
             Ok(Self::Verifier(&input[2..]))
-        } else if &input[0..2] == b"e=" {
+        } else if input.starts_with(b"e=") {
             use ServerErrorValue::*;
             let e = match &input[2..] {
                 b"invalid-encoding" => InvalidEncoding,
@@ -442,7 +349,7 @@ impl<'scram> ServerFinal<'scram> {
             };
             Ok(Self::Error(e))
         } else {
-            Err(ParseError::InvalidAttribute(input[0]))
+            Err(invalid_attribute(input))
         }
     }
 
@@ -461,25 +368,25 @@ mod tests {
     use std::io::Cursor;
 
     #[test]
-    fn test_parse_gs2_cbind_flag() {
-        let valid: [(&[u8], GS2CBindFlag); 7] = [
-            (b"n", GS2CBindFlag::NotSupported),
-            (b"y", GS2CBindFlag::SupportedNotUsed),
-            (b"p=tls-unique", GS2CBindFlag::Used("tls-unique")),
-            (b"p=.", GS2CBindFlag::Used(".")),
-            (b"p=-", GS2CBindFlag::Used("-")),
-            (b"p=a", GS2CBindFlag::Used("a")),
-            (
-                b"p=a-very-long-cb-name.indeed",
-                GS2CBindFlag::Used("a-very-long-cb-name.indeed"),
-            ),
-        ];
-
-        for (input, output) in valid.iter() {
-            assert_eq!(GS2CBindFlag::parse(input), Ok(*output))
+    fn test_parse_truncated_attributes() {
+        let inputs: [&[u8]; 6] = [b"", b"r", b"r=a,", b"r=a,s", b",,", b"n,,n"];
+        for input in inputs.iter() {
+            assert!(ServerFirst::parse(input).is_err());
+            assert!(ClientFinal::parse(input).is_err());
+            assert!(ServerFinal::parse(input).is_err());
+            assert!(ClientFirstMessage::parse(input).is_err());
         }
     }
 
+    #[test]
+    fn test_parse_server_first_extensions() {
+        let sf = ServerFirst::parse(b"r=abc,s=c2FsdA==,i=4096,x=ignored,d=aGFzaA==").unwrap();
+        assert_eq!(sf.iteration_count, b"4096");
+        assert_eq!(sf.downgrade_protection, Some(&b"aGFzaA=="[..]));
+        assert!(ServerFirst::parse(b"r=abc,s=c2FsdA==,i=4096,").is_err());
+        assert!(ServerFirst::parse(b"r=abc,s=c2FsdA==,i=4096,==").is_err());
+    }
+
     #[test]
     fn write_client_first_message() {
         let username = "testuser";
@@ -487,7 +394,7 @@ mod tests {
         let cbname = "tls-unique";
 
         let msg = ClientFirstMessage {
-            cbflag: GS2CBindFlag::Used(cbname),
+            cbflag: CbFlag::Used(cbname),
             authzid: None,
             username,
             nonce,
@@ -506,10 +413,27 @@ mod tests {
 
         let parsed = ClientFirstMessage::parse(expected.as_bytes()).unwrap();
         println!("Parsed: {:?}", parsed);
-        assert_eq!(parsed.cbflag, GS2CBindFlag::Used("tls-unique"));
+        assert_eq!(parsed.cbflag, CbFlag::Used("tls-unique"));
         assert_eq!(parsed.authzid, None);
         assert_eq!(parsed.username, username);
         assert_eq!(parsed.nonce, nonce);
+
+        let with_authzid = "n,a=ad=2Cmin,n=testuser,r=testnonce";
+        let parsed = ClientFirstMessage::parse(with_authzid.as_bytes()).unwrap();
+        assert_eq!(parsed.authzid.map(SaslName::as_str), Some("ad=2Cmin"));
+        assert_eq!(parsed.authzid.unwrap().unescape(), "ad,min");
+        assert_eq!(
+            ClientFirstMessage::parse(b"n,admin,n=testuser,r=testnonce"),
+            Err(ParseError::BadGS2Header)
+        );
+        assert_eq!(
+            ClientFirstMessage::parse(b"x,,n=testuser,r=testnonce"),
+            Err(ParseError::BadCBFlag)
+        );
+        assert_eq!(
+            ClientFirstMessage::parse(b"F,n,,n=testuser,r=testnonce"),
+            Err(ParseError::BadGS2Header)
+        );
     }
 }
 
@@ -571,45 +495,13 @@ unsafe fn c_isalpha(c: libc::c_int) -> bool {
 /* Get memcpy, strlen. */
 /* Get validator. */
 /* Get c_isalpha. */
-unsafe fn unescape(mut str: *const libc::c_char, mut len: size_t) -> *mut libc::c_char {
-    let out: *mut libc::c_char = malloc(len.wrapping_add(1)) as *mut libc::c_char;
-    let mut p: *mut libc::c_char = out;
-    if out.is_null() {
-        return 0 as *mut libc::c_char;
-    }
-    while len > 0 && *str as libc::c_int != 0 {
-        if len >= 3
-            && *str.offset(0 as libc::c_int as isize) as libc::c_int == '=' as i32
-            && *str.offset(1 as libc::c_int as isize) as libc::c_int == '2' as i32
-            && *str.offset(2 as libc::c_int as isize) as libc::c_int == 'C' as i32
-        {
-            let fresh0 = p;
-            p = p.offset(1);
-            *fresh0 = ',' as i32 as libc::c_char;
-            str = str.offset(3 as libc::c_int as isize);
-            len = (len as libc::c_ulong).wrapping_sub(3 as libc::c_int as libc::c_ulong) as size_t
-                as size_t
-        } else if len >= 3
-            && *str.offset(0 as libc::c_int as isize) as libc::c_int == '=' as i32
-            && *str.offset(1 as libc::c_int as isize) as libc::c_int == '3' as i32
-            && *str.offset(2 as libc::c_int as isize) as libc::c_int == 'D' as i32
-        {
-            let fresh1 = p;
-            p = p.offset(1);
-            *fresh1 = '=' as i32 as libc::c_char;
-            str = str.offset(3 as libc::c_int as isize);
-            len = (len as libc::c_ulong).wrapping_sub(3 as libc::c_int as libc::c_ulong) as size_t
-                as size_t
-        } else {
-            let fresh2 = p;
-            p = p.offset(1);
-            *fresh2 = *str;
-            str = str.offset(1);
-            len = len.wrapping_sub(1)
-        }
+/* Return a newly allocated C string copy of VALUE, or NULL on memory
+allocation errors. VALUE must not contain NUL. */
+unsafe fn c_string(value: &str) -> *mut libc::c_char {
+    match CString::new(value) {
+        Ok(value) => strdup(value.as_ptr()),
+        Err(_) => std::ptr::null_mut(),
     }
-    *p = '\u{0}' as i32 as libc::c_char;
-    return out;
 }
 pub unsafe fn scram_parse_client_first(
     mut str: *const libc::c_char,
@@ -627,75 +519,29 @@ pub unsafe fn scram_parse_client_first(
     {
         return -(1 as libc::c_int);
     }
-    (*cf).cbflag = *str;
-    str = str.offset(1);
-    len = len.wrapping_sub(1);
-    if (*cf).cbflag as libc::c_int == 'p' as i32 {
-        let p;
-        if len == 0 || *str as libc::c_int != '=' as i32 {
-            return -(1 as libc::c_int);
-        }
-        str = str.offset(1);
-        len = len.wrapping_sub(1);
-        p = memchr(str as *const libc::c_void, ',' as i32, len) as *const libc::c_char;
-        if p.is_null() {
-            return -(1 as libc::c_int);
-        }
-        (*cf).cbname = malloc((p.offset_from(str) + 1) as size_t) as *mut libc::c_char;
-        if (*cf).cbname.is_null() {
-            return -(1 as libc::c_int);
-        }
-        memcpy(
-            (*cf).cbname as *mut libc::c_void,
-            str as *const libc::c_void,
-            p.offset_from(str) as size_t,
-        );
-        *(*cf)
-            .cbname
-            .offset(p.offset_from(str) as libc::c_long as isize) = '\u{0}' as i32 as libc::c_char;
-        len = (len as libc::c_ulong)
-            .wrapping_sub(p.offset_from(str) as libc::c_long as libc::c_ulong)
-            as size_t as size_t;
-        str = str.offset(p.offset_from(str) as libc::c_long as isize)
-    }
-    if len == 0 || *str as libc::c_int != ',' as i32 {
-        return -(1 as libc::c_int);
-    }
-    str = str.offset(1);
-    len = len.wrapping_sub(1);
-    if len == 0 {
-        return -(1 as libc::c_int);
-    }
-    if *str as libc::c_int == 'a' as i32 {
-        let p_0;
-        let l;
-        str = str.offset(1);
-        len = len.wrapping_sub(1);
-        if len == 0 || *str as libc::c_int != '=' as i32 {
-            return -(1 as libc::c_int);
-        }
-        str = str.offset(1);
-        len = len.wrapping_sub(1);
-        p_0 = memchr(str as *const libc::c_void, ',' as i32, len) as *const libc::c_char;
-        if p_0.is_null() {
-            return -(1 as libc::c_int);
-        }
-        l = p_0.offset_from(str) as libc::c_long as size_t;
-        if len < l {
-            return -(1 as libc::c_int);
+    let (header, _) = match parse_header(std::slice::from_raw_parts(str as *const u8, len)) {
+        Ok((header, rest)) if !header.nonstd => (header, rest),
+        _ => return -(1 as libc::c_int),
+    };
+    (*cf).cbflag = match header.cbflag {
+        CbFlag::NotSupported => 'n' as i32 as libc::c_char,
+        CbFlag::SupportedNotUsed => 'y' as i32 as libc::c_char,
+        CbFlag::Used(name) => {
+            (*cf).cbname = c_string(name);
+            if (*cf).cbname.is_null() {
+                return -(1 as libc::c_int);
+            }
+            'p' as i32 as libc::c_char
         }
-        (*cf).authzid = unescape(str, l);
+    };
+    if let Some(authzid) = header.authzid() {
+        (*cf).authzid = c_string(&authzid);
         if (*cf).authzid.is_null() {
             return -(1 as libc::c_int);
         }
-        str = p_0;
-        len = len.wrapping_sub(l) as size_t as size_t
     }
-    if len == 0 || *str as libc::c_int != ',' as i32 {
-        return -(1 as libc::c_int);
-    }
-    str = str.offset(1);
-    len = len.wrapping_sub(1);
+    str = str.offset(header.raw.len() as isize);
+    len = len.wrapping_sub(header.raw.len());
     if len == 0 || *str as libc::c_int != 'n' as i32 {
         return -(1 as libc::c_int);
     }
@@ -716,7 +562,13 @@ pub unsafe fn scram_parse_client_first(
     if len < l_0 {
         return -(1 as libc::c_int);
     }
-    (*cf).username = unescape(str, l_0);
+    let username = std::str::from_utf8(std::slice::from_raw_parts(str as *const u8, l_0))
+        .ok()
+        .and_then(|username| SaslName::new(username).ok());
+    (*cf).username = match username {
+        Some(username) => c_string(&username.unescape()),
+        None => std::ptr::null_mut(),
+    };
     if (*cf).username.is_null() {
         return -(1 as libc::c_int);
     }
@@ -1,4 +1,5 @@
 use crate::gsasl::gl::free::rpl_free;
+use crate::mechanisms::gs2_header::SaslName;
 use crate::mechanisms::scram::client::{scram_client_final, scram_client_first};
 use crate::mechanisms::scram::server::{scram_server_final, scram_server_first};
 use crate::mechanisms::scram::validate::{
@@ -6,7 +7,8 @@ use crate::mechanisms::scram::validate::{
     scram_valid_server_first,
 };
 use ::libc;
-use libc::{malloc, memcpy, strlen};
+use libc::strdup;
+use std::ffi::{CStr, CString};
 
 extern "C" {
     fn asprintf(__ptr: *mut *mut libc::c_char, __fmt: *const libc::c_char, _: ...) -> libc::c_int;
@@ -38,39 +40,16 @@ extern "C" {
 /* Get asprintf. */
 /* Get strdup. */
 /* Get token validator. */
-unsafe fn scram_escape(mut str: *const libc::c_char) -> *mut libc::c_char {
-    let out: *mut libc::c_char =
-        malloc(strlen(str).wrapping_mul(3).wrapping_add(1)) as *mut libc::c_char;
-
-    let mut p: *mut libc::c_char = out;
-    if out.is_null() {
-        return 0 as *mut libc::c_char;
-    }
-    while *str != 0 {
-        if *str as libc::c_int == ',' as i32 {
-            memcpy(
-                p as *mut libc::c_void,
-                b"=2C\x00" as *const u8 as *const libc::c_void,
-                3,
-            );
-
-            p = p.offset(3)
-        } else if *str as libc::c_int == '=' as i32 {
-            memcpy(
-                p as *mut libc::c_void,
-                b"=3D\x00" as *const u8 as *const libc::c_void,
-                3,
-            );
-
-            p = p.offset(3 as libc::c_int as isize)
-        } else {
-            *p = *str;
-            p = p.offset(1)
-        }
-        str = str.offset(1)
+unsafe fn scram_escape(str: *const libc::c_char) -> *mut libc::c_char {
+    let escaped = CStr::from_ptr(str)
+        .to_str()
+        .ok()
+        .and_then(|str| SaslName::escape(str).ok())
+        .and_then(|escaped| CString::new(escaped.into_owned()).ok());
+    match escaped {
+        Some(escaped) => strdup(escaped.as_ptr()),
+        None => std::ptr::null_mut(),
     }
-    *p = '\u{0}' as i32 as libc::c_char;
-    return out;
 }
 /* printer.h --- Convert SCRAM token structures into strings.
  * Copyright (C) 2009-2021 Simon Josefsson
@@ -17,6 +17,7 @@ use crate::gsasl::saslprep::{gsasl_saslprep, GSASL_ALLOW_UNASSIGNED};
 use crate::mechanisms::scram::client::{scram_client_final, scram_client_first};
 use crate::mechanisms::scram::parser::{scram_parse_client_final, scram_parse_client_first};
 use crate::mechanisms::scram::printer::{scram_print_server_final, scram_print_server_first};
+use crate::mechanisms::scram::ssdp;
 use crate::mechanisms::scram::tokens::{
     scram_free_client_final, scram_free_client_first, scram_free_server_final,
     scram_free_server_first,
@@ -28,6 +29,7 @@ use ::libc;
 use libc::{
     calloc, malloc, memchr, memcmp, memcpy, memmem, size_t, strcmp, strdup, strlen, strtoul,
 };
+use std::ffi::{CStr, CString};
 use std::ptr::NonNull;
 
 extern "C" {
@@ -405,6 +407,26 @@ pub unsafe fn _gsasl_scram_server_step(
             if rc != 0 as libc::c_int {
                 return GSASL_MALLOC_ERROR as libc::c_int;
             }
+            /* Append the downgrade protection attribute if the advertised
+            mechanisms are known. */
+            let advertised = match ssdp::advertised(sctx) {
+                Ok(advertised) => advertised,
+                Err(_) => return GSASL_AUTHENTICATION_ERROR as libc::c_int,
+            };
+            if let Some(advertised) = advertised {
+                let value = match ssdp::attribute_value((*state).hash, &advertised) {
+                    Some(value) => value,
+                    None => return GSASL_AUTHENTICATION_ERROR as libc::c_int,
+                };
+                let sf = CStr::from_ptr((*state).sf_str).to_string_lossy();
+                // Neither part can contain a NUL byte
+                let sf_str = CString::new(format!("{},d={}", sf, value)).unwrap();
+                rpl_free((*state).sf_str as *mut libc::c_void);
+                (*state).sf_str = strdup(sf_str.as_ptr());
+                if (*state).sf_str.is_null() {
+                    return GSASL_MALLOC_ERROR as libc::c_int;
+                }
+            }
             *output = strdup((*state).sf_str);
             if (*output).is_null() {
                 return GSASL_MALLOC_ERROR as libc::c_int;
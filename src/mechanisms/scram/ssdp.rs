@@ -0,0 +1,159 @@
+//! SCRAM Downgrade Protection ([XEP-0474](https://xmpp.org/extensions/xep-0474.html))
+//!
+//! The server sends a hash of the mechanisms and channel binding types it advertised as the `d`
+//! attribute of its server-first message. Since the server-first message is part of the
+//! AuthMessage signed by both parties an attacker can neither alter nor strip the attribute, so a
+//! client comparing it with what it saw advertised detects mechanisms or channel binding types
+//! having been removed before the exchange started.
+
+use crate::error::SessionError;
+use crate::gsasl::crypto::gsasl_hash_length;
+use crate::gsasl::mechtools::{_gsasl_hash, Gsasl_hash, GSASL_HASH_MAX_SIZE};
+use crate::mechanisms::scram::parser::ServerFirst;
+use crate::property::{Advertised, AdvertisedMechanisms};
+use crate::session::SessionData;
+use std::sync::Arc;
+
+/// Query the [`AdvertisedMechanisms`] property
+///
+/// Downgrade protection is optional. Callbacks commonly answer properties they don't know with
+/// [`SessionError::NoProperty`], which just like a missing callback means the exchange continues
+/// without it. Any other error is returned.
+pub(crate) fn advertised(
+    session: &mut SessionData,
+) -> Result<Option<Arc<Advertised>>, SessionError> {
+    match session.get_property_or_callback::<AdvertisedMechanisms>() {
+        Err(SessionError::NoProperty { .. }) => Ok(None),
+        result => result,
+    }
+}
+
+/// The string that is hashed: the sorted mechanisms separated by `,`, followed by `|` and the
+/// sorted channel binding types if any were advertised.
+fn hash_input(advertised: &Advertised) -> String {
+    let mut mechanisms: Vec<&str> = advertised.mechanisms.iter().map(String::as_str).collect();
+    mechanisms.sort_unstable();
+    let mut input = mechanisms.join(",");
+    if !advertised.channel_bindings.is_empty() {
+        let mut channel_bindings: Vec<&str> = advertised
+            .channel_bindings
+            .iter()
+            .map(String::as_str)
+            .collect();
+        channel_bindings.sort_unstable();
+        input.push('|');
+        input.push_str(&channel_bindings.join(","));
+    }
+    input
+}
+
+/// Base64-encoded value of the `d` attribute for the given advertisement
+pub(crate) fn attribute_value(hash: Gsasl_hash, advertised: &Advertised) -> Option<String> {
+    let input = hash_input(advertised);
+    let mut out = [0u8; GSASL_HASH_MAX_SIZE as usize];
+    unsafe {
+        if _gsasl_hash(
+            hash,
+            input.as_ptr().cast(),
+            input.len(),
+            out.as_mut_ptr().cast(),
+        ) != 0
+        {
+            return None;
+        }
+        Some(base64::encode(&out[..gsasl_hash_length(hash)]))
+    }
+}
+
+/// Check the `d` attribute of a server-first message
+///
+/// This is only called if the advertisement is known, in which case the server is expected to
+/// support downgrade protection and a server-first message without the attribute is rejected.
+pub(crate) fn verify(hash: Gsasl_hash, advertised: &Advertised, server_first: &[u8]) -> bool {
+    let received = match ServerFirst::parse(server_first) {
+        Ok(ServerFirst {
+            downgrade_protection: Some(received),
+            ..
+        }) => received,
+        _ => return false,
+    };
+    matches!(attribute_value(hash, advertised), Some(expected) if expected.as_bytes() == received)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gsasl::mechtools::{GSASL_HASH_SHA1, GSASL_HASH_SHA256};
+
+    #[test]
+    fn hash_input_is_sorted() {
+        let advertised = Advertised::new(
+            ["SCRAM-SHA-256-PLUS", "PLAIN", "SCRAM-SHA-1"],
+            ["tls-server-end-point", "tls-exporter"],
+        );
+        assert_eq!(
+            hash_input(&advertised),
+            "PLAIN,SCRAM-SHA-1,SCRAM-SHA-256-PLUS|tls-exporter,tls-server-end-point"
+        );
+        let advertised = Advertised::new(["SCRAM-SHA-1", "PLAIN"], Vec::<String>::new());
+        assert_eq!(hash_input(&advertised), "PLAIN,SCRAM-SHA-1");
+    }
+
+    /// The advertisement of the XEP-0474 examples
+    #[test]
+    fn xep0474_vector() {
+        let advertised = Advertised::new(
+            [
+                "SCRAM-SHA3-512-PLUS",
+                "SCRAM-SHA-1",
+                "SCRAM-SHA-1-PLUS",
+                "SCRAM-SHA-256",
+                "SCRAM-SHA-256-PLUS",
+                "SCRAM-SHA-512",
+                "SCRAM-SHA-512-PLUS",
+                "SCRAM-SHA3-512",
+            ],
+            ["tls-server-end-point", "tls-exporter"],
+        );
+        assert_eq!(
+            hash_input(&advertised),
+            "SCRAM-SHA-1,SCRAM-SHA-1-PLUS,SCRAM-SHA-256,SCRAM-SHA-256-PLUS,SCRAM-SHA-512,\
+             SCRAM-SHA-512-PLUS,SCRAM-SHA3-512,SCRAM-SHA3-512-PLUS|tls-exporter,tls-server-end-point"
+        );
+        assert_eq!(
+            attribute_value(GSASL_HASH_SHA1, &advertised).as_deref(),
+            Some("LqNxST/AeOUxPNubjzHe5TVzZqw=")
+        );
+        assert_eq!(
+            attribute_value(GSASL_HASH_SHA256, &advertised).as_deref(),
+            Some("KhaRDxEGLY52VslJ/DXu34cRejVwcE+V1x1BN1RUkKE=")
+        );
+    }
+
+    #[test]
+    fn verify_attribute() {
+        let advertised = Advertised::new(["SCRAM-SHA-1", "SCRAM-SHA-1-PLUS"], ["tls-exporter"]);
+        let value = attribute_value(GSASL_HASH_SHA1, &advertised).unwrap();
+        let server_first = format!("r=abcdef,s=c2FsdA==,i=4096,d={}", value);
+        assert!(verify(
+            GSASL_HASH_SHA1,
+            &advertised,
+            server_first.as_bytes()
+        ));
+        assert!(!verify(
+            GSASL_HASH_SHA256,
+            &advertised,
+            server_first.as_bytes()
+        ));
+
+        let stripped = Advertised::new(["SCRAM-SHA-1"], Vec::<String>::new());
+        assert!(!verify(GSASL_HASH_SHA1, &stripped, server_first.as_bytes()));
+
+        // A missing attribute could have been stripped
+        assert!(!verify(
+            GSASL_HASH_SHA1,
+            &stripped,
+            b"r=abcdef,s=c2FsdA==,i=4096"
+        ));
+    }
+}
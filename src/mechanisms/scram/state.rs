@@ -0,0 +1,92 @@
+//! Exporting and resuming the state of the server side, see [`crate::state`]
+
+use crate::gsasl::gsasl::CMechanismStateKeeper;
+use crate::gsasl::mechtools::Gsasl_hash;
+use crate::mechanism::Authentication;
+use crate::mechanisms::scram::mechinfo::server_vtable;
+use crate::mechanisms::scram::server::scram_server_state;
+use crate::state::{malloc_copy, StateError, StateReader, StateWriter};
+use libc::{c_char, calloc, size_t};
+use std::alloc::{handle_alloc_error, Layout};
+use std::ptr::NonNull;
+
+pub(crate) unsafe fn export_server(
+    mech_data: NonNull<()>,
+    writer: &mut StateWriter,
+) -> Result<(), StateError> {
+    let state = &*(mech_data.as_ptr() as *const scram_server_state);
+    // Once the exchange completed there is nothing left to resume
+    if state.step > 1 {
+        return Err(StateError::Unsupported);
+    }
+    writer.put_u8(state.step as u8);
+    writer.put_c_str(state.snonce);
+    writer.put_c_str(state.sf.salt);
+    if state.step == 1 {
+        writer.put_u8(state.cf.cbflag as u8);
+        writer.put_c_str(state.gs2header);
+        writer.put_c_str(state.cfmb_str);
+        writer.put_c_str(state.sf_str);
+        writer.put_c_str(state.sf.nonce);
+        writer.put_u64(state.sf.iter as u64);
+        let cbtlsunique = if state.cbtlsunique.is_null() {
+            None
+        } else {
+            Some(std::slice::from_raw_parts(
+                state.cbtlsunique as *const u8,
+                state.cbtlsuniquelen,
+            ))
+        };
+        writer.put_optional(cbtlsunique);
+    }
+    Ok(())
+}
+
+pub(crate) fn import_server(
+    hash: Gsasl_hash,
+    reader: &mut StateReader,
+) -> Result<Box<dyn Authentication>, StateError> {
+    let step = reader.get_u8()?;
+    if step > 1 {
+        return Err(StateError::Invalid);
+    }
+    unsafe {
+        let state = calloc(std::mem::size_of::<scram_server_state>(), 1) as *mut scram_server_state;
+        let mech_data = match NonNull::new(state) {
+            Some(state) => state.cast(),
+            None => handle_alloc_error(Layout::new::<scram_server_state>()),
+        };
+        // The keeper owns the state from here on and frees it on errors too
+        let mechanism =
+            CMechanismStateKeeper::resume(server_vtable(None), mech_data, Some(export_server));
+
+        (*state).hash = hash;
+        (*state).step = step as libc::c_int;
+        (*state).snonce = reader.get_c_string()?;
+        (*state).sf.salt = reader.get_c_string()?;
+        if (*state).snonce.is_null() || (*state).sf.salt.is_null() {
+            return Err(StateError::Invalid);
+        }
+        if step == 1 {
+            (*state).cf.cbflag = reader.get_u8()? as c_char;
+            (*state).gs2header = reader.get_c_string()?;
+            (*state).cfmb_str = reader.get_c_string()?;
+            (*state).sf_str = reader.get_c_string()?;
+            (*state).sf.nonce = reader.get_c_string()?;
+            (*state).sf.iter = reader.get_u64()? as size_t;
+            if let Some(cbtlsunique) = reader.get_optional()? {
+                (*state).cbtlsunique = malloc_copy(cbtlsunique);
+                (*state).cbtlsuniquelen = cbtlsunique.len();
+            }
+            // The second step relies on all of these being set
+            if (*state).gs2header.is_null()
+                || (*state).cfmb_str.is_null()
+                || (*state).sf_str.is_null()
+                || (*state).sf.nonce.is_null()
+            {
+                return Err(StateError::Invalid);
+            }
+        }
+        Ok(mechanism)
+    }
+}
@@ -1,242 +1,86 @@
-use crate::gsasl::consts::{
-    GSASL_AUTHID, GSASL_AUTHZID, GSASL_MALLOC_ERROR, GSASL_MECHANISM_CALLED_TOO_MANY_TIMES,
-    GSASL_NO_AUTHID, GSASL_NO_PASSCODE, GSASL_NO_PIN, GSASL_OK, GSASL_PASSCODE, GSASL_PIN,
-    GSASL_SUGGESTED_PIN,
-};
-use crate::gsasl::gl::free::rpl_free;
-use crate::gsasl::property::{gsasl_property_get, gsasl_property_set_raw};
-use crate::session::SessionData;
-use crate::Shared;
-use ::libc;
-use libc::{malloc, memcmp, memcpy, size_t, strlen};
-use std::ptr::NonNull;
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::securid::server::SecuridError;
+use crate::property::{AuthId, AuthzId, Passcode, Pin, SuggestedPin};
+use crate::session::Step::Done;
+use crate::session::{SessionData, StepResult};
+use crate::vectored_io::VectoredWriter;
+use std::io::Write;
+use std::sync::Arc;
 
-pub(crate) unsafe fn _gsasl_securid_client_start(
-    mut _sctx: &Shared,
-    mech_data: &mut Option<NonNull<()>>,
-) -> libc::c_int {
-    let step;
-    step = malloc(::std::mem::size_of::<libc::c_int>()) as *mut libc::c_int;
-    if step.is_null() {
-        return GSASL_MALLOC_ERROR as libc::c_int;
-    }
-    *step = 0 as libc::c_int;
-    *mech_data = NonNull::new(step as *mut ());
-    return GSASL_OK as libc::c_int;
+/// Client side of `SECURID`
+///
+/// If the server asks for the next token code the [`Passcode`] is queried from the callback
+/// again. If it asks for a new PIN the suggested one, if any, is made available as
+/// [`SuggestedPin`] before querying the [`Pin`].
+///
+/// Every message sent completes the exchange from the client's point of view. Should the server
+/// ask for more instead of indicating success the mechanism can be stepped again with that
+/// challenge.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Securid {
+    sent: bool,
 }
 
-pub unsafe fn _gsasl_securid_client_step(
-    sctx: &mut SessionData,
-    mech_data: Option<NonNull<()>>,
-    input: Option<&[u8]>,
-    output: *mut *mut libc::c_char,
-    output_len: *mut size_t,
-) -> libc::c_int {
-    let mech_data = mech_data
-        .map(|ptr| ptr.as_ptr())
-        .unwrap_or_else(std::ptr::null_mut);
-
-    let input_len = input.map(|i| i.len()).unwrap_or(0);
-    let input: *const libc::c_char = input.map(|i| i.as_ptr().cast()).unwrap_or(std::ptr::null());
-
-    let step: *mut libc::c_int = mech_data as *mut libc::c_int;
-    let authzid;
-    let authid;
-    let passcode;
-    let mut pin: *const libc::c_char = 0 as *const libc::c_char;
-    let authzidlen;
-    let authidlen;
-    let passcodelen;
-    let mut pinlen: size_t = 0 as libc::c_int as size_t;
-    let mut do_pin: libc::c_int = 0 as libc::c_int;
-    let mut res: libc::c_int = 0;
-    let current_block_53: u64;
-    match *step {
-        1 => {
-            if input_len == strlen(b"passcode\x00" as *const u8 as *const libc::c_char)
-                && memcmp(
-                    input as *const libc::c_void,
-                    b"passcode\x00" as *const u8 as *const libc::c_char as *const libc::c_void,
-                    strlen(b"passcode\x00" as *const u8 as *const libc::c_char),
-                ) == 0 as libc::c_int
-            {
-                *step = 0 as libc::c_int;
-                current_block_53 = 7859779714627992552;
-            } else if input_len >= strlen(b"pin\x00" as *const u8 as *const libc::c_char)
-                && memcmp(
-                    input as *const libc::c_void,
-                    b"pin\x00" as *const u8 as *const libc::c_char as *const libc::c_void,
-                    strlen(b"pin\x00" as *const u8 as *const libc::c_char),
-                ) == 0 as libc::c_int
-            {
-                do_pin = 1 as libc::c_int;
-                *step = 0 as libc::c_int;
-                current_block_53 = 7859779714627992552;
-            } else {
-                *output_len = 0 as libc::c_int as size_t;
-                res = GSASL_OK as libc::c_int;
-                current_block_53 = 10930818133215224067;
-            }
-        }
-        0 => {
-            current_block_53 = 7859779714627992552;
-        }
-        2 => {
-            *output_len = 0 as libc::c_int as size_t;
-            *output = 0 as *mut libc::c_char;
-            *step += 1;
-            res = GSASL_OK as libc::c_int;
-            current_block_53 = 10930818133215224067;
-        }
-        _ => {
-            res = GSASL_MECHANISM_CALLED_TOO_MANY_TIMES as libc::c_int;
-            current_block_53 = 10930818133215224067;
-        }
-    }
-    match current_block_53 {
-        7859779714627992552 =>
-        /* fall through */
-        {
-            authzid = gsasl_property_get(sctx, GSASL_AUTHZID);
-            if !authzid.is_null() {
-                authzidlen = strlen(authzid)
-            } else {
-                authzidlen = 0 as libc::c_int as size_t
+impl Authentication for Securid {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        let send_pin = match input {
+            // Only the first step may come without a challenge
+            None | Some(b"") if self.sent => return Err(SecuridError::BadChallenge.into()),
+            None | Some(b"") => false,
+            Some(b"passcode") => {
+                // The previous passcode was used up, a fresh one has to be provided
+                session.clear_property::<Passcode>();
+                false
             }
-            authid = gsasl_property_get(sctx, GSASL_AUTHID);
-            if authid.is_null() {
-                return GSASL_NO_AUTHID as libc::c_int;
-            }
-            authidlen = strlen(authid);
-            passcode = gsasl_property_get(sctx, GSASL_PASSCODE);
-            if passcode.is_null() {
-                return GSASL_NO_PASSCODE as libc::c_int;
-            }
-            passcodelen = strlen(passcode);
-            if do_pin != 0 {
-                if input_len > strlen(b"pin\x00" as *const u8 as *const libc::c_char) {
-                    res =
-                        gsasl_property_set_raw(
-                            sctx,
-                            GSASL_SUGGESTED_PIN,
-                            &*input.offset(
-                                strlen(b"pin\x00" as *const u8 as *const libc::c_char) as isize
-                            ),
-                            input_len.wrapping_sub(strlen(
-                                b"pin\x00" as *const u8 as *const libc::c_char,
-                            )),
-                        );
-                    if res != GSASL_OK as libc::c_int {
-                        return res;
-                    }
+            Some(input) if input.starts_with(b"pin") => {
+                let suggested = std::str::from_utf8(&input[3..]).map_err(SecuridError::BadUtf8)?;
+                if suggested.is_empty() {
+                    session.clear_property::<SuggestedPin>();
+                } else {
+                    session.set_property::<SuggestedPin>(Arc::new(suggested.to_string()));
                 }
-                pin = gsasl_property_get(sctx, GSASL_PIN);
-                if pin.is_null() {
-                    return GSASL_NO_PIN as libc::c_int;
-                }
-                pinlen = strlen(pin)
-            }
-            *output_len = authzidlen
-                .wrapping_add(1)
-                .wrapping_add(authidlen)
-                .wrapping_add(1)
-                .wrapping_add(passcodelen)
-                .wrapping_add(1);
-            if do_pin != 0 {
-                *output_len = *output_len.wrapping_add(pinlen.wrapping_add(1))
+                true
             }
-            *output = malloc(*output_len) as *mut libc::c_char;
-            if (*output).is_null() {
-                return GSASL_MALLOC_ERROR as libc::c_int;
-            }
-            if !authzid.is_null() {
-                memcpy(
-                    *output as *mut libc::c_void,
-                    authzid as *const libc::c_void,
-                    authzidlen,
-                );
-            }
-            *(*output).offset(authzidlen as isize) = '\u{0}' as i32 as libc::c_char;
-            memcpy(
-                (*output)
-                    .offset(authzidlen as isize)
-                    .offset(1 as libc::c_int as isize) as *mut libc::c_void,
-                authid as *const libc::c_void,
-                authidlen,
-            );
-            *(*output).offset(authzidlen.wrapping_add(1).wrapping_add(authidlen) as isize) =
-                '\u{0}' as i32 as libc::c_char;
-            memcpy(
-                (*output)
-                    .offset(authzidlen as isize)
-                    .offset(1)
-                    .offset(authidlen as isize)
-                    .offset(1) as *mut libc::c_void,
-                passcode as *const libc::c_void,
-                passcodelen,
-            );
-            *(*output).offset(
-                authzidlen
-                    .wrapping_add(1)
-                    .wrapping_add(authidlen)
-                    .wrapping_add(1)
-                    .wrapping_add(passcodelen) as isize,
-            ) = '\u{0}' as i32 as libc::c_char;
-            if do_pin != 0 {
-                memcpy(
-                    (*output)
-                        .offset(authzidlen as isize)
-                        .offset(1)
-                        .offset(authidlen as isize)
-                        .offset(1)
-                        .offset(passcodelen as isize)
-                        .offset(1) as *mut libc::c_void,
-                    pin as *const libc::c_void,
-                    pinlen,
-                );
-                *(*output).offset(
-                    authzidlen
-                        .wrapping_add(1)
-                        .wrapping_add(authidlen)
-                        .wrapping_add(1)
-                        .wrapping_add(passcodelen)
-                        .wrapping_add(1)
-                        .wrapping_add(pinlen) as isize,
-                ) = '\u{0}' as i32 as libc::c_char
-            }
-            *step += 1;
-            res = GSASL_OK as libc::c_int
-        }
-        _ => {}
-    }
-    return res;
-}
-/* securid.h --- Prototypes for SASL mechanism SECURID as defined in RFC 2808.
- * Copyright (C) 2002-2021 Simon Josefsson
- *
- * This file is part of GNU SASL Library.
- *
- * GNU SASL Library is free software; you can redistribute it and/or
- * modify it under the terms of the GNU Lesser General Public License
- * as published by the Free Software Foundation; either version 2.1 of
- * the License, or (at your option) any later version.
- *
- * GNU SASL Library is distributed in the hope that it will be useful,
- * but WITHOUT ANY WARRANTY; without even the implied warranty of
- * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
- * Lesser General Public License for more details.
- *
- * You should have received a copy of the GNU Lesser General Public
- * License along with GNU SASL Library; if not, write to the Free
- * Free Software Foundation, Inc., 51 Franklin Street, Fifth Floor,
- * Boston, MA 02110-1301, USA.
- *
- */
-pub unsafe fn _gsasl_securid_client_finish(mech_data: Option<NonNull<()>>) {
-    let mech_data = mech_data
-        .map(|ptr| ptr.as_ptr())
-        .unwrap_or_else(std::ptr::null_mut);
+            Some(_) => return Err(SecuridError::BadChallenge.into()),
+        };
+
+        let authzid = session.get_property_or_callback::<AuthzId>()?;
+        let authid = session
+            .get_property_or_callback::<AuthId>()?
+            .ok_or(SessionError::no_property::<AuthId>())?;
+        let passcode = session
+            .get_property_or_callback::<Passcode>()?
+            .ok_or(SessionError::no_property::<Passcode>())?;
+        let pin = if send_pin {
+            let pin = session
+                .get_property_or_callback::<Pin>()?
+                .ok_or(SessionError::no_property::<Pin>())?;
+            Some(pin)
+        } else {
+            None
+        };
 
-    let step: *mut libc::c_int = mech_data as *mut libc::c_int;
-    rpl_free(step as *mut libc::c_void);
+        let authzid = authzid.as_deref().map(String::as_bytes).unwrap_or(&[]);
+        let pin = pin.as_deref().map(String::as_bytes);
+        let data: [&[u8]; 8] = [
+            authzid,
+            &[0],
+            authid.as_bytes(),
+            &[0],
+            passcode.as_bytes(),
+            &[0],
+            pin.unwrap_or(&[]),
+            if pin.is_some() { &[0] } else { &[] },
+        ];
+        let written = VectoredWriter::new(data).write_all_vectored(writer)?;
+        self.sent = true;
+
+        Ok(Done(Some(written)))
+    }
 }
@@ -1,159 +1,106 @@
-use crate::gsasl::callback::gsasl_callback;
-use crate::gsasl::consts::{
-    GSASL_AUTHID, GSASL_AUTHZID, GSASL_MALLOC_ERROR, GSASL_MECHANISM_PARSE_ERROR, GSASL_NEEDS_MORE,
-    GSASL_OK, GSASL_PASSCODE, GSASL_PIN, GSASL_SUGGESTED_PIN, GSASL_VALIDATE_SECURID,
-};
-use crate::gsasl::property::{gsasl_property_get, gsasl_property_set};
-use crate::session::SessionData;
-use crate::Shared;
-use ::libc;
-use libc::{malloc, memchr, memcpy, size_t, strdup, strlen};
-use std::ptr::NonNull;
+use crate::error::{MechanismError, MechanismErrorKind, SessionError};
+use crate::mechanism::Authentication;
+use crate::property::{AuthId, AuthzId, Passcode, Pin, SecuridOutcome, SecuridValidation};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use crate::validate::validations::SECURID;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::str::Utf8Error;
+use std::sync::Arc;
 
-/* securid.h --- Prototypes for SASL mechanism SECURID as defined in RFC 2808.
- * Copyright (C) 2002-2021 Simon Josefsson
- *
- * This file is part of GNU SASL Library.
- *
- * GNU SASL Library is free software; you can redistribute it and/or
- * modify it under the terms of the GNU Lesser General Public License
- * as published by the Free Software Foundation; either version 2.1 of
- * the License, or (at your option) any later version.
- *
- * GNU SASL Library is distributed in the hope that it will be useful,
- * but WITHOUT ANY WARRANTY; without even the implied warranty of
- * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
- * Lesser General Public License for more details.
- *
- * You should have received a copy of the GNU Lesser General Public
- * License along with GNU SASL Library; if not, write to the Free
- * Free Software Foundation, Inc., 51 Franklin Street, Fifth Floor,
- * Boston, MA 02110-1301, USA.
- *
- */
-pub unsafe fn _gsasl_securid_server_step(
-    sctx: &mut SessionData,
-    _mech_data: Option<NonNull<()>>,
-    input: Option<&[u8]>,
-    output: *mut *mut libc::c_char,
-    output_len: *mut size_t,
-) -> libc::c_int {
-    let input_len = input.map(|i| i.len()).unwrap_or(0);
-    let input: *const libc::c_char = input.map(|i| i.as_ptr().cast()).unwrap_or(std::ptr::null());
+#[derive(Debug)]
+pub(super) enum SecuridError {
+    BadFormat,
+    BadUtf8(Utf8Error),
+    BadChallenge,
+}
 
-    let authorization_id;
-    let mut authentication_id;
-    let mut passcode: *const libc::c_char = 0 as *const libc::c_char;
-    let suggestedpin;
-    let mut pin: *mut libc::c_char = 0 as *mut libc::c_char;
-    let mut res;
-    let len;
-    if input_len == 0 {
-        *output_len = 0 as libc::c_int as size_t;
-        *output = 0 as *mut libc::c_char;
-        return GSASL_NEEDS_MORE as libc::c_int;
-    }
-    authorization_id = input;
-    authentication_id = memchr(
-        input as *const libc::c_void,
-        '\u{0}' as i32,
-        input_len.wrapping_sub(1),
-    ) as *const libc::c_char;
-    if !authentication_id.is_null() {
-        authentication_id = authentication_id.offset(1);
-        passcode = memchr(
-            authentication_id as *const libc::c_void,
-            '\u{0}' as i32,
-            input_len
-                .wrapping_sub(strlen(authorization_id))
-                .wrapping_sub(1)
-                .wrapping_sub(1),
-        ) as *const libc::c_char;
-        if !passcode.is_null() {
-            passcode = passcode.offset(1);
-            pin = memchr(
-                passcode as *const libc::c_void,
-                '\u{0}' as i32,
-                input_len
-                    .wrapping_sub(strlen(authorization_id))
-                    .wrapping_sub(1)
-                    .wrapping_sub(strlen(authentication_id))
-                    .wrapping_sub(1)
-                    .wrapping_sub(1),
-            ) as *mut libc::c_char;
-            if !pin.is_null() {
-                pin = pin.offset(1);
-                if !pin.is_null() && *pin == 0 {
-                    pin = 0 as *mut libc::c_char
-                }
+impl Display for SecuridError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadFormat => f.write_str(
+                "invalid format, expected authzid, authcid, passcode and optionally a PIN \
+                 separated by NULL-bytes",
+            ),
+            Self::BadUtf8(e) => write!(f, "message is invalid UTF-8: {}", e),
+            Self::BadChallenge => {
+                f.write_str("invalid challenge, expected a request for a passcode or PIN")
             }
         }
     }
-    if passcode.is_null() {
-        return GSASL_MECHANISM_PARSE_ERROR as libc::c_int;
-    }
-    res = gsasl_property_set(sctx, GSASL_AUTHID, authentication_id);
-    if res != GSASL_OK as libc::c_int {
-        return res;
-    }
-    res = gsasl_property_set(sctx, GSASL_AUTHZID, authorization_id);
-    if res != GSASL_OK as libc::c_int {
-        return res;
-    }
-    res = gsasl_property_set(sctx, GSASL_PASSCODE, passcode);
-    if res != GSASL_OK as libc::c_int {
-        return res;
-    }
-    if !pin.is_null() {
-        res = gsasl_property_set(sctx, GSASL_PIN, pin)
-    } else {
-        res = gsasl_property_set(sctx, GSASL_PIN, 0 as *const libc::c_char)
-    }
-    if res != GSASL_OK as libc::c_int {
-        return res;
+}
+
+impl MechanismError for SecuridError {
+    fn kind(&self) -> MechanismErrorKind {
+        MechanismErrorKind::Parse
     }
-    res = gsasl_callback(0 as *mut Shared, sctx, GSASL_VALIDATE_SECURID);
-    match res {
-        48 => {
-            *output = strdup(b"passcode\x00" as *const u8 as *const libc::c_char);
-            if (*output).is_null() {
-                return GSASL_MALLOC_ERROR as libc::c_int;
-            }
-            *output_len = strlen(b"passcode\x00" as *const u8 as *const libc::c_char);
-            res = GSASL_NEEDS_MORE as libc::c_int
+}
+
+/// Server side of `SECURID`
+///
+/// Every message from the client is validated with [`SECURID`], the [`SecuridValidation`] set
+/// by the callback decides whether the exchange completes or the client is asked for the next
+/// token code or a new PIN. The client is rejected if the callback doesn't set it.
+#[derive(Copy, Clone, Debug)]
+pub struct Securid;
+
+impl Authentication for Securid {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        let input = match input {
+            Some(input) if !input.is_empty() => input,
+            _ => return Ok(NeedsMore(None)),
+        };
+
+        // Every field is terminated by a NULL-byte, but be lenient about the last one
+        let input = input.strip_suffix(&[0]).unwrap_or(input);
+        let fields = input
+            .split(|byte| *byte == 0)
+            .map(std::str::from_utf8)
+            .collect::<Result<Vec<&str>, Utf8Error>>()
+            .map_err(SecuridError::BadUtf8)?;
+        let (authzid, authcid, passcode, pin) = match fields[..] {
+            [authzid, authcid, passcode] => (authzid, authcid, passcode, None),
+            [authzid, authcid, passcode, pin] => (authzid, authcid, passcode, Some(pin)),
+            _ => return Err(SecuridError::BadFormat.into()),
+        };
+
+        if authzid.is_empty() {
+            session.clear_property::<AuthzId>();
+        } else {
+            session.set_property::<AuthzId>(Arc::new(authzid.to_string()));
         }
-        49 => {
-            suggestedpin = gsasl_property_get(sctx, GSASL_SUGGESTED_PIN);
-            if !suggestedpin.is_null() {
-                len = strlen(suggestedpin)
-            } else {
-                len = 0 as libc::c_int as size_t
+        session.set_property::<AuthId>(Arc::new(authcid.to_string()));
+        session.set_property::<Passcode>(Arc::new(passcode.to_string()));
+        match pin.filter(|pin| !pin.is_empty()) {
+            Some(pin) => {
+                session.set_property::<Pin>(Arc::new(pin.to_string()));
             }
-            *output_len = strlen(b"pin\x00" as *const u8 as *const libc::c_char).wrapping_add(len);
-            *output = malloc(*output_len) as *mut libc::c_char;
-            if (*output).is_null() {
-                return GSASL_MALLOC_ERROR as libc::c_int;
+            None => session.clear_property::<Pin>(),
+        }
+
+        // Don't pick up the outcome of validating a previous message
+        session.clear_property::<SecuridValidation>();
+        session.validate(SECURID)?;
+        let outcome = session.get_property::<SecuridValidation>();
+        match outcome.as_deref().unwrap_or(&SecuridOutcome::Reject) {
+            SecuridOutcome::Accept => Ok(Done(None)),
+            SecuridOutcome::Reject => Err(SessionError::AuthenticationFailure),
+            SecuridOutcome::NextTokenCode => {
+                writer.write_all(b"passcode")?;
+                Ok(NeedsMore(Some(8)))
             }
-            memcpy(
-                *output as *mut libc::c_void,
-                b"pin\x00" as *const u8 as *const libc::c_char as *const libc::c_void,
-                strlen(b"pin\x00" as *const u8 as *const libc::c_char),
-            );
-            if !suggestedpin.is_null() {
-                memcpy(
-                    (*output)
-                        .offset(strlen(b"pin\x00" as *const u8 as *const libc::c_char) as isize)
-                        as *mut libc::c_void,
-                    suggestedpin as *const libc::c_void,
-                    len,
-                );
+            SecuridOutcome::NewPin(suggested) => {
+                let suggested = suggested.as_deref().unwrap_or("");
+                writer.write_all(b"pin")?;
+                writer.write_all(suggested.as_bytes())?;
+                Ok(NeedsMore(Some(3 + suggested.len())))
             }
-            res = GSASL_NEEDS_MORE as libc::c_int
-        }
-        _ => {
-            *output_len = 0 as libc::c_int as size_t;
-            *output = 0 as *mut libc::c_char
         }
     }
-    return res;
 }
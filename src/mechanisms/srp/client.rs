@@ -0,0 +1,235 @@
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::srp::layer::Layer;
+use crate::mechanisms::srp::protocol::{
+    constant_time_eq, random_iv, server_proof, Builder, Choice, Offer, Params, Reader, SrpError,
+    Transcript, IV_LEN,
+};
+use crate::property::{AuthId, AuthzId, Password, SrpGroup, SrpLayer, SrpSecurityLayer};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use num_bigint::BigUint;
+use std::io::Write;
+
+/// What the client has to remember to verify the server's proof
+#[derive(Debug)]
+struct Sent {
+    choice: Choice,
+    big_a: BigUint,
+    proof: Vec<u8>,
+    key: Vec<u8>,
+    authzid: String,
+    iv: [u8; IV_LEN],
+}
+
+#[derive(Debug, Default)]
+enum State {
+    #[default]
+    Initial,
+    Started {
+        user: String,
+        authzid: String,
+    },
+    Proved(Box<Sent>),
+    Completed,
+}
+
+/// Client side of `SRP`
+///
+/// Authenticates as [`AuthId`] with the [`Password`], optionally authorizing as [`AuthzId`]. The
+/// server must prove knowing the user's verifier for the exchange to succeed. The security layer
+/// requested is the one of the [`SrpSecurityLayer`] property, or a stronger one if the server
+/// requires it. SHA-256 is used if the server offers it.
+#[derive(Debug, Default)]
+pub struct Srp {
+    state: State,
+    layer: Option<Layer>,
+}
+
+impl Srp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn start(
+        &mut self,
+        session: &mut SessionData,
+        writer: &mut dyn Write,
+    ) -> Result<usize, SessionError> {
+        let user = session
+            .get_property_or_callback::<AuthId>()?
+            .ok_or(SessionError::no_property::<AuthId>())?;
+        let authzid = session
+            .get_property_or_callback::<AuthzId>()?
+            .map(|authzid| authzid.to_string())
+            .unwrap_or_default();
+        let message = Builder::new()
+            .utf8(&user)
+            .utf8(&authzid)
+            // No session to reuse and thus no client nonce
+            .utf8("")
+            .os(&[])
+            .finish();
+        writer.write_all(&message)?;
+        self.state = State::Started {
+            user: user.to_string(),
+            authzid,
+        };
+        Ok(message.len())
+    }
+
+    fn prove(
+        session: &mut SessionData,
+        user: &str,
+        authzid: String,
+        input: &[u8],
+        writer: &mut dyn Write,
+    ) -> Result<(usize, Sent), SessionError> {
+        let mut reader = Reader::new(input)?;
+        if reader.byte()? != 0 {
+            return Err(SrpError::UnexpectedReuse.into());
+        }
+        let n = reader.mpi()?;
+        let g = reader.mpi()?;
+        let salt = reader.os()?;
+        let big_b = reader.mpi()?;
+        let offer = reader.utf8()?;
+        reader.finish()?;
+
+        // Only the well-known groups are accepted, checking arbitrary ones isn't feasible
+        let group = SrpGroup::identify(&n, &g).ok_or(SrpError::UnknownGroup)?;
+        let layer = session
+            .get_property_or_callback::<SrpSecurityLayer>()?
+            .map(|layer| *layer)
+            .unwrap_or(SrpLayer::None);
+        let choice = Offer::parse(offer).choose(layer)?;
+        let params = Params::new(group, choice.digest);
+        params.check_public(&big_b)?;
+
+        let password = session
+            .get_property_or_callback::<Password>()?
+            .ok_or(SessionError::no_property::<Password>())?;
+        let x = params.x(salt, user, &password);
+        let a = Params::private_key();
+        let big_a = params.client_public(&a);
+        let key = params.client_key(&a, &big_a, &big_b, &x)?;
+        let proof = params.client_proof(&Transcript {
+            user,
+            authzid: &authzid,
+            salt,
+            big_a: &big_a,
+            big_b: &big_b,
+            key: &key,
+            offer,
+        });
+
+        let iv = random_iv();
+        let sent_iv: &[u8] = if choice.layer == SrpLayer::Confidentiality {
+            &iv
+        } else {
+            &[]
+        };
+        let message = Builder::new()
+            .mpi(&big_a)
+            .os(&proof)
+            .utf8(&choice.encode())
+            .os(sent_iv)
+            .finish();
+        writer.write_all(&message)?;
+        let sent = Sent {
+            choice,
+            big_a,
+            proof,
+            key,
+            authzid,
+            iv,
+        };
+        Ok((message.len(), sent))
+    }
+
+    fn verify(&mut self, sent: &Sent, input: &[u8]) -> Result<(), SessionError> {
+        let mut reader = Reader::new(input)?;
+        let proof = reader.os()?;
+        let iv = reader.os()?;
+        let sid = reader.utf8()?;
+        let ttl = reader.uint()?;
+        reader.finish()?;
+
+        let expected = server_proof(
+            sent.choice.digest,
+            &sent.big_a,
+            &sent.proof,
+            &sent.key,
+            &sent.authzid,
+            &sent.choice.encode(),
+            (sid, ttl),
+        );
+        if !constant_time_eq(&expected, proof) {
+            return Err(SrpError::BadServerProof.into());
+        }
+
+        let mut recv_iv = [0; IV_LEN];
+        if sent.choice.layer == SrpLayer::Confidentiality {
+            if iv.len() != IV_LEN {
+                return Err(SrpError::BadFormat.into());
+            }
+            recv_iv.copy_from_slice(iv);
+        }
+        self.layer = Layer::new(
+            sent.choice.layer,
+            sent.choice.digest,
+            &sent.key,
+            sent.choice.replay_detection,
+            sent.iv,
+            recv_iv,
+        );
+        Ok(())
+    }
+}
+
+impl Authentication for Srp {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        // Any error ends the exchange
+        match std::mem::replace(&mut self.state, State::Completed) {
+            State::Initial => {
+                let len = self.start(session, writer)?;
+                Ok(NeedsMore(Some(len)))
+            }
+            State::Started { user, authzid } => {
+                let input = input.ok_or(SessionError::InputDataRequired)?;
+                let (len, sent) = Self::prove(session, &user, authzid, input, writer)?;
+                self.state = State::Proved(Box::new(sent));
+                Ok(NeedsMore(Some(len)))
+            }
+            State::Proved(sent) => {
+                let input = input.ok_or(SessionError::InputDataRequired)?;
+                self.verify(&sent, input)?;
+                Ok(Done(None))
+            }
+            State::Completed => Err(SrpError::Completed.into()),
+        }
+    }
+
+    fn encode(&mut self, input: &[u8]) -> Result<Box<[u8]>, SessionError> {
+        match self.layer.as_mut() {
+            Some(layer) => Ok(layer.encode(input)),
+            None => Err(SessionError::NoSecurityLayer),
+        }
+    }
+
+    fn decode(&mut self, input: &[u8]) -> Result<Box<[u8]>, SessionError> {
+        match self.layer.as_mut() {
+            Some(layer) => Ok(layer.decode(input)?),
+            None => Err(SessionError::NoSecurityLayer),
+        }
+    }
+
+    fn has_security_layer(&self) -> bool {
+        self.layer.is_some()
+    }
+}
@@ -0,0 +1,146 @@
+//! The security layer negotiated by an `SRP` exchange
+//!
+//! Every buffer is protected with an HMAC keyed with the shared key `K` over the payload and, with
+//! replay detection, a sequence number. With confidentiality the payload is encrypted with
+//! AES-128 in CBC mode first, keyed with the start of `K` and chaining the IV across buffers.
+
+use crate::mechanisms::srp::protocol::{constant_time_eq, SrpError, IV_LEN};
+use crate::property::{SrpDigest, SrpLayer};
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::{Aes128, Block};
+use std::fmt::{Debug, Formatter};
+
+const BLOCK_LEN: usize = 16;
+
+struct Cbc {
+    cipher: Aes128,
+    send_iv: [u8; IV_LEN],
+    recv_iv: [u8; IV_LEN],
+}
+
+impl Cbc {
+    fn encrypt(&mut self, data: &[u8]) -> Vec<u8> {
+        // PKCS#7 padding, always adding at least one byte
+        let padding = BLOCK_LEN - data.len() % BLOCK_LEN;
+        let mut buffer = data.to_vec();
+        buffer.resize(data.len() + padding, padding as u8);
+        for chunk in buffer.chunks_exact_mut(BLOCK_LEN) {
+            for (byte, iv) in chunk.iter_mut().zip(self.send_iv.iter()) {
+                *byte ^= iv;
+            }
+            self.cipher.encrypt_block(Block::from_mut_slice(chunk));
+            self.send_iv.copy_from_slice(chunk);
+        }
+        buffer
+    }
+
+    fn decrypt(&mut self, data: &[u8]) -> Result<Vec<u8>, SrpError> {
+        if data.is_empty() || !data.chunks_exact(BLOCK_LEN).remainder().is_empty() {
+            return Err(SrpError::BadFormat);
+        }
+        let mut buffer = data.to_vec();
+        for chunk in buffer.chunks_exact_mut(BLOCK_LEN) {
+            let mut ciphertext = [0; BLOCK_LEN];
+            ciphertext.copy_from_slice(chunk);
+            self.cipher.decrypt_block(Block::from_mut_slice(chunk));
+            for (byte, iv) in chunk.iter_mut().zip(self.recv_iv.iter()) {
+                *byte ^= iv;
+            }
+            self.recv_iv = ciphertext;
+        }
+        let padding = buffer[buffer.len() - 1] as usize;
+        let (data, pad) = buffer.split_at(buffer.len().saturating_sub(padding));
+        if padding == 0 || padding > BLOCK_LEN || pad.iter().any(|&byte| byte as usize != padding) {
+            return Err(SrpError::BadFormat);
+        }
+        let len = data.len();
+        buffer.truncate(len);
+        Ok(buffer)
+    }
+}
+
+pub(super) struct Layer {
+    digest: SrpDigest,
+    key: Vec<u8>,
+    replay_detection: bool,
+    cbc: Option<Cbc>,
+    send_seq: u32,
+    recv_seq: u32,
+}
+
+impl Debug for Layer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Layer")
+            .field("digest", &self.digest)
+            .field("replay_detection", &self.replay_detection)
+            .field("confidentiality", &self.cbc.is_some())
+            .finish()
+    }
+}
+
+impl Layer {
+    /// The layer chosen, if any. `send_iv` and `recv_iv` are the IVs the local and the remote side
+    /// sent during the exchange respectively.
+    pub(super) fn new(
+        layer: SrpLayer,
+        digest: SrpDigest,
+        key: &[u8],
+        replay_detection: bool,
+        send_iv: [u8; IV_LEN],
+        recv_iv: [u8; IV_LEN],
+    ) -> Option<Self> {
+        let cbc = match layer {
+            SrpLayer::None => return None,
+            SrpLayer::Integrity => None,
+            SrpLayer::Confidentiality => Some(Cbc {
+                cipher: Aes128::new_from_slice(&key[..16]).expect("the shared key is long enough"),
+                send_iv,
+                recv_iv,
+            }),
+        };
+        Some(Self {
+            digest,
+            key: key.to_vec(),
+            replay_detection,
+            cbc,
+            send_seq: 0,
+            recv_seq: 0,
+        })
+    }
+
+    fn mac(&self, payload: &[u8], seq: u32) -> Vec<u8> {
+        if self.replay_detection {
+            self.digest.hmac(&self.key, &[payload, &seq.to_be_bytes()])
+        } else {
+            self.digest.hmac(&self.key, &[payload])
+        }
+    }
+
+    pub(super) fn encode(&mut self, data: &[u8]) -> Box<[u8]> {
+        let mut buffer = match self.cbc.as_mut() {
+            Some(cbc) => cbc.encrypt(data),
+            None => data.to_vec(),
+        };
+        let mac = self.mac(&buffer, self.send_seq);
+        self.send_seq = self.send_seq.wrapping_add(1);
+        buffer.extend_from_slice(&mac);
+        buffer.into_boxed_slice()
+    }
+
+    pub(super) fn decode(&mut self, data: &[u8]) -> Result<Box<[u8]>, SrpError> {
+        let mac_len = self.digest.hash(&[]).len();
+        if data.len() < mac_len {
+            return Err(SrpError::BadFormat);
+        }
+        let (payload, mac) = data.split_at(data.len() - mac_len);
+        if !constant_time_eq(&self.mac(payload, self.recv_seq), mac) {
+            return Err(SrpError::BadMac);
+        }
+        self.recv_seq = self.recv_seq.wrapping_add(1);
+        let data = match self.cbc.as_mut() {
+            Some(cbc) => cbc.decrypt(payload)?,
+            None => payload.to_vec(),
+        };
+        Ok(data.into_boxed_slice())
+    }
+}
@@ -0,0 +1,34 @@
+use crate::mechanisms::srp::{client, server};
+use crate::property::properties::{AUTHID, AUTHZID, PASSWORD, SRP_SECRET, SRP_SECURITY_LAYER};
+use crate::registry::{MechanismSecurityFactors, PropertyRequirements};
+use crate::{Mechanism, Mechname, Side};
+
+#[cfg(feature = "registry_static")]
+use crate::registry::{distributed_slice, MECHANISMS};
+#[cfg_attr(feature = "registry_static", distributed_slice(MECHANISMS))]
+pub static SRP: Mechanism = Mechanism {
+    mechanism: Mechname::const_new_unchecked(b"SRP"),
+    priority: 400,
+    client: Some(|_sasl| Ok(Box::new(client::Srp::new()))),
+    server: Some(|_sasl| Ok(Box::new(server::Srp::new()))),
+    first: Side::Client,
+    client_properties: PropertyRequirements::new(
+        &[AUTHID, PASSWORD],
+        &[AUTHZID, SRP_SECURITY_LAYER],
+    ),
+    server_properties: PropertyRequirements::new(&[SRP_SECRET], &[SRP_SECURITY_LAYER]),
+    description: "Secure Remote Password with an optional confidentiality layer",
+    specification: Some("draft-burdis-cat-srp-sasl"),
+    channel_binding: false,
+    security_layer: true,
+    security: SRP_SECURITY,
+};
+
+/// SRP authenticates both sides without the server storing password equivalents, and can
+/// negotiate an AES-128 confidentiality layer.
+pub const SRP_SECURITY: MechanismSecurityFactors = MechanismSecurityFactors {
+    max_ssf: 128,
+    noplain: true,
+    mutual: true,
+    weak: false,
+};
@@ -0,0 +1,697 @@
+//! Messages, options and computations shared by both sides of `SRP`
+//!
+//! Implements [draft-burdis-cat-srp-sasl-08](https://datatracker.ietf.org/doc/html/draft-burdis-cat-srp-sasl-08)
+//! with the SRP-6a computations of [RFC 5054](https://www.rfc-editor.org/rfc/rfc5054). Session
+//! reuse is not supported, servers always perform a full exchange.
+
+use crate::error::{MechanismError, MechanismErrorKind};
+use crate::property::{SrpDigest, SrpGroup, SrpLayer};
+use hmac::{Hmac, Mac};
+use num_bigint::BigUint;
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter};
+use std::str::Utf8Error;
+
+/// The only confidentiality algorithm offered, AES-128 in CBC mode
+const AES: &str = "aes";
+/// Length of the random private keys `a` and `b` in bytes
+const PRIVATE_KEY_LEN: usize = 32;
+pub(super) const IV_LEN: usize = 16;
+
+#[derive(Debug)]
+pub(super) enum SrpError {
+    BadFormat,
+    BadUtf8(Utf8Error),
+    /// The server sent a group that is not one of RFC 5054
+    UnknownGroup,
+    /// A public key is zero modulo N, or the scrambling parameter is zero
+    IllegalPublicKey,
+    /// The server requested reusing a session the client never asked for
+    UnexpectedReuse,
+    NoCommonDigest,
+    /// The security layer requested or required isn't offered by the other side
+    LayerUnavailable,
+    /// The server could not prove knowing the verifier
+    BadServerProof,
+    /// A buffer of the security layer failed the integrity check
+    BadMac,
+    Completed,
+}
+
+impl Display for SrpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadFormat => f.write_str("invalid format of the SRP message"),
+            Self::BadUtf8(e) => write!(f, "SRP message contains invalid UTF-8: {}", e),
+            Self::UnknownGroup => f.write_str("server sent an unknown SRP group"),
+            Self::IllegalPublicKey => f.write_str("illegal SRP public key"),
+            Self::UnexpectedReuse => f.write_str("server reused a session never requested"),
+            Self::NoCommonDigest => f.write_str("no common SRP message digest algorithm"),
+            Self::LayerUnavailable => f.write_str("the SRP security layer is not available"),
+            Self::BadServerProof => f.write_str("server proof of the SRP exchange is invalid"),
+            Self::BadMac => f.write_str("integrity check of the SRP security layer failed"),
+            Self::Completed => f.write_str("the exchange is already complete"),
+        }
+    }
+}
+
+impl MechanismError for SrpError {
+    fn kind(&self) -> MechanismErrorKind {
+        match self {
+            Self::BadFormat | Self::BadUtf8(_) => MechanismErrorKind::Parse,
+            Self::BadServerProof => MechanismErrorKind::Outcome,
+            Self::UnknownGroup
+            | Self::IllegalPublicKey
+            | Self::UnexpectedReuse
+            | Self::NoCommonDigest
+            | Self::LayerUnavailable
+            | Self::BadMac
+            | Self::Completed => MechanismErrorKind::Protocol,
+        }
+    }
+}
+
+impl SrpDigest {
+    /// Digests in order of preference
+    const PREFERRED: [SrpDigest; 2] = [Self::Sha256, Self::Sha1];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Sha1 => "SHA-160",
+            Self::Sha256 => "SHA-256",
+        }
+    }
+
+    fn integrity_name(self) -> &'static str {
+        match self {
+            Self::Sha1 => "HMAC-SHA-160",
+            Self::Sha256 => "HMAC-SHA-256",
+        }
+    }
+
+    pub(super) fn hash(self, parts: &[&[u8]]) -> Vec<u8> {
+        fn digest<D: Digest>(parts: &[&[u8]]) -> Vec<u8> {
+            let mut hasher = D::new();
+            for part in parts {
+                hasher.update(part);
+            }
+            hasher.finalize().to_vec()
+        }
+        match self {
+            Self::Sha1 => digest::<Sha1>(parts),
+            Self::Sha256 => digest::<Sha256>(parts),
+        }
+    }
+
+    pub(super) fn hmac(self, key: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+        fn mac<M: Mac + hmac::digest::KeyInit>(key: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+            let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC can take keys of any size");
+            for part in parts {
+                mac.update(part);
+            }
+            mac.finalize().into_bytes().to_vec()
+        }
+        match self {
+            Self::Sha1 => mac::<Hmac<Sha1>>(key, parts),
+            Self::Sha256 => mac::<Hmac<Sha256>>(key, parts),
+        }
+    }
+}
+
+/// Compare without short-circuiting to not leak how much of a proof matched
+pub(super) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+pub(super) fn random_iv() -> [u8; IV_LEN] {
+    rand::random()
+}
+
+/// Parser of a message, i.e. a buffer prefixed with its 4-byte length
+pub(super) struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub(super) fn new(message: &'a [u8]) -> Result<Self, SrpError> {
+        let mut reader = Self(message);
+        let len = reader.uint()? as usize;
+        if reader.0.len() != len {
+            return Err(SrpError::BadFormat);
+        }
+        Ok(reader)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SrpError> {
+        if self.0.len() < len {
+            return Err(SrpError::BadFormat);
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    pub(super) fn byte(&mut self) -> Result<u8, SrpError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(super) fn uint(&mut self) -> Result<u32, SrpError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn short(&mut self) -> Result<usize, SrpError> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as usize)
+    }
+
+    pub(super) fn mpi(&mut self) -> Result<BigUint, SrpError> {
+        let len = self.short()?;
+        Ok(BigUint::from_bytes_be(self.take(len)?))
+    }
+
+    pub(super) fn os(&mut self) -> Result<&'a [u8], SrpError> {
+        let len = self.byte()? as usize;
+        self.take(len)
+    }
+
+    pub(super) fn utf8(&mut self) -> Result<&'a str, SrpError> {
+        let len = self.short()?;
+        std::str::from_utf8(self.take(len)?).map_err(SrpError::BadUtf8)
+    }
+
+    pub(super) fn finish(self) -> Result<(), SrpError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(SrpError::BadFormat)
+        }
+    }
+}
+
+/// Builder of a message, prefixing it with its length when finished
+pub(super) struct Builder(Vec<u8>);
+
+impl Builder {
+    pub(super) fn new() -> Self {
+        Self(vec![0; 4])
+    }
+
+    pub(super) fn byte(mut self, value: u8) -> Self {
+        self.0.push(value);
+        self
+    }
+
+    pub(super) fn uint(mut self, value: u32) -> Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub(super) fn mpi(mut self, value: &BigUint) -> Self {
+        let bytes = value.to_bytes_be();
+        self.0
+            .extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+        self.0.extend_from_slice(&bytes);
+        self
+    }
+
+    pub(super) fn os(mut self, value: &[u8]) -> Self {
+        debug_assert!(value.len() <= u8::MAX as usize);
+        self.0.push(value.len() as u8);
+        self.0.extend_from_slice(value);
+        self
+    }
+
+    pub(super) fn utf8(mut self, value: &str) -> Self {
+        self.0
+            .extend_from_slice(&(value.len() as u16).to_be_bytes());
+        self.0.extend_from_slice(value.as_bytes());
+        self
+    }
+
+    pub(super) fn finish(mut self) -> Vec<u8> {
+        let len = (self.0.len() - 4) as u32;
+        self.0[..4].copy_from_slice(&len.to_be_bytes());
+        self.0
+    }
+}
+
+/// The options the server offers (`L`)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(super) struct Offer {
+    digests: Vec<SrpDigest>,
+    integrity: Vec<SrpDigest>,
+    replay_detection: bool,
+    confidentiality: bool,
+    mandatory: SrpLayer,
+}
+
+impl Offer {
+    /// The options of a server with a verifier computed with `digest`
+    pub(super) fn new(digest: SrpDigest, mandatory: SrpLayer) -> Self {
+        Self {
+            digests: vec![digest],
+            integrity: vec![digest],
+            replay_detection: true,
+            confidentiality: true,
+            mandatory,
+        }
+    }
+
+    pub(super) fn parse(options: &str) -> Self {
+        let mut offer = Self {
+            digests: Vec::new(),
+            integrity: Vec::new(),
+            replay_detection: false,
+            confidentiality: false,
+            mandatory: SrpLayer::None,
+        };
+        // Options not known are ignored so servers can offer more than this implementation knows
+        for option in options.split(',') {
+            match option.split_once('=') {
+                Some(("mda", name)) => offer.digests.extend(digest_by_name(name)),
+                Some(("integrity", name)) => offer.integrity.extend(integrity_by_name(name)),
+                Some(("confidentiality", AES)) => offer.confidentiality = true,
+                Some(("mandatory", "integrity")) => {
+                    offer.mandatory = offer.mandatory.max(SrpLayer::Integrity)
+                }
+                Some(("mandatory", "confidentiality")) => {
+                    offer.mandatory = SrpLayer::Confidentiality
+                }
+                None if option == "replay_detection" => offer.replay_detection = true,
+                _ => {}
+            }
+        }
+        offer
+    }
+
+    pub(super) fn encode(&self) -> String {
+        let mut options: Vec<String> = self
+            .digests
+            .iter()
+            .map(|digest| format!("mda={}", digest.name()))
+            .collect();
+        if self.replay_detection {
+            options.push("replay_detection".to_string());
+        }
+        options.extend(
+            self.integrity
+                .iter()
+                .map(|digest| format!("integrity={}", digest.integrity_name())),
+        );
+        if self.confidentiality {
+            options.push(format!("confidentiality={}", AES));
+        }
+        match self.mandatory {
+            SrpLayer::None => {}
+            SrpLayer::Integrity => options.push("mandatory=integrity".to_string()),
+            SrpLayer::Confidentiality => options.push("mandatory=confidentiality".to_string()),
+        }
+        options.join(",")
+    }
+
+    /// Choose the options for a client wanting the security layer `layer`
+    pub(super) fn choose(&self, layer: SrpLayer) -> Result<Choice, SrpError> {
+        let digest = SrpDigest::PREFERRED
+            .iter()
+            .copied()
+            .find(|digest| self.digests.contains(digest))
+            .ok_or(SrpError::NoCommonDigest)?;
+        let choice = Choice {
+            digest,
+            layer: layer.max(self.mandatory),
+            replay_detection: self.replay_detection,
+        };
+        self.check(&choice)?;
+        Ok(choice)
+    }
+
+    /// Check that the choice of the client is allowed by this offer
+    pub(super) fn check(&self, choice: &Choice) -> Result<(), SrpError> {
+        if !self.digests.contains(&choice.digest) {
+            return Err(SrpError::NoCommonDigest);
+        }
+        let available = choice.layer < self.mandatory
+            || (choice.layer >= SrpLayer::Integrity && !self.integrity.contains(&choice.digest))
+            || (choice.layer == SrpLayer::Confidentiality && !self.confidentiality)
+            || (choice.replay_detection && !self.replay_detection);
+        if available {
+            return Err(SrpError::LayerUnavailable);
+        }
+        Ok(())
+    }
+}
+
+fn digest_by_name(name: &str) -> Option<SrpDigest> {
+    SrpDigest::PREFERRED
+        .iter()
+        .copied()
+        .find(|digest| digest.name() == name)
+}
+
+fn integrity_by_name(name: &str) -> Option<SrpDigest> {
+    SrpDigest::PREFERRED
+        .iter()
+        .copied()
+        .find(|digest| digest.integrity_name() == name)
+}
+
+/// The options the client chose (`o`)
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(super) struct Choice {
+    pub(super) digest: SrpDigest,
+    pub(super) layer: SrpLayer,
+    /// Only meaningful with a security layer
+    pub(super) replay_detection: bool,
+}
+
+impl Choice {
+    pub(super) fn parse(options: &str) -> Result<Self, SrpError> {
+        let mut digest = None;
+        let mut integrity = None;
+        let mut confidentiality = false;
+        let mut replay_detection = false;
+        for option in options.split(',') {
+            match option.split_once('=') {
+                Some(("mda", name)) if digest.is_none() => {
+                    digest = Some(digest_by_name(name).ok_or(SrpError::NoCommonDigest)?)
+                }
+                Some(("integrity", name)) if integrity.is_none() => {
+                    integrity = Some(integrity_by_name(name).ok_or(SrpError::LayerUnavailable)?)
+                }
+                Some(("confidentiality", AES)) if !confidentiality => confidentiality = true,
+                Some(("maxbuffersize", _)) => {}
+                None if option == "replay_detection" && !replay_detection => {
+                    replay_detection = true
+                }
+                _ => return Err(SrpError::BadFormat),
+            }
+        }
+        let digest = digest.ok_or(SrpError::BadFormat)?;
+        let layer = match (integrity, confidentiality) {
+            (None, false) => SrpLayer::None,
+            (Some(integrity), _) if integrity != digest => return Err(SrpError::LayerUnavailable),
+            (Some(_), false) => SrpLayer::Integrity,
+            (Some(_), true) => SrpLayer::Confidentiality,
+            // Confidentiality without integrity protection is not allowed
+            (None, true) => return Err(SrpError::LayerUnavailable),
+        };
+        Ok(Self {
+            digest,
+            layer,
+            replay_detection: replay_detection && layer != SrpLayer::None,
+        })
+    }
+
+    pub(super) fn encode(&self) -> String {
+        let mut options = vec![format!("mda={}", self.digest.name())];
+        if self.layer >= SrpLayer::Integrity {
+            if self.replay_detection {
+                options.push("replay_detection".to_string());
+            }
+            options.push(format!("integrity={}", self.digest.integrity_name()));
+        }
+        if self.layer == SrpLayer::Confidentiality {
+            options.push(format!("confidentiality={}", AES));
+        }
+        options.join(",")
+    }
+}
+
+/// Group and digest of an exchange with the computations depending on them
+pub(super) struct Params {
+    pub(super) digest: SrpDigest,
+    pub(super) n: BigUint,
+    pub(super) g: BigUint,
+}
+
+impl Params {
+    pub(super) fn new(group: SrpGroup, digest: SrpDigest) -> Self {
+        Self {
+            digest,
+            n: group.prime(),
+            g: group.generator(),
+        }
+    }
+
+    /// Left-pad to the length of `N`
+    fn pad(&self, value: &BigUint) -> Vec<u8> {
+        let len = self.n.bits().div_ceil(8) as usize;
+        let bytes = value.to_bytes_be();
+        let mut padded = vec![0; len.saturating_sub(bytes.len())];
+        padded.extend_from_slice(&bytes);
+        padded
+    }
+
+    fn hash_int(&self, parts: &[&[u8]]) -> BigUint {
+        BigUint::from_bytes_be(&self.digest.hash(parts))
+    }
+
+    /// The multiplier `k = H(N | PAD(g))`
+    fn k(&self) -> BigUint {
+        self.hash_int(&[&self.n.to_bytes_be(), &self.pad(&self.g)])
+    }
+
+    /// The scrambling parameter `u = H(PAD(A) | PAD(B))`, which must not be zero
+    fn u(&self, a: &BigUint, b: &BigUint) -> Result<BigUint, SrpError> {
+        let u = self.hash_int(&[&self.pad(a), &self.pad(b)]);
+        if u == BigUint::default() {
+            return Err(SrpError::IllegalPublicKey);
+        }
+        Ok(u)
+    }
+
+    /// The private key `x = H(s | H(U | ":" | p))`
+    pub(super) fn x(&self, salt: &[u8], user: &str, password: &str) -> BigUint {
+        let inner = self
+            .digest
+            .hash(&[user.as_bytes(), b":", password.as_bytes()]);
+        self.hash_int(&[salt, &inner])
+    }
+
+    pub(super) fn verifier(&self, x: &BigUint) -> BigUint {
+        self.g.modpow(x, &self.n)
+    }
+
+    /// Check that a public key isn't zero modulo N
+    pub(super) fn check_public(&self, key: &BigUint) -> Result<(), SrpError> {
+        if key % &self.n == BigUint::default() {
+            return Err(SrpError::IllegalPublicKey);
+        }
+        Ok(())
+    }
+
+    pub(super) fn private_key() -> BigUint {
+        let mut bytes = [0; PRIVATE_KEY_LEN];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        BigUint::from_bytes_be(&bytes)
+    }
+
+    /// The client's public key `A = g^a`
+    pub(super) fn client_public(&self, a: &BigUint) -> BigUint {
+        self.g.modpow(a, &self.n)
+    }
+
+    /// The server's public key `B = k*v + g^b`
+    pub(super) fn server_public(&self, v: &BigUint, b: &BigUint) -> BigUint {
+        (self.k() * v + self.g.modpow(b, &self.n)) % &self.n
+    }
+
+    /// The shared key `K = H(S)` with `S = (B - k*g^x)^(a + u*x)`
+    pub(super) fn client_key(
+        &self,
+        a: &BigUint,
+        big_a: &BigUint,
+        big_b: &BigUint,
+        x: &BigUint,
+    ) -> Result<Vec<u8>, SrpError> {
+        let u = self.u(big_a, big_b)?;
+        let kgx = (self.k() * self.g.modpow(x, &self.n)) % &self.n;
+        let base = (big_b + &self.n - kgx) % &self.n;
+        let s = base.modpow(&(a + u * x), &self.n);
+        Ok(self.digest.hash(&[&s.to_bytes_be()]))
+    }
+
+    /// The shared key `K = H(S)` with `S = (A * v^u)^b`
+    pub(super) fn server_key(
+        &self,
+        b: &BigUint,
+        big_a: &BigUint,
+        big_b: &BigUint,
+        v: &BigUint,
+    ) -> Result<Vec<u8>, SrpError> {
+        let u = self.u(big_a, big_b)?;
+        let s = ((big_a * v.modpow(&u, &self.n)) % &self.n).modpow(b, &self.n);
+        Ok(self.digest.hash(&[&s.to_bytes_be()]))
+    }
+}
+
+/// Everything the client's proof `M1` covers
+pub(super) struct Transcript<'a> {
+    pub(super) user: &'a str,
+    pub(super) authzid: &'a str,
+    pub(super) salt: &'a [u8],
+    pub(super) big_a: &'a BigUint,
+    pub(super) big_b: &'a BigUint,
+    pub(super) key: &'a [u8],
+    pub(super) offer: &'a str,
+}
+
+impl Params {
+    /// `M1 = H((H(N) ^ H(g)) | H(U) | s | A | B | K | H(I) | H(L))`
+    pub(super) fn client_proof(&self, t: &Transcript) -> Vec<u8> {
+        let h = |data: &[u8]| self.digest.hash(&[data]);
+        let group: Vec<u8> = h(&self.n.to_bytes_be())
+            .iter()
+            .zip(h(&self.g.to_bytes_be()))
+            .map(|(n, g)| n ^ g)
+            .collect();
+        self.digest.hash(&[
+            &group,
+            &h(t.user.as_bytes()),
+            t.salt,
+            &t.big_a.to_bytes_be(),
+            &t.big_b.to_bytes_be(),
+            t.key,
+            &h(t.authzid.as_bytes()),
+            &h(t.offer.as_bytes()),
+        ])
+    }
+}
+
+/// The server's proof `M2 = H(A | M1 | K | H(I) | H(o) | sid | ttl)`, `session` being the
+/// identifier and lifetime of the session for reuse
+pub(super) fn server_proof(
+    digest: SrpDigest,
+    big_a: &BigUint,
+    client_proof: &[u8],
+    key: &[u8],
+    authzid: &str,
+    choice: &str,
+    session: (&str, u32),
+) -> Vec<u8> {
+    let (sid, ttl) = session;
+    digest.hash(&[
+        &big_a.to_bytes_be(),
+        client_proof,
+        key,
+        &digest.hash(&[authzid.as_bytes()]),
+        &digest.hash(&[choice.as_bytes()]),
+        sid.as_bytes(),
+        &ttl.to_be_bytes(),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> BigUint {
+        BigUint::parse_bytes(s.as_bytes(), 16).unwrap()
+    }
+
+    /// The test vectors of RFC 5054, appendix B
+    #[test]
+    fn rfc5054_vectors() {
+        let params = Params::new(SrpGroup::Bits1024, SrpDigest::Sha1);
+        assert_eq!(params.k(), hex("7556AA045AEF2CDD07ABAF0F665C3E818913186F"));
+        let salt = hex("BEB25379D1A8581EB5A727673A2441EE").to_bytes_be();
+        let x = params.x(&salt, "alice", "password123");
+        assert_eq!(x, hex("94B7555AABE9127CC58CCF4993DB6CF84D16C124"));
+        let v = params.verifier(&x);
+
+        let a = hex("60975527035CF2AD1989806F0407210BC81EDC04E2762A56AFD529DDDA2D4393");
+        let b = hex("E487CB59D31AC550471E81F00F6928E01DDA08E974A004F49E61F5D105284D20");
+        let big_a = params.client_public(&a);
+        let big_b = params.server_public(&v, &b);
+        assert_eq!(
+            params.u(&big_a, &big_b).unwrap(),
+            hex("CE38B9593487DA98554ED47D70A7AE5F462EF019")
+        );
+        let client = params.client_key(&a, &big_a, &big_b, &x).unwrap();
+        let server = params.server_key(&b, &big_a, &big_b, &v).unwrap();
+        assert_eq!(client, server);
+        let s = hex(concat!(
+            "B0DC82BABCF30674AE450C0287745E7990A3381F63B387AAF271A10D233861E3",
+            "59B48220F7C4693C9AE12B0A6F67809F0876E2D013800D6C41BB59B6D5979B5C",
+            "00A172B4A2A5903A0BDCAF8A709585EB2AFAFA8F3499B200210DCC1F10EB3394",
+            "3CD67FC88A2F39A4BE5BEC4EC0A3212DC346D7E474B29EDE8A469FFECA686E5A",
+        ));
+        assert_eq!(server, SrpDigest::Sha1.hash(&[&s.to_bytes_be()]));
+    }
+
+    #[test]
+    fn groups() {
+        for group in SrpGroup::ALL.iter().copied() {
+            let params = Params::new(group, SrpDigest::Sha256);
+            assert_eq!(params.n.bits() as usize, group.bits());
+            assert_eq!(SrpGroup::identify(&params.n, &params.g), Some(group));
+        }
+        let params = Params::new(SrpGroup::Bits2048, SrpDigest::Sha256);
+        assert_eq!(SrpGroup::identify(&params.n, &BigUint::from(3u32)), None);
+        assert_eq!(SrpGroup::identify(&(&params.n + 2u32), &params.g), None);
+    }
+
+    #[test]
+    fn illegal_public_keys() {
+        let params = Params::new(SrpGroup::Bits1024, SrpDigest::Sha1);
+        assert!(params.check_public(&BigUint::default()).is_err());
+        assert!(params.check_public(&params.n).is_err());
+        assert!(params.check_public(&(&params.n * 2u32)).is_err());
+        assert!(params.check_public(&BigUint::from(2u32)).is_ok());
+    }
+
+    #[test]
+    fn messages() {
+        let message = Builder::new()
+            .byte(0)
+            .mpi(&BigUint::from(0x1234u32))
+            .os(b"salt")
+            .utf8("mda=SHA-160")
+            .uint(7)
+            .finish();
+        let mut reader = Reader::new(&message).unwrap();
+        assert_eq!(reader.byte().unwrap(), 0);
+        assert_eq!(reader.mpi().unwrap(), BigUint::from(0x1234u32));
+        assert_eq!(reader.os().unwrap(), b"salt");
+        assert_eq!(reader.utf8().unwrap(), "mda=SHA-160");
+        assert_eq!(reader.uint().unwrap(), 7);
+        reader.finish().unwrap();
+
+        assert!(Reader::new(&message[..message.len() - 1]).is_err());
+        let mut reader = Reader::new(&message).unwrap();
+        reader.byte().unwrap();
+        assert!(reader.finish().is_err());
+    }
+
+    #[test]
+    fn options() {
+        let offer = Offer::new(SrpDigest::Sha1, SrpLayer::Integrity);
+        let encoded = offer.encode();
+        assert_eq!(
+            encoded,
+            "mda=SHA-160,replay_detection,integrity=HMAC-SHA-160,confidentiality=aes,\
+             mandatory=integrity"
+        );
+        assert_eq!(Offer::parse(&encoded), offer);
+        assert_eq!(Offer::parse(&format!("{},unknown=1,other", encoded)), offer);
+
+        let choice = offer.choose(SrpLayer::None).unwrap();
+        assert_eq!(choice.layer, SrpLayer::Integrity);
+        assert_eq!(choice.digest, SrpDigest::Sha1);
+        assert_eq!(Choice::parse(&choice.encode()).unwrap(), choice);
+        offer.check(&choice).unwrap();
+
+        let none = Choice {
+            layer: SrpLayer::None,
+            replay_detection: false,
+            ..choice
+        };
+        assert_eq!(none.encode(), "mda=SHA-160");
+        assert!(offer.check(&none).is_err());
+
+        assert!(Choice::parse("mda=SHA-160,confidentiality=aes").is_err());
+        assert!(Choice::parse("mda=SHA-160,mda=SHA-256").is_err());
+        assert!(Choice::parse("mda=MD5").is_err());
+        assert!(Offer::parse("mda=MD5").choose(SrpLayer::None).is_err());
+    }
+}
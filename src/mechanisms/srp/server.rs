@@ -0,0 +1,227 @@
+use crate::error::SessionError;
+use crate::mechanism::Authentication;
+use crate::mechanisms::srp::layer::Layer;
+use crate::mechanisms::srp::protocol::{
+    constant_time_eq, random_iv, server_proof, Builder, Choice, Offer, Params, Reader, SrpError,
+    Transcript, IV_LEN,
+};
+use crate::property::{AuthId, AuthzId, SrpLayer, SrpSecret, SrpSecurityLayer, SrpVerifier};
+use crate::session::Step::{Done, NeedsMore};
+use crate::session::{SessionData, StepResult};
+use num_bigint::BigUint;
+use std::io::Write;
+use std::sync::Arc;
+
+/// What the server has to remember to verify the client's proof
+#[derive(Debug)]
+struct Challenge {
+    user: String,
+    authzid: String,
+    verifier: Arc<SrpVerifier>,
+    b: BigUint,
+    big_b: BigUint,
+    offer: Offer,
+    /// The offer as sent, which the client's proof covers
+    encoded_offer: String,
+}
+
+#[derive(Debug, Default)]
+enum State {
+    #[default]
+    Initial,
+    Challenged(Box<Challenge>),
+    Completed,
+}
+
+/// Server side of `SRP`
+///
+/// Sets [`AuthId`] and [`AuthzId`] from the client's first message and then queries the user's
+/// verifier as [`SrpSecret`]. The group and digest of the exchange are those of the verifier. The
+/// client must choose at least the security layer of the [`SrpSecurityLayer`] property if it is
+/// provided.
+#[derive(Debug, Default)]
+pub struct Srp {
+    state: State,
+    layer: Option<Layer>,
+}
+
+impl Srp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn challenge(
+        session: &mut SessionData,
+        input: &[u8],
+        writer: &mut dyn Write,
+    ) -> Result<(usize, Challenge), SessionError> {
+        let mut reader = Reader::new(input)?;
+        let user = reader.utf8()?;
+        let authzid = reader.utf8()?;
+        // A session the client wants to reuse is never known so these are ignored
+        let _sid = reader.utf8()?;
+        let _nonce = reader.os()?;
+        reader.finish()?;
+
+        session.set_property::<AuthId>(Arc::new(user.to_string()));
+        if authzid.is_empty() {
+            session.clear_property::<AuthzId>();
+        } else {
+            session.set_property::<AuthzId>(Arc::new(authzid.to_string()));
+        }
+        let verifier = session
+            .get_property_or_callback::<SrpSecret>()?
+            .ok_or(SessionError::no_property::<SrpSecret>())?;
+        let required = session
+            .get_property_or_callback::<SrpSecurityLayer>()?
+            .map(|layer| *layer)
+            .unwrap_or(SrpLayer::None);
+
+        let params = Params::new(verifier.group, verifier.digest);
+        let v = BigUint::from_bytes_be(&verifier.verifier);
+        let b = Params::private_key();
+        let big_b = params.server_public(&v, &b);
+        let offer = Offer::new(verifier.digest, required);
+        let encoded_offer = offer.encode();
+
+        let message = Builder::new()
+            .byte(0)
+            .mpi(&params.n)
+            .mpi(&params.g)
+            .os(&verifier.salt)
+            .mpi(&big_b)
+            .utf8(&encoded_offer)
+            .finish();
+        writer.write_all(&message)?;
+        let challenge = Challenge {
+            user: user.to_string(),
+            authzid: authzid.to_string(),
+            verifier,
+            b,
+            big_b,
+            offer,
+            encoded_offer,
+        };
+        Ok((message.len(), challenge))
+    }
+
+    fn verify(
+        &mut self,
+        challenge: &Challenge,
+        input: &[u8],
+        writer: &mut dyn Write,
+    ) -> Result<usize, SessionError> {
+        let mut reader = Reader::new(input)?;
+        let big_a = reader.mpi()?;
+        let proof = reader.os()?;
+        let encoded_choice = reader.utf8()?;
+        let iv = reader.os()?;
+        reader.finish()?;
+
+        let choice = Choice::parse(encoded_choice)?;
+        challenge.offer.check(&choice)?;
+        let mut recv_iv = [0; IV_LEN];
+        if choice.layer == SrpLayer::Confidentiality {
+            if iv.len() != IV_LEN {
+                return Err(SrpError::BadFormat.into());
+            }
+            recv_iv.copy_from_slice(iv);
+        }
+
+        let verifier = &challenge.verifier;
+        let params = Params::new(verifier.group, verifier.digest);
+        params.check_public(&big_a)?;
+        let v = BigUint::from_bytes_be(&verifier.verifier);
+        let key = params.server_key(&challenge.b, &big_a, &challenge.big_b, &v)?;
+        let expected = params.client_proof(&Transcript {
+            user: &challenge.user,
+            authzid: &challenge.authzid,
+            salt: &verifier.salt,
+            big_a: &big_a,
+            big_b: &challenge.big_b,
+            key: &key,
+            offer: &challenge.encoded_offer,
+        });
+        if !constant_time_eq(&expected, proof) {
+            return Err(SessionError::AuthenticationFailure);
+        }
+
+        let own_proof = server_proof(
+            verifier.digest,
+            &big_a,
+            proof,
+            &key,
+            &challenge.authzid,
+            encoded_choice,
+            ("", 0),
+        );
+        let send_iv = random_iv();
+        let sent_iv: &[u8] = if choice.layer == SrpLayer::Confidentiality {
+            &send_iv
+        } else {
+            &[]
+        };
+        let message = Builder::new()
+            .os(&own_proof)
+            .os(sent_iv)
+            .utf8("")
+            .uint(0)
+            .finish();
+        writer.write_all(&message)?;
+        self.layer = Layer::new(
+            choice.layer,
+            verifier.digest,
+            &key,
+            choice.replay_detection,
+            send_iv,
+            recv_iv,
+        );
+        Ok(message.len())
+    }
+}
+
+impl Authentication for Srp {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        input: Option<&[u8]>,
+        writer: &mut dyn Write,
+    ) -> StepResult {
+        // Any error ends the exchange
+        match (std::mem::replace(&mut self.state, State::Completed), input) {
+            (State::Initial, None) | (State::Initial, Some(&[])) => {
+                self.state = State::Initial;
+                Ok(NeedsMore(None))
+            }
+            (State::Initial, Some(input)) => {
+                let (len, challenge) = Self::challenge(session, input, writer)?;
+                self.state = State::Challenged(Box::new(challenge));
+                Ok(NeedsMore(Some(len)))
+            }
+            (State::Challenged(challenge), input) => {
+                let input = input.ok_or(SessionError::InputDataRequired)?;
+                let len = self.verify(&challenge, input, writer)?;
+                Ok(Done(Some(len)))
+            }
+            (State::Completed, _) => Err(SrpError::Completed.into()),
+        }
+    }
+
+    fn encode(&mut self, input: &[u8]) -> Result<Box<[u8]>, SessionError> {
+        match self.layer.as_mut() {
+            Some(layer) => Ok(layer.encode(input)),
+            None => Err(SessionError::NoSecurityLayer),
+        }
+    }
+
+    fn decode(&mut self, input: &[u8]) -> Result<Box<[u8]>, SessionError> {
+        match self.layer.as_mut() {
+            Some(layer) => Ok(layer.decode(input)?),
+            None => Err(SessionError::NoSecurityLayer),
+        }
+    }
+
+    fn has_security_layer(&self) -> bool {
+        self.layer.is_some()
+    }
+}
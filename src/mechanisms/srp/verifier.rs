@@ -0,0 +1,51 @@
+//! Provisioning of `SRP` users
+
+use crate::mechanisms::srp::protocol::Params;
+use crate::property::{SrpDigest, SrpGroup, SrpVerifier};
+
+/// Length of the salt generated by [`SrpVerifier::new`]
+const SALT_LEN: usize = 16;
+
+impl SrpVerifier {
+    /// Create the verifier for a user with a random salt
+    ///
+    /// The result is what a server stores instead of the password and provides as the
+    /// [`SrpSecret`](crate::property::SrpSecret) property. `username` must be the
+    /// [`AuthId`](crate::property::AuthId) the client authenticates as.
+    ///
+    /// ```
+    /// # use rsasl::property::{SrpDigest, SrpGroup, SrpVerifier};
+    /// let verifier = SrpVerifier::new("alice", "password123", SrpGroup::Bits2048, SrpDigest::Sha256);
+    /// assert_eq!(verifier.salt.len(), 16);
+    /// ```
+    pub fn new(username: &str, password: &str, group: SrpGroup, digest: SrpDigest) -> Self {
+        let salt: [u8; SALT_LEN] = rand::random();
+        Self::with_salt(username, password, salt.to_vec(), group, digest)
+    }
+
+    /// Create the verifier for a user with the given salt
+    ///
+    /// # Panics
+    /// If the salt is longer than 255 bytes, which the protocol can't transmit.
+    pub fn with_salt(
+        username: &str,
+        password: &str,
+        salt: Vec<u8>,
+        group: SrpGroup,
+        digest: SrpDigest,
+    ) -> Self {
+        assert!(
+            salt.len() <= u8::MAX as usize,
+            "SRP salts are at most 255 bytes"
+        );
+        let params = Params::new(group, digest);
+        let x = params.x(&salt, username, password);
+        let verifier = params.verifier(&x).to_bytes_be();
+        Self {
+            group,
+            digest,
+            salt,
+            verifier,
+        }
+    }
+}
@@ -112,49 +112,80 @@ impl PropertyQ for AuthzId {
     }
 }
 
+/// Request to authenticate to the user's OpenID IdP in a web browser
+///
+/// Issued by the `OPENID20` client after it stored the URL to open in [`OpenID20RedirectUrl`].
+/// The callback should only return once the user completed authentication. No value has to be
+/// set for this property.
 #[derive(Debug)]
 pub struct OpenID20AuthenticateInBrowser(PhantomData<()>);
+impl PropertyQ for OpenID20AuthenticateInBrowser {
+    type Item = ();
+    fn property() -> Property {
+        OPENID20_AUTHENTICATE_IN_BROWSER
+    }
+}
 
+/// Request to authenticate to the user's SAML IdP in a web browser
+///
+/// Issued by the `SAML20` client after it stored the URL to open in [`SAML20RedirectUrl`]. The
+/// callback should only return once the user completed authentication. No value has to be set
+/// for this property.
 #[derive(Debug)]
 pub struct Saml20AuthenticateInBrowser(PhantomData<()>);
 impl PropertyQ for Saml20AuthenticateInBrowser {
-    type Item = CString;
+    type Item = ();
     fn property() -> Property {
         SAML20_AUTHENTICATE_IN_BROWSER
     }
 }
 
+/// Outcome of the OpenID authentication, e.g. `openid.sreg` attributes
+///
+/// Optionally provided by the `OPENID20` server after a successful validation, and set on the
+/// client to the data the server sent.
 #[derive(Debug)]
 pub struct OpenID20OutcomeData(PhantomData<()>);
 impl PropertyQ for OpenID20OutcomeData {
-    type Item = CString;
+    type Item = String;
     fn property() -> Property {
         OPENID20_OUTCOME_DATA
     }
 }
 
+/// URL the user has to visit to authenticate with OpenID
+///
+/// Queried by the `OPENID20` server after the client sent its identifier in [`AuthId`], and set
+/// on the client to the URL the server sent.
 #[derive(Debug)]
 pub struct OpenID20RedirectUrl(PhantomData<()>);
 impl PropertyQ for OpenID20RedirectUrl {
-    type Item = CString;
+    type Item = String;
     fn property() -> Property {
         OPENID20_REDIRECT_URL
     }
 }
 
+/// URL the user has to visit to authenticate with SAML
+///
+/// Queried by the `SAML20` server after the client sent its [`SAML20IDPIdentifier`], and set on
+/// the client to the URL the server sent.
 #[derive(Debug)]
 pub struct SAML20RedirectUrl(PhantomData<()>);
 impl PropertyQ for SAML20RedirectUrl {
-    type Item = CString;
+    type Item = String;
     fn property() -> Property {
         SAML20_REDIRECT_URL
     }
 }
 
+/// Identifier of the user's SAML IdP, either a domain name or a URL
+///
+/// Required by the `SAML20` client and set on the server to the identifier the client sent.
 #[derive(Debug)]
 pub struct SAML20IDPIdentifier(PhantomData<()>);
 impl PropertyQ for SAML20IDPIdentifier {
-    type Item = CString;
+    type Item = String;
     fn property() -> Property {
         SAML20_IDP_IDENTIFIER
     }
@@ -253,7 +284,7 @@ impl PropertyQ for Realm {
 #[derive(Debug)]
 pub struct Pin(PhantomData<()>);
 impl PropertyQ for Pin {
-    type Item = CString;
+    type Item = String;
     fn property() -> Property {
         PIN
     }
@@ -262,7 +293,7 @@ impl PropertyQ for Pin {
 #[derive(Debug)]
 pub struct SuggestedPin(PhantomData<()>);
 impl PropertyQ for SuggestedPin {
-    type Item = CString;
+    type Item = String;
     fn property() -> Property {
         SUGGESTED_PIN
     }
@@ -271,7 +302,7 @@ impl PropertyQ for SuggestedPin {
 #[derive(Debug)]
 pub struct Passcode(PhantomData<()>);
 impl PropertyQ for Passcode {
-    type Item = CString;
+    type Item = String;
     fn property() -> Property {
         PASSCODE
     }
@@ -322,6 +353,568 @@ impl PropertyQ for Password {
     }
 }
 
+#[derive(Debug)]
+/// Token used by the `HT-*` mechanisms, e.g. an XMPP FAST token
+pub struct HtToken(PhantomData<()>);
+impl PropertyQ for HtToken {
+    type Item = String;
+    fn property() -> Property {
+        HT_TOKEN
+    }
+}
+
+/// Stored secret the `CRAM-MD5` server verifies responses with instead of the [`Password`]
+///
+/// Lets servers support `CRAM-MD5` without storing plaintext passwords. The server queries this
+/// property first and only falls back to [`Password`] if it isn't provided.
+#[derive(Debug)]
+pub struct CramMd5Secret(PhantomData<()>);
+impl PropertyQ for CramMd5Secret {
+    type Item = HmacMd5Context;
+    fn property() -> Property {
+        CRAM_MD5_SECRET
+    }
+}
+
+/// Value of the [`CramMd5Secret`] property
+///
+/// The MD5 states after hashing the HMAC key padded with the inner and outer pad respectively.
+/// They are enough to compute HMAC-MD5 for any message but don't reveal the password.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct HmacMd5Context {
+    pub inner: [u32; 4],
+    pub outer: [u32; 4],
+}
+
+impl HmacMd5Context {
+    const DOVECOT_SCHEME: &'static str = "{CRAM-MD5}";
+
+    /// Parse a password of the Dovecot `CRAM-MD5` scheme
+    ///
+    /// These are 64 hex digits of the outer and then the inner state, each word little-endian.
+    /// The `{CRAM-MD5}` scheme prefix is optional.
+    pub fn from_dovecot(password: &str) -> Option<Self> {
+        let hex = password
+            .strip_prefix(Self::DOVECOT_SCHEME)
+            .unwrap_or(password);
+        if hex.len() != 64 || !hex.is_ascii() {
+            return None;
+        }
+        let mut words = [0u32; 8];
+        for (i, word) in words.iter_mut().enumerate() {
+            let mut bytes = [0u8; 4];
+            for (j, byte) in bytes.iter_mut().enumerate() {
+                let pos = (i * 4 + j) * 2;
+                *byte = u8::from_str_radix(&hex[pos..pos + 2], 16).ok()?;
+            }
+            *word = u32::from_le_bytes(bytes);
+        }
+        Some(Self {
+            outer: [words[0], words[1], words[2], words[3]],
+            inner: [words[4], words[5], words[6], words[7]],
+        })
+    }
+
+    /// Format as password of the Dovecot `CRAM-MD5` scheme, including the `{CRAM-MD5}` prefix
+    pub fn to_dovecot(&self) -> String {
+        let mut password = Self::DOVECOT_SCHEME.to_string();
+        for word in self.outer.iter().chain(self.inner.iter()) {
+            for byte in word.to_le_bytes().iter() {
+                password.push_str(&format!("{:02x}", byte));
+            }
+        }
+        password
+    }
+
+    /// Parse a Cyrus SASL `HMAC_MD5_STATE`
+    ///
+    /// These are 32 bytes of the inner and then the outer state, each word big-endian.
+    pub fn from_cyrus(state: &[u8]) -> Option<Self> {
+        if state.len() != 32 {
+            return None;
+        }
+        let mut words = [0u32; 8];
+        for (word, bytes) in words.iter_mut().zip(state.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        Some(Self {
+            inner: [words[0], words[1], words[2], words[3]],
+            outer: [words[4], words[5], words[6], words[7]],
+        })
+    }
+
+    /// Format as Cyrus SASL `HMAC_MD5_STATE`
+    pub fn to_cyrus(&self) -> [u8; 32] {
+        let mut state = [0u8; 32];
+        for (bytes, word) in state
+            .chunks_exact_mut(4)
+            .zip(self.inner.iter().chain(self.outer.iter()))
+        {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        state
+    }
+}
+
+/// Stored secret the `NTLM` server verifies responses with instead of the [`Password`]
+///
+/// The server queries this property first and only falls back to [`Password`] if it isn't
+/// provided. Note that the NT hash is enough to authenticate as the user with `NTLM`, so it must
+/// be protected as well as a plaintext password.
+#[derive(Debug)]
+pub struct NtlmSecret(PhantomData<()>);
+impl PropertyQ for NtlmSecret {
+    type Item = NtHash;
+    fn property() -> Property {
+        NTLM_SECRET
+    }
+}
+
+/// Value of the [`NtlmSecret`] property, the MD4 hash of the UTF-16LE encoded password
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct NtHash(pub [u8; 16]);
+
+/// Verifier the `SRP` server authenticates a user with
+///
+/// Queried by the server after setting [`AuthId`] and [`AuthzId`]. Unlike a password the verifier
+/// can't be used to authenticate as the user, it is created when provisioning the user with
+/// [`SrpVerifier::new`].
+#[derive(Debug)]
+pub struct SrpSecret(PhantomData<()>);
+impl PropertyQ for SrpSecret {
+    type Item = SrpVerifier;
+    fn property() -> Property {
+        SRP_SECRET
+    }
+}
+
+/// Value of the [`SrpSecret`] property
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SrpVerifier {
+    pub group: SrpGroup,
+    pub digest: SrpDigest,
+    pub salt: Vec<u8>,
+    /// The verifier `v` as big-endian integer
+    pub verifier: Vec<u8>,
+}
+
+/// A group of [RFC 5054](https://www.rfc-editor.org/rfc/rfc5054#appendix-A) by the size of its
+/// prime modulus
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SrpGroup {
+    Bits1024,
+    Bits2048,
+    Bits3072,
+    Bits4096,
+    Bits6144,
+    Bits8192,
+}
+
+/// The message digest algorithm of an `SRP` exchange
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SrpDigest {
+    /// `SHA-160`, i.e. SHA-1, which all implementations support
+    Sha1,
+    Sha256,
+}
+
+/// Security layer of an `SRP` exchange
+///
+/// Queried by the client for the layer to request and by the server for the layer it requires at
+/// least. Without it no security layer is requested or required. Once the exchange completed the
+/// layer is applied with [`Session::encode`](crate::session::Session::encode) and
+/// [`Session::decode`](crate::session::Session::decode).
+#[derive(Debug)]
+pub struct SrpSecurityLayer(PhantomData<()>);
+impl PropertyQ for SrpSecurityLayer {
+    type Item = SrpLayer;
+    fn property() -> Property {
+        SRP_SECURITY_LAYER
+    }
+}
+
+/// Value of the [`SrpSecurityLayer`] property
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum SrpLayer {
+    None,
+    /// Integrity protection and replay detection with HMAC
+    Integrity,
+    /// AES encryption in addition to integrity protection
+    Confidentiality,
+}
+
+/// Outcome of a `SECURID` validation
+///
+/// Set by the callback while handling the [`SECURID`](crate::validate::validations::SECURID)
+/// validation to tell the server how to continue. Not setting it at all rejects the client.
+#[derive(Debug)]
+pub struct SecuridValidation(PhantomData<()>);
+impl PropertyQ for SecuridValidation {
+    type Item = SecuridOutcome;
+    fn property() -> Property {
+        SECURID_VALIDATION
+    }
+}
+
+/// Value of the [`SecuridValidation`] property, covering the branches of RFC 2808
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SecuridOutcome {
+    /// The passcode (and PIN, if one was sent) are valid
+    Accept,
+    /// Authentication failed
+    Reject,
+    /// The token is in next token mode; the client has to send the next token code
+    NextTokenCode,
+    /// The user has to set a new PIN, optionally suggesting one to the client
+    NewPin(Option<String>),
+}
+
+/// A user's one-time password sequence as stored by the `OTP` server
+///
+/// Queried by the server after the client sent its authentication identity. Once the response
+/// was verified it is set to the updated entry before the
+/// [`OTP`](crate::validate::validations::OTP) validation, which has to store it in place of the
+/// previous one.
+#[derive(Debug)]
+pub struct OtpState(PhantomData<()>);
+impl PropertyQ for OtpState {
+    type Item = OtpEntry;
+    fn property() -> Property {
+        OTP_STATE
+    }
+}
+
+/// New parameters the `OTP` client re-initializes the sequence with
+///
+/// Queried by the client after receiving the challenge. If provided the response re-initializes
+/// the sequence (`init-hex`) instead of only answering the challenge.
+#[derive(Debug)]
+pub struct OtpInit(PhantomData<()>);
+impl PropertyQ for OtpInit {
+    type Item = OtpInitParameters;
+    fn property() -> Property {
+        OTP_INIT
+    }
+}
+
+/// Hash algorithms of RFC 2289 one-time passwords
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum OtpAlgorithm {
+    Md4,
+    Md5,
+    Sha1,
+}
+
+impl OtpAlgorithm {
+    /// Name of the algorithm as used in challenges and re-initialization, e.g. `md5`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Md4 => "md4",
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "md4" => Some(Self::Md4),
+            "md5" => Some(Self::Md5),
+            "sha1" => Some(Self::Sha1),
+            _ => None,
+        }
+    }
+}
+
+/// Value of the [`OtpState`] property
+///
+/// `otp` is the one-time password for `sequence`, i.e. the one last accepted or the initial one.
+/// The next challenge asks for the password of `sequence - 1`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OtpEntry {
+    pub algorithm: OtpAlgorithm,
+    pub sequence: u32,
+    pub seed: String,
+    pub otp: [u8; 8],
+}
+
+/// Value of the [`OtpInit`] property
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OtpInitParameters {
+    pub algorithm: OtpAlgorithm,
+    pub sequence: u32,
+    pub seed: String,
+    /// Pass phrase for the new sequence, the current [`Password`] is kept if `None`
+    pub passphrase: Option<String>,
+}
+
+/// A user's HOTP or TOTP secret as stored by the `X-OATH` server
+///
+/// Like [`OtpState`] it is set to the updated entry before the
+/// [`OTP`](crate::validate::validations::OTP) validation, which has to store it so the same
+/// password can't be used twice.
+#[derive(Debug)]
+pub struct OathState(PhantomData<()>);
+impl PropertyQ for OathState {
+    type Item = OathEntry;
+    fn property() -> Property {
+        OATH_STATE
+    }
+}
+
+/// HMAC algorithms of HOTP and TOTP one-time passwords
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum OathAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// The moving factor of an [`OathEntry`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OathCounter {
+    /// HOTP of RFC 4226, `next` is the counter of the next password to accept
+    Hotp { next: u64 },
+    /// TOTP of RFC 6238 with a time step of `period` seconds. `last` is the time step of the
+    /// password last accepted, passwords of that or earlier time steps are rejected.
+    Totp { period: u64, last: Option<u64> },
+}
+
+/// Value of the [`OathState`] property
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OathEntry {
+    pub algorithm: OathAlgorithm,
+    pub secret: Vec<u8>,
+    /// Number of decimal digits of a password, between 6 and 8
+    pub digits: u32,
+    pub counter: OathCounter,
+}
+
+/// Identity from the certificate a TLS client authenticated with
+///
+/// Set by a server before an `EXTERNAL` exchange on a connection using mutual TLS so the
+/// [`EXTERNAL`](crate::validate::validations::EXTERNAL) validation can inspect it. The
+/// certificate must have been verified by the TLS implementation already.
+#[derive(Debug)]
+pub struct TlsClientIdentity(PhantomData<()>);
+impl PropertyQ for TlsClientIdentity {
+    type Item = CertificateIdentity;
+    fn property() -> Property {
+        TLS_CLIENT_IDENTITY
+    }
+}
+
+/// Value of the [`TlsClientIdentity`] property
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CertificateIdentity {
+    /// Subject distinguished name in its RFC 4514 string form, e.g. `CN=backup,O=Example`
+    pub subject: String,
+    pub subject_alt_names: Vec<SubjectAltName>,
+}
+
+/// An entry of the subject alternative name extension of a certificate
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SubjectAltName {
+    Dns(String),
+    Email(String),
+    Uri(String),
+    IpAddress(std::net::IpAddr),
+}
+
+/// Credentials of the process on the other end of a Unix domain socket
+///
+/// Set by a server before an `EXTERNAL` exchange on a Unix domain socket so the
+/// [`EXTERNAL`](crate::validate::validations::EXTERNAL) validation can inspect it.
+#[derive(Debug)]
+pub struct PeerCredentials(PhantomData<()>);
+impl PropertyQ for PeerCredentials {
+    type Item = UnixCredentials;
+    fn property() -> Property {
+        PEER_CREDENTIALS
+    }
+}
+
+/// Value of the [`PeerCredentials`] property
+///
+/// The credentials are those of the peer at the time it connected.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct UnixCredentials {
+    pub uid: u32,
+    pub gid: u32,
+    /// Process ID of the peer, if the platform reports it
+    pub pid: Option<i32>,
+}
+
+/// Policy the `ANONYMOUS` server applies to trace tokens
+///
+/// Queried by the server before the [`ANONYMOUS`](crate::validate::validations::ANONYMOUS)
+/// validation, all trace tokens conforming to RFC 4505 are allowed without it. The rate limit of
+/// a policy is tracked in the policy itself, so the callback should set the same `Arc` for every
+/// session:
+/// ```
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// # use rsasl::callback::Callback;
+/// # use rsasl::error::SessionError;
+/// # use rsasl::Property;
+/// use rsasl::property::{properties, AnonymousAccess, AnonymousPolicy, TraceRequirement};
+/// # use rsasl::session::SessionData;
+/// struct Archive {
+///     policy: Arc<AnonymousPolicy>,
+/// }
+/// impl Callback for Archive {
+///     fn provide_prop(&self, session: &mut SessionData, property: Property) -> Result<(), SessionError> {
+///         match property {
+///             properties::ANONYMOUS_ACCESS => {
+///                 session.set_property::<AnonymousAccess>(self.policy.clone());
+///                 Ok(())
+///             }
+///             _ => Err(SessionError::NoCallback { property }),
+///         }
+///     }
+/// }
+/// let policy = AnonymousPolicy::new()
+///     .require(TraceRequirement::Email)
+///     .rate_limit(10, Duration::from_secs(60))
+///     .audit(|attempt| println!("anonymous login by {:?}: {:?}", attempt.trace, attempt.outcome));
+/// let archive = Archive { policy: Arc::new(policy) };
+/// ```
+#[derive(Debug)]
+pub struct AnonymousAccess(PhantomData<()>);
+impl PropertyQ for AnonymousAccess {
+    type Item = AnonymousPolicy;
+    fn property() -> Property {
+        ANONYMOUS_ACCESS
+    }
+}
+
+/// Value of the [`AnonymousAccess`] property
+pub struct AnonymousPolicy {
+    pub(crate) requirement: TraceRequirement,
+    pub(crate) rate_limit: Option<(u32, std::time::Duration)>,
+    pub(crate) attempts: std::sync::Mutex<AnonymousAttempts>,
+    pub(crate) audit: Option<Box<dyn Fn(&AnonymousAttempt<'_>) + Send + Sync>>,
+}
+
+/// Attempts counted for the rate limit of an [`AnonymousPolicy`]
+#[derive(Default)]
+pub(crate) struct AnonymousAttempts {
+    /// Start of the current period and the number of attempts in it, per trace token
+    pub(crate) counters: std::collections::HashMap<String, (std::time::Instant, u32)>,
+    /// Counter shared by all trace tokens that are not tracked because `counters` is full
+    pub(crate) overflow: Option<(std::time::Instant, u32)>,
+    /// When to next drop the counters of past periods
+    pub(crate) next_prune: Option<std::time::Instant>,
+}
+
+impl Debug for AnonymousPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnonymousPolicy")
+            .field("requirement", &self.requirement)
+            .field("rate_limit", &self.rate_limit)
+            .field("has audit hook", &self.audit.is_some())
+            .finish()
+    }
+}
+
+/// Which trace tokens an [`AnonymousPolicy`] allows
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TraceRequirement {
+    /// The client may omit the trace token, which is the behaviour RFC 4505 specifies
+    Optional,
+    /// The client must send a trace token of either form
+    Required,
+    /// The client must send an email address
+    Email,
+    /// The client must send an opaque token, i.e. anything not containing an `@`
+    Opaque,
+}
+
+/// An attempt to authenticate with `ANONYMOUS`, passed to the audit hook of an
+/// [`AnonymousPolicy`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct AnonymousAttempt<'a> {
+    /// The trace token sent by the client, if any. Invalid UTF-8 is replaced for malformed
+    /// messages.
+    pub trace: Option<&'a str>,
+    pub outcome: AnonymousOutcome,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AnonymousOutcome {
+    Accepted,
+    /// The message is not a valid trace token
+    Malformed,
+    /// The trace token does not meet the [`TraceRequirement`] of the policy
+    Disallowed,
+    /// The trace token was used too often
+    RateLimited,
+    /// The [`ANONYMOUS`](crate::validate::validations::ANONYMOUS) validation failed
+    Rejected,
+}
+
+/// Prompts the `LOGIN` server sends
+///
+/// Queried by the server before sending its first prompt, `Username:` and `Password:` are used
+/// without it. Protocols transporting the prompts base64 encoded, e.g. SMTP, expect these
+/// defaults, so only servers talking to clients matching on other wordings should change them.
+#[derive(Debug)]
+pub struct LoginPrompts(PhantomData<()>);
+impl PropertyQ for LoginPrompts {
+    type Item = LoginPromptText;
+    fn property() -> Property {
+        LOGIN_PROMPTS
+    }
+}
+
+/// Value of the [`LoginPrompts`] property
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LoginPromptText {
+    pub(crate) username: String,
+    pub(crate) password: String,
+}
+
+#[derive(Debug)]
+/// The mechanisms and channel binding types a server advertised
+///
+/// Used by the `SCRAM-*` mechanisms for downgrade protection ([XEP-0474](https://xmpp.org/extensions/xep-0474.html)):
+/// A server having this property set includes a hash of it in its first message, a client having
+/// it set verifies that hash against what it saw advertised, failing if the server didn't send
+/// one. See also
+/// [`SASL::client_start_advertised`](crate::SASL::client_start_advertised).
+pub struct AdvertisedMechanisms(PhantomData<()>);
+impl PropertyQ for AdvertisedMechanisms {
+    type Item = Advertised;
+    fn property() -> Property {
+        ADVERTISED_MECHANISMS
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+/// Value of the [`AdvertisedMechanisms`] property
+pub struct Advertised {
+    /// Names of the advertised mechanisms
+    pub mechanisms: Vec<String>,
+    /// Names of the advertised channel binding types, e.g. `tls-exporter`. Empty if the server
+    /// does not support channel bindings.
+    pub channel_bindings: Vec<String>,
+}
+
+impl Advertised {
+    pub fn new<M, C>(
+        mechanisms: impl IntoIterator<Item = M>,
+        channel_bindings: impl IntoIterator<Item = C>,
+    ) -> Self
+    where
+        M: ToString,
+        C: ToString,
+    {
+        Self {
+            mechanisms: mechanisms.into_iter().map(|m| m.to_string()).collect(),
+            channel_bindings: channel_bindings.into_iter().map(|c| c.to_string()).collect(),
+        }
+    }
+}
+
 pub mod properties {
     use super::*;
 
@@ -382,6 +975,58 @@ pub mod properties {
     pub const ANONYMOUS_TOKEN: Property =
         Property::new(&PropertyDefinition::new("AnonymousToken", ""));
     pub const PASSWORD: Property = Property::new(&PropertyDefinition::new("password", ""));
+    pub const ADVERTISED_MECHANISMS: Property = Property::new(&PropertyDefinition::new(
+        "advertised_mechanisms",
+        "mechanisms and channel binding types advertised by the server",
+    ));
+    pub const HT_TOKEN: Property =
+        Property::new(&PropertyDefinition::new("ht_token", "hashed token mechanism token"));
+    pub const CRAM_MD5_SECRET: Property = Property::new(&PropertyDefinition::new(
+        "cram_md5_secret",
+        "precomputed HMAC-MD5 context of the password",
+    ));
+    pub const SECURID_VALIDATION: Property = Property::new(&PropertyDefinition::new(
+        "securid_validation",
+        "outcome of validating a SecurID passcode",
+    ));
+    pub const OTP_STATE: Property = Property::new(&PropertyDefinition::new(
+        "otp_state",
+        "stored state of a one-time password sequence",
+    ));
+    pub const OTP_INIT: Property = Property::new(&PropertyDefinition::new(
+        "otp_init",
+        "parameters to re-initialize a one-time password sequence with",
+    ));
+    pub const OATH_STATE: Property = Property::new(&PropertyDefinition::new(
+        "oath_state",
+        "stored HOTP or TOTP secret and counter",
+    ));
+    pub const TLS_CLIENT_IDENTITY: Property = Property::new(&PropertyDefinition::new(
+        "tls_client_identity",
+        "identity from the verified TLS client certificate",
+    ));
+    pub const PEER_CREDENTIALS: Property = Property::new(&PropertyDefinition::new(
+        "peer_credentials",
+        "credentials of the peer of a Unix domain socket",
+    ));
+    pub const ANONYMOUS_ACCESS: Property = Property::new(&PropertyDefinition::new(
+        "anonymous_access",
+        "policy for trace tokens of anonymous logins",
+    ));
+    pub const LOGIN_PROMPTS: Property = Property::new(&PropertyDefinition::new(
+        "login_prompts",
+        "prompts the LOGIN server asks for the username and password with",
+    ));
+    pub const NTLM_SECRET: Property = Property::new(&PropertyDefinition::new(
+        "ntlm_secret",
+        "NT hash of the password",
+    ));
+    pub const SRP_SECRET: Property =
+        Property::new(&PropertyDefinition::new("srp_secret", "SRP verifier of the user"));
+    pub const SRP_SECURITY_LAYER: Property = Property::new(&PropertyDefinition::new(
+        "srp_security_layer",
+        "security layer requested or required with SRP",
+    ));
 }
 use properties::*;
 
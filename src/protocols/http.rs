@@ -0,0 +1,626 @@
+//! HTTP `SASL` authentication scheme ([draft-vanrein-httpauth-sasl](https://datatracker.ietf.org/doc/draft-vanrein-httpauth-sasl/))
+//!
+//! *requires feature `protocol_http`*
+//!
+//! A server asks for authentication by answering a request with `401 Unauthorized` and a
+//! `WWW-Authenticate: SASL mech="..."` header listing its mechanisms. The client repeats the
+//! request with an `Authorization: SASL mech="...", c2s="..."` header carrying the selected
+//! mechanism and its initial response. As long as the exchange continues the server replies
+//! with another `401`, its challenge in `s2c` and an opaque `s2s` token that the client has to
+//! send back unchanged along with its next response. Once the mechanism succeeded the server
+//! processes the request and sends any additional data in an `Authentication-Info` header.
+//!
+//! Since every round is a separate HTTP request, possibly handled by a different process, the
+//! server can not keep the in-progress [`Session`] on the connection. Instead it hands the
+//! session to a [`StateStore`] that returns the `s2s` token to resume it with. [`MemoryStore`]
+//! keeps sessions in memory, which only works if all requests of an exchange reach the same
+//! process. With feature `session_state` [`EncryptedStore`] instead sends the encrypted state of
+//! the session itself as `s2s`, so that any process knowing the key can continue the exchange.
+//!
+//! Only the header values are handled here; status codes and sending the headers are left to
+//! the HTTP library in use.
+
+use crate::error::SessionError;
+use crate::mechname::Mechname;
+use crate::protocols::{ClientExchange, ProtocolError};
+use crate::session::{Session, Step};
+#[cfg(feature = "session_state")]
+use crate::state::StateKey;
+use crate::SASL;
+use std::collections::HashMap;
+#[cfg(feature = "session_state")]
+use std::collections::{HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+#[cfg(feature = "session_state")]
+use std::sync::Arc;
+use std::sync::Mutex;
+#[cfg(feature = "session_state")]
+use std::time::{Duration, Instant};
+
+/// Name of the authentication scheme
+pub const SCHEME: &str = "SASL";
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum HeaderError {
+    /// The header uses a different authentication scheme
+    OtherScheme,
+    /// The header value is not a valid list of parameters
+    Malformed,
+    /// A required parameter is missing
+    Missing(&'static str),
+}
+
+impl Display for HeaderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OtherScheme => f.write_str("authentication scheme is not SASL"),
+            Self::Malformed => f.write_str("malformed authentication parameters"),
+            Self::Missing(name) => write!(f, "required parameter {} is missing", name),
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {}
+
+/// Parse the parameters of a header value using the `SASL` scheme
+fn parse_params(value: &str) -> Result<HashMap<String, String>, HeaderError> {
+    let value = value.trim();
+    let (scheme, mut rest) = value.split_at(value.find(' ').unwrap_or(value.len()));
+    if !scheme.eq_ignore_ascii_case(SCHEME) {
+        return Err(HeaderError::OtherScheme);
+    }
+
+    let mut params = HashMap::new();
+    loop {
+        rest = rest.trim_start_matches(&[' ', ','][..]);
+        if rest.is_empty() {
+            return Ok(params);
+        }
+        let eq = rest.find('=').ok_or(HeaderError::Malformed)?;
+        let name = rest[..eq].trim().to_ascii_lowercase();
+        rest = rest[eq + 1..].trim_start();
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            loop {
+                match chars.next() {
+                    Some((_, '\\')) => value.push(chars.next().ok_or(HeaderError::Malformed)?.1),
+                    Some((i, '"')) => {
+                        rest = &quoted[i + 1..];
+                        break;
+                    }
+                    Some((_, c)) => value.push(c),
+                    None => return Err(HeaderError::Malformed),
+                }
+            }
+            value
+        } else {
+            let end = rest.find(&[',', ' '][..]).unwrap_or(rest.len());
+            let (value, tail) = rest.split_at(end);
+            rest = tail;
+            value.to_string()
+        };
+        if name.is_empty() || (!rest.is_empty() && !rest.starts_with(&[',', ' '][..])) {
+            return Err(HeaderError::Malformed);
+        }
+        params.insert(name, value);
+    }
+}
+
+/// Writes the parameters of a header value, quoting all values
+struct Params<'a>(&'a [(&'a str, Option<&'a str>)]);
+
+impl Display for Params<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(SCHEME)?;
+        let mut first = true;
+        for (name, value) in self.0.iter() {
+            if let Some(value) = value {
+                f.write_str(if first { " " } else { ", " })?;
+                first = false;
+                write!(f, "{}=\"", name)?;
+                for c in value.chars() {
+                    if c == '"' || c == '\\' {
+                        f.write_str("\\")?;
+                    }
+                    write!(f, "{}", c)?;
+                }
+                f.write_str("\"")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Value of a `WWW-Authenticate` header sent with `401 Unauthorized`
+///
+/// Starting an exchange it lists all offered mechanisms; during an exchange it names the
+/// selected mechanism and carries the challenge.
+pub struct Challenge {
+    pub realm: Option<String>,
+    pub mechanisms: Vec<String>,
+    /// Base64-encoded challenge
+    pub s2c: Option<String>,
+    pub s2s: Option<String>,
+}
+
+impl Challenge {
+    pub fn parse(value: &str) -> Result<Self, HeaderError> {
+        let mut params = parse_params(value)?;
+        let mechanisms = params
+            .remove("mech")
+            .ok_or(HeaderError::Missing("mech"))?
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        Ok(Self {
+            realm: params.remove("realm"),
+            mechanisms,
+            s2c: params.remove("s2c"),
+            s2s: params.remove("s2s"),
+        })
+    }
+
+    /// Iterate over the offered mechanisms, skipping any that aren't valid mechanism names
+    ///
+    /// Can be passed directly to [`SASL::client_start_suggested`].
+    pub fn mechanisms(&self) -> impl Iterator<Item = &Mechname> {
+        self.mechanisms
+            .iter()
+            .filter_map(|mechanism| Mechname::new(mechanism.as_bytes()).ok())
+    }
+}
+
+impl Display for Challenge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mechanisms = self.mechanisms.join(" ");
+        Display::fmt(
+            &Params(&[
+                ("realm", self.realm.as_deref()),
+                ("mech", Some(&mechanisms)),
+                ("s2s", self.s2s.as_deref()),
+                ("s2c", self.s2c.as_deref()),
+            ]),
+            f,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Value of an `Authorization` header
+pub struct Credentials {
+    pub realm: Option<String>,
+    pub mechanism: String,
+    /// Base64-encoded response
+    pub c2s: Option<String>,
+    pub s2s: Option<String>,
+}
+
+impl Credentials {
+    pub fn parse(value: &str) -> Result<Self, HeaderError> {
+        let mut params = parse_params(value)?;
+        Ok(Self {
+            realm: params.remove("realm"),
+            mechanism: params.remove("mech").ok_or(HeaderError::Missing("mech"))?,
+            c2s: params.remove("c2s"),
+            s2s: params.remove("s2s"),
+        })
+    }
+}
+
+impl Display for Credentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(
+            &Params(&[
+                ("realm", self.realm.as_deref()),
+                ("mech", Some(&self.mechanism)),
+                ("s2s", self.s2s.as_deref()),
+                ("c2s", self.c2s.as_deref()),
+            ]),
+            f,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+/// Value of an `Authentication-Info` header sent with the response to a successfully
+/// authenticated request
+pub struct AuthenticationInfo {
+    /// Base64-encoded additional data
+    pub s2c: Option<String>,
+}
+
+impl AuthenticationInfo {
+    pub fn parse(value: &str) -> Result<Self, HeaderError> {
+        let mut params = parse_params(value)?;
+        Ok(Self {
+            s2c: params.remove("s2c"),
+        })
+    }
+}
+
+impl Display for AuthenticationInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&Params(&[("s2c", self.s2c.as_deref())]), f)
+    }
+}
+
+/// Step the session, returning whether it completed and the base64-encoded output
+fn step64(
+    session: &mut Session,
+    input: Option<&str>,
+) -> Result<(bool, Option<String>), SessionError> {
+    let mut output = Vec::new();
+    let (complete, written) = match session.step64(input, &mut output)? {
+        Step::Done(written) => (true, written),
+        Step::NeedsMore(written) => (false, written),
+    };
+    // The base64 output is always ASCII
+    Ok((
+        complete,
+        written.map(|_| String::from_utf8_lossy(&output).into_owned()),
+    ))
+}
+
+/// Client side of an HTTP SASL exchange
+///
+/// The session is usually started from the mechanisms of the initial [`Challenge`] using
+/// [`SASL::client_start_suggested`].
+pub struct Client {
+    exchange: ClientExchange,
+    realm: Option<String>,
+}
+
+impl Client {
+    pub fn new(session: Session) -> Self {
+        Self {
+            exchange: ClientExchange::new(session),
+            realm: None,
+        }
+    }
+
+    /// Generate the first `Authorization` header in reply to the initial [`Challenge`]
+    pub fn credentials(&mut self, challenge: &Challenge) -> Result<Credentials, SessionError> {
+        self.realm = challenge.realm.clone();
+        let c2s = if self.exchange.session().are_we_first() {
+            self.step(None)?
+        } else {
+            None
+        };
+        Ok(self.reply(c2s, None))
+    }
+
+    /// Handle a `401` carrying the challenge of an ongoing exchange, returning the next
+    /// `Authorization` header
+    pub fn handle_challenge(
+        &mut self,
+        challenge: &Challenge,
+    ) -> Result<Credentials, ProtocolError> {
+        if self.exchange.is_complete() || challenge.s2s.is_none() {
+            return Err(ProtocolError::UnexpectedMessage);
+        }
+        let c2s = self.step(challenge.s2c.as_deref())?;
+        Ok(self.reply(c2s, challenge.s2s.clone()))
+    }
+
+    /// Handle a successful response, checking the additional data in its `Authentication-Info`
+    /// header if there is one
+    ///
+    /// If this returns an error the server could not prove its identity and the response
+    /// should not be trusted.
+    pub fn handle_success(
+        &mut self,
+        info: Option<&AuthenticationInfo>,
+    ) -> Result<(), ProtocolError> {
+        let s2c = info
+            .and_then(|info| info.s2c.as_deref())
+            .map(base64::decode)
+            .transpose()
+            .map_err(SessionError::from)?;
+        self.exchange.success(s2c.as_deref())
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        self.exchange.session_mut()
+    }
+
+    pub fn into_session(self) -> Session {
+        self.exchange.into_session()
+    }
+
+    fn reply(&self, c2s: Option<String>, s2s: Option<String>) -> Credentials {
+        Credentials {
+            realm: self.realm.clone(),
+            mechanism: self.exchange.session().get_mechname().to_string(),
+            c2s,
+            s2s,
+        }
+    }
+
+    fn step(&mut self, input: Option<&str>) -> Result<Option<String>, SessionError> {
+        let input = input.map(base64::decode).transpose()?;
+        let output = self.exchange.step(input.as_deref())?;
+        Ok(output.map(base64::encode))
+    }
+}
+
+/// Storage for the sessions of exchanges in progress on the server side
+///
+/// The token returned by `save` is sent to the client as `s2s`, and the client sends it back
+/// with its next response to `load` the session again.
+pub trait StateStore {
+    /// Store the session, returning the token to resume it with
+    fn save(&self, session: Session) -> Result<String, SessionError>;
+
+    /// Take the session for the token out of the store, if it exists
+    fn load(&self, s2s: &str) -> Option<Session>;
+}
+
+/// [`StateStore`] keeping sessions in memory, keyed by random tokens
+///
+/// Sessions of abandoned exchanges are only removed when the store is dropped, so servers
+/// should replace the store periodically or use their own [`StateStore`] with expiry.
+#[derive(Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn save(&self, session: Session) -> Result<String, SessionError> {
+        let key: [u8; 16] = rand::random();
+        let token = base64::encode_config(key, base64::URL_SAFE_NO_PAD);
+        self.sessions.lock().unwrap().insert(token.clone(), session);
+        Ok(token)
+    }
+
+    fn load(&self, s2s: &str) -> Option<Session> {
+        self.sessions.lock().unwrap().remove(s2s)
+    }
+}
+
+#[cfg(feature = "session_state")]
+/// [`StateStore`] sending the encrypted session state to the client instead of storing it
+///
+/// *requires feature `session_state`*
+///
+/// The `s2s` token is the state exported with [`Session::export_state`], so the server keeps no
+/// state at all. Sessions are resumed with the given `SASL`, which has to provide the mechanisms
+/// offered by the server. Refer to [`crate::state`] for the mechanisms supporting this.
+///
+/// Each token is only accepted once by a store. The tokens used are remembered in memory until
+/// they expire after the maximum age of the key, so this replay protection does not hold across
+/// processes or load-balanced nodes: every other store with the same key accepts a replayed
+/// token once within that window. Route the requests of an exchange to the same store, or use a
+/// short maximum age, if that is a concern.
+#[derive(Debug)]
+pub struct EncryptedStore {
+    sasl: Arc<SASL>,
+    key: StateKey,
+    used: Mutex<UsedStates>,
+}
+
+#[cfg(feature = "session_state")]
+impl EncryptedStore {
+    pub fn new(sasl: Arc<SASL>, key: StateKey) -> Self {
+        Self {
+            sasl,
+            key,
+            used: Mutex::default(),
+        }
+    }
+}
+
+#[cfg(feature = "session_state")]
+#[derive(Debug, Default)]
+/// The states resumed by an [`EncryptedStore`] that have not expired yet
+struct UsedStates {
+    ids: HashSet<[u8; 12]>,
+    // Ordered by when they were used, which is also the order they can be forgotten in
+    expiry: VecDeque<(Instant, [u8; 12])>,
+}
+
+#[cfg(feature = "session_state")]
+impl UsedStates {
+    /// Record the state as used, returning false if it was used before
+    fn insert(&mut self, id: [u8; 12], max_age: Duration) -> bool {
+        let now = Instant::now();
+        while let Some(&(expires, expired)) = self.expiry.front() {
+            if expires > now {
+                break;
+            }
+            self.expiry.pop_front();
+            self.ids.remove(&expired);
+        }
+        if !self.ids.insert(id) {
+            return false;
+        }
+        self.expiry.push_back((now + max_age, id));
+        true
+    }
+}
+
+#[cfg(feature = "session_state")]
+impl StateStore for EncryptedStore {
+    fn save(&self, session: Session) -> Result<String, SessionError> {
+        let state = session.export_state(&self.key)?;
+        Ok(base64::encode_config(state, base64::URL_SAFE_NO_PAD))
+    }
+
+    fn load(&self, s2s: &str) -> Option<Session> {
+        let state = base64::decode_config(s2s, base64::URL_SAFE_NO_PAD).ok()?;
+        let session = self.sasl.resume_session(&self.key, &state).ok()?;
+        let id = StateKey::state_id(&state)?;
+        let mut used = self.used.lock().unwrap();
+        used.insert(id, self.key.max_age()).then_some(session)
+    }
+}
+
+/// What a server has to do after handling an `Authorization` header
+pub enum ServerStep {
+    /// Answer with `401 Unauthorized` and the challenge as `WWW-Authenticate` header
+    Continue(Challenge),
+    /// The request is authenticated; process it and send the `Authentication-Info` header with
+    /// the response
+    Authenticated {
+        info: AuthenticationInfo,
+        session: Session,
+    },
+    /// Answer with `401 Unauthorized` and the challenge as `WWW-Authenticate` header, starting
+    /// over; the exchange failed, with the mechanism error if there is one
+    Failure {
+        challenge: Challenge,
+        error: Option<SessionError>,
+    },
+}
+
+/// Server side of HTTP SASL exchanges
+///
+/// Unlike the other servers in this module a single `Server` handles all exchanges, as the
+/// state of each is kept in the [`StateStore`].
+pub struct Server<S> {
+    store: S,
+    realm: Option<String>,
+    mechanisms: Vec<String>,
+}
+
+impl<S: StateStore> Server<S> {
+    pub fn new<'a>(store: S, mechanisms: impl IntoIterator<Item = &'a Mechname>) -> Self {
+        Self {
+            store,
+            realm: None,
+            mechanisms: mechanisms
+                .into_iter()
+                .map(|mechanism| mechanism.as_str().to_string())
+                .collect(),
+        }
+    }
+
+    pub fn set_realm(&mut self, realm: String) {
+        self.realm = Some(realm);
+    }
+
+    /// The `WWW-Authenticate` header to send with a `401` for unauthenticated requests
+    pub fn challenge(&self) -> Challenge {
+        Challenge {
+            realm: self.realm.clone(),
+            mechanisms: self.mechanisms.clone(),
+            s2c: None,
+            s2s: None,
+        }
+    }
+
+    pub fn handle(&self, sasl: &SASL, credentials: &Credentials) -> ServerStep {
+        let (mut session, input) = match credentials.s2s.as_deref() {
+            Some(s2s) => match self.store.load(s2s) {
+                Some(session) if session.get_mechname().as_str() == credentials.mechanism => {
+                    (session, credentials.c2s.as_deref())
+                }
+                // Unknown or expired token
+                _ => return self.failure(None),
+            },
+            None => {
+                let session = self
+                    .mechanisms
+                    .iter()
+                    .find(|mechanism| **mechanism == credentials.mechanism)
+                    .and_then(|mechanism| Mechname::new(mechanism.as_bytes()).ok())
+                    .and_then(|mechanism| sasl.server_start(mechanism).ok());
+                match session {
+                    // A mechanism where the server sends the first data can't use a response
+                    Some(session) if session.are_we_first() => (session, None),
+                    Some(session) => (session, credentials.c2s.as_deref()),
+                    None => return self.failure(None),
+                }
+            }
+        };
+
+        match step64(&mut session, input) {
+            Ok((false, s2c)) => match self.store.save(session) {
+                Ok(s2s) => ServerStep::Continue(Challenge {
+                    realm: self.realm.clone(),
+                    mechanisms: vec![credentials.mechanism.clone()],
+                    s2c,
+                    s2s: Some(s2s),
+                }),
+                Err(error) => self.failure(Some(error)),
+            },
+            Ok((true, s2c)) => ServerStep::Authenticated {
+                info: AuthenticationInfo { s2c },
+                session,
+            },
+            Err(error) => self.failure(Some(error)),
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    fn failure(&self, error: Option<SessionError>) -> ServerStep {
+        ServerStep::Failure {
+            challenge: self.challenge(),
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_roundtrip() {
+        let challenge = Challenge {
+            realm: Some("Quoted \"realm\"".to_string()),
+            mechanisms: vec!["SCRAM-SHA-256".to_string(), "PLAIN".to_string()],
+            s2c: None,
+            s2s: None,
+        };
+        assert_eq!(
+            challenge.to_string(),
+            "SASL realm=\"Quoted \\\"realm\\\"\", mech=\"SCRAM-SHA-256 PLAIN\""
+        );
+        assert_eq!(Challenge::parse(&challenge.to_string()).unwrap(), challenge);
+
+        let credentials = Credentials {
+            realm: None,
+            mechanism: "SCRAM-SHA-256".to_string(),
+            c2s: Some("biwsbj11c2VyLHI9cm9PclByY25jbg==".to_string()),
+            s2s: Some("c2Vzc2lvbg".to_string()),
+        };
+        assert_eq!(
+            Credentials::parse(&credentials.to_string()).unwrap(),
+            credentials
+        );
+    }
+
+    #[test]
+    fn parse_tokens() {
+        let credentials = Credentials::parse("sasl mech=PLAIN,c2s=\"AHVzZXIAcGFzcw==\"").unwrap();
+        assert_eq!(credentials.mechanism, "PLAIN");
+        assert_eq!(credentials.c2s.as_deref(), Some("AHVzZXIAcGFzcw=="));
+        assert_eq!(
+            Credentials::parse("Basic dXNlcjpwYXNz"),
+            Err(HeaderError::OtherScheme)
+        );
+        assert_eq!(
+            Credentials::parse("SASL c2s=\"\""),
+            Err(HeaderError::Missing("mech"))
+        );
+        assert_eq!(
+            Credentials::parse("SASL mech=\"PLAIN"),
+            Err(HeaderError::Malformed)
+        );
+        assert_eq!(
+            Credentials::parse("SASL mech=\"PLAIN\"c2s=x"),
+            Err(HeaderError::Malformed)
+        );
+    }
+}
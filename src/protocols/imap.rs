@@ -0,0 +1,266 @@
+//! IMAP `AUTHENTICATE` ([RFC 3501](https://tools.ietf.org/html/rfc3501#section-6.2.2))
+//!
+//! *requires feature `protocol_imap`*
+//!
+//! Initial responses are only sent by clients if the server advertised the `SASL-IR` capability
+//! ([RFC 4959](https://tools.ietf.org/html/rfc4959)). Servers using this module always accept
+//! them and should thus advertise `SASL-IR`.
+//!
+//! Failures are reported with the response codes from
+//! [RFC 5530](https://tools.ietf.org/html/rfc5530).
+//!
+//! ```
+//! use rsasl::protocols::imap;
+//! use rsasl::protocols::line::ServerAction;
+//! # fn f(sasl: &rsasl::SASL, line: &str) {
+//! // line = "a001 AUTHENTICATE PLAIN AHVzZXIAcGFzcw=="
+//! let command = imap::parse_authenticate(line).unwrap();
+//! let mechanism = command.mechanism.to_ascii_uppercase();
+//! let mechanism = rsasl::mechname::Mechname::new(mechanism.as_bytes()).unwrap();
+//! let session = sasl.server_start(mechanism).unwrap();
+//! let mut server = imap::server(session, command.tag.unwrap());
+//! match server.start(command.initial_response) {
+//!     ServerAction::Continue(line) => { /* send line, pass reply to server.handle_line() */ }
+//!     ServerAction::Success(line) => { /* send line, the client is authenticated */ }
+//!     ServerAction::Failure(line, reason) => { /* send line */ }
+//! }
+//! # }
+//! ```
+
+use crate::protocols::line::{
+    strip_command, AuthCommand, Client, Completion, Dialect, FailureReason, Server,
+};
+use crate::session::Session;
+
+/// The IMAP dialect of line-based SASL framing
+pub struct Imap {
+    tag: String,
+    sasl_ir: bool,
+}
+
+impl Dialect for Imap {
+    const CONTINUATION: &'static str = "+ ";
+
+    fn initial_response(&self) -> bool {
+        self.sasl_ir
+    }
+
+    fn command(&self, mechanism: &str, initial_response: Option<&str>) -> String {
+        match initial_response {
+            Some(initial_response) => format!(
+                "{} AUTHENTICATE {} {}",
+                self.tag, mechanism, initial_response
+            ),
+            None => format!("{} AUTHENTICATE {}", self.tag, mechanism),
+        }
+    }
+
+    fn completion<'a>(&self, line: &'a str) -> Option<Completion<'a>> {
+        let line = line.strip_prefix(self.tag.as_str())?.strip_prefix(' ')?;
+        let (status, text) = line.split_at(line.find(' ').unwrap_or(line.len()));
+        let text = text.trim_start();
+        if status.eq_ignore_ascii_case("OK") {
+            Some(Completion::Success)
+        } else if status.eq_ignore_ascii_case("NO") || status.eq_ignore_ascii_case("BAD") {
+            Some(Completion::Failure(text))
+        } else {
+            None
+        }
+    }
+
+    fn success(&self) -> String {
+        format!("{} OK AUTHENTICATE completed", self.tag)
+    }
+
+    fn failure(&self, reason: &FailureReason) -> String {
+        if let FailureReason::Cancelled = reason {
+            format!("{} BAD AUTHENTICATE cancelled", self.tag)
+        } else if reason.is_syntax_error() {
+            format!("{} BAD Invalid AUTHENTICATE response", self.tag)
+        } else if reason.is_temporary() {
+            format!(
+                "{} NO [UNAVAILABLE] Authentication temporarily unavailable",
+                self.tag
+            )
+        } else {
+            format!(
+                "{} NO [AUTHENTICATIONFAILED] Authentication failed",
+                self.tag
+            )
+        }
+    }
+}
+
+/// Start the client side of an `AUTHENTICATE` exchange
+///
+/// Set `sasl_ir` if the server advertised the `SASL-IR` capability.
+pub fn client(session: Session, tag: impl Into<String>, sasl_ir: bool) -> Client<Imap> {
+    Client::new(
+        session,
+        Imap {
+            tag: tag.into(),
+            sasl_ir,
+        },
+    )
+}
+
+/// Start the server side of an `AUTHENTICATE` exchange for the command tagged with `tag`
+pub fn server(session: Session, tag: impl Into<String>) -> Server<Imap> {
+    Server::new(
+        session,
+        Imap {
+            tag: tag.into(),
+            sasl_ir: true,
+        },
+    )
+}
+
+/// Parse a full `<tag> AUTHENTICATE <mechanism> [<initial-response>]` command line
+pub fn parse_authenticate(line: &str) -> Option<AuthCommand<'_>> {
+    let (tag, command) = line.split_at(line.find(' ')?);
+    let arguments = strip_command(&command[1..], "AUTHENTICATE")?;
+    AuthCommand::parse(Some(tag), arguments)
+}
+
+#[cfg(all(test, feature = "plain"))]
+mod tests {
+    use super::*;
+    use crate::callback::Callback;
+    use crate::error::SessionError;
+    use crate::property::{AuthId, Password};
+    use crate::protocols::line::{ClientAction, ServerAction};
+    use crate::session::SessionData;
+    use crate::validate::{validations, Validation};
+    use crate::{Mechname, SASL};
+    use std::sync::Arc;
+
+    struct CB;
+    impl Callback for CB {
+        fn validate(
+            &self,
+            session: &mut SessionData,
+            validation: Validation,
+            _mechanism: &Mechname,
+        ) -> Result<(), SessionError> {
+            match validation {
+                validations::SIMPLE => {
+                    let password = session
+                        .get_property::<Password>()
+                        .ok_or_else(SessionError::no_property::<Password>)?;
+                    if password.as_str() == "secret" {
+                        Ok(())
+                    } else {
+                        Err(SessionError::AuthenticationFailure)
+                    }
+                }
+                _ => Err(SessionError::no_validate(validation)),
+            }
+        }
+    }
+
+    fn plain_client(sasl: &SASL, password: &str, sasl_ir: bool) -> Client<Imap> {
+        let mut session = sasl.client_start(Mechname::new(b"PLAIN").unwrap()).unwrap();
+        session.set_property::<AuthId>(Arc::new("user".to_string()));
+        session.set_property::<Password>(Arc::new(password.to_string()));
+        client(session, "a001", sasl_ir)
+    }
+
+    fn plain_server(sasl: &SASL, line: &str) -> (Server<Imap>, ServerAction) {
+        let command = parse_authenticate(line).unwrap();
+        assert_eq!(command.tag, Some("a001"));
+        let mechanism = command.mechanism.to_ascii_uppercase();
+        let session = sasl
+            .server_start(Mechname::new(mechanism.as_bytes()).unwrap())
+            .unwrap();
+        let mut server = server(session, "a001");
+        let action = server.start(command.initial_response);
+        (server, action)
+    }
+
+    #[test]
+    fn sasl_ir() {
+        let mut sasl = SASL::new();
+        sasl.install_callback(Arc::new(CB));
+
+        let mut client = plain_client(&sasl, "secret", true);
+        let command = client.start().unwrap();
+        assert_eq!(command, "a001 AUTHENTICATE PLAIN AHVzZXIAc2VjcmV0");
+
+        let (_, action) = plain_server(&sasl, &command);
+        let line = match action {
+            ServerAction::Success(line) => line,
+            other => panic!("expected success, got {:?}", other),
+        };
+        assert_eq!(line, "a001 OK AUTHENTICATE completed");
+        assert!(matches!(
+            client.handle_line(&line),
+            Ok(ClientAction::Success)
+        ));
+    }
+
+    #[test]
+    fn without_sasl_ir() {
+        let mut sasl = SASL::new();
+        sasl.install_callback(Arc::new(CB));
+
+        let mut client = plain_client(&sasl, "wrong", false);
+        let command = client.start().unwrap();
+        assert_eq!(command, "a001 AUTHENTICATE PLAIN");
+
+        let (mut server, action) = plain_server(&sasl, &command);
+        let line = match action {
+            ServerAction::Continue(line) => line,
+            other => panic!("expected continuation, got {:?}", other),
+        };
+        assert_eq!(line, "+ ");
+
+        let response = match client.handle_line(&line).unwrap() {
+            ClientAction::Respond(response) => response,
+            other => panic!("expected response, got {:?}", other),
+        };
+        let line = match server.handle_line(&response) {
+            ServerAction::Failure(line, FailureReason::Session(_)) => line,
+            other => panic!("expected failure, got {:?}", other),
+        };
+        assert_eq!(line, "a001 NO [AUTHENTICATIONFAILED] Authentication failed");
+        match client.handle_line(&line).unwrap() {
+            ClientAction::Failure(text) => {
+                assert_eq!(text, "[AUTHENTICATIONFAILED] Authentication failed")
+            }
+            other => panic!("expected failure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn empty_and_cancelled() {
+        let sasl = SASL::new();
+
+        // An empty initial response is passed to the mechanism, which for PLAIN asks for the
+        // credentials again
+        let (_, action) = plain_server(&sasl, "a001 authenticate plain =");
+        assert!(matches!(action, ServerAction::Continue(line) if line == "+ "));
+
+        let (mut server, _) = plain_server(&sasl, "a001 AUTHENTICATE PLAIN");
+        match server.handle_line("*") {
+            ServerAction::Failure(line, FailureReason::Cancelled) => {
+                assert_eq!(line, "a001 BAD AUTHENTICATE cancelled")
+            }
+            other => panic!("expected cancellation, got {:?}", other),
+        }
+
+        let (_, action) = plain_server(&sasl, "a001 AUTHENTICATE PLAIN !!!");
+        assert!(matches!(
+            action,
+            ServerAction::Failure(_, FailureReason::InvalidEncoding)
+        ));
+    }
+
+    #[test]
+    fn premature_success() {
+        let sasl = SASL::new();
+        let mut client = plain_client(&sasl, "secret", false);
+        client.start().unwrap();
+        assert!(client.handle_line("a001 OK done").is_err());
+        assert!(client.handle_line("* CAPABILITY IMAP4rev1").is_err());
+    }
+}
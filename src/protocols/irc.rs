@@ -0,0 +1,455 @@
+//! IRCv3 `AUTHENTICATE` ([IRCv3 SASL 3.1](https://ircv3.net/specs/extensions/sasl-3.1))
+//!
+//! *requires feature `protocol_irc`*
+//!
+//! IRC messages are limited in length, so SASL data is base64-encoded and split into chunks of
+//! [`CHUNK_SIZE`] bytes, each sent as the parameter of an `AUTHENTICATE` message. A chunk shorter
+//! than that ends the message; if the encoded data is a multiple of `CHUNK_SIZE` long (including
+//! empty data) an additional `AUTHENTICATE +` ends it instead. Either side can abort the exchange
+//! by sending `AUTHENTICATE *`.
+//!
+//! [`encode`] and [`Decoder`] implement this chunking on its own, while [`Authenticate`] uses
+//! them to drive a [`Session`] through a complete exchange. All functions in this module take and
+//! return only the parameter of the `AUTHENTICATE` message; constructing and parsing the full
+//! IRC message including any prefix and the numeric replies ending an exchange is left to the
+//! caller as those depend on the connection state.
+
+use crate::error::SessionError;
+use crate::session::{Session, Side, Step};
+use std::fmt::{Display, Formatter};
+
+/// Maximum length of a single chunk of base64-encoded data
+pub const CHUNK_SIZE: usize = 400;
+
+/// Default limit for the length of a reassembled message before decoding, see
+/// [`Decoder::with_limit`]
+pub const DEFAULT_LIMIT: usize = 16 * 1024;
+
+/// `RPL_SASLSUCCESS`, sent by a server after a successful exchange
+pub const RPL_SASLSUCCESS: &str = "903";
+/// `ERR_SASLFAIL`, sent by a server if an exchange failed
+pub const ERR_SASLFAIL: &str = "904";
+/// `ERR_SASLTOOLONG`, sent by a server if a chunk or message was too long
+pub const ERR_SASLTOOLONG: &str = "905";
+/// `ERR_SASLABORTED`, sent by a server if the client aborted an exchange
+pub const ERR_SASLABORTED: &str = "906";
+
+#[derive(Debug)]
+pub enum IrcError {
+    /// A chunk was longer than [`CHUNK_SIZE`] or the reassembled message exceeded the limit
+    TooLong,
+    /// The reassembled message is not valid base64
+    InvalidEncoding(base64::DecodeError),
+    /// An `AUTHENTICATE` message was received that isn't valid at this point in the exchange
+    UnexpectedMessage,
+    /// The mechanism failed
+    Session(SessionError),
+}
+
+impl IrcError {
+    /// The numeric a server should reply with to report this error
+    pub fn numeric(&self) -> &'static str {
+        match self {
+            Self::TooLong => ERR_SASLTOOLONG,
+            _ => ERR_SASLFAIL,
+        }
+    }
+}
+
+impl Display for IrcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLong => f.write_str("SASL message too long"),
+            Self::InvalidEncoding(source) => {
+                write!(f, "failed to decode base64-encoded message: {}", source)
+            }
+            Self::UnexpectedMessage => f.write_str("unexpected AUTHENTICATE message"),
+            Self::Session(source) => Display::fmt(source, f),
+        }
+    }
+}
+
+impl std::error::Error for IrcError {}
+
+impl From<SessionError> for IrcError {
+    fn from(source: SessionError) -> Self {
+        Self::Session(source)
+    }
+}
+
+/// Base64-encode `data` and split it into `AUTHENTICATE` parameters
+///
+/// Empty data results in a single `+`.
+pub fn encode(data: &[u8]) -> Vec<String> {
+    let encoded = base64::encode(data);
+    // base64 is pure ASCII so splitting at any byte offset is fine
+    let mut chunks: Vec<String> = encoded
+        .as_bytes()
+        .chunks(CHUNK_SIZE)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect();
+    // A message ending on a full chunk needs to be terminated explicitly
+    match chunks.last() {
+        Some(last) if last.len() < CHUNK_SIZE => {}
+        _ => chunks.push("+".to_string()),
+    }
+    chunks
+}
+
+#[derive(Debug, Eq, PartialEq)]
+/// Result of passing a chunk to the [`Decoder`]
+pub enum Chunk {
+    /// The message continues in further chunks
+    Partial,
+    /// The message is complete
+    Complete(Vec<u8>),
+    /// The other side aborted the exchange
+    Aborted,
+}
+
+#[derive(Debug)]
+/// Reassembles chunked `AUTHENTICATE` parameters into messages
+pub struct Decoder {
+    buffer: String,
+    limit: usize,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::with_limit(DEFAULT_LIMIT)
+    }
+
+    /// Construct a decoder rejecting messages longer than `limit` bytes before decoding
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            buffer: String::new(),
+            limit,
+        }
+    }
+
+    /// Add the parameter of a received `AUTHENTICATE` message
+    ///
+    /// After an error the partial message is discarded.
+    pub fn push(&mut self, param: &str) -> Result<Chunk, IrcError> {
+        if param == "*" {
+            self.buffer.clear();
+            return Ok(Chunk::Aborted);
+        }
+        if param != "+" {
+            if param.len() > CHUNK_SIZE || self.buffer.len() + param.len() > self.limit {
+                self.buffer.clear();
+                return Err(IrcError::TooLong);
+            }
+            self.buffer.push_str(param);
+            if param.len() == CHUNK_SIZE {
+                return Ok(Chunk::Partial);
+            }
+        }
+        let message = std::mem::take(&mut self.buffer);
+        base64::decode(&message)
+            .map(Chunk::Complete)
+            .map_err(IrcError::InvalidEncoding)
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+/// What to do after handling an `AUTHENTICATE` message
+pub enum Action {
+    /// The message continues, wait for the next chunk
+    Partial,
+    /// Send each of these as the parameter of an `AUTHENTICATE` message
+    Respond(Vec<String>),
+    /// The server side completed successfully and should send [`RPL_SASLSUCCESS`]
+    Success,
+    /// The other side aborted the exchange. A server should reply with [`ERR_SASLABORTED`].
+    Aborted,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum State {
+    Initial,
+    Running,
+    /// The server completed with additional data that was sent as a challenge, the client has
+    /// to confirm with an empty response.
+    Confirming,
+    Completed,
+    /// The exchange completed successfully
+    Finished,
+    /// The exchange failed or was aborted
+    Failed,
+}
+
+/// Drives a [`Session`] through an `AUTHENTICATE` exchange
+pub struct Authenticate {
+    session: Session,
+    side: Side,
+    decoder: Decoder,
+    state: State,
+}
+
+impl Authenticate {
+    pub fn client(session: Session) -> Self {
+        Self::new(session, Side::Client)
+    }
+
+    pub fn server(session: Session) -> Self {
+        Self::new(session, Side::Server)
+    }
+
+    fn new(session: Session, side: Side) -> Self {
+        Self {
+            session,
+            side,
+            decoder: Decoder::new(),
+            state: State::Initial,
+        }
+    }
+
+    /// Replace the decoder, e.g. with one using a different limit
+    pub fn with_decoder(mut self, decoder: Decoder) -> Self {
+        self.decoder = decoder;
+        self
+    }
+
+    /// Start the exchange
+    ///
+    /// A client sends the returned mechanism name as parameter of the initial `AUTHENTICATE`
+    /// message. A server calls this after receiving that message and sends the returned
+    /// parameters; an empty challenge (`+`) if the client has to send the first data.
+    pub fn start(&mut self) -> Result<Vec<String>, IrcError> {
+        if self.state != State::Initial {
+            return Err(IrcError::UnexpectedMessage);
+        }
+        match self.side {
+            Side::Client => Ok(vec![self.session.get_mechname().to_string()]),
+            Side::Server if self.session.are_we_first() => {
+                self.step(None).map(|action| match action {
+                    Action::Respond(params) => params,
+                    _ => Vec::new(),
+                })
+            }
+            Side::Server => {
+                self.state = State::Running;
+                Ok(encode(&[]))
+            }
+        }
+    }
+
+    /// Handle the parameter of a received `AUTHENTICATE` message
+    ///
+    /// If this returns an error a server should reply with [`IrcError::numeric`], a client should
+    /// abort the exchange using [`Authenticate::abort`].
+    pub fn handle(&mut self, param: &str) -> Result<Action, IrcError> {
+        let input = match self.decoder.push(param) {
+            Ok(Chunk::Partial) => return Ok(Action::Partial),
+            Ok(Chunk::Aborted) => {
+                self.state = State::Failed;
+                return Ok(Action::Aborted);
+            }
+            Ok(Chunk::Complete(input)) => input,
+            Err(error) => {
+                self.state = State::Failed;
+                return Err(error);
+            }
+        };
+
+        match (self.side, &self.state) {
+            // The server answers the mechanism selection with an empty challenge if the client
+            // has to send the first data. That is no input to the mechanism.
+            (Side::Client, State::Initial) if self.session.are_we_first() && input.is_empty() => {
+                self.step(None)
+            }
+            (Side::Client, State::Initial) | (_, State::Running) => self.step(Some(&input)),
+            (Side::Server, State::Confirming) if input.is_empty() => {
+                self.state = State::Finished;
+                Ok(Action::Success)
+            }
+            _ => {
+                self.state = State::Failed;
+                Err(IrcError::UnexpectedMessage)
+            }
+        }
+    }
+
+    /// Abort the exchange, returning the parameter to send
+    pub fn abort(&mut self) -> String {
+        self.state = State::Failed;
+        "*".to_string()
+    }
+
+    /// Returns true if the mechanism completed successfully on this side
+    ///
+    /// A client should only consider an [`RPL_SASLSUCCESS`] valid if this returns true, otherwise
+    /// the server may not have proven its identity yet. After an error or abort this always
+    /// returns false.
+    pub fn is_complete(&self) -> bool {
+        matches!(self.state, State::Completed | State::Finished)
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+
+    fn step(&mut self, input: Option<&[u8]>) -> Result<Action, IrcError> {
+        let mut output = Vec::new();
+        let step = match self.session.step(input, &mut output) {
+            Ok(step) => step,
+            Err(error) => {
+                self.state = State::Failed;
+                return Err(error.into());
+            }
+        };
+        match (self.side, step) {
+            (_, Step::NeedsMore(_)) => {
+                self.state = State::Running;
+                // Every message has to be answered, so no data is sent as empty data
+                Ok(Action::Respond(encode(&output)))
+            }
+            (Side::Client, Step::Done(written)) => {
+                self.state = State::Completed;
+                Ok(written.map_or(Action::Respond(encode(&[])), |_| {
+                    Action::Respond(encode(&output))
+                }))
+            }
+            // Additional data on success is sent as a final challenge. Empty additional data is
+            // indistinguishable from none at all in that case and skipped to save the round trip.
+            (Side::Server, Step::Done(Some(_))) if !output.is_empty() => {
+                self.state = State::Confirming;
+                Ok(Action::Respond(encode(&output)))
+            }
+            (Side::Server, Step::Done(_)) => {
+                self.state = State::Finished;
+                Ok(Action::Success)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunking() {
+        assert_eq!(encode(b""), vec!["+"]);
+        assert_eq!(encode(b"foo"), vec!["Zm9v"]);
+
+        // 300 bytes encode to exactly 400 bytes of base64
+        let data = vec![0x55u8; 300];
+        let chunks = encode(&data);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].len(), CHUNK_SIZE);
+        assert_eq!(chunks[1], "+");
+
+        let data = vec![0xAAu8; 1000];
+        let chunks = encode(&data);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks[3].len() < CHUNK_SIZE);
+
+        let mut decoder = Decoder::new();
+        for chunk in &chunks[..3] {
+            assert_eq!(decoder.push(chunk).unwrap(), Chunk::Partial);
+        }
+        assert_eq!(decoder.push(&chunks[3]).unwrap(), Chunk::Complete(data));
+        assert_eq!(decoder.push("+").unwrap(), Chunk::Complete(Vec::new()));
+    }
+
+    #[test]
+    fn limits() {
+        let mut decoder = Decoder::with_limit(CHUNK_SIZE);
+        let chunk = "A".repeat(CHUNK_SIZE);
+        assert!(matches!(
+            decoder.push(&format!("{}A", chunk)),
+            Err(IrcError::TooLong)
+        ));
+        assert_eq!(decoder.push(&chunk).unwrap(), Chunk::Partial);
+        assert!(matches!(decoder.push("AAAA"), Err(IrcError::TooLong)));
+        assert_eq!(decoder.push("*").unwrap(), Chunk::Aborted);
+        assert!(matches!(
+            decoder.push("!!!!"),
+            Err(IrcError::InvalidEncoding(_))
+        ));
+    }
+
+    #[cfg(feature = "plain")]
+    #[test]
+    fn plain_exchange() {
+        use crate::mechname::Mechname;
+        use crate::property::{AuthId, Password};
+        use crate::SASL;
+        use std::sync::Arc;
+
+        let sasl = SASL::new();
+        let mut session = sasl.client_start(Mechname::new(b"PLAIN").unwrap()).unwrap();
+        session.set_property::<AuthId>(Arc::new("user".to_string()));
+        session.set_property::<Password>(Arc::new("secret".to_string()));
+        let mut client = Authenticate::client(session);
+        assert_eq!(client.start().unwrap(), vec!["PLAIN"]);
+
+        let session = sasl.server_start(Mechname::new(b"PLAIN").unwrap()).unwrap();
+        let mut server = Authenticate::server(session);
+        assert_eq!(server.start().unwrap(), vec!["+"]);
+
+        assert_eq!(
+            client.handle("+").unwrap(),
+            Action::Respond(vec!["AHVzZXIAc2VjcmV0".to_string()])
+        );
+        assert!(client.is_complete());
+
+        // No validation callback is installed so the server fails
+        let error = server.handle("AHVzZXIAc2VjcmV0").unwrap_err();
+        assert_eq!(error.numeric(), ERR_SASLFAIL);
+        assert!(!server.is_complete());
+
+        // Data after the client completed is an error and the exchange is no longer complete
+        assert!(matches!(
+            client.handle("Zm9v"),
+            Err(IrcError::UnexpectedMessage)
+        ));
+        assert!(!client.is_complete());
+    }
+
+    #[cfg(feature = "scram-sha-2")]
+    #[test]
+    fn failed_server_verification() {
+        use crate::mechname::Mechname;
+        use crate::property::{AuthId, Password};
+        use crate::SASL;
+        use std::sync::Arc;
+
+        let sasl = SASL::new();
+        let mut session = sasl
+            .client_start(Mechname::new(b"SCRAM-SHA-256").unwrap())
+            .unwrap();
+        session.set_property::<AuthId>(Arc::new("user".to_string()));
+        session.set_property::<Password>(Arc::new("secret".to_string()));
+        let mut client = Authenticate::client(session);
+        client.start().unwrap();
+        let client_first = match client.handle("+").unwrap() {
+            Action::Respond(params) => base64::decode(&params[0]).unwrap(),
+            action => panic!("unexpected action {:?}", action),
+        };
+        let nonce = String::from_utf8(client_first).unwrap();
+        let nonce = &nonce[nonce.find("r=").unwrap() + 2..];
+        let server_first = format!("r={}server,s=c2FsdA==,i=4096", nonce);
+        assert!(matches!(
+            client.handle(&base64::encode(server_first)).unwrap(),
+            Action::Respond(_)
+        ));
+        assert!(!client.is_complete());
+
+        // A server final message with a bad proof fails the client
+        assert!(client.handle(&base64::encode("v=AAAA")).is_err());
+        assert!(!client.is_complete());
+    }
+}
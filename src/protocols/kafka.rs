@@ -0,0 +1,653 @@
+//! Kafka `SaslHandshake` and `SaslAuthenticate` ([KIP-43](https://cwiki.apache.org/confluence/display/KAFKA/KIP-43%3A+Kafka+SASL+enhancements), [KIP-152](https://cwiki.apache.org/confluence/display/KAFKA/KIP-152+-+Improve+diagnostics+for+SASL+authentication+failures), [KIP-368](https://cwiki.apache.org/confluence/display/KAFKA/KIP-368%3A+Allow+SASL+Connections+to+Periodically+Re-Authenticate))
+//!
+//! *requires feature `protocol_kafka`*
+//!
+//! A Kafka client selects a mechanism with a `SaslHandshake` request, after which SASL tokens
+//! are exchanged in the `auth_bytes` of `SaslAuthenticate` requests and responses. There is no
+//! separate success message: the exchange ends with the response to the last token, which
+//! carries any additional data and, since version 1, the lifetime of the authenticated session
+//! after which the client has to re-authenticate.
+//!
+//! Older clients use version 0 of `SaslHandshake` and then send the tokens without any Kafka
+//! framing, each prefixed only by its length as a 4-byte big-endian integer, see
+//! [`encode_token`] and [`decode_token`]. The oldest clients only supported GSSAPI and skip the
+//! handshake altogether, starting with the raw GSSAPI token; [`is_legacy_gssapi`] detects this
+//! on a broker. In both legacy modes a failed authentication can not be reported, the broker
+//! closes the connection instead.
+//!
+//! The structs in here can be encoded and decoded as the bodies of the non-flexible request and
+//! response versions 0 and 1. Building the request and response headers is left to the caller,
+//! as is the flexible version 2 encoding; Kafka libraries usually bring their own codec for
+//! those and only need the types and the [`Client`] and [`Server`] driving the session.
+
+use crate::error::SessionError;
+use crate::mechname::Mechname;
+use crate::protocols::ClientExchange;
+use crate::session::{Outcome, Session};
+use crate::SASL;
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter};
+
+/// API key of `SaslHandshake`
+pub const SASL_HANDSHAKE: i16 = 17;
+/// API key of `ApiVersions`, which clients may send before the `SaslHandshake`
+pub const API_VERSIONS: i16 = 18;
+/// API key of `SaslAuthenticate`
+pub const SASL_AUTHENTICATE: i16 = 36;
+
+/// Error code indicating success
+pub const NONE: i16 = 0;
+/// Error code sent if the requested mechanism is not enabled
+pub const UNSUPPORTED_SASL_MECHANISM: i16 = 33;
+/// Error code sent if a request is not valid in the current state of the exchange
+pub const ILLEGAL_SASL_STATE: i16 = 34;
+/// Error code sent if the authentication failed
+pub const SASL_AUTHENTICATION_FAILED: i16 = 58;
+
+/// Default limit for the length of a legacy token, see [`decode_token`]
+pub const DEFAULT_TOKEN_LIMIT: usize = 512 * 1024;
+
+#[derive(Debug)]
+pub enum KafkaError {
+    /// A request or response body could not be decoded
+    Malformed,
+    /// The version of a request or response is not supported
+    UnsupportedVersion(i16),
+    /// A legacy token exceeded the length limit
+    TooLong,
+    /// The other party rejected the exchange with the given error code and message
+    Rejected {
+        error_code: i16,
+        message: Option<String>,
+    },
+    /// The other party sent a message that isn't valid at this point in the exchange
+    UnexpectedMessage,
+    /// The mechanism failed
+    Session(SessionError),
+}
+
+impl Display for KafkaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed => f.write_str("malformed SASL request or response"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported version {}", version),
+            Self::TooLong => f.write_str("SASL token too long"),
+            Self::Rejected {
+                error_code,
+                message: Some(message),
+            } => write!(f, "rejected with error code {}: {}", error_code, message),
+            Self::Rejected { error_code, .. } => {
+                write!(f, "rejected with error code {}", error_code)
+            }
+            Self::UnexpectedMessage => f.write_str("unexpected SASL message"),
+            Self::Session(source) => Display::fmt(source, f),
+        }
+    }
+}
+
+impl std::error::Error for KafkaError {}
+
+impl From<SessionError> for KafkaError {
+    fn from(source: SessionError) -> Self {
+        Self::Session(source)
+    }
+}
+
+/// Prefix a token with its length for legacy exchanges
+pub fn encode_token(token: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(token.len() + 4);
+    out.extend_from_slice(&(token.len() as u32).to_be_bytes());
+    out.extend_from_slice(token);
+    out
+}
+
+/// Decode a length-prefixed legacy token from the start of `buf`
+///
+/// Returns the token and the number of bytes it took up in `buf`, or `None` if `buf` does not
+/// contain the complete token yet. Tokens longer than `limit` are rejected before waiting for
+/// the rest of them.
+pub fn decode_token(buf: &[u8], limit: usize) -> Result<Option<(&[u8], usize)>, KafkaError> {
+    if buf.len() < 4 {
+        return Ok(None);
+    }
+    let len = u32::from_be_bytes(buf[..4].try_into().unwrap()) as usize;
+    if len > limit {
+        return Err(KafkaError::TooLong);
+    }
+    Ok(buf.get(4..4 + len).map(|token| (token, len + 4)))
+}
+
+/// Check whether the first request on a connection is a raw GSSAPI token
+///
+/// `request` is the request without its size prefix. Clients predating `SaslHandshake` start
+/// GSSAPI right away, so anything but a `SaslHandshake` or `ApiVersions` request has to be
+/// such a token.
+pub fn is_legacy_gssapi(request: &[u8]) -> bool {
+    match request.get(..2) {
+        Some(key) => {
+            let key = i16::from_be_bytes(key.try_into().unwrap());
+            key != SASL_HANDSHAKE && key != API_VERSIONS
+        }
+        None => true,
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], KafkaError> {
+        if self.0.len() < len {
+            return Err(KafkaError::Malformed);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn i16(&mut self) -> Result<i16, KafkaError> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, KafkaError> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64, KafkaError> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn nullable_string(&mut self) -> Result<Option<String>, KafkaError> {
+        match self.i16()? {
+            -1 => Ok(None),
+            len if len >= 0 => {
+                let bytes = self.take(len as usize)?;
+                let s = std::str::from_utf8(bytes).map_err(|_| KafkaError::Malformed)?;
+                Ok(Some(s.to_string()))
+            }
+            _ => Err(KafkaError::Malformed),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, KafkaError> {
+        self.nullable_string()?.ok_or(KafkaError::Malformed)
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, KafkaError> {
+        match self.i32()? {
+            len if len >= 0 => Ok(self.take(len as usize)?.to_vec()),
+            _ => Err(KafkaError::Malformed),
+        }
+    }
+
+    fn finish<T>(self, value: T) -> Result<T, KafkaError> {
+        if self.0.is_empty() {
+            Ok(value)
+        } else {
+            Err(KafkaError::Malformed)
+        }
+    }
+}
+
+fn put_nullable_string(out: &mut Vec<u8>, s: Option<&str>) {
+    match s {
+        Some(s) => {
+            out.extend_from_slice(&(s.len() as i16).to_be_bytes());
+            out.extend_from_slice(s.as_bytes());
+        }
+        None => out.extend_from_slice(&(-1i16).to_be_bytes()),
+    }
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as i32).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn check_version(version: i16) -> Result<(), KafkaError> {
+    if version == 0 || version == 1 {
+        Ok(())
+    } else {
+        Err(KafkaError::UnsupportedVersion(version))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// `SaslHandshake` request, selecting a mechanism
+///
+/// The body is the same for versions 0 and 1. Version 0 means the tokens will be sent without
+/// `SaslAuthenticate` framing.
+pub struct SaslHandshakeRequest {
+    pub mechanism: String,
+}
+
+impl SaslHandshakeRequest {
+    pub fn decode(body: &[u8]) -> Result<Self, KafkaError> {
+        let mut reader = Reader(body);
+        let mechanism = reader.string()?;
+        reader.finish(Self { mechanism })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        put_nullable_string(&mut out, Some(&self.mechanism));
+        out
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// `SaslHandshake` response, listing the enabled mechanisms
+pub struct SaslHandshakeResponse {
+    pub error_code: i16,
+    pub mechanisms: Vec<String>,
+}
+
+impl SaslHandshakeResponse {
+    pub fn decode(body: &[u8]) -> Result<Self, KafkaError> {
+        let mut reader = Reader(body);
+        let error_code = reader.i16()?;
+        let count = reader.i32()?;
+        let mut mechanisms = Vec::new();
+        for _ in 0..count.max(0) {
+            mechanisms.push(reader.string()?);
+        }
+        reader.finish(Self {
+            error_code,
+            mechanisms,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.error_code.to_be_bytes());
+        out.extend_from_slice(&(self.mechanisms.len() as i32).to_be_bytes());
+        for mechanism in self.mechanisms.iter() {
+            put_nullable_string(&mut out, Some(mechanism));
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// `SaslAuthenticate` request, carrying a token from the client
+///
+/// The body is the same for versions 0 and 1.
+pub struct SaslAuthenticateRequest {
+    pub auth_bytes: Vec<u8>,
+}
+
+impl SaslAuthenticateRequest {
+    pub fn decode(body: &[u8]) -> Result<Self, KafkaError> {
+        let mut reader = Reader(body);
+        let auth_bytes = reader.bytes()?;
+        reader.finish(Self { auth_bytes })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        put_bytes(&mut out, &self.auth_bytes);
+        out
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// `SaslAuthenticate` response, carrying a token from the server or the reason for a failure
+pub struct SaslAuthenticateResponse {
+    pub error_code: i16,
+    pub error_message: Option<String>,
+    pub auth_bytes: Vec<u8>,
+    /// Milliseconds after which the client has to re-authenticate, or 0 if it never has to.
+    /// Only sent with version 1 or later.
+    pub session_lifetime_ms: i64,
+}
+
+impl SaslAuthenticateResponse {
+    pub fn decode(body: &[u8], version: i16) -> Result<Self, KafkaError> {
+        check_version(version)?;
+        let mut reader = Reader(body);
+        let error_code = reader.i16()?;
+        let error_message = reader.nullable_string()?;
+        let auth_bytes = reader.bytes()?;
+        let session_lifetime_ms = if version >= 1 { reader.i64()? } else { 0 };
+        reader.finish(Self {
+            error_code,
+            error_message,
+            auth_bytes,
+            session_lifetime_ms,
+        })
+    }
+
+    pub fn encode(&self, version: i16) -> Result<Vec<u8>, KafkaError> {
+        check_version(version)?;
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.error_code.to_be_bytes());
+        put_nullable_string(&mut out, self.error_message.as_deref());
+        put_bytes(&mut out, &self.auth_bytes);
+        if version >= 1 {
+            out.extend_from_slice(&self.session_lifetime_ms.to_be_bytes());
+        }
+        Ok(out)
+    }
+
+    fn error(error_code: i16, message: &str) -> Self {
+        Self {
+            error_code,
+            error_message: Some(message.to_string()),
+            auth_bytes: Vec::new(),
+            session_lifetime_ms: 0,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+/// What a client has to do after handling a `SaslAuthenticate` response
+pub enum ClientStep {
+    /// Send the request and wait for the next response
+    Send(SaslAuthenticateRequest),
+    /// The exchange completed successfully
+    ///
+    /// The client has to re-authenticate after `session_lifetime_ms` milliseconds, unless it is
+    /// 0.
+    Authenticated { session_lifetime_ms: i64 },
+}
+
+/// Client side of a Kafka SASL exchange
+pub struct Client {
+    exchange: ClientExchange,
+}
+
+impl Client {
+    pub fn new(session: Session) -> Self {
+        Self {
+            exchange: ClientExchange::new(session),
+        }
+    }
+
+    /// Generate the `SaslHandshake` request for the session's mechanism
+    pub fn handshake(&self) -> SaslHandshakeRequest {
+        SaslHandshakeRequest {
+            mechanism: self.exchange.session().get_mechname().to_string(),
+        }
+    }
+
+    /// Handle the `SaslHandshake` response, returning the first `SaslAuthenticate` request
+    pub fn handle_handshake(
+        &mut self,
+        response: &SaslHandshakeResponse,
+    ) -> Result<SaslAuthenticateRequest, KafkaError> {
+        if response.error_code != NONE {
+            return Err(KafkaError::Rejected {
+                error_code: response.error_code,
+                message: None,
+            });
+        }
+        Ok(self.initial_request()?)
+    }
+
+    /// Generate the first token without a handshake
+    ///
+    /// Only needed for legacy exchanges, see the [module documentation](self). A mechanism
+    /// where the server sends the first data starts with an empty token.
+    pub fn initial_request(&mut self) -> Result<SaslAuthenticateRequest, SessionError> {
+        let auth_bytes = if self.exchange.session().are_we_first() {
+            self.exchange.step(None)?.unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        Ok(SaslAuthenticateRequest { auth_bytes })
+    }
+
+    pub fn handle_authenticate(
+        &mut self,
+        response: &SaslAuthenticateResponse,
+    ) -> Result<ClientStep, KafkaError> {
+        if response.error_code != NONE {
+            return Err(KafkaError::Rejected {
+                error_code: response.error_code,
+                message: response.error_message.clone(),
+            });
+        }
+        if self.exchange.is_complete() {
+            // The server only acknowledges the last token
+            if !response.auth_bytes.is_empty() {
+                return Err(KafkaError::UnexpectedMessage);
+            }
+        } else if let Some(auth_bytes) = self.exchange.step(Some(&response.auth_bytes))? {
+            return Ok(ClientStep::Send(SaslAuthenticateRequest { auth_bytes }));
+        } else if !self.exchange.is_complete() {
+            return Ok(ClientStep::Send(SaslAuthenticateRequest {
+                auth_bytes: Vec::new(),
+            }));
+        }
+        Ok(ClientStep::Authenticated {
+            session_lifetime_ms: response.session_lifetime_ms,
+        })
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        self.exchange.session_mut()
+    }
+
+    pub fn into_session(self) -> Session {
+        self.exchange.into_session()
+    }
+}
+
+#[derive(Debug)]
+/// What a server has to do after handling a `SaslAuthenticate` request
+pub enum ServerStep {
+    /// Send the response and wait for the next request
+    Continue(SaslAuthenticateResponse),
+    /// The mechanism completed successfully; send the response carrying the additional data and
+    /// session lifetime
+    Authenticated(SaslAuthenticateResponse),
+    /// Send the response and close the connection; the exchange failed, with the mechanism
+    /// error if there is one
+    Failure {
+        response: SaslAuthenticateResponse,
+        error: Option<SessionError>,
+    },
+}
+
+/// Server side of a Kafka SASL exchange
+pub struct Server {
+    session: Session,
+    session_lifetime_ms: i64,
+    started: bool,
+    finished: bool,
+}
+
+impl Server {
+    /// Construct a server for a session started with the mechanism named in the
+    /// [`SaslHandshakeRequest`]
+    pub fn new(session: Session) -> Self {
+        Self {
+            session,
+            session_lifetime_ms: 0,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Handle a `SaslHandshake` request, starting a session if the mechanism is one of `enabled`
+    ///
+    /// Either way the returned response has to be sent to the client.
+    pub fn handshake<'a>(
+        sasl: &SASL,
+        enabled: impl IntoIterator<Item = &'a Mechname>,
+        request: &SaslHandshakeRequest,
+    ) -> (SaslHandshakeResponse, Option<Self>) {
+        let enabled: Vec<&Mechname> = enabled.into_iter().collect();
+        let server = enabled
+            .iter()
+            .find(|mechanism| mechanism.as_str() == request.mechanism)
+            .and_then(|mechanism| sasl.server_start(mechanism).ok())
+            .map(Self::new);
+        let error_code = if server.is_some() {
+            NONE
+        } else {
+            UNSUPPORTED_SASL_MECHANISM
+        };
+        let response = SaslHandshakeResponse {
+            error_code,
+            mechanisms: enabled
+                .iter()
+                .map(|mechanism| mechanism.as_str().to_string())
+                .collect(),
+        };
+        (response, server)
+    }
+
+    /// Set the lifetime sent to the client on success, after which it has to re-authenticate
+    ///
+    /// Defaults to 0, meaning the session never expires.
+    pub fn set_session_lifetime_ms(&mut self, session_lifetime_ms: i64) {
+        self.session_lifetime_ms = session_lifetime_ms;
+    }
+
+    pub fn handle_authenticate(&mut self, request: &SaslAuthenticateRequest) -> ServerStep {
+        if self.finished {
+            return ServerStep::Failure {
+                response: SaslAuthenticateResponse::error(
+                    ILLEGAL_SASL_STATE,
+                    "Unexpected SaslAuthenticate request",
+                ),
+                error: None,
+            };
+        }
+        // A mechanism where the server sends the first data is started by an empty token
+        let input = if !self.started && self.session.are_we_first() {
+            if !request.auth_bytes.is_empty() {
+                self.finished = true;
+                return ServerStep::Failure {
+                    response: SaslAuthenticateResponse::error(
+                        ILLEGAL_SASL_STATE,
+                        "Unexpected initial response",
+                    ),
+                    error: None,
+                };
+            }
+            None
+        } else {
+            Some(request.auth_bytes.as_slice())
+        };
+        self.started = true;
+
+        match self.session.step_outcome(input) {
+            Ok(Outcome::Continue(data)) => ServerStep::Continue(SaslAuthenticateResponse {
+                error_code: NONE,
+                error_message: None,
+                auth_bytes: data.unwrap_or_default(),
+                session_lifetime_ms: 0,
+            }),
+            Ok(Outcome::Success(data)) | Ok(Outcome::Final(data)) => {
+                self.finished = true;
+                ServerStep::Authenticated(SaslAuthenticateResponse {
+                    error_code: NONE,
+                    error_message: None,
+                    auth_bytes: data.unwrap_or_default(),
+                    session_lifetime_ms: self.session_lifetime_ms,
+                })
+            }
+            Err(error) => {
+                self.finished = true;
+                ServerStep::Failure {
+                    response: SaslAuthenticateResponse::error(
+                        SASL_AUTHENTICATION_FAILED,
+                        "Authentication failed",
+                    ),
+                    error: Some(error),
+                }
+            }
+        }
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_roundtrip() {
+        let handshake = SaslHandshakeRequest {
+            mechanism: "PLAIN".to_string(),
+        };
+        assert_eq!(handshake.encode(), b"\0\x05PLAIN");
+        assert_eq!(
+            SaslHandshakeRequest::decode(&handshake.encode()).unwrap(),
+            handshake
+        );
+
+        let response = SaslHandshakeResponse {
+            error_code: UNSUPPORTED_SASL_MECHANISM,
+            mechanisms: vec!["SCRAM-SHA-256".to_string(), "PLAIN".to_string()],
+        };
+        assert_eq!(
+            SaslHandshakeResponse::decode(&response.encode()).unwrap(),
+            response
+        );
+
+        let request = SaslAuthenticateRequest {
+            auth_bytes: b"\0user\0pencil".to_vec(),
+        };
+        assert_eq!(
+            SaslAuthenticateRequest::decode(&request.encode()).unwrap(),
+            request
+        );
+
+        let mut response = SaslAuthenticateResponse {
+            error_code: NONE,
+            error_message: None,
+            auth_bytes: b"v=dGVzdA==".to_vec(),
+            session_lifetime_ms: 3_600_000,
+        };
+        let v1 = response.encode(1).unwrap();
+        assert_eq!(SaslAuthenticateResponse::decode(&v1, 1).unwrap(), response);
+        let v0 = response.encode(0).unwrap();
+        assert_eq!(v0.len() + 8, v1.len());
+        response.session_lifetime_ms = 0;
+        assert_eq!(SaslAuthenticateResponse::decode(&v0, 0).unwrap(), response);
+        assert!(matches!(
+            response.encode(2),
+            Err(KafkaError::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn reject_malformed() {
+        assert!(SaslHandshakeRequest::decode(b"\0\x05PLA").is_err());
+        assert!(SaslHandshakeRequest::decode(b"\xff\xff").is_err());
+        assert!(SaslAuthenticateRequest::decode(b"\0\0\0\x01ab").is_err());
+        assert!(SaslAuthenticateResponse::decode(b"\0\0\xff\xff\0\0\0\0", 1).is_err());
+    }
+
+    #[test]
+    fn legacy_tokens() {
+        let encoded = encode_token(b"token");
+        assert_eq!(encoded, b"\0\0\0\x05token");
+        assert_eq!(
+            decode_token(&encoded, DEFAULT_TOKEN_LIMIT).unwrap(),
+            Some((&b"token"[..], 9))
+        );
+        assert_eq!(
+            decode_token(&encoded[..6], DEFAULT_TOKEN_LIMIT).unwrap(),
+            None
+        );
+        assert!(matches!(
+            decode_token(&encoded, 4),
+            Err(KafkaError::TooLong)
+        ));
+
+        assert!(!is_legacy_gssapi(b"\0\x11\0\x01\0\0\0\x01"));
+        assert!(!is_legacy_gssapi(b"\0\x12\0\x03\0\0\0\x01"));
+        // A GSS-API InitialContextToken starts with an ASN.1 application tag
+        assert!(is_legacy_gssapi(b"\x60\x82\x02\x0a\x06\x09"));
+    }
+}
@@ -0,0 +1,478 @@
+//! LDAP SASL bind ([RFC 4513, Section 5.2](https://www.rfc-editor.org/rfc/rfc4513#section-5.2))
+//!
+//! *requires feature `protocol_ldap`*
+//!
+//! A SASL bind is a sequence of `BindRequest`s carrying the mechanism name and optional
+//! credentials. The server replies to each with a `BindResponse`; as long as the exchange
+//! continues its result code is `saslBindInProgress` and `serverSaslCreds` carries the
+//! challenge. The final response carries the outcome and, on success, any additional data. A
+//! client aborts an exchange by sending a bind request for a different mechanism.
+//!
+//! If the mechanism negotiated a security layer it is installed right after the successful bind
+//! response and protects the whole LDAP stream from then on, see `into_security_layer` on
+//! [`Client`] and [`Server`]. Each protected buffer is prefixed
+//! by its length as a 4-byte big-endian integer, see [`SecurityLayer`].
+//!
+//! The requests and responses are represented as plain structs; BER-encoding them into the
+//! `LDAPMessage` envelope is left to the caller, which usually has an ASN.1 codec already.
+
+use crate::error::{MechanismErrorKind, SessionError};
+use crate::mechname::Mechname;
+use crate::protocols::{ClientExchange, ProtocolError};
+use crate::session::{Outcome, Session};
+use crate::SASL;
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// The `resultCode` of a `BindResponse`
+pub struct ResultCode(pub u32);
+
+impl ResultCode {
+    pub const SUCCESS: Self = Self(0);
+    pub const PROTOCOL_ERROR: Self = Self(2);
+    pub const AUTH_METHOD_NOT_SUPPORTED: Self = Self(7);
+    pub const SASL_BIND_IN_PROGRESS: Self = Self(14);
+    pub const INAPPROPRIATE_AUTHENTICATION: Self = Self(48);
+    pub const INVALID_CREDENTIALS: Self = Self(49);
+    pub const OTHER: Self = Self(80);
+}
+
+impl From<&SessionError> for ResultCode {
+    fn from(error: &SessionError) -> Self {
+        match error {
+            // The mechanism needs credentials that the client did not provide
+            SessionError::InputDataRequired => Self::INAPPROPRIATE_AUTHENTICATION,
+            SessionError::MechanismError(error) => match error.kind() {
+                MechanismErrorKind::Parse => Self::PROTOCOL_ERROR,
+                _ => Self::INVALID_CREDENTIALS,
+            },
+            SessionError::Io { .. }
+            | SessionError::NoCallback { .. }
+            | SessionError::NoValidate { .. }
+            | SessionError::NoProperty { .. } => Self::OTHER,
+            _ => Self::INVALID_CREDENTIALS,
+        }
+    }
+}
+
+/// Default limit for the length of a protected buffer, see [`SecurityLayer::with_max_buffer`]
+pub const DEFAULT_MAX_BUFFER: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub enum LdapError {
+    /// The server rejected the bind
+    Rejected {
+        result_code: ResultCode,
+        diagnostic_message: String,
+    },
+    /// The server sent a response that isn't valid at this point in the exchange
+    UnexpectedMessage,
+    /// The server indicated a successful bind before the mechanism completed
+    PrematureSuccess,
+    /// A protected buffer exceeded the length limit
+    TooLong,
+    /// The mechanism failed
+    Session(SessionError),
+}
+
+impl Display for LdapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rejected {
+                result_code,
+                diagnostic_message,
+            } => write!(
+                f,
+                "bind failed with result code {}: {}",
+                result_code.0, diagnostic_message
+            ),
+            Self::UnexpectedMessage => f.write_str("unexpected bind response"),
+            Self::PrematureSuccess => {
+                f.write_str("bind indicated as successful before the mechanism completed")
+            }
+            Self::TooLong => f.write_str("protected buffer too long"),
+            Self::Session(source) => Display::fmt(source, f),
+        }
+    }
+}
+
+impl std::error::Error for LdapError {}
+
+impl From<SessionError> for LdapError {
+    fn from(source: SessionError) -> Self {
+        Self::Session(source)
+    }
+}
+
+impl From<ProtocolError> for LdapError {
+    fn from(error: ProtocolError) -> Self {
+        match error {
+            ProtocolError::UnexpectedMessage => Self::UnexpectedMessage,
+            ProtocolError::PrematureSuccess => Self::PrematureSuccess,
+            ProtocolError::Session(source) => Self::Session(source),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// A `BindRequest` using SASL authentication
+pub struct BindRequest {
+    /// The `name` of the request, usually empty for SASL binds
+    pub name: String,
+    pub mechanism: String,
+    pub credentials: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// A `BindResponse`
+pub struct BindResponse {
+    pub result_code: ResultCode,
+    pub diagnostic_message: String,
+    pub server_sasl_creds: Option<Vec<u8>>,
+}
+
+impl BindResponse {
+    fn error(result_code: ResultCode, diagnostic_message: &str) -> Self {
+        Self {
+            result_code,
+            diagnostic_message: diagnostic_message.to_string(),
+            server_sasl_creds: None,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+/// What a client has to do after handling a bind response
+pub enum ClientStep {
+    /// Send the bind request and wait for the next response
+    Bind(BindRequest),
+    /// The bind completed successfully
+    Bound,
+}
+
+/// Client side of a SASL bind
+pub struct Client {
+    exchange: ClientExchange,
+}
+
+impl Client {
+    pub fn new(session: Session) -> Self {
+        Self {
+            exchange: ClientExchange::new(session),
+        }
+    }
+
+    /// Generate the first `BindRequest`, including the initial response for mechanisms where
+    /// the client sends the first data
+    pub fn bind_request(&mut self) -> Result<BindRequest, SessionError> {
+        let credentials = if self.exchange.session().are_we_first() {
+            self.exchange.step(None)?
+        } else {
+            None
+        };
+        Ok(self.request(credentials))
+    }
+
+    pub fn handle_bind_response(
+        &mut self,
+        response: &BindResponse,
+    ) -> Result<ClientStep, LdapError> {
+        let creds = response.server_sasl_creds.as_deref();
+        match response.result_code {
+            ResultCode::SASL_BIND_IN_PROGRESS => {
+                if self.exchange.is_complete() {
+                    return Err(LdapError::UnexpectedMessage);
+                }
+                let credentials = self.exchange.step(Some(creds.unwrap_or_default()))?;
+                Ok(ClientStep::Bind(self.request(credentials)))
+            }
+            ResultCode::SUCCESS => {
+                self.exchange.success(creds)?;
+                Ok(ClientStep::Bound)
+            }
+            result_code => Err(LdapError::Rejected {
+                result_code,
+                diagnostic_message: response.diagnostic_message.clone(),
+            }),
+        }
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        self.exchange.session_mut()
+    }
+
+    pub fn into_session(self) -> Session {
+        self.exchange.into_session()
+    }
+
+    /// Returns true if the mechanism negotiated a security layer
+    pub fn has_security_layer(&self) -> bool {
+        self.exchange.session().has_security_layer()
+    }
+
+    /// Install the security layer negotiated by the mechanism after a successful bind
+    ///
+    /// Returns `None` if the mechanism did not negotiate one, in which case the connection
+    /// continues unprotected.
+    pub fn into_security_layer(self) -> Option<SecurityLayer> {
+        let session = self.exchange.into_session();
+        session
+            .has_security_layer()
+            .then(|| SecurityLayer::new(session))
+    }
+
+    fn request(&self, credentials: Option<Vec<u8>>) -> BindRequest {
+        BindRequest {
+            name: String::new(),
+            mechanism: self.exchange.session().get_mechname().to_string(),
+            credentials,
+        }
+    }
+}
+
+#[derive(Debug)]
+/// What a server has to do after handling a bind request
+pub enum ServerStep {
+    /// Send the `saslBindInProgress` response and wait for the next bind request
+    InProgress(BindResponse),
+    /// The bind completed successfully; send the response and install the security layer if
+    /// the mechanism negotiated one
+    Bound(BindResponse),
+    /// Send the error response; the bind failed, with the mechanism error if there is one
+    Failure {
+        response: BindResponse,
+        error: Option<SessionError>,
+    },
+    /// The client aborted the exchange by requesting a different mechanism; the request has to
+    /// be handled as a new bind
+    Aborted,
+}
+
+/// Server side of a SASL bind
+pub struct Server {
+    session: Session,
+    started: bool,
+    finished: bool,
+}
+
+impl Server {
+    pub fn new(session: Session) -> Self {
+        Self {
+            session,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Start a server for the mechanism requested in the first bind request
+    ///
+    /// Returns the `authMethodNotSupported` response to send if the mechanism is not available.
+    pub fn start(sasl: &SASL, request: &BindRequest) -> Result<Self, BindResponse> {
+        Mechname::new(request.mechanism.as_bytes())
+            .ok()
+            .and_then(|mechanism| sasl.server_start(mechanism).ok())
+            .map(Self::new)
+            .ok_or_else(|| {
+                BindResponse::error(
+                    ResultCode::AUTH_METHOD_NOT_SUPPORTED,
+                    "SASL mechanism not supported",
+                )
+            })
+    }
+
+    pub fn handle_bind_request(&mut self, request: &BindRequest) -> ServerStep {
+        if request.mechanism != self.session.get_mechname().as_str() {
+            self.finished = true;
+            return ServerStep::Aborted;
+        }
+        if self.finished {
+            return ServerStep::Failure {
+                response: BindResponse::error(ResultCode::PROTOCOL_ERROR, "bind already completed"),
+                error: None,
+            };
+        }
+        // A mechanism where the server sends the first data is started without credentials
+        let input = if !self.started && self.session.are_we_first() {
+            if request.credentials.is_some() {
+                self.finished = true;
+                return ServerStep::Failure {
+                    response: BindResponse::error(
+                        ResultCode::PROTOCOL_ERROR,
+                        "unexpected initial credentials",
+                    ),
+                    error: None,
+                };
+            }
+            None
+        } else {
+            Some(request.credentials.as_deref().unwrap_or_default())
+        };
+        self.started = true;
+
+        match self.session.step_outcome(input) {
+            Ok(Outcome::Continue(data)) => ServerStep::InProgress(BindResponse {
+                result_code: ResultCode::SASL_BIND_IN_PROGRESS,
+                diagnostic_message: String::new(),
+                server_sasl_creds: Some(data.unwrap_or_default()),
+            }),
+            Ok(Outcome::Success(data)) | Ok(Outcome::Final(data)) => {
+                self.finished = true;
+                ServerStep::Bound(BindResponse {
+                    result_code: ResultCode::SUCCESS,
+                    diagnostic_message: String::new(),
+                    server_sasl_creds: data,
+                })
+            }
+            Err(error) => {
+                self.finished = true;
+                ServerStep::Failure {
+                    response: BindResponse::error(
+                        ResultCode::from(&error),
+                        "SASL authentication failed",
+                    ),
+                    error: Some(error),
+                }
+            }
+        }
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+
+    /// Returns true if the mechanism negotiated a security layer
+    pub fn has_security_layer(&self) -> bool {
+        self.session.has_security_layer()
+    }
+
+    /// Install the security layer negotiated by the mechanism after a successful bind
+    ///
+    /// Returns `None` if the mechanism did not negotiate one, in which case the connection
+    /// continues unprotected.
+    pub fn into_security_layer(self) -> Option<SecurityLayer> {
+        self.session
+            .has_security_layer()
+            .then(|| SecurityLayer::new(self.session))
+    }
+}
+
+/// Reassembles length-prefixed buffers from a byte stream
+#[derive(Debug)]
+struct Frames {
+    buffer: Vec<u8>,
+    max_buffer: usize,
+}
+
+impl Frames {
+    fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    fn next(&mut self) -> Result<Option<Vec<u8>>, LdapError> {
+        if self.buffer.len() < 4 {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes(self.buffer[..4].try_into().unwrap()) as usize;
+        if len > self.max_buffer {
+            return Err(LdapError::TooLong);
+        }
+        if self.buffer.len() < len + 4 {
+            return Ok(None);
+        }
+        let frame = self.buffer[4..len + 4].to_vec();
+        self.buffer.drain(..len + 4);
+        Ok(Some(frame))
+    }
+}
+
+fn frame(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 4);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+/// The security layer protecting an LDAP connection after a SASL bind
+///
+/// Outgoing LDAP messages are protected with [`SecurityLayer::wrap`]. Incoming bytes are passed
+/// to [`SecurityLayer::push`] as they are read from the connection, after which
+/// [`SecurityLayer::unwrap_next`] returns the unprotected data once a complete buffer arrived.
+/// Unprotected data is a stream of BER-encoded LDAP messages that don't necessarily align with
+/// the buffer boundaries.
+pub struct SecurityLayer {
+    session: Session,
+    frames: Frames,
+}
+
+impl SecurityLayer {
+    pub fn new(session: Session) -> Self {
+        Self::with_max_buffer(session, DEFAULT_MAX_BUFFER)
+    }
+
+    /// Construct a security layer rejecting protected buffers longer than `max_buffer` bytes,
+    /// usually the maximum buffer size negotiated by the mechanism
+    pub fn with_max_buffer(session: Session, max_buffer: usize) -> Self {
+        Self {
+            session,
+            frames: Frames {
+                buffer: Vec::new(),
+                max_buffer,
+            },
+        }
+    }
+
+    /// Protect outgoing data, returning the length-prefixed buffer to send
+    pub fn wrap(&mut self, data: &[u8]) -> Result<Vec<u8>, LdapError> {
+        Ok(frame(&self.session.encode(data)?))
+    }
+
+    /// Add bytes read from the connection
+    pub fn push(&mut self, data: &[u8]) {
+        self.frames.push(data);
+    }
+
+    /// Unprotect the next complete buffer, if one arrived
+    pub fn unwrap_next(&mut self) -> Result<Option<Vec<u8>>, LdapError> {
+        match self.frames.next()? {
+            Some(frame) => Ok(Some(self.session.decode(&frame)?.into_vec())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_reassemble() {
+        let mut frames = Frames {
+            buffer: Vec::new(),
+            max_buffer: 16,
+        };
+        let mut stream = frame(b"first");
+        stream.extend_from_slice(&frame(b""));
+        stream.extend_from_slice(&frame(b"second"));
+
+        let (head, tail) = stream.split_at(7);
+        frames.push(head);
+        assert_eq!(frames.next().unwrap(), None);
+        frames.push(tail);
+        assert_eq!(frames.next().unwrap().unwrap(), b"first");
+        assert_eq!(frames.next().unwrap().unwrap(), b"");
+        assert_eq!(frames.next().unwrap().unwrap(), b"second");
+        assert_eq!(frames.next().unwrap(), None);
+
+        frames.push(&frame(&[0; 17])[..4]);
+        assert!(matches!(frames.next(), Err(LdapError::TooLong)));
+    }
+}
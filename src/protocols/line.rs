@@ -0,0 +1,395 @@
+//! Line-based framing shared by IMAP, SMTP and POP3
+//!
+//! All three protocols frame an authentication exchange the same way: The client sends a command
+//! naming the mechanism, optionally followed by a base64-encoded initial response with `=`
+//! standing for an empty one. The server answers with continuation lines carrying base64-encoded
+//! challenges, each of which is answered by the client with a line containing the base64-encoded
+//! response or with `*` to cancel the exchange. The exchange ends with a protocol-specific status
+//! line.
+//!
+//! The protocol modules only describe their lines as a [`Dialect`] and provide constructors for
+//! [`Client`] and [`Server`] that implement the exchange itself.
+//!
+//! All lines are taken and returned without their terminating CRLF.
+
+use crate::error::SessionError;
+use crate::protocols::ProtocolError;
+use crate::session::{Session, Step};
+
+/// The status line that ended an exchange, as seen by the client
+pub enum Completion<'a> {
+    Success,
+    /// Failure, with the human-readable remainder of the status line
+    Failure(&'a str),
+}
+
+/// Description of the lines specific to a protocol
+pub trait Dialect {
+    /// Prefix of a continuation line sent by the server, including the separating space
+    const CONTINUATION: &'static str;
+
+    /// Whether the initial response may be sent as part of the authentication command
+    fn initial_response(&self) -> bool;
+
+    /// Format the authentication command
+    fn command(&self, mechanism: &str, initial_response: Option<&str>) -> String;
+
+    /// Parse a status line ending the exchange, returning `None` if `line` is not one
+    fn completion<'a>(&self, line: &'a str) -> Option<Completion<'a>>;
+
+    /// Format the status line indicating a successful authentication
+    fn success(&self) -> String;
+
+    /// Format the status line indicating a failed authentication
+    fn failure(&self, reason: &FailureReason) -> String;
+}
+
+#[derive(Debug)]
+/// What a client has to do after handling a line received from the server
+pub enum ClientAction {
+    /// Send this line to the server and pass its reply to [`Client::handle_line`]
+    Respond(String),
+    /// The server accepted the authentication and the mechanism completed successfully
+    Success,
+    /// The server rejected the authentication, with the human-readable remainder of the status
+    /// line
+    Failure(String),
+}
+
+#[derive(Debug)]
+/// What a server has to do after handling a line received from the client
+pub enum ServerAction {
+    /// Send this continuation line to the client and pass its reply to [`Server::handle_line`]
+    Continue(String),
+    /// Send this status line; the client is authenticated
+    Success(String),
+    /// Send this status line; the exchange failed for the given reason
+    Failure(String, FailureReason),
+}
+
+#[derive(Debug)]
+/// Why an exchange failed on the server side
+pub enum FailureReason {
+    /// The client cancelled the exchange by sending `*`
+    Cancelled,
+    /// The client sent data that is not valid base64
+    InvalidEncoding,
+    /// The client sent a line that is not valid at this point in the exchange
+    UnexpectedLine,
+    /// The mechanism failed
+    Session(SessionError),
+}
+
+impl FailureReason {
+    /// Returns true if the exchange failed due to a problem on the server side instead of bad
+    /// credentials or a misbehaving client, e.g. a missing callback or property.
+    pub fn is_temporary(&self) -> bool {
+        matches!(
+            self,
+            Self::Session(SessionError::Io { .. })
+                | Self::Session(SessionError::NoCallback { .. })
+                | Self::Session(SessionError::NoValidate { .. })
+                | Self::Session(SessionError::NoProperty { .. })
+        )
+    }
+
+    /// Returns true if the client sent something that doesn't fit the protocol, as opposed to a
+    /// well-formed exchange that failed.
+    pub fn is_syntax_error(&self) -> bool {
+        matches!(
+            self,
+            Self::Cancelled | Self::InvalidEncoding | Self::UnexpectedLine
+        )
+    }
+}
+
+#[derive(Debug)]
+/// A command received by a server starting an authentication exchange
+pub struct AuthCommand<'a> {
+    /// The command tag, for protocols that use them
+    pub tag: Option<&'a str>,
+    /// The mechanism name as sent by the client
+    ///
+    /// Mechanism names are case-insensitive on the wire, so this may need to be converted to
+    /// uppercase before constructing a [`Mechname`](crate::Mechname).
+    pub mechanism: &'a str,
+    /// The raw initial response, to be passed to [`Server::start`]
+    pub initial_response: Option<&'a str>,
+}
+
+impl<'a> AuthCommand<'a> {
+    /// Parse the arguments of an authentication command, i.e. everything after the command name
+    pub(crate) fn parse(tag: Option<&'a str>, arguments: &'a str) -> Option<Self> {
+        let mut parts = arguments.split(' ');
+        let mechanism = parts.next().filter(|m| !m.is_empty())?;
+        let initial_response = parts.next();
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            tag,
+            mechanism,
+            initial_response,
+        })
+    }
+}
+
+/// Split a line into its command name and arguments, matching the command case-insensitively
+pub(crate) fn strip_command<'a>(line: &'a str, command: &str) -> Option<&'a str> {
+    let (verb, rest) = line.split_at(line.find(' ')?);
+    if verb.eq_ignore_ascii_case(command) {
+        Some(&rest[1..])
+    } else {
+        None
+    }
+}
+
+fn continuation<D: Dialect>(line: &str) -> Option<&str> {
+    if line == D::CONTINUATION.trim_end() {
+        Some("")
+    } else {
+        line.strip_prefix(D::CONTINUATION)
+    }
+}
+
+/// Step the session, returning whether the mechanism completed and the base64-encoded output
+fn step(
+    session: &mut Session,
+    input: Option<&[u8]>,
+) -> Result<(bool, Option<String>), SessionError> {
+    let mut output = Vec::new();
+    let (done, written) = match session.step(input, &mut output)? {
+        Step::Done(written) => (true, written),
+        Step::NeedsMore(written) => (false, written),
+    };
+    Ok((done, written.map(|_| base64::encode(&output))))
+}
+
+enum ClientState {
+    Initial,
+    /// The initial response could not be sent with the command and has to be sent in response
+    /// to the first (empty) challenge instead.
+    Deferred {
+        response: String,
+        done: bool,
+    },
+    Running,
+    Completed,
+    Finished,
+}
+
+/// Client side of a line-based authentication exchange
+pub struct Client<D> {
+    session: Session,
+    dialect: D,
+    state: ClientState,
+}
+
+impl<D: Dialect> Client<D> {
+    pub fn new(session: Session, dialect: D) -> Self {
+        Self {
+            session,
+            dialect,
+            state: ClientState::Initial,
+        }
+    }
+
+    /// Generate the command line starting the exchange
+    ///
+    /// For mechanisms where the client sends the first data this will include the initial
+    /// response if the protocol allows for it. Otherwise it's sent in response to the first
+    /// challenge.
+    pub fn start(&mut self) -> Result<String, ProtocolError> {
+        if !matches!(self.state, ClientState::Initial) {
+            return Err(ProtocolError::UnexpectedMessage);
+        }
+        let mechanism = self.session.get_mechname();
+        if !self.session.are_we_first() {
+            self.state = ClientState::Running;
+            return Ok(self.dialect.command(mechanism, None));
+        }
+
+        let (done, response) = step(&mut self.session, None)?;
+        self.state = if done {
+            ClientState::Completed
+        } else {
+            ClientState::Running
+        };
+        match response {
+            // An empty initial response is sent as `=` to tell it apart from no initial response
+            Some(response) if self.dialect.initial_response() => {
+                let response = if response.is_empty() { "=" } else { &response };
+                Ok(self.dialect.command(mechanism, Some(response)))
+            }
+            Some(response) => {
+                self.state = ClientState::Deferred { response, done };
+                Ok(self.dialect.command(mechanism, None))
+            }
+            None => Ok(self.dialect.command(mechanism, None)),
+        }
+    }
+
+    /// Handle a continuation or status line received from the server
+    ///
+    /// If this returns an error the exchange can not be continued and has to be aborted by
+    /// sending the line returned by [`Client::cancel`] if the server is still expecting a
+    /// response.
+    pub fn handle_line(&mut self, line: &str) -> Result<ClientAction, ProtocolError> {
+        if let Some(challenge) = continuation::<D>(line) {
+            match std::mem::replace(&mut self.state, ClientState::Finished) {
+                ClientState::Deferred { response, done } => {
+                    self.state = if done {
+                        ClientState::Completed
+                    } else {
+                        ClientState::Running
+                    };
+                    Ok(ClientAction::Respond(response))
+                }
+                ClientState::Running => {
+                    let challenge = base64::decode(challenge)
+                        .map_err(|source| SessionError::Base64 { source })?;
+                    let (done, response) = step(&mut self.session, Some(&challenge))?;
+                    self.state = if done {
+                        ClientState::Completed
+                    } else {
+                        ClientState::Running
+                    };
+                    // Every challenge must be answered, so no data is sent as an empty line
+                    Ok(ClientAction::Respond(response.unwrap_or_default()))
+                }
+                _ => Err(ProtocolError::UnexpectedMessage),
+            }
+        } else if let Some(completion) = self.dialect.completion(line) {
+            let state = std::mem::replace(&mut self.state, ClientState::Finished);
+            match completion {
+                Completion::Success if matches!(state, ClientState::Completed) => {
+                    Ok(ClientAction::Success)
+                }
+                Completion::Success => Err(ProtocolError::PrematureSuccess),
+                Completion::Failure(text) => Ok(ClientAction::Failure(text.to_string())),
+            }
+        } else {
+            Err(ProtocolError::UnexpectedMessage)
+        }
+    }
+
+    /// Abort the exchange, returning the line to send to the server
+    pub fn cancel(&mut self) -> String {
+        self.state = ClientState::Finished;
+        "*".to_string()
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+}
+
+enum ServerState {
+    Initial,
+    Running,
+    /// The mechanism completed with additional data that was sent as a challenge, the client has
+    /// to confirm with an empty response.
+    Confirming,
+    Finished,
+}
+
+/// Server side of a line-based authentication exchange
+pub struct Server<D> {
+    session: Session,
+    dialect: D,
+    state: ServerState,
+}
+
+impl<D: Dialect> Server<D> {
+    pub fn new(session: Session, dialect: D) -> Self {
+        Self {
+            session,
+            dialect,
+            state: ServerState::Initial,
+        }
+    }
+
+    /// Start the exchange with the initial response sent along with the command, if any
+    ///
+    /// `initial_response` is passed as received, i.e. base64-encoded with `=` indicating an
+    /// empty initial response.
+    pub fn start(&mut self, initial_response: Option<&str>) -> ServerAction {
+        if !matches!(self.state, ServerState::Initial) {
+            return self.fail(FailureReason::UnexpectedLine);
+        }
+        match initial_response {
+            Some("=") => self.step(Some(&[])),
+            Some(initial_response) => match base64::decode(initial_response) {
+                Ok(input) => self.step(Some(&input)),
+                Err(_) => self.fail(FailureReason::InvalidEncoding),
+            },
+            None if self.session.are_we_first() => self.step(None),
+            // The client has to send the first data but didn't. Ask for it with an empty
+            // challenge.
+            None => {
+                self.state = ServerState::Running;
+                ServerAction::Continue(D::CONTINUATION.to_string())
+            }
+        }
+    }
+
+    /// Handle a response line received from the client
+    pub fn handle_line(&mut self, line: &str) -> ServerAction {
+        match self.state {
+            ServerState::Running | ServerState::Confirming if line == "*" => {
+                self.fail(FailureReason::Cancelled)
+            }
+            ServerState::Running => match base64::decode(line) {
+                Ok(input) => self.step(Some(&input)),
+                Err(_) => self.fail(FailureReason::InvalidEncoding),
+            },
+            ServerState::Confirming if line.is_empty() => {
+                self.state = ServerState::Finished;
+                ServerAction::Success(self.dialect.success())
+            }
+            _ => self.fail(FailureReason::UnexpectedLine),
+        }
+    }
+
+    fn step(&mut self, input: Option<&[u8]>) -> ServerAction {
+        match step(&mut self.session, input) {
+            Ok((false, challenge)) => {
+                self.state = ServerState::Running;
+                ServerAction::Continue(format!(
+                    "{}{}",
+                    D::CONTINUATION,
+                    challenge.unwrap_or_default()
+                ))
+            }
+            // None of these protocols can transport additional data with the status line, so it
+            // is sent as a final challenge instead. Empty additional data is indistinguishable
+            // from none at all in that case and skipped to save the round trip.
+            Ok((true, Some(data))) if !data.is_empty() => {
+                self.state = ServerState::Confirming;
+                ServerAction::Continue(format!("{}{}", D::CONTINUATION, data))
+            }
+            Ok((true, _)) => {
+                self.state = ServerState::Finished;
+                ServerAction::Success(self.dialect.success())
+            }
+            Err(error) => self.fail(FailureReason::Session(error)),
+        }
+    }
+
+    fn fail(&mut self, reason: FailureReason) -> ServerAction {
+        self.state = ServerState::Finished;
+        ServerAction::Failure(self.dialect.failure(&reason), reason)
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+}
@@ -0,0 +1,182 @@
+//! Framing helpers for using SASL in common protocols
+//!
+//! *requires one of the `protocol_*` features, e.g. `protocol_imap`*
+//!
+//! Most protocols embed SASL the same handful of ways: a command naming the mechanism, optionally
+//! carrying an initial response, continuation messages transporting the challenges and responses
+//! and a final message indicating the outcome of the exchange. The modules in here implement the
+//! framing for specific protocols on top of [`Session::step`](crate::session::Session::step) so
+//! protocol crates don't have to. They do not perform any I/O themselves; all messages are
+//! returned to the caller to be sent over whatever transport the protocol uses.
+//!
+//! Each protocol is gated behind its own feature and none of them are enabled by default.
+//!
+//! Servers never send the error that made a mechanism fail to the client, as that could leak
+//! details such as whether a user exists. The failure messages only carry a generic description
+//! and the error is returned to the application alongside instead, e.g. for logging.
+
+use crate::error::SessionError;
+#[cfg(any(
+    feature = "protocol_http",
+    feature = "protocol_kafka",
+    feature = "protocol_ldap",
+    feature = "protocol_mongodb",
+    feature = "protocol_postgres",
+    feature = "protocol_xmpp"
+))]
+use crate::session::{Outcome, Session};
+use std::fmt::{Display, Formatter};
+
+#[cfg(any(
+    feature = "protocol_imap",
+    feature = "protocol_smtp",
+    feature = "protocol_pop3"
+))]
+pub mod line;
+
+#[cfg(feature = "protocol_http")]
+pub mod http;
+#[cfg(feature = "protocol_imap")]
+pub mod imap;
+#[cfg(feature = "protocol_irc")]
+pub mod irc;
+#[cfg(feature = "protocol_kafka")]
+pub mod kafka;
+#[cfg(feature = "protocol_ldap")]
+pub mod ldap;
+#[cfg(feature = "protocol_mongodb")]
+pub mod mongodb;
+#[cfg(feature = "protocol_pop3")]
+pub mod pop3;
+#[cfg(feature = "protocol_postgres")]
+pub mod postgres;
+#[cfg(feature = "protocol_smtp")]
+pub mod smtp;
+#[cfg(feature = "protocol_xmpp")]
+pub mod xmpp;
+
+#[derive(Debug)]
+/// Errors occurring while driving the client side of an exchange
+///
+/// On the server side errors are instead reported to the other party as part of the failure
+/// message the protocol uses.
+pub enum ProtocolError {
+    /// The other party sent a message that isn't valid at this point in the exchange
+    UnexpectedMessage,
+
+    /// The other party indicated a successful authentication before the mechanism completed,
+    /// e.g. before the server proved its identity with a mutually authenticating mechanism.
+    PrematureSuccess,
+
+    /// The mechanism itself failed
+    Session(SessionError),
+}
+
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedMessage => f.write_str("unexpected message received"),
+            Self::PrematureSuccess => {
+                f.write_str("authentication indicated as successful before the mechanism completed")
+            }
+            Self::Session(source) => Display::fmt(source, f),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+impl From<SessionError> for ProtocolError {
+    fn from(source: SessionError) -> Self {
+        Self::Session(source)
+    }
+}
+
+#[cfg(any(
+    feature = "protocol_http",
+    feature = "protocol_kafka",
+    feature = "protocol_ldap",
+    feature = "protocol_mongodb",
+    feature = "protocol_postgres",
+    feature = "protocol_xmpp"
+))]
+/// The client side of an exchange as driven by the protocol adapters
+///
+/// Tracks whether the mechanism completed, which decides whether the server indicating success
+/// can be trusted.
+pub(crate) struct ClientExchange {
+    session: Session,
+    complete: bool,
+}
+
+#[cfg(any(
+    feature = "protocol_http",
+    feature = "protocol_kafka",
+    feature = "protocol_ldap",
+    feature = "protocol_mongodb",
+    feature = "protocol_postgres",
+    feature = "protocol_xmpp"
+))]
+impl ClientExchange {
+    pub(crate) fn new(session: Session) -> Self {
+        Self {
+            session,
+            complete: false,
+        }
+    }
+
+    pub(crate) fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub(crate) fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub(crate) fn into_session(self) -> Session {
+        self.session
+    }
+
+    /// Returns true once the mechanism completed on the client side
+    pub(crate) fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Step the mechanism, returning the data to send to the server
+    pub(crate) fn step(&mut self, input: Option<&[u8]>) -> Result<Option<Vec<u8>>, SessionError> {
+        match self.session.step_outcome(input)? {
+            Outcome::Continue(data) => Ok(data),
+            Outcome::Final(data) | Outcome::Success(data) => {
+                self.complete = true;
+                Ok(data)
+            }
+        }
+    }
+
+    // Kafka has no separate success message to handle
+    #[cfg(any(
+        feature = "protocol_http",
+        feature = "protocol_ldap",
+        feature = "protocol_mongodb",
+        feature = "protocol_postgres",
+        feature = "protocol_xmpp"
+    ))]
+    /// Handle the server indicating success, with the additional data sent along if any
+    ///
+    /// The additional data is checked by the mechanism, e.g. to verify the server's identity.
+    /// Since the exchange is over at this point the mechanism has no way to send a response
+    /// anymore, so producing one is an error. The mechanism also has to have completed,
+    /// otherwise the server did not prove its identity yet.
+    pub(crate) fn success(&mut self, additional_data: Option<&[u8]>) -> Result<(), ProtocolError> {
+        if let Some(data) = additional_data {
+            if self.complete || self.step(Some(data))?.is_some() {
+                return Err(ProtocolError::UnexpectedMessage);
+            }
+        }
+        if self.complete {
+            Ok(())
+        } else {
+            Err(ProtocolError::PrematureSuccess)
+        }
+    }
+}
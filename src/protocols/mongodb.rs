@@ -0,0 +1,281 @@
+//! MongoDB `saslStart` and `saslContinue` commands ([Authentication spec](https://github.com/mongodb/specifications/blob/master/source/auth/auth.md))
+//!
+//! *requires feature `protocol_mongodb`*
+//!
+//! A MongoDB driver starts an exchange with a `saslStart` command naming the mechanism and
+//! carrying the first payload, and continues it with `saslContinue` commands referring to the
+//! `conversationId` the server assigned. Each reply carries the server's payload and a `done`
+//! flag that is set once the server considers the exchange complete.
+//!
+//! For mechanisms where the server sends additional data on success, like SCRAM, the server
+//! traditionally replies with the additional data and `done: false`, and the client has to send
+//! one more `saslContinue` with an empty payload to receive `done: true`. Setting the
+//! `skipEmptyExchange` option in `saslStart` allows the server to set `done` together with the
+//! additional data instead, saving the round trip. [`Server`] honours the option; [`Client`]
+//! handles both kinds of servers.
+//!
+//! The commands and replies are represented as plain structs; converting them to and from BSON
+//! documents is left to the caller. Payloads are the raw bytes of the BSON binary field.
+
+use crate::error::SessionError;
+use crate::protocols::{ClientExchange, ProtocolError};
+use crate::session::{Outcome, Session};
+
+/// Error code `ProtocolError`, sent if a command isn't valid at this point in the exchange
+pub const PROTOCOL_ERROR: i32 = 17;
+/// Error code `AuthenticationFailed`
+pub const AUTHENTICATION_FAILED: i32 = 18;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// The `saslStart` command
+pub struct SaslStart {
+    pub mechanism: String,
+    pub payload: Vec<u8>,
+    /// The `skipEmptyExchange` option
+    pub skip_empty_exchange: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// The `saslContinue` command
+pub struct SaslContinue {
+    pub conversation_id: i32,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// A successful reply to `saslStart` or `saslContinue`, i.e. with `ok: 1`
+pub struct SaslReply {
+    pub conversation_id: i32,
+    pub done: bool,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// A failed reply to `saslStart` or `saslContinue`, i.e. with `ok: 0`
+pub struct CommandError {
+    pub code: i32,
+    /// The `codeName`
+    pub code_name: String,
+    /// The `errmsg`
+    pub message: String,
+}
+
+impl CommandError {
+    pub fn authentication_failed() -> Self {
+        Self {
+            code: AUTHENTICATION_FAILED,
+            code_name: "AuthenticationFailed".to_string(),
+            message: "Authentication failed.".to_string(),
+        }
+    }
+
+    pub fn protocol_error(message: impl ToString) -> Self {
+        Self {
+            code: PROTOCOL_ERROR,
+            code_name: "ProtocolError".to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+/// What a client has to do after handling a reply
+pub enum ClientStep {
+    /// Send the `saslContinue` and wait for the next reply
+    Continue(SaslContinue),
+    /// The exchange completed successfully
+    Authenticated,
+}
+
+/// Client side of a MongoDB SASL conversation
+pub struct Client {
+    exchange: ClientExchange,
+    conversation_id: Option<i32>,
+}
+
+impl Client {
+    pub fn new(session: Session) -> Self {
+        Self {
+            exchange: ClientExchange::new(session),
+            conversation_id: None,
+        }
+    }
+
+    /// Generate the `saslStart` command for the session's mechanism
+    ///
+    /// Mechanisms where the server sends the first data start with an empty payload.
+    pub fn sasl_start(&mut self, skip_empty_exchange: bool) -> Result<SaslStart, SessionError> {
+        let payload = if self.exchange.session().are_we_first() {
+            self.exchange.step(None)?.unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        Ok(SaslStart {
+            mechanism: self.exchange.session().get_mechname().to_string(),
+            payload,
+            skip_empty_exchange,
+        })
+    }
+
+    /// Handle a successful reply to either `saslStart` or `saslContinue`
+    ///
+    /// Returns [`ProtocolError::PrematureSuccess`] if the server indicated it is done before the
+    /// mechanism completed, e.g. before the server proved its identity.
+    pub fn handle_reply(&mut self, reply: &SaslReply) -> Result<ClientStep, ProtocolError> {
+        match self.conversation_id {
+            Some(id) if id != reply.conversation_id => {
+                return Err(ProtocolError::UnexpectedMessage)
+            }
+            _ => self.conversation_id = Some(reply.conversation_id),
+        }
+
+        if reply.done {
+            // Once the mechanism completed only the empty exchange ending a conversation is left
+            let payload = (!self.exchange.is_complete() || !reply.payload.is_empty())
+                .then_some(reply.payload.as_slice());
+            self.exchange.success(payload)?;
+            return Ok(ClientStep::Authenticated);
+        }
+
+        let payload = if self.exchange.is_complete() {
+            if !reply.payload.is_empty() {
+                return Err(ProtocolError::UnexpectedMessage);
+            }
+            Vec::new()
+        } else {
+            self.exchange
+                .step(Some(&reply.payload))?
+                .unwrap_or_default()
+        };
+        Ok(ClientStep::Continue(SaslContinue {
+            conversation_id: reply.conversation_id,
+            payload,
+        }))
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        self.exchange.session_mut()
+    }
+
+    pub fn into_session(self) -> Session {
+        self.exchange.into_session()
+    }
+}
+
+#[derive(Debug)]
+/// What a server has to do after handling a command
+pub enum ServerStep {
+    /// Send the reply and wait for the next `saslContinue`
+    Continue(SaslReply),
+    /// The exchange completed successfully; send the reply with `done: true`
+    Authenticated(SaslReply),
+    /// Send the error reply; the exchange failed, with the mechanism error if there is one
+    Failure {
+        reply: CommandError,
+        error: Option<SessionError>,
+    },
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum State {
+    Initial,
+    Running,
+    /// The mechanism succeeded but the client still has to send the empty `saslContinue`
+    EmptyExchange,
+    Finished,
+}
+
+/// Server side of a MongoDB SASL conversation
+pub struct Server {
+    session: Session,
+    conversation_id: i32,
+    skip_empty_exchange: bool,
+    state: State,
+}
+
+impl Server {
+    /// Construct a server for a session started with the mechanism named in the [`SaslStart`]
+    pub fn new(session: Session, conversation_id: i32) -> Self {
+        Self {
+            session,
+            conversation_id,
+            skip_empty_exchange: false,
+            state: State::Initial,
+        }
+    }
+
+    pub fn handle_start(&mut self, command: &SaslStart) -> ServerStep {
+        if self.state != State::Initial || command.mechanism != self.session.get_mechname().as_str()
+        {
+            return self.fail(CommandError::protocol_error("Unexpected saslStart"), None);
+        }
+        self.skip_empty_exchange = command.skip_empty_exchange;
+        self.state = State::Running;
+        let input = if self.session.are_we_first() {
+            if !command.payload.is_empty() {
+                return self.fail(CommandError::protocol_error("Unexpected payload"), None);
+            }
+            None
+        } else {
+            Some(command.payload.as_slice())
+        };
+        self.step(input)
+    }
+
+    pub fn handle_continue(&mut self, command: &SaslContinue) -> ServerStep {
+        if command.conversation_id != self.conversation_id {
+            return self.fail(CommandError::protocol_error("Invalid conversationId"), None);
+        }
+        match self.state {
+            State::Running => self.step(Some(&command.payload)),
+            State::EmptyExchange if command.payload.is_empty() => {
+                self.state = State::Finished;
+                ServerStep::Authenticated(self.reply(true, Vec::new()))
+            }
+            _ => self.fail(
+                CommandError::protocol_error("Unexpected saslContinue"),
+                None,
+            ),
+        }
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+
+    fn reply(&self, done: bool, payload: Vec<u8>) -> SaslReply {
+        SaslReply {
+            conversation_id: self.conversation_id,
+            done,
+            payload,
+        }
+    }
+
+    fn fail(&mut self, reply: CommandError, error: Option<SessionError>) -> ServerStep {
+        self.state = State::Finished;
+        ServerStep::Failure { reply, error }
+    }
+
+    fn step(&mut self, input: Option<&[u8]>) -> ServerStep {
+        match self.session.step_outcome(input) {
+            Ok(Outcome::Continue(data)) => {
+                ServerStep::Continue(self.reply(false, data.unwrap_or_default()))
+            }
+            Ok(Outcome::Success(Some(data))) | Ok(Outcome::Final(Some(data)))
+                if !self.skip_empty_exchange =>
+            {
+                self.state = State::EmptyExchange;
+                ServerStep::Continue(self.reply(false, data))
+            }
+            Ok(Outcome::Success(data)) | Ok(Outcome::Final(data)) => {
+                self.state = State::Finished;
+                ServerStep::Authenticated(self.reply(true, data.unwrap_or_default()))
+            }
+            Err(error) => self.fail(CommandError::authentication_failed(), Some(error)),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! POP3 `AUTH` ([RFC 5034](https://tools.ietf.org/html/rfc5034))
+//!
+//! *requires feature `protocol_pop3`*
+//!
+//! Failures are reported with the response codes from
+//! [RFC 3206](https://tools.ietf.org/html/rfc3206).
+
+use crate::protocols::line::{
+    strip_command, AuthCommand, Client, Completion, Dialect, FailureReason, Server,
+};
+use crate::session::Session;
+
+/// The POP3 dialect of line-based SASL framing
+pub struct Pop3;
+
+impl Dialect for Pop3 {
+    const CONTINUATION: &'static str = "+ ";
+
+    fn initial_response(&self) -> bool {
+        true
+    }
+
+    fn command(&self, mechanism: &str, initial_response: Option<&str>) -> String {
+        match initial_response {
+            Some(initial_response) => format!("AUTH {} {}", mechanism, initial_response),
+            None => format!("AUTH {}", mechanism),
+        }
+    }
+
+    fn completion<'a>(&self, line: &'a str) -> Option<Completion<'a>> {
+        if line.starts_with("+OK") {
+            Some(Completion::Success)
+        } else {
+            line.strip_prefix("-ERR")
+                .map(|text| Completion::Failure(text.trim_start()))
+        }
+    }
+
+    fn success(&self) -> String {
+        "+OK Authentication successful".to_string()
+    }
+
+    fn failure(&self, reason: &FailureReason) -> String {
+        if let FailureReason::Cancelled = reason {
+            "-ERR Authentication cancelled".to_string()
+        } else if reason.is_syntax_error() {
+            "-ERR Invalid AUTH response".to_string()
+        } else if reason.is_temporary() {
+            "-ERR [SYS/TEMP] Authentication temporarily unavailable".to_string()
+        } else {
+            "-ERR [AUTH] Authentication failed".to_string()
+        }
+    }
+}
+
+/// Start the client side of an `AUTH` exchange
+pub fn client(session: Session) -> Client<Pop3> {
+    Client::new(session, Pop3)
+}
+
+/// Start the server side of an `AUTH` exchange
+pub fn server(session: Session) -> Server<Pop3> {
+    Server::new(session, Pop3)
+}
+
+/// Parse a full `AUTH <mechanism> [<initial-response>]` command line
+pub fn parse_auth(line: &str) -> Option<AuthCommand<'_>> {
+    AuthCommand::parse(None, strip_command(line, "AUTH")?)
+}
+
+#[cfg(all(test, feature = "plain"))]
+mod tests {
+    use super::*;
+    use crate::protocols::line::ServerAction;
+    use crate::{Mechname, SASL};
+
+    #[test]
+    fn status_lines() {
+        assert!(matches!(Pop3.completion("+OK"), Some(Completion::Success)));
+        assert!(matches!(
+            Pop3.completion("-ERR [AUTH] Authentication failed"),
+            Some(Completion::Failure("[AUTH] Authentication failed"))
+        ));
+        assert!(Pop3.completion("+ ").is_none());
+    }
+
+    #[test]
+    fn cancel() {
+        let sasl = SASL::new();
+        let session = sasl.server_start(Mechname::new(b"PLAIN").unwrap()).unwrap();
+        let mut server = server(session);
+        assert!(matches!(server.start(None), ServerAction::Continue(line) if line == "+ "));
+        assert!(matches!(
+            server.handle_line("*"),
+            ServerAction::Failure(line, FailureReason::Cancelled) if line == "-ERR Authentication cancelled"
+        ));
+        assert!(matches!(
+            server.handle_line(""),
+            ServerAction::Failure(_, FailureReason::UnexpectedLine)
+        ));
+    }
+}
@@ -0,0 +1,668 @@
+//! PostgreSQL SASL authentication ([Protocol Flow, 55.3.2](https://www.postgresql.org/docs/current/sasl-authentication.html))
+//!
+//! *requires feature `protocol_postgres`*
+//!
+//! PostgreSQL wraps SASL in binary messages of its frontend/backend protocol: the server
+//! advertises its mechanisms with `AuthenticationSASL`, the client picks one and replies with
+//! `SASLInitialResponse`, after which challenges and responses are exchanged as
+//! `AuthenticationSASLContinue` and `SASLResponse` messages. Additional data sent with the
+//! successful outcome is carried by an `AuthenticationSASLFinal` that is separate from the
+//! `AuthenticationOk` actually ending the authentication, so the server may still reject the
+//! client after the mechanism succeeded, e.g. because the authenticated role may not connect to
+//! the requested database.
+//!
+//! Messages are encoded with their type byte and length prefix. As the type byte `p` is shared by
+//! all password-type frontend messages the server has to know which one it expects, so there is
+//! a separate decode function for each of them. [`frame_len`] can be used to find the end of the
+//! next message in a receive buffer.
+
+use crate::error::{MechanismErrorKind, SessionError};
+use crate::mechname::Mechname;
+use crate::protocols::{ClientExchange, ProtocolError};
+use crate::session::{Outcome, Session};
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter};
+
+/// Type byte of all authentication request messages sent by the server
+pub const AUTHENTICATION: u8 = b'R';
+/// Type byte of `ErrorResponse`
+pub const ERROR_RESPONSE: u8 = b'E';
+/// Type byte of password-type messages sent by the client, including `SASLInitialResponse` and
+/// `SASLResponse`
+pub const PASSWORD_MESSAGE: u8 = b'p';
+
+const AUTH_OK: i32 = 0;
+const AUTH_SASL: i32 = 10;
+const AUTH_SASL_CONTINUE: i32 = 11;
+const AUTH_SASL_FINAL: i32 = 12;
+
+/// Upper limit on the length of a message, matching the limit the PostgreSQL server applies to
+/// authentication messages
+pub const MAX_MESSAGE_LEN: usize = 10_000;
+
+/// SQLSTATE `28P01`, `invalid_password`
+pub const INVALID_PASSWORD: &str = "28P01";
+/// SQLSTATE `28000`, `invalid_authorization_specification`
+pub const INVALID_AUTHORIZATION_SPECIFICATION: &str = "28000";
+/// SQLSTATE `08P01`, `protocol_violation`
+pub const PROTOCOL_VIOLATION: &str = "08P01";
+/// SQLSTATE `XX000`, `internal_error`
+pub const INTERNAL_ERROR: &str = "XX000";
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The buffer does not contain a complete message
+    Incomplete,
+    /// The length prefix is out of range or doesn't match the message
+    InvalidLength,
+    /// The message is of a different type than expected
+    UnexpectedType(u8),
+    /// An authentication request not related to SASL, e.g. `AuthenticationMD5Password`
+    UnsupportedAuthentication(i32),
+    /// The message content is malformed
+    Malformed,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Incomplete => f.write_str("incomplete message"),
+            Self::InvalidLength => f.write_str("invalid message length"),
+            Self::UnexpectedType(ty) => write!(f, "unexpected message type {:?}", *ty as char),
+            Self::UnsupportedAuthentication(code) => {
+                write!(f, "unsupported authentication request {}", code)
+            }
+            Self::Malformed => f.write_str("malformed message"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Length of the first message in `buf` including its type byte, if `buf` contains all of it
+///
+/// Returns an error if the length prefix is invalid or exceeds [`MAX_MESSAGE_LEN`].
+pub fn frame_len(buf: &[u8]) -> Result<Option<usize>, DecodeError> {
+    if buf.len() < 5 {
+        return Ok(None);
+    }
+    let len = i32::from_be_bytes(buf[1..5].try_into().unwrap());
+    if len < 4 || len as usize > MAX_MESSAGE_LEN {
+        return Err(DecodeError::InvalidLength);
+    }
+    let total = len as usize + 1;
+    Ok(if buf.len() >= total {
+        Some(total)
+    } else {
+        None
+    })
+}
+
+/// Split a complete message into its type byte and body
+fn unframe(buf: &[u8]) -> Result<(u8, &[u8]), DecodeError> {
+    match frame_len(buf)? {
+        Some(len) if len == buf.len() => Ok((buf[0], &buf[5..])),
+        Some(_) => Err(DecodeError::InvalidLength),
+        None => Err(DecodeError::Incomplete),
+    }
+}
+
+fn frame(ty: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(body.len() + 5);
+    out.push(ty);
+    out.extend_from_slice(&(body.len() as i32 + 4).to_be_bytes());
+    out.extend_from_slice(body);
+    out
+}
+
+fn authentication(code: i32, data: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(data.len() + 4);
+    body.extend_from_slice(&code.to_be_bytes());
+    body.extend_from_slice(data);
+    frame(AUTHENTICATION, &body)
+}
+
+/// Split a NUL-terminated string off the front of `buf`
+fn cstr(buf: &[u8]) -> Result<(&str, &[u8]), DecodeError> {
+    let nul = buf
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or(DecodeError::Malformed)?;
+    let s = std::str::from_utf8(&buf[..nul]).map_err(|_| DecodeError::Malformed)?;
+    Ok((s, &buf[nul + 1..]))
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// `AuthenticationSASL`, listing the mechanisms the server offers
+pub struct AuthenticationSasl {
+    pub mechanisms: Vec<String>,
+}
+
+impl AuthenticationSasl {
+    pub fn new<'a>(mechanisms: impl IntoIterator<Item = &'a Mechname>) -> Self {
+        Self {
+            mechanisms: mechanisms
+                .into_iter()
+                .map(|mechanism| mechanism.as_str().to_string())
+                .collect(),
+        }
+    }
+
+    /// Iterate over the offered mechanisms, skipping any that aren't valid mechanism names
+    ///
+    /// Can be passed directly to [`SASL::client_start_suggested`](crate::SASL::client_start_suggested).
+    pub fn mechanisms(&self) -> impl Iterator<Item = &Mechname> {
+        self.mechanisms
+            .iter()
+            .filter_map(|mechanism| Mechname::new(mechanism.as_bytes()).ok())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        for mechanism in self.mechanisms.iter() {
+            data.extend_from_slice(mechanism.as_bytes());
+            data.push(0);
+        }
+        data.push(0);
+        authentication(AUTH_SASL, &data)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// `AuthenticationSASLContinue`, carrying a challenge
+pub struct AuthenticationSaslContinue {
+    pub data: Vec<u8>,
+}
+
+impl AuthenticationSaslContinue {
+    pub fn encode(&self) -> Vec<u8> {
+        authentication(AUTH_SASL_CONTINUE, &self.data)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// `AuthenticationSASLFinal`, carrying the additional data sent with a successful outcome
+pub struct AuthenticationSaslFinal {
+    pub data: Vec<u8>,
+}
+
+impl AuthenticationSaslFinal {
+    pub fn encode(&self) -> Vec<u8> {
+        authentication(AUTH_SASL_FINAL, &self.data)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// `AuthenticationOk`, ending the authentication successfully
+pub struct AuthenticationOk;
+
+impl AuthenticationOk {
+    pub fn encode(&self) -> Vec<u8> {
+        authentication(AUTH_OK, &[])
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// `ErrorResponse`, reduced to the fields relevant for failed authentication
+pub struct ErrorResponse {
+    /// Severity, `FATAL` for failed authentication
+    pub severity: String,
+    /// The SQLSTATE error code
+    pub code: String,
+    pub message: String,
+}
+
+impl ErrorResponse {
+    /// Construct a `FATAL` error
+    pub fn new(code: &str, message: impl ToString) -> Self {
+        Self {
+            severity: "FATAL".to_string(),
+            code: code.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        // The localized and non-localized severity are the same since we don't localize
+        for (field, value) in [
+            (b'S', &self.severity),
+            (b'V', &self.severity),
+            (b'C', &self.code),
+            (b'M', &self.message),
+        ]
+        .iter()
+        {
+            body.push(*field);
+            body.extend_from_slice(value.as_bytes());
+            body.push(0);
+        }
+        body.push(0);
+        frame(ERROR_RESPONSE, &body)
+    }
+
+    fn decode_body(mut body: &[u8]) -> Result<Self, DecodeError> {
+        let mut response = Self {
+            severity: String::new(),
+            code: String::new(),
+            message: String::new(),
+        };
+        loop {
+            match body.split_first() {
+                Some((0, [])) => return Ok(response),
+                Some((0, _)) => return Err(DecodeError::Malformed),
+                Some((field, rest)) => {
+                    let (value, rest) = cstr(rest)?;
+                    match field {
+                        b'S' => response.severity = value.to_string(),
+                        b'C' => response.code = value.to_string(),
+                        b'M' => response.message = value.to_string(),
+                        // Detail, hint, position and all the other fields are not of interest here
+                        _ => {}
+                    }
+                    body = rest;
+                }
+                None => return Err(DecodeError::Malformed),
+            }
+        }
+    }
+}
+
+impl From<&SessionError> for ErrorResponse {
+    fn from(error: &SessionError) -> Self {
+        let code = match error {
+            SessionError::InputDataRequired => PROTOCOL_VIOLATION,
+            SessionError::MechanismError(error) => match error.kind() {
+                MechanismErrorKind::Parse => PROTOCOL_VIOLATION,
+                _ => INVALID_PASSWORD,
+            },
+            SessionError::Io { .. }
+            | SessionError::NoCallback { .. }
+            | SessionError::NoValidate { .. }
+            | SessionError::NoProperty { .. } => INTERNAL_ERROR,
+            _ => INVALID_PASSWORD,
+        };
+        let message = match code {
+            PROTOCOL_VIOLATION => "malformed SASL message",
+            INTERNAL_ERROR => "internal error during SASL authentication",
+            _ => "SASL authentication failed",
+        };
+        Self::new(code, message)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Any message a server sends during SASL authentication
+pub enum BackendMessage {
+    AuthenticationSasl(AuthenticationSasl),
+    AuthenticationSaslContinue(AuthenticationSaslContinue),
+    AuthenticationSaslFinal(AuthenticationSaslFinal),
+    AuthenticationOk(AuthenticationOk),
+    ErrorResponse(ErrorResponse),
+}
+
+impl BackendMessage {
+    /// Decode a single complete message
+    pub fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
+        let (ty, body) = unframe(buf)?;
+        match ty {
+            AUTHENTICATION => {}
+            ERROR_RESPONSE => return ErrorResponse::decode_body(body).map(Self::ErrorResponse),
+            other => return Err(DecodeError::UnexpectedType(other)),
+        }
+        if body.len() < 4 {
+            return Err(DecodeError::InvalidLength);
+        }
+        let (code, data) = body.split_at(4);
+        match i32::from_be_bytes(code.try_into().unwrap()) {
+            AUTH_OK if data.is_empty() => Ok(Self::AuthenticationOk(AuthenticationOk)),
+            AUTH_SASL => {
+                let mut mechanisms = Vec::new();
+                let mut rest = data;
+                loop {
+                    let (mechanism, next) = cstr(rest)?;
+                    if mechanism.is_empty() {
+                        if !next.is_empty() {
+                            return Err(DecodeError::Malformed);
+                        }
+                        break;
+                    }
+                    mechanisms.push(mechanism.to_string());
+                    rest = next;
+                }
+                Ok(Self::AuthenticationSasl(AuthenticationSasl { mechanisms }))
+            }
+            AUTH_SASL_CONTINUE => Ok(Self::AuthenticationSaslContinue(
+                AuthenticationSaslContinue {
+                    data: data.to_vec(),
+                },
+            )),
+            AUTH_SASL_FINAL => Ok(Self::AuthenticationSaslFinal(AuthenticationSaslFinal {
+                data: data.to_vec(),
+            })),
+            AUTH_OK => Err(DecodeError::InvalidLength),
+            other => Err(DecodeError::UnsupportedAuthentication(other)),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::AuthenticationSasl(message) => message.encode(),
+            Self::AuthenticationSaslContinue(message) => message.encode(),
+            Self::AuthenticationSaslFinal(message) => message.encode(),
+            Self::AuthenticationOk(message) => message.encode(),
+            Self::ErrorResponse(message) => message.encode(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// `SASLInitialResponse`, selecting a mechanism and carrying the optional initial response
+pub struct SaslInitialResponse {
+    pub mechanism: String,
+    pub data: Option<Vec<u8>>,
+}
+
+impl SaslInitialResponse {
+    /// Decode a single complete message
+    pub fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
+        let (ty, body) = unframe(buf)?;
+        if ty != PASSWORD_MESSAGE {
+            return Err(DecodeError::UnexpectedType(ty));
+        }
+        let (mechanism, rest) = cstr(body)?;
+        if rest.len() < 4 {
+            return Err(DecodeError::Malformed);
+        }
+        let (len, data) = rest.split_at(4);
+        let data = match i32::from_be_bytes(len.try_into().unwrap()) {
+            -1 if data.is_empty() => None,
+            len if len >= 0 && len as usize == data.len() => Some(data.to_vec()),
+            _ => return Err(DecodeError::InvalidLength),
+        };
+        Ok(Self {
+            mechanism: mechanism.to_string(),
+            data,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let data = self.data.as_deref().unwrap_or_default();
+        let mut body = Vec::with_capacity(self.mechanism.len() + data.len() + 5);
+        body.extend_from_slice(self.mechanism.as_bytes());
+        body.push(0);
+        let len = self
+            .data
+            .as_ref()
+            .map(|data| data.len() as i32)
+            .unwrap_or(-1);
+        body.extend_from_slice(&len.to_be_bytes());
+        body.extend_from_slice(data);
+        frame(PASSWORD_MESSAGE, &body)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// `SASLResponse`, carrying a response to a challenge
+pub struct SaslResponse {
+    pub data: Vec<u8>,
+}
+
+impl SaslResponse {
+    /// Decode a single complete message
+    pub fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
+        match unframe(buf)? {
+            (PASSWORD_MESSAGE, data) => Ok(Self {
+                data: data.to_vec(),
+            }),
+            (other, _) => Err(DecodeError::UnexpectedType(other)),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        frame(PASSWORD_MESSAGE, &self.data)
+    }
+}
+
+/// Client side of a PostgreSQL SASL exchange
+///
+/// The session is usually started from the mechanisms in the server's [`AuthenticationSasl`]
+/// using [`SASL::client_start_suggested`](crate::SASL::client_start_suggested).
+pub struct Client {
+    exchange: ClientExchange,
+}
+
+impl Client {
+    pub fn new(session: Session) -> Self {
+        Self {
+            exchange: ClientExchange::new(session),
+        }
+    }
+
+    /// Generate the `SASLInitialResponse` selecting the session's mechanism
+    pub fn initial_response(&mut self) -> Result<SaslInitialResponse, SessionError> {
+        let data = if self.exchange.session().are_we_first() {
+            // A client-first mechanism always sends an initial response, even if it's empty
+            Some(self.exchange.step(None)?.unwrap_or_default())
+        } else {
+            None
+        };
+        Ok(SaslInitialResponse {
+            mechanism: self.exchange.session().get_mechname().to_string(),
+            data,
+        })
+    }
+
+    pub fn handle_continue(
+        &mut self,
+        message: &AuthenticationSaslContinue,
+    ) -> Result<SaslResponse, ProtocolError> {
+        if self.exchange.is_complete() {
+            return Err(ProtocolError::UnexpectedMessage);
+        }
+        let data = self.exchange.step(Some(&message.data))?.unwrap_or_default();
+        Ok(SaslResponse { data })
+    }
+
+    /// Handle an `AuthenticationSASLFinal`, checking the additional data with the mechanism
+    ///
+    /// If this returns an error the server could not prove its identity and the connection should
+    /// be closed. Otherwise the server will follow up with either `AuthenticationOk` or an
+    /// `ErrorResponse`.
+    pub fn handle_final(&mut self, message: &AuthenticationSaslFinal) -> Result<(), ProtocolError> {
+        self.exchange.success(Some(&message.data))
+    }
+
+    /// Handle `AuthenticationOk`
+    ///
+    /// Returns [`ProtocolError::PrematureSuccess`] if the mechanism has not completed yet, e.g.
+    /// because a server skipped the `AuthenticationSASLFinal` to avoid proving its identity.
+    pub fn handle_ok(&mut self, _message: &AuthenticationOk) -> Result<(), ProtocolError> {
+        self.exchange.success(None)
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        self.exchange.session_mut()
+    }
+
+    pub fn into_session(self) -> Session {
+        self.exchange.into_session()
+    }
+}
+
+#[derive(Debug)]
+/// What a server has to do after handling a message from the client
+pub enum ServerStep {
+    /// Send the challenge and wait for a [`SaslResponse`]
+    Continue(AuthenticationSaslContinue),
+    /// The mechanism completed successfully
+    ///
+    /// The application has to send the `AuthenticationSASLFinal` if there is one. After that it
+    /// may still perform its own checks, ending the authentication with either an
+    /// [`AuthenticationOk`] or an [`ErrorResponse`].
+    Authenticated {
+        final_message: Option<AuthenticationSaslFinal>,
+    },
+    /// Send the error response; the exchange failed, with the mechanism error if there is one
+    Failure {
+        response: ErrorResponse,
+        error: Option<SessionError>,
+    },
+}
+
+/// Server side of a PostgreSQL SASL exchange
+pub struct Server {
+    session: Session,
+    finished: bool,
+}
+
+impl Server {
+    /// Construct a server for a session started with the mechanism named in the
+    /// [`SaslInitialResponse`]
+    pub fn new(session: Session) -> Self {
+        Self {
+            session,
+            finished: false,
+        }
+    }
+
+    pub fn handle_initial_response(&mut self, message: &SaslInitialResponse) -> ServerStep {
+        if message.mechanism != self.session.get_mechname().as_str() {
+            self.finished = true;
+            return ServerStep::Failure {
+                response: ErrorResponse::new(PROTOCOL_VIOLATION, "invalid SASL mechanism"),
+                error: None,
+            };
+        }
+        self.step(message.data.as_deref())
+    }
+
+    pub fn handle_response(&mut self, message: &SaslResponse) -> ServerStep {
+        if self.finished {
+            return ServerStep::Failure {
+                response: ErrorResponse::new(PROTOCOL_VIOLATION, "unexpected SASL response"),
+                error: None,
+            };
+        }
+        self.step(Some(&message.data))
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+
+    fn step(&mut self, input: Option<&[u8]>) -> ServerStep {
+        match self.session.step_outcome(input) {
+            Ok(Outcome::Continue(data)) => ServerStep::Continue(AuthenticationSaslContinue {
+                data: data.unwrap_or_default(),
+            }),
+            Ok(Outcome::Success(data)) | Ok(Outcome::Final(data)) => {
+                self.finished = true;
+                ServerStep::Authenticated {
+                    // PostgreSQL only sends the final message if there is data to send
+                    final_message: data
+                        .filter(|data| !data.is_empty())
+                        .map(|data| AuthenticationSaslFinal { data }),
+                }
+            }
+            Err(error) => {
+                self.finished = true;
+                ServerStep::Failure {
+                    response: ErrorResponse::from(&error),
+                    error: Some(error),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backend_roundtrip() {
+        let messages = vec![
+            BackendMessage::AuthenticationSasl(AuthenticationSasl {
+                mechanisms: vec![
+                    "SCRAM-SHA-256-PLUS".to_string(),
+                    "SCRAM-SHA-256".to_string(),
+                ],
+            }),
+            BackendMessage::AuthenticationSaslContinue(AuthenticationSaslContinue {
+                data: b"r=abc,s=c2FsdA==,i=4096".to_vec(),
+            }),
+            BackendMessage::AuthenticationSaslFinal(AuthenticationSaslFinal {
+                data: b"v=dGVzdA==".to_vec(),
+            }),
+            BackendMessage::AuthenticationOk(AuthenticationOk),
+            BackendMessage::ErrorResponse(ErrorResponse::new(INVALID_PASSWORD, "no")),
+        ];
+        for message in messages {
+            let encoded = message.encode();
+            assert_eq!(frame_len(&encoded), Ok(Some(encoded.len())));
+            assert_eq!(BackendMessage::decode(&encoded), Ok(message));
+        }
+    }
+
+    #[test]
+    fn backend_encoding() {
+        let sasl = AuthenticationSasl {
+            mechanisms: vec!["SCRAM-SHA-256".to_string()],
+        };
+        assert_eq!(sasl.encode(), b"R\0\0\0\x17\0\0\0\x0aSCRAM-SHA-256\0\0");
+        assert_eq!(AuthenticationOk.encode(), b"R\0\0\0\x08\0\0\0\0");
+        assert_eq!(
+            BackendMessage::decode(b"R\0\0\0\x0c\0\0\0\x05salt"),
+            Err(DecodeError::UnsupportedAuthentication(5))
+        );
+        assert_eq!(
+            BackendMessage::decode(b"R\0\0\0\x08\0\0\0"),
+            Err(DecodeError::Incomplete)
+        );
+        assert_eq!(
+            BackendMessage::decode(b"Z\0\0\0\x05I"),
+            Err(DecodeError::UnexpectedType(b'Z'))
+        );
+    }
+
+    #[test]
+    fn frontend_roundtrip() {
+        for data in [None, Some(Vec::new()), Some(b"n,,n=,r=abc".to_vec())].iter() {
+            let message = SaslInitialResponse {
+                mechanism: "SCRAM-SHA-256".to_string(),
+                data: data.clone(),
+            };
+            assert_eq!(SaslInitialResponse::decode(&message.encode()), Ok(message));
+        }
+        assert_eq!(
+            SaslInitialResponse {
+                mechanism: "X".to_string(),
+                data: None,
+            }
+            .encode(),
+            b"p\0\0\0\x0aX\0\xff\xff\xff\xff"
+        );
+        let response = SaslResponse {
+            data: b"c=biws".to_vec(),
+        };
+        assert_eq!(SaslResponse::decode(&response.encode()), Ok(response));
+        assert_eq!(
+            SaslInitialResponse::decode(b"p\0\0\0\x0bX\0\0\0\0\x02a"),
+            Err(DecodeError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn frame_len_limits() {
+        assert_eq!(frame_len(b"R\0\0"), Ok(None));
+        assert_eq!(frame_len(b"R\0\0\0\x08\0\0"), Ok(None));
+        assert_eq!(frame_len(b"R\0\0\0\x03"), Err(DecodeError::InvalidLength));
+        assert_eq!(frame_len(b"R\x7f\0\0\0"), Err(DecodeError::InvalidLength));
+        assert_eq!(frame_len(b"R\0\0\0\x04R"), Ok(Some(5)));
+    }
+}
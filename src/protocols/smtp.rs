@@ -0,0 +1,129 @@
+//! SMTP `AUTH` ([RFC 4954](https://tools.ietf.org/html/rfc4954))
+//!
+//! *requires feature `protocol_smtp`*
+//!
+//! Challenges are sent as `334` replies, a successful authentication is answered with `235` and
+//! failures with `535`, `454` or `501` depending on whether the credentials were rejected, the
+//! server failed to check them or the client sent malformed data or cancelled the exchange.
+
+use crate::protocols::line::{
+    strip_command, AuthCommand, Client, Completion, Dialect, FailureReason, Server,
+};
+use crate::session::Session;
+
+/// The SMTP dialect of line-based SASL framing
+pub struct Smtp;
+
+impl Dialect for Smtp {
+    const CONTINUATION: &'static str = "334 ";
+
+    fn initial_response(&self) -> bool {
+        true
+    }
+
+    fn command(&self, mechanism: &str, initial_response: Option<&str>) -> String {
+        match initial_response {
+            Some(initial_response) => format!("AUTH {} {}", mechanism, initial_response),
+            None => format!("AUTH {}", mechanism),
+        }
+    }
+
+    fn completion<'a>(&self, line: &'a str) -> Option<Completion<'a>> {
+        let code = line.get(0..3)?;
+        let text = line[3..].trim_start_matches(&[' ', '-'][..]);
+        match code.as_bytes() {
+            b"235" => Some(Completion::Success),
+            [b'4' | b'5', b'0'..=b'9', b'0'..=b'9'] => Some(Completion::Failure(text)),
+            _ => None,
+        }
+    }
+
+    fn success(&self) -> String {
+        "235 2.7.0 Authentication successful".to_string()
+    }
+
+    fn failure(&self, reason: &FailureReason) -> String {
+        if let FailureReason::Cancelled = reason {
+            "501 5.0.0 Authentication cancelled".to_string()
+        } else if reason.is_syntax_error() {
+            "501 5.5.2 Cannot decode response".to_string()
+        } else if reason.is_temporary() {
+            "454 4.7.0 Temporary authentication failure".to_string()
+        } else {
+            "535 5.7.8 Authentication credentials invalid".to_string()
+        }
+    }
+}
+
+/// Start the client side of an `AUTH` exchange
+pub fn client(session: Session) -> Client<Smtp> {
+    Client::new(session, Smtp)
+}
+
+/// Start the server side of an `AUTH` exchange
+pub fn server(session: Session) -> Server<Smtp> {
+    Server::new(session, Smtp)
+}
+
+/// Parse a full `AUTH <mechanism> [<initial-response>]` command line
+pub fn parse_auth(line: &str) -> Option<AuthCommand<'_>> {
+    AuthCommand::parse(None, strip_command(line, "AUTH")?)
+}
+
+#[cfg(all(test, feature = "plain"))]
+mod tests {
+    use super::*;
+    use crate::property::{AuthId, Password};
+    use crate::protocols::line::{ClientAction, ServerAction};
+    use crate::{Mechname, SASL};
+    use std::sync::Arc;
+
+    #[test]
+    fn plain_exchange() {
+        let sasl = SASL::new();
+        let mut session = sasl.client_start(Mechname::new(b"PLAIN").unwrap()).unwrap();
+        session.set_property::<AuthId>(Arc::new("user".to_string()));
+        session.set_property::<Password>(Arc::new("secret".to_string()));
+        let mut client = client(session);
+        let command = client.start().unwrap();
+        assert_eq!(command, "AUTH PLAIN AHVzZXIAc2VjcmV0");
+
+        let command = parse_auth(&command).unwrap();
+        assert_eq!(command.mechanism, "PLAIN");
+        let session = sasl
+            .server_start(Mechname::new(command.mechanism.as_bytes()).unwrap())
+            .unwrap();
+        let mut server = server(session);
+        // No validation callback installed
+        let line = match server.start(command.initial_response) {
+            ServerAction::Failure(line, reason) => {
+                assert!(reason.is_temporary());
+                line
+            }
+            other => panic!("expected failure, got {:?}", other),
+        };
+        assert_eq!(line, "454 4.7.0 Temporary authentication failure");
+        assert!(matches!(
+            client.handle_line(&line),
+            Ok(ClientAction::Failure(_))
+        ));
+    }
+
+    #[test]
+    fn status_lines() {
+        assert!(matches!(
+            Smtp.completion("235 2.7.0 Authentication successful"),
+            Some(Completion::Success)
+        ));
+        assert!(matches!(
+            Smtp.completion("535 5.7.8 Authentication credentials invalid"),
+            Some(Completion::Failure(
+                "5.7.8 Authentication credentials invalid"
+            ))
+        ));
+        assert!(Smtp.completion("334 ").is_none());
+        assert!(parse_auth("auth plain =").is_some());
+        assert!(parse_auth("AUTH").is_none());
+        assert!(parse_auth("AUTH PLAIN a b").is_none());
+    }
+}
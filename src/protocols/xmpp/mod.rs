@@ -0,0 +1,852 @@
+//! XMPP Extensible SASL Profile ([XEP-0388](https://xmpp.org/extensions/xep-0388.html))
+//!
+//! *requires feature `protocol_xmpp`*
+//!
+//! SASL2 frames an authentication exchange in the `urn:xmpp:sasl:2` namespace: The client sends
+//! an [`Authenticate`] naming the mechanism and optionally carrying an initial response, the
+//! server answers with [`Challenge`]s that the client answers with [`Response`]s until the server
+//! ends the exchange with a [`Success`] or [`Failure`]. Unlike RFC 6120 SASL the server may
+//! instead send a [`Continue`] requiring the client to perform further tasks, e.g. a second
+//! factor or a password change, after the mechanism completed.
+//!
+//! The stanza types in this module implement `Display` to serialize them and can be parsed from
+//! a single serialized element with [`Stanza::parse`]. Elements that are not part of SASL2 itself
+//! (e.g. [Bind2](https://xmpp.org/extensions/xep-0386.html) requests carried in an
+//! `<authenticate/>`) are kept as raw XML in the `inline` fields.
+//!
+//! [`Client`] and [`Server`] drive a [`Session`] using these stanzas. In contrast to
+//! [`Session::step`] they differentiate challenges from the additional data a server sends with
+//! its success, see [`Session::step_outcome`].
+
+use crate::error::{MechanismErrorKind, SessionError};
+use crate::protocols::{ClientExchange, ProtocolError};
+use crate::session::{Outcome, Session};
+use std::fmt::{Display, Formatter};
+
+mod xml;
+pub use xml::XmlError;
+use xml::{Element, Escaped};
+
+/// Namespace of all SASL2 elements
+pub const NS_SASL2: &str = "urn:xmpp:sasl:2";
+/// Namespace of the RFC 6120 failure conditions
+pub const NS_SASL: &str = "urn:ietf:params:xml:ns:xmpp-sasl";
+/// Namespace of Bind2 elements
+pub const NS_BIND2: &str = "urn:xmpp:bind:0";
+
+/// Encode SASL data, using `=` for empty data as opposed to no data at all
+fn encode(data: &[u8]) -> String {
+    if data.is_empty() {
+        "=".to_string()
+    } else {
+        base64::encode(data)
+    }
+}
+
+fn decode(text: &str) -> Result<Option<Vec<u8>>, XmlError> {
+    match text.trim() {
+        "" => Ok(None),
+        "=" => Ok(Some(Vec::new())),
+        text => base64::decode(text)
+            .map(Some)
+            .map_err(|_| XmlError::InvalidEncoding),
+    }
+}
+
+fn optional_text(element: &Element, name: &str) -> Option<String> {
+    element.child(name).map(|child| child.text.clone())
+}
+
+fn write_text_element(f: &mut Formatter<'_>, name: &str, text: &str) -> std::fmt::Result {
+    write!(f, "<{0}>{1}</{0}>", name, Escaped(text))
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// The `<authentication/>` stream feature advertising SASL2 support
+pub struct Authentication {
+    pub mechanisms: Vec<String>,
+    /// Raw XML of the features that can be negotiated inline, e.g. Bind2
+    pub inline: Vec<String>,
+}
+
+impl Display for Authentication {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<authentication xmlns='{}'>", NS_SASL2)?;
+        for mechanism in &self.mechanisms {
+            write_text_element(f, "mechanism", mechanism)?;
+        }
+        if !self.inline.is_empty() {
+            f.write_str("<inline>")?;
+            for inline in &self.inline {
+                f.write_str(inline)?;
+            }
+            f.write_str("</inline>")?;
+        }
+        f.write_str("</authentication>")
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+/// Identification of the client software sent with an [`Authenticate`]
+pub struct UserAgent {
+    /// A stable identifier for this client installation, usually an UUID
+    pub id: Option<String>,
+    pub software: Option<String>,
+    pub device: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Sent by the client to start an exchange
+pub struct Authenticate {
+    pub mechanism: String,
+    pub initial_response: Option<Vec<u8>>,
+    pub user_agent: Option<UserAgent>,
+    /// Raw XML of requests to be processed after a successful authentication, e.g. Bind2
+    pub inline: Vec<String>,
+}
+
+impl Display for Authenticate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "<authenticate xmlns='{}' mechanism='{}'>",
+            NS_SASL2,
+            Escaped(&self.mechanism)
+        )?;
+        if let Some(ref initial_response) = self.initial_response {
+            write_text_element(f, "initial-response", &encode(initial_response))?;
+        }
+        if let Some(ref user_agent) = self.user_agent {
+            f.write_str("<user-agent")?;
+            if let Some(ref id) = user_agent.id {
+                write!(f, " id='{}'", Escaped(id))?;
+            }
+            f.write_str(">")?;
+            if let Some(ref software) = user_agent.software {
+                write_text_element(f, "software", software)?;
+            }
+            if let Some(ref device) = user_agent.device {
+                write_text_element(f, "device", device)?;
+            }
+            f.write_str("</user-agent>")?;
+        }
+        for inline in &self.inline {
+            f.write_str(inline)?;
+        }
+        f.write_str("</authenticate>")
+    }
+}
+
+impl Authenticate {
+    /// Parse the Bind2 request carried inline, if any
+    pub fn bind(&self) -> Option<Result<Bind, XmlError>> {
+        self.inline
+            .iter()
+            .filter_map(|raw| Element::parse(raw).ok().map(|element| (raw, element)))
+            .find(|(_, element)| {
+                element.name == "bind" && element.attribute("xmlns") == Some(NS_BIND2)
+            })
+            .map(|(raw, _)| Bind::parse(raw))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// A challenge sent by the server
+///
+/// `None` means that the challenge carries no data while `Some` of an empty `Vec` means it
+/// carries zero-length data.
+pub struct Challenge {
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// A response sent by the client, see [`Challenge`] on `None` vs empty data
+pub struct Response {
+    pub data: Option<Vec<u8>>,
+}
+
+fn fmt_data(f: &mut Formatter<'_>, name: &str, data: &Option<Vec<u8>>) -> std::fmt::Result {
+    match data {
+        Some(data) => write!(
+            f,
+            "<{0} xmlns='{1}'>{2}</{0}>",
+            name,
+            NS_SASL2,
+            encode(data)
+        ),
+        None => write!(f, "<{} xmlns='{}'/>", name, NS_SASL2),
+    }
+}
+
+impl Display for Challenge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_data(f, "challenge", &self.data)
+    }
+}
+
+impl Display for Response {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fmt_data(f, "response", &self.data)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Sent by the server if the authentication succeeded
+pub struct Success {
+    /// Additional data from the mechanism, e.g. the SCRAM server signature
+    pub additional_data: Option<Vec<u8>>,
+    /// The full JID the client is now authorized as
+    pub authorization_identifier: String,
+    /// Raw XML of the results of inline requests, e.g. a Bind2 `<bound/>`
+    pub inline: Vec<String>,
+}
+
+impl Success {
+    pub fn new(additional_data: Option<Vec<u8>>, authorization_identifier: String) -> Self {
+        Self {
+            additional_data,
+            authorization_identifier,
+            inline: Vec::new(),
+        }
+    }
+}
+
+impl Display for Success {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<success xmlns='{}'>", NS_SASL2)?;
+        if let Some(ref additional_data) = self.additional_data {
+            write_text_element(f, "additional-data", &encode(additional_data))?;
+        }
+        write_text_element(
+            f,
+            "authorization-identifier",
+            &self.authorization_identifier,
+        )?;
+        for inline in &self.inline {
+            f.write_str(inline)?;
+        }
+        f.write_str("</success>")
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// Failure conditions defined by [RFC 6120](https://tools.ietf.org/html/rfc6120#section-6.5)
+pub enum Condition {
+    Aborted,
+    AccountDisabled,
+    CredentialsExpired,
+    EncryptionRequired,
+    IncorrectEncoding,
+    InvalidAuthzid,
+    InvalidMechanism,
+    MalformedRequest,
+    MechanismTooWeak,
+    NotAuthorized,
+    TemporaryAuthFailure,
+}
+
+impl Condition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Aborted => "aborted",
+            Self::AccountDisabled => "account-disabled",
+            Self::CredentialsExpired => "credentials-expired",
+            Self::EncryptionRequired => "encryption-required",
+            Self::IncorrectEncoding => "incorrect-encoding",
+            Self::InvalidAuthzid => "invalid-authzid",
+            Self::InvalidMechanism => "invalid-mechanism",
+            Self::MalformedRequest => "malformed-request",
+            Self::MechanismTooWeak => "mechanism-too-weak",
+            Self::NotAuthorized => "not-authorized",
+            Self::TemporaryAuthFailure => "temporary-auth-failure",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "aborted" => Self::Aborted,
+            "account-disabled" => Self::AccountDisabled,
+            "credentials-expired" => Self::CredentialsExpired,
+            "encryption-required" => Self::EncryptionRequired,
+            "incorrect-encoding" => Self::IncorrectEncoding,
+            "invalid-authzid" => Self::InvalidAuthzid,
+            "invalid-mechanism" => Self::InvalidMechanism,
+            "malformed-request" => Self::MalformedRequest,
+            "mechanism-too-weak" => Self::MechanismTooWeak,
+            "not-authorized" => Self::NotAuthorized,
+            "temporary-auth-failure" => Self::TemporaryAuthFailure,
+            _ => return None,
+        })
+    }
+}
+
+impl From<&SessionError> for Condition {
+    fn from(error: &SessionError) -> Self {
+        match error {
+            SessionError::Base64 { .. } => Self::IncorrectEncoding,
+            SessionError::InputDataRequired => Self::MalformedRequest,
+            SessionError::MechanismError(error) => match error.kind() {
+                MechanismErrorKind::Parse => Self::MalformedRequest,
+                _ => Self::NotAuthorized,
+            },
+            SessionError::Io { .. }
+            | SessionError::NoCallback { .. }
+            | SessionError::NoValidate { .. }
+            | SessionError::NoProperty { .. } => Self::TemporaryAuthFailure,
+            _ => Self::NotAuthorized,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Sent by the server if the authentication failed
+pub struct Failure {
+    pub condition: Condition,
+    pub text: Option<String>,
+    /// Raw XML of additional application-specific elements
+    pub inline: Vec<String>,
+}
+
+impl Failure {
+    pub fn new(condition: Condition) -> Self {
+        Self {
+            condition,
+            text: None,
+            inline: Vec::new(),
+        }
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "<failure xmlns='{}'><{} xmlns='{}'/>",
+            NS_SASL2,
+            self.condition.as_str(),
+            NS_SASL
+        )?;
+        if let Some(ref text) = self.text {
+            write_text_element(f, "text", text)?;
+        }
+        for inline in &self.inline {
+            f.write_str(inline)?;
+        }
+        f.write_str("</failure>")
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Sent by the server instead of a [`Success`] if the client has to perform further tasks
+pub struct Continue {
+    /// Additional data from the mechanism, see [`Success::additional_data`]
+    pub additional_data: Option<Vec<u8>>,
+    /// The tasks of which the client has to select one using [`Next`]
+    pub tasks: Vec<String>,
+    pub text: Option<String>,
+}
+
+impl Display for Continue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<continue xmlns='{}'>", NS_SASL2)?;
+        if let Some(ref additional_data) = self.additional_data {
+            write_text_element(f, "additional-data", &encode(additional_data))?;
+        }
+        f.write_str("<tasks>")?;
+        for task in &self.tasks {
+            write_text_element(f, "task", task)?;
+        }
+        f.write_str("</tasks>")?;
+        if let Some(ref text) = self.text {
+            write_text_element(f, "text", text)?;
+        }
+        f.write_str("</continue>")
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Sent by the client to select the task to perform after a [`Continue`]
+pub struct Next {
+    pub task: String,
+    /// Raw XML of task-specific data
+    pub payload: Vec<String>,
+}
+
+impl Display for Next {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "<next xmlns='{}' task='{}'>",
+            NS_SASL2,
+            Escaped(&self.task)
+        )?;
+        for payload in &self.payload {
+            f.write_str(payload)?;
+        }
+        f.write_str("</next>")
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Task-specific data exchanged while performing a task
+pub struct TaskData {
+    /// Raw XML of task-specific data
+    pub payload: Vec<String>,
+}
+
+impl Display for TaskData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<task-data xmlns='{}'>", NS_SASL2)?;
+        for payload in &self.payload {
+            f.write_str(payload)?;
+        }
+        f.write_str("</task-data>")
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Sent by the client to abort the exchange
+pub struct Abort {
+    pub text: Option<String>,
+}
+
+impl Display for Abort {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<abort xmlns='{}'>", NS_SASL2)?;
+        if let Some(ref text) = self.text {
+            write_text_element(f, "text", text)?;
+        }
+        f.write_str("</abort>")
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// A Bind2 request, sent inline in an [`Authenticate`]
+pub struct Bind {
+    /// The client-chosen tag used as part of the resource
+    pub tag: Option<String>,
+    /// Raw XML of features to enable when binding, e.g. carbons or stream management
+    pub inline: Vec<String>,
+}
+
+impl Bind {
+    pub fn parse(xml: &str) -> Result<Self, XmlError> {
+        let element = Element::parse(xml)?;
+        if element.name != "bind" || element.attribute("xmlns") != Some(NS_BIND2) {
+            return Err(XmlError::UnknownElement);
+        }
+        Ok(Self {
+            tag: optional_text(&element, "tag"),
+            inline: element
+                .children
+                .iter()
+                .filter(|child| child.name != "tag")
+                .map(|child| child.raw.to_string())
+                .collect(),
+        })
+    }
+}
+
+impl Display for Bind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<bind xmlns='{}'>", NS_BIND2)?;
+        if let Some(ref tag) = self.tag {
+            write_text_element(f, "tag", tag)?;
+        }
+        for inline in &self.inline {
+            f.write_str(inline)?;
+        }
+        f.write_str("</bind>")
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// Any SASL2 element
+pub enum Stanza {
+    Authentication(Authentication),
+    Authenticate(Authenticate),
+    Challenge(Challenge),
+    Response(Response),
+    Success(Success),
+    Failure(Failure),
+    Continue(Continue),
+    Next(Next),
+    TaskData(TaskData),
+    Abort(Abort),
+}
+
+impl Stanza {
+    /// Parse a single serialized SASL2 element
+    ///
+    /// The element must either declare the SASL2 namespace or inherit it, i.e. not declare a
+    /// namespace at all.
+    pub fn parse(xml: &str) -> Result<Self, XmlError> {
+        let element = Element::parse(xml)?;
+        match element.attribute("xmlns") {
+            Some(NS_SASL2) | None => {}
+            Some(_) => return Err(XmlError::UnknownElement),
+        }
+        let inline = |skip: &[&str]| -> Vec<String> {
+            element
+                .children
+                .iter()
+                .filter(|child| !skip.contains(&child.name))
+                .map(|child| child.raw.to_string())
+                .collect()
+        };
+        let data = |name: &str| -> Result<Option<Vec<u8>>, XmlError> {
+            element
+                .child(name)
+                .map(|child| decode(&child.text))
+                .transpose()
+                .map(Option::flatten)
+        };
+
+        let stanza = match element.name {
+            "authentication" => Self::Authentication(Authentication {
+                mechanisms: element
+                    .children
+                    .iter()
+                    .filter(|child| child.name == "mechanism")
+                    .map(|child| child.text.trim().to_string())
+                    .collect(),
+                inline: element
+                    .child("inline")
+                    .map(|inline| {
+                        inline
+                            .children
+                            .iter()
+                            .map(|child| child.raw.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            }),
+            "authenticate" => Self::Authenticate(Authenticate {
+                mechanism: element
+                    .attribute("mechanism")
+                    .ok_or(XmlError::Missing("mechanism attribute"))?
+                    .to_string(),
+                initial_response: data("initial-response")?,
+                user_agent: element.child("user-agent").map(|user_agent| UserAgent {
+                    id: user_agent.attribute("id").map(str::to_string),
+                    software: optional_text(user_agent, "software"),
+                    device: optional_text(user_agent, "device"),
+                }),
+                inline: inline(&["initial-response", "user-agent"]),
+            }),
+            "challenge" => Self::Challenge(Challenge {
+                data: decode(&element.text)?,
+            }),
+            "response" => Self::Response(Response {
+                data: decode(&element.text)?,
+            }),
+            "success" => Self::Success(Success {
+                additional_data: data("additional-data")?,
+                authorization_identifier: element
+                    .child("authorization-identifier")
+                    .ok_or(XmlError::Missing("authorization-identifier"))?
+                    .text
+                    .trim()
+                    .to_string(),
+                inline: inline(&["additional-data", "authorization-identifier"]),
+            }),
+            "failure" => Self::Failure(Failure {
+                condition: element
+                    .children
+                    .iter()
+                    .find_map(|child| Condition::from_name(child.name))
+                    .ok_or(XmlError::Missing("failure condition"))?,
+                text: optional_text(&element, "text"),
+                inline: element
+                    .children
+                    .iter()
+                    .filter(|child| {
+                        child.name != "text" && Condition::from_name(child.name).is_none()
+                    })
+                    .map(|child| child.raw.to_string())
+                    .collect(),
+            }),
+            "continue" => Self::Continue(Continue {
+                additional_data: data("additional-data")?,
+                tasks: element
+                    .child("tasks")
+                    .ok_or(XmlError::Missing("tasks"))?
+                    .children
+                    .iter()
+                    .filter(|child| child.name == "task")
+                    .map(|child| child.text.trim().to_string())
+                    .collect(),
+                text: optional_text(&element, "text"),
+            }),
+            "next" => Self::Next(Next {
+                task: element
+                    .attribute("task")
+                    .ok_or(XmlError::Missing("task attribute"))?
+                    .to_string(),
+                payload: inline(&[]),
+            }),
+            "task-data" => Self::TaskData(TaskData {
+                payload: inline(&[]),
+            }),
+            "abort" => Self::Abort(Abort {
+                text: optional_text(&element, "text"),
+            }),
+            _ => return Err(XmlError::UnknownElement),
+        };
+        Ok(stanza)
+    }
+}
+
+impl Display for Stanza {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Authentication(stanza) => Display::fmt(stanza, f),
+            Self::Authenticate(stanza) => Display::fmt(stanza, f),
+            Self::Challenge(stanza) => Display::fmt(stanza, f),
+            Self::Response(stanza) => Display::fmt(stanza, f),
+            Self::Success(stanza) => Display::fmt(stanza, f),
+            Self::Failure(stanza) => Display::fmt(stanza, f),
+            Self::Continue(stanza) => Display::fmt(stanza, f),
+            Self::Next(stanza) => Display::fmt(stanza, f),
+            Self::TaskData(stanza) => Display::fmt(stanza, f),
+            Self::Abort(stanza) => Display::fmt(stanza, f),
+        }
+    }
+}
+
+/// Client side of a SASL2 exchange
+pub struct Client {
+    exchange: ClientExchange,
+}
+
+impl Client {
+    pub fn new(session: Session) -> Self {
+        Self {
+            exchange: ClientExchange::new(session),
+        }
+    }
+
+    /// Generate the `<authenticate/>` starting the exchange, including the initial response for
+    /// mechanisms where the client sends the first data.
+    pub fn authenticate(&mut self) -> Result<Authenticate, SessionError> {
+        let initial_response = if self.exchange.session().are_we_first() {
+            self.exchange.step(None)?
+        } else {
+            None
+        };
+        Ok(Authenticate {
+            mechanism: self.exchange.session().get_mechname().to_string(),
+            initial_response,
+            user_agent: None,
+            inline: Vec::new(),
+        })
+    }
+
+    pub fn handle_challenge(&mut self, challenge: &Challenge) -> Result<Response, ProtocolError> {
+        if self.exchange.is_complete() {
+            return Err(ProtocolError::UnexpectedMessage);
+        }
+        let data = self.exchange.step(challenge.data.as_deref())?;
+        Ok(Response { data })
+    }
+
+    /// Handle a `<success/>`, checking any additional data with the mechanism
+    ///
+    /// If this returns an error the server could not prove its identity and the connection should
+    /// be closed.
+    pub fn handle_success(&mut self, success: &Success) -> Result<(), ProtocolError> {
+        self.exchange.success(success.additional_data.as_deref())
+    }
+
+    /// Handle a `<continue/>`, checking any additional data with the mechanism
+    ///
+    /// As with [`Client::handle_success`] an error means the server could not prove its
+    /// identity. Otherwise the application has to select one of the tasks and continue the
+    /// exchange with a [`Next`].
+    pub fn handle_continue(&mut self, cont: &Continue) -> Result<(), ProtocolError> {
+        self.exchange.success(cont.additional_data.as_deref())
+    }
+
+    /// Abort the exchange, returning the `<abort/>` to send to the server
+    pub fn abort(&mut self) -> Abort {
+        Abort { text: None }
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        self.exchange.session_mut()
+    }
+
+    pub fn into_session(self) -> Session {
+        self.exchange.into_session()
+    }
+}
+
+#[derive(Debug)]
+/// What a server has to do after handling a stanza from the client
+pub enum ServerStep {
+    /// Send the challenge and wait for a [`Response`]
+    Challenge(Challenge),
+    /// The mechanism completed successfully
+    ///
+    /// The application must now either send a [`Success`] carrying the additional data and the
+    /// authorization identifier, or a [`Continue`] carrying the additional data if the client
+    /// has to perform further tasks.
+    Authenticated { additional_data: Option<Vec<u8>> },
+    /// Send the failure; the exchange failed, with the mechanism error if there is one
+    Failure {
+        failure: Failure,
+        error: Option<SessionError>,
+    },
+}
+
+/// Server side of a SASL2 exchange
+pub struct Server {
+    session: Session,
+    finished: bool,
+}
+
+impl Server {
+    /// Construct a server for a session started with the mechanism named in the
+    /// [`Authenticate`]
+    pub fn new(session: Session) -> Self {
+        Self {
+            session,
+            finished: false,
+        }
+    }
+
+    pub fn handle_authenticate(&mut self, authenticate: &Authenticate) -> ServerStep {
+        self.step(authenticate.initial_response.as_deref())
+    }
+
+    pub fn handle_response(&mut self, response: &Response) -> ServerStep {
+        if self.finished {
+            return ServerStep::Failure {
+                failure: Failure::new(Condition::MalformedRequest),
+                error: None,
+            };
+        }
+        self.step(response.data.as_deref())
+    }
+
+    /// Handle an `<abort/>` sent by the client, returning the failure to reply with
+    pub fn handle_abort(&mut self, _abort: &Abort) -> Failure {
+        self.finished = true;
+        Failure::new(Condition::Aborted)
+    }
+
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub fn into_session(self) -> Session {
+        self.session
+    }
+
+    fn step(&mut self, input: Option<&[u8]>) -> ServerStep {
+        match self.session.step_outcome(input) {
+            Ok(Outcome::Continue(data)) => ServerStep::Challenge(Challenge { data }),
+            Ok(Outcome::Success(additional_data)) | Ok(Outcome::Final(additional_data)) => {
+                self.finished = true;
+                ServerStep::Authenticated { additional_data }
+            }
+            Err(error) => {
+                self.finished = true;
+                ServerStep::Failure {
+                    failure: Failure::new(Condition::from(&error)),
+                    error: Some(error),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(stanza: Stanza) {
+        let xml = stanza.to_string();
+        assert_eq!(Stanza::parse(&xml).unwrap(), stanza, "{}", xml);
+    }
+
+    #[test]
+    fn stanza_roundtrip() {
+        roundtrip(Stanza::Authentication(Authentication {
+            mechanisms: vec!["SCRAM-SHA-1".to_string(), "PLAIN".to_string()],
+            inline: vec![format!("<bind xmlns='{}'/>", NS_BIND2)],
+        }));
+        roundtrip(Stanza::Authenticate(Authenticate {
+            mechanism: "PLAIN".to_string(),
+            initial_response: Some(b"\0user\0pencil".to_vec()),
+            user_agent: Some(UserAgent {
+                id: Some("d4565fa7-4d72-4749-b3d3-740edbf87770".to_string()),
+                software: Some("AwesomeXMPP".to_string()),
+                device: Some("Kiva's Phone & <Tablet>".to_string()),
+            }),
+            inline: vec![Bind {
+                tag: Some("AwesomeXMPP".to_string()),
+                inline: Vec::new(),
+            }
+            .to_string()],
+        }));
+        roundtrip(Stanza::Challenge(Challenge { data: None }));
+        roundtrip(Stanza::Challenge(Challenge {
+            data: Some(Vec::new()),
+        }));
+        roundtrip(Stanza::Response(Response {
+            data: Some(b"c=biws".to_vec()),
+        }));
+        roundtrip(Stanza::Success(Success {
+            additional_data: Some(b"v=msVHs/BzIOHDqXeVH7EmmDu9id8=".to_vec()),
+            authorization_identifier: "user@example.org/resource".to_string(),
+            inline: vec![format!("<bound xmlns='{}'/>", NS_BIND2)],
+        }));
+        roundtrip(Stanza::Failure(Failure {
+            condition: Condition::CredentialsExpired,
+            text: Some("Password expired".to_string()),
+            inline: Vec::new(),
+        }));
+        roundtrip(Stanza::Continue(Continue {
+            additional_data: None,
+            tasks: vec!["HOTP-EXAMPLE".to_string()],
+            text: None,
+        }));
+        roundtrip(Stanza::Next(Next {
+            task: "HOTP-EXAMPLE".to_string(),
+            payload: Vec::new(),
+        }));
+        roundtrip(Stanza::TaskData(TaskData {
+            payload: vec!["<otp xmlns='urn:example'>123456</otp>".to_string()],
+        }));
+        roundtrip(Stanza::Abort(Abort { text: None }));
+    }
+
+    #[test]
+    fn parse_foreign() {
+        assert_eq!(
+            Stanza::parse("<challenge xmlns='urn:ietf:params:xml:ns:xmpp-sasl'>=</challenge>"),
+            Err(XmlError::UnknownElement)
+        );
+        assert_eq!(
+            Stanza::parse("<success><additional-data>=</additional-data></success>"),
+            Err(XmlError::Missing("authorization-identifier"))
+        );
+        let authenticate = Stanza::parse(
+            "<authenticate xmlns='urn:xmpp:sasl:2' mechanism='PLAIN'>\
+             <initial-response>=</initial-response>\
+             <bind xmlns='urn:xmpp:bind:0'><tag>client</tag><enable xmlns='urn:xmpp:carbons:2'/></bind>\
+             </authenticate>",
+        )
+        .unwrap();
+        match authenticate {
+            Stanza::Authenticate(authenticate) => {
+                assert_eq!(authenticate.initial_response, Some(Vec::new()));
+                let bind = authenticate.bind().unwrap().unwrap();
+                assert_eq!(bind.tag.as_deref(), Some("client"));
+                assert_eq!(bind.inline, vec!["<enable xmlns='urn:xmpp:carbons:2'/>"]);
+            }
+            other => panic!("parsed as {:?}", other),
+        }
+    }
+}
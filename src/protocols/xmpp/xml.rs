@@ -0,0 +1,273 @@
+//! Minimal XML support for single SASL2 stanzas
+//!
+//! XMPP libraries bring their own streaming XML parser so this only needs to handle the elements
+//! defined by SASL2 after they were cut from the stream: no declarations, comments, processing
+//! instructions or CDATA sections. Children that are not part of SASL2 itself (e.g. Bind2
+//! requests) are kept as their raw XML.
+
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum XmlError {
+    /// The input ended in the middle of an element
+    UnexpectedEnd,
+    /// The input is not well-formed XML, at the given byte offset
+    Malformed(usize),
+    /// An unknown entity reference was used
+    UnknownEntity,
+    /// The element is not one defined by SASL2
+    UnknownElement,
+    /// A required attribute or child element is missing
+    Missing(&'static str),
+    /// Base64-encoded content is invalid
+    InvalidEncoding,
+}
+
+impl Display for XmlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEnd => f.write_str("unexpected end of XML input"),
+            Self::Malformed(offset) => write!(f, "malformed XML at byte {}", offset),
+            Self::UnknownEntity => f.write_str("unknown XML entity reference"),
+            Self::UnknownElement => f.write_str("element is not a SASL2 element"),
+            Self::Missing(name) => write!(f, "required {} is missing", name),
+            Self::InvalidEncoding => f.write_str("invalid base64-encoded content"),
+        }
+    }
+}
+
+impl std::error::Error for XmlError {}
+
+#[derive(Debug)]
+pub(super) struct Element<'a> {
+    /// Local name, i.e. without any namespace prefix
+    pub name: &'a str,
+    pub attributes: Vec<(&'a str, String)>,
+    pub children: Vec<Element<'a>>,
+    pub text: String,
+    /// The complete source of this element
+    pub raw: &'a str,
+}
+
+impl<'a> Element<'a> {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attr, _)| *attr == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element<'a>> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn parse(input: &'a str) -> Result<Self, XmlError> {
+        let mut parser = Parser { input, pos: 0 };
+        parser.skip_whitespace();
+        let element = parser.element(0)?;
+        parser.skip_whitespace();
+        if parser.pos != input.len() {
+            return Err(XmlError::Malformed(parser.pos));
+        }
+        Ok(element)
+    }
+}
+
+const MAX_DEPTH: usize = 32;
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), XmlError> {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            Ok(())
+        } else if self.rest().is_empty() {
+            Err(XmlError::UnexpectedEnd)
+        } else {
+            Err(XmlError::Malformed(self.pos))
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, XmlError> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/' || c == '>')
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(XmlError::Malformed(self.pos));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn element(&mut self, depth: usize) -> Result<Element<'a>, XmlError> {
+        // SASL2 stanzas are shallow, so anything deeper is likely an attempt to exhaust the stack
+        if depth > MAX_DEPTH {
+            return Err(XmlError::Malformed(self.pos));
+        }
+        let start = self.pos;
+        self.expect("<")?;
+        let qname = self.name()?;
+        let name = qname.rsplit(':').next().unwrap_or(qname);
+        let mut attributes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(Element {
+                    name,
+                    attributes,
+                    children: Vec::new(),
+                    text: String::new(),
+                    raw: &self.input[start..self.pos],
+                });
+            } else if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let attribute = self.name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(quote @ '\'') | Some(quote @ '"') => quote,
+                Some(_) => return Err(XmlError::Malformed(self.pos)),
+                None => return Err(XmlError::UnexpectedEnd),
+            };
+            self.pos += 1;
+            let len = self.rest().find(quote).ok_or(XmlError::UnexpectedEnd)?;
+            let value = unescape(&self.rest()[..len])?;
+            self.pos += len + 1;
+            attributes.push((attribute, value));
+        }
+
+        let mut children = Vec::new();
+        let mut text = String::new();
+        loop {
+            let rest = self.rest();
+            let len = rest.find('<').ok_or(XmlError::UnexpectedEnd)?;
+            text.push_str(&unescape(&rest[..len])?);
+            self.pos += len;
+            if self.rest().starts_with("</") {
+                self.pos += 2;
+                if self.name()? != qname {
+                    return Err(XmlError::Malformed(self.pos));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                return Ok(Element {
+                    name,
+                    attributes,
+                    children,
+                    text,
+                    raw: &self.input[start..self.pos],
+                });
+            }
+            children.push(self.element(depth + 1)?);
+        }
+    }
+}
+
+fn unescape(s: &str) -> Result<String, XmlError> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp + 1..];
+        let end = rest.find(';').ok_or(XmlError::UnknownEntity)?;
+        let c = match &rest[..end] {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            entity => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32)
+                    .ok_or(XmlError::UnknownEntity)?
+            }
+        };
+        out.push(c);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Escape text for use in both character data and attribute values
+pub(super) struct Escaped<'a>(pub &'a str);
+
+impl Display for Escaped<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut rest = self.0;
+        while let Some(i) = rest.find(&['<', '>', '&', '"', '\''][..]) {
+            f.write_str(&rest[..i])?;
+            f.write_str(match rest.as_bytes()[i] {
+                b'<' => "&lt;",
+                b'>' => "&gt;",
+                b'&' => "&amp;",
+                b'"' => "&quot;",
+                _ => "&apos;",
+            })?;
+            rest = &rest[i + 1..];
+        }
+        f.write_str(rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_nested() {
+        let input = "<a xmlns='urn:x' b=\"1 &amp; 2\"><c>te&lt;xt</c><d:e xmlns:d='urn:y'/> </a>";
+        let element = Element::parse(input).unwrap();
+        assert_eq!(element.name, "a");
+        assert_eq!(element.attribute("xmlns"), Some("urn:x"));
+        assert_eq!(element.attribute("b"), Some("1 & 2"));
+        assert_eq!(element.child("c").unwrap().text, "te<xt");
+        assert_eq!(element.child("e").unwrap().raw, "<d:e xmlns:d='urn:y'/>");
+        assert_eq!(element.raw, input);
+    }
+
+    #[test]
+    fn reject_malformed() {
+        assert!(matches!(
+            Element::parse("<a>"),
+            Err(XmlError::UnexpectedEnd)
+        ));
+        assert!(Element::parse("<a></b>").is_err());
+        assert!(Element::parse("<a/><b/>").is_err());
+        assert!(Element::parse("<a b=c/>").is_err());
+        assert!(Element::parse("<a>&foo;</a>").is_err());
+        assert!(Element::parse(&"<a>".repeat(1000)).is_err());
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            Escaped("<a href=\"x\">&'").to_string(),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&apos;"
+        );
+    }
+}
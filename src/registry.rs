@@ -11,9 +11,9 @@
 //! - OPENID20, SAML20, GS2-*, GSSAPI
 //! - SCRAM-SHA-256(-PLUS)
 //! - SCRAM-SHA-1(-PLUS)
-//! - PLAIN, SECURID
+//! - PLAIN, SECURID, OTP
 //! - LOGIN
-//! - ANONYMOUS, EXTERNAL
+//! - ANONYMOUS, EXTERNAL, HT-*
 //! - CRAM_MD5, DIGEST_MD5
 //!
 //! ## Static compile-time registry using dtolnay's `linkme` crate
@@ -37,7 +37,8 @@
 //! # }
 //! }
 //!
-//! use rsasl::registry::Mechanism;
+//! use rsasl::property::properties;
+//! use rsasl::registry::{Mechanism, MechanismSecurityFactors, PropertyRequirements};
 //!
 //! // Since the static registry requires a feature flag, downstream crates should gate
 //! // automatic registration the same way. Either by matching on `feature = "rsasl/registry_static"
@@ -55,6 +56,15 @@
 //!     // In this case only the client side is implemented
 //!     server: None,
 //!     first: Side::Client,
+//!     // The client needs a username, the server side doesn't exist
+//!     client_properties: PropertyRequirements::new(&[properties::AUTHID], &[]),
+//!     server_properties: PropertyRequirements::NONE,
+//!     description: "An example mechanism",
+//!     // Not specified anywhere
+//!     specification: None,
+//!     channel_binding: false,
+//!     security_layer: false,
+//!     security: MechanismSecurityFactors::NONE,
 //! };
 //! ```
 //!
@@ -64,6 +74,7 @@
 
 use crate::mechanism::Authentication;
 use crate::mechname::Mechname;
+use crate::property::Property;
 use crate::{SASLError, Side, SASL};
 use std::fmt::{Debug, Display, Formatter};
 
@@ -88,8 +99,60 @@ pub struct Mechanism {
     pub server: Option<StartFn>,
 
     pub first: Side,
+
+    /// Properties the client side queries
+    pub client_properties: PropertyRequirements,
+    /// Properties the server side queries, excluding those only read during validation
+    pub server_properties: PropertyRequirements,
+
+    /// Short human-readable description, e.g. to be shown in a UI
+    pub description: &'static str,
+    /// The document specifying the mechanism, e.g. `"RFC 4616"`
+    pub specification: Option<&'static str>,
+    /// The mechanism binds the exchange to the channel binding data set on the session and fails
+    /// without it, as e.g. all `-PLUS` variants do
+    pub channel_binding: bool,
+    /// The mechanism can negotiate a security layer, used with
+    /// [`Session::encode`](crate::session::Session::encode) and
+    /// [`Session::decode`](crate::session::Session::decode)
+    pub security_layer: bool,
+    /// The security properties of the mechanism, e.g. to exclude weak mechanisms with
+    /// [`SASL::set_filter`]
+    pub security: MechanismSecurityFactors,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// Properties one side of a mechanism queries during an authentication exchange
+///
+/// This allows applications to collect all data a mechanism needs before starting an exchange
+/// instead of when [`Callback::provide_prop`](crate::callback::Callback::provide_prop) is
+/// called, e.g. to show the right fields in a login dialog.
+pub struct PropertyRequirements {
+    /// Properties the mechanism needs
+    ///
+    /// Some mechanisms accept an alternative listed in `optional` instead, e.g. a precomputed
+    /// secret in place of the [`Password`](crate::property::Password).
+    pub required: &'static [Property],
+    /// Properties the mechanism uses if they are provided
+    pub optional: &'static [Property],
 }
 
+impl PropertyRequirements {
+    /// The mechanism doesn't query any properties
+    pub const NONE: Self = Self::new(&[], &[]);
+
+    pub const fn new(required: &'static [Property], optional: &'static [Property]) -> Self {
+        Self { required, optional }
+    }
+
+    /// Returns whether the property is either required or optional
+    pub fn uses(&self, property: Property) -> bool {
+        self.required.contains(&property) || self.optional.contains(&property)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// Security properties of a mechanism
 pub struct MechanismSecurityFactors {
     /// Maximum possible Security Strength Factor (SSF) of the security layers installed
     ///
@@ -106,9 +169,37 @@ pub struct MechanismSecurityFactors {
     /// This mechanism supports mutual authentication, i.e. if the authentication exchange
     /// succeeds then both the client and server have verified the identity of the other.
     pub mutual: bool,
+    /// This mechanism relies on cryptography considered broken or is otherwise only supported for
+    /// compatibility with legacy systems and should not be offered unless required.
+    pub weak: bool,
+}
+
+impl MechanismSecurityFactors {
+    /// The mechanism provides none of the security properties, e.g. because it sends the
+    /// password in plain text
+    pub const NONE: Self = Self {
+        max_ssf: 0,
+        noplain: false,
+        mutual: false,
+        weak: false,
+    };
 }
 
 impl Mechanism {
+    /// Returns whether the client can send data with the authentication request
+    ///
+    /// This is the case for all client-first mechanisms, protocols supporting it (e.g. SASL-IR or
+    /// `AUTH PLAIN <initial-response>`) can save a round trip for those.
+    ///
+    /// ```
+    /// # use rsasl::mechanisms::{cram_md5::mechinfo::CRAM_MD5, plain::mechinfo::PLAIN};
+    /// assert!(PLAIN.initial_response());
+    /// assert!(!CRAM_MD5.initial_response());
+    /// ```
+    pub fn initial_response(&self) -> bool {
+        self.first == Side::Client
+    }
+
     pub fn client(&self, sasl: &SASL) -> Option<Result<Box<dyn Authentication>, SASLError>> {
         self.client.map(|f| f(sasl))
     }
@@ -124,6 +215,7 @@ impl Debug for Mechanism {
             .field("name", &self.mechanism)
             .field("has client", &self.client.is_some())
             .field("has server", &self.server.is_some())
+            .field("description", &self.description)
             .finish()
     }
 }
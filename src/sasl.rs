@@ -18,6 +18,7 @@ impl SASL {
             static_mechs: &registry::MECHANISMS,
 
             sort_fn: |a, b| a.priority.cmp(&b.priority),
+            filter_fn: |_| true,
         }
     }
 
@@ -34,6 +35,31 @@ impl SASL {
     pub fn install_callback(&mut self, callback: Arc<dyn Callback + Send + Sync>) {
         self.callback = Some(callback);
     }
+
+    /// Only offer and start mechanisms for which `filter` returns `true`
+    ///
+    /// The filter applies to both the client and the server side. This can be used to exclude
+    /// mechanisms not providing the required [security
+    /// properties](crate::registry::MechanismSecurityFactors):
+    ///
+    /// ```
+    /// # use rsasl::SASL;
+    /// use rsasl::mechname::Mechname;
+    ///
+    /// let mut sasl = SASL::new();
+    /// sasl.set_filter(|mechanism| !mechanism.security.weak);
+    ///
+    /// let cram_md5 = Mechname::new(b"CRAM-MD5").unwrap();
+    /// assert!(!sasl.client_supports(cram_md5));
+    /// assert!(!sasl.server_supports(cram_md5));
+    /// assert!(sasl
+    ///     .client_mech_list()
+    ///     .into_iter()
+    ///     .all(|mechanism| !mechanism.security.weak));
+    /// ```
+    pub fn set_filter(&mut self, filter: fn(mechanism: &Mechanism) -> bool) {
+        self.filter_fn = filter;
+    }
 }
 
 pub struct Builder {
@@ -41,6 +67,7 @@ pub struct Builder {
     dynamic_mechs: Option<Vec<&'static Mechanism>>,
     static_mechs: Option<&'static [Mechanism]>,
     sort_fn: Option<fn(a: &&Mechanism, b: &&Mechanism) -> Ordering>,
+    filter_fn: Option<fn(mechanism: &Mechanism) -> bool>,
 }
 impl Builder {
     pub fn new() -> Self {
@@ -49,6 +76,7 @@ impl Builder {
             dynamic_mechs: None,
             static_mechs: None,
             sort_fn: None,
+            filter_fn: None,
         }
     }
     pub fn finish(self) -> SASL {
@@ -56,12 +84,14 @@ impl Builder {
         let dynamic_mechs = self.dynamic_mechs.unwrap_or_else(Vec::new);
         let static_mechs = self.static_mechs.unwrap_or(&MECHANISMS);
         let sort_fn = self.sort_fn.unwrap_or(|a, b| a.priority.cmp(&b.priority));
+        let filter_fn = self.filter_fn.unwrap_or(|_| true);
 
         SASL {
             callback,
             dynamic_mechs,
             static_mechs,
             sort_fn,
+            filter_fn,
         }
     }
 
@@ -69,4 +99,11 @@ impl Builder {
         self.static_mechs = Some(static_mechs);
         self
     }
+
+    /// Only offer and start mechanisms for which `filter` returns `true`, see
+    /// [`SASL::set_filter`]
+    pub fn with_filter(mut self, filter: fn(mechanism: &Mechanism) -> bool) -> Self {
+        self.filter_fn = Some(filter);
+        self
+    }
 }
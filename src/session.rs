@@ -1,6 +1,8 @@
 use base64::write::EncoderWriter;
 use std::any::Any;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::ffi::CString;
 use std::fmt::{Debug, Formatter};
 use std::io::Write;
 use std::sync::Arc;
@@ -9,6 +11,8 @@ use crate::error::SessionError;
 use crate::gsasl::consts::{property_from_code, Gsasl_property};
 use crate::mechanism::Authentication;
 use crate::property::PropertyQ;
+#[cfg(feature = "session_state")]
+use crate::state::{StateError, StateKey, StateReader, StateWriter, EXPORTED_PROPERTIES};
 use crate::validate::*;
 use crate::{Callback, Mechanism, Mechname, Property};
 
@@ -58,8 +62,12 @@ impl Session {
         self.session_data.side == self.session_data.mechanism.first
     }
 
+    /// Returns true if the mechanism negotiated a security layer
+    ///
+    /// Only then do [`Session::encode`] and [`Session::decode`] have to be applied to all data
+    /// exchanged after the authentication completed.
     pub fn has_security_layer(&self) -> bool {
-        todo!()
+        self.mechanism.has_security_layer()
     }
 }
 
@@ -104,6 +112,88 @@ impl Session {
     pub fn set_channel_binding_data(&mut self, name: &'static str, value: Box<[u8]>) {
         self.session_data.set_channel_binding_data(name, value);
     }
+
+    /// Perform one step of SASL authentication, classifying the data to send.
+    ///
+    /// *requires feature `provider`*
+    ///
+    /// With [`Session::step`] the meaning of data returned alongside [`Step::Done`] depends on
+    /// the side: A client has to send it as its last response while a server has to send it as
+    /// additional data along with the indication of success. Protocols transporting the latter in
+    /// a different message than challenges, e.g. XMPP, can use this method to have that
+    /// distinction made for them. See [`Outcome`] for details.
+    pub fn step_outcome(&mut self, input: Option<&[u8]>) -> Result<Outcome, SessionError> {
+        let mut output = Vec::new();
+        let step = self.step(input, &mut output)?;
+        let outcome = match (self.session_data.side, step) {
+            (_, Step::NeedsMore(written)) => Outcome::Continue(written.map(|_| output)),
+            (Side::Client, Step::Done(written)) => Outcome::Final(written.map(|_| output)),
+            (Side::Server, Step::Done(written)) => Outcome::Success(written.map(|_| output)),
+        };
+        Ok(outcome)
+    }
+
+    /// Protect outgoing data with the security layer negotiated by the mechanism.
+    ///
+    /// *requires feature `provider`*
+    ///
+    /// Returns [`SessionError::NoSecurityLayer`] if the mechanism did not install one. Framing
+    /// the protected data is up to the protocol.
+    pub fn encode(&mut self, input: &[u8]) -> Result<Box<[u8]>, SessionError> {
+        self.mechanism.encode(input)
+    }
+
+    /// Unprotect incoming data with the security layer negotiated by the mechanism.
+    ///
+    /// *requires feature `provider`*
+    ///
+    /// The input must be exactly one buffer as produced by the other party's `encode`.
+    pub fn decode(&mut self, input: &[u8]) -> Result<Box<[u8]>, SessionError> {
+        self.mechanism.decode(input)
+    }
+}
+
+#[cfg(feature = "session_state")]
+impl Session {
+    /// Export the state of the exchange in progress to resume it later, possibly in another
+    /// process.
+    ///
+    /// *requires feature `session_state`*
+    ///
+    /// The returned blob is encrypted and authenticated with the given key and can be resumed
+    /// with [`SASL::resume_session`](crate::SASL::resume_session). See [`crate::state`] for
+    /// details.
+    ///
+    /// Only server sessions of `SCRAM-SHA-1`, `SCRAM-SHA-256`, `CRAM-MD5` and `LOGIN` can be
+    /// exported, others return [`StateError::UnsupportedMechanism`]. Exporting a session that
+    /// completed returns [`StateError::Unsupported`].
+    pub fn export_state(&self, key: &StateKey) -> Result<Vec<u8>, StateError> {
+        crate::state::check_exportable(self.get_mechname(), self.session_data.side)?;
+        let mut writer = StateWriter::new();
+        writer.put_u8(match self.session_data.side {
+            Side::Client => 0,
+            Side::Server => 1,
+        });
+        writer.put_str(self.get_mechname().as_str());
+        self.session_data.export_properties(&mut writer);
+        self.mechanism.export_state(&mut writer)?;
+        Ok(key.seal(writer))
+    }
+
+    pub(crate) fn import_state(
+        callback: Option<Arc<dyn Callback + Send + Sync>>,
+        mechdesc: &'static Mechanism,
+        side: Side,
+        reader: &mut StateReader,
+    ) -> Result<Self, StateError> {
+        let mut session_data = SessionData::new(callback, mechdesc, side);
+        session_data.import_properties(reader)?;
+        let mechanism = crate::state::import_mechanism(mechdesc.mechanism, side, reader)?;
+        Ok(Self {
+            mechanism,
+            session_data,
+        })
+    }
 }
 
 #[cfg(feature = "provider_base64")]
@@ -137,6 +227,8 @@ pub struct SessionData {
     //       where caching makes no sense much more reasonable to implement.
     pub(crate) callback: Option<Arc<dyn Callback + Send + Sync>>,
     property_cache: HashMap<Property, Arc<dyn Any + Send + Sync>>,
+    // NUL-terminated copies of `String` properties handed out to the C mechanisms
+    c_string_cache: HashMap<Property, CString>,
     mechanism: &'static Mechanism,
     side: Side,
     channel_binding_data: Option<(&'static str, Box<[u8]>)>,
@@ -160,6 +252,24 @@ pub enum Step {
     Done(Option<usize>),
     NeedsMore(Option<usize>),
 }
+
+#[derive(Debug, Eq, PartialEq)]
+/// The outcome of a single step as returned by [`Session::step_outcome`]
+///
+/// As with [`Step`] a `None` means that there is no data to send while `Some` of an empty `Vec`
+/// means that zero-length data has to be sent.
+pub enum Outcome {
+    /// The exchange continues. The data has to be sent as challenge (server side) or response
+    /// (client side).
+    Continue(Option<Vec<u8>>),
+    /// The client side completed, but the server still has to process the last response before
+    /// indicating success or failure. Only returned on the client side.
+    Final(Option<Vec<u8>>),
+    /// The exchange completed successfully. The data has to be sent as additional data with the
+    /// indication of success. Only returned on the server side.
+    Success(Option<Vec<u8>>),
+}
+
 // FIXME: This is wrong. There are three outcomes: Authentication Successfully ended, Auth is
 //  still in progress, authentication errored.
 //  *Completely* independent of that a mech may return data, even in the case of an error.
@@ -177,6 +287,7 @@ impl SessionData {
         Self {
             callback,
             property_cache: HashMap::new(),
+            c_string_cache: HashMap::new(),
             mechanism,
             side,
             channel_binding_data: None,
@@ -185,6 +296,10 @@ impl SessionData {
 }
 
 impl SessionData {
+    pub fn get_mechname(&self) -> &'static Mechname {
+        self.mechanism.mechanism
+    }
+
     pub fn callback<P: PropertyQ>(&mut self) -> Result<(), SessionError> {
         let property = P::property();
         self.callback_property(property)
@@ -232,6 +347,41 @@ impl SessionData {
         self.property_cache.insert(property, data);
     }
 
+    pub(crate) fn clear_property<P: PropertyQ>(&mut self) {
+        self.property_cache.remove(&P::property());
+    }
+
+    pub(crate) fn clear_property_raw(&mut self, prop: Gsasl_property) {
+        let property = property_from_code(prop).unwrap();
+        self.property_cache.remove(&property);
+    }
+
+    /// Return a pointer to a NUL-terminated copy of a `String` property
+    ///
+    /// The pointer stays valid for as long as the value of the property doesn't change, i.e.
+    /// repeated calls for an unchanged property return the same pointer.
+    pub(crate) fn get_property_c_str<P: PropertyQ<Item = String>>(
+        &mut self,
+    ) -> Option<*const libc::c_char> {
+        let value = self.get_property::<P>()?;
+        let cstring = match self.c_string_cache.entry(P::property()) {
+            Entry::Occupied(entry) if entry.get().as_bytes() == value.as_bytes() => {
+                entry.into_mut()
+            }
+            entry => {
+                let cstring = CString::new(value.as_bytes()).ok()?;
+                match entry {
+                    Entry::Occupied(mut entry) => {
+                        entry.insert(cstring);
+                        entry.into_mut()
+                    }
+                    Entry::Vacant(entry) => entry.insert(cstring),
+                }
+            }
+        };
+        Some(cstring.as_ptr())
+    }
+
     pub(crate) fn callback_raw(&mut self, prop: Gsasl_property) -> Result<(), SessionError> {
         let property = property_from_code(prop).unwrap();
         self.callback_property(property)
@@ -255,6 +405,41 @@ impl SessionData {
     }
 }
 
+#[cfg(feature = "session_state")]
+impl SessionData {
+    fn export_properties(&self, writer: &mut StateWriter) {
+        let properties: Vec<(&Property, &String)> = EXPORTED_PROPERTIES
+            .iter()
+            .filter_map(|property| {
+                let value = self
+                    .property_cache
+                    .get(property)?
+                    .downcast_ref::<String>()?;
+                Some((property, value))
+            })
+            .collect();
+        writer.put_u8(properties.len() as u8);
+        for (property, value) in properties {
+            writer.put_str(property.name());
+            writer.put_str(value);
+        }
+    }
+
+    fn import_properties(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        for _ in 0..reader.get_u8()? {
+            let name = reader.get_str()?;
+            let property = EXPORTED_PROPERTIES
+                .iter()
+                .find(|property| property.name() == name)
+                .ok_or(StateError::Invalid)?;
+            let value = reader.get_str()?;
+            self.property_cache
+                .insert(*property, Arc::new(value.to_string()));
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,421 @@
+//! Exporting and resuming authentication exchanges in progress
+//!
+//! *requires feature `session_state`*
+//!
+//! A [`Session`] keeps the state of its mechanism in memory, so a multi-round exchange normally
+//! has to be completed by the process that started it. Stateless or load-balanced servers, e.g.
+//! using the [HTTP authentication scheme](crate::protocols::http), can instead export the state
+//! after a step with [`Session::export_state`], send the opaque blob along to wherever the next
+//! message of the client arrives and continue the exchange there with [`SASL::resume_session`].
+//!
+//! The blob contains the mechanism name and side, the state of the mechanism such as nonces and
+//! derived keys, and the non-secret properties set during the exchange, like the authentication
+//! id. It is encrypted and authenticated with ChaCha20-Poly1305 using a [`StateKey`] derived from
+//! a secret shared by all processes of the application. Passwords and keys derived from them are
+//! never part of the blob; resumed sessions query them from the callback again.
+//!
+//! [`SASL::resume_session`] accepts a blob any number of times until it is older than the
+//! maximum age of the key, [`DEFAULT_MAX_AGE`] unless changed. Within that window a captured blob
+//! can be replayed to repeat a step of the exchange. Applications that need to prevent this have
+//! to keep track of the blobs already used, e.g. by their [`StateKey::state_id`], like
+//! [`EncryptedStore`](crate::protocols::http::EncryptedStore) does for the blobs it resumed
+//! itself. Processes not sharing that bookkeeping can each still resume a blob once within the
+//! maximum age.
+//!
+//! Only the server side of the built-in mechanisms that take more than one round currently
+//! supports this, i.e. `SCRAM-SHA-1`, `SCRAM-SHA-256`, `CRAM-MD5` and `LOGIN`. Exporting other
+//! sessions returns [`StateError::UnsupportedMechanism`]. This includes the `-PLUS` variants of
+//! `SCRAM-*`, as their exchange is bound to the channel it started on and can't be continued on
+//! another one.
+
+use crate::error::MechanismArray;
+use crate::mechanism::Authentication;
+use crate::mechname::Mechname;
+use crate::property::properties::{
+    ANONYMOUS_TOKEN, AUTHID, AUTHZID, HOSTNAME, REALM, SCRAM_ITER, SCRAM_SALT, SERVICE,
+};
+use crate::property::Property;
+use crate::session::{Session, Side};
+use crate::SASL;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::alloc::{handle_alloc_error, Layout};
+use std::convert::{TryFrom, TryInto};
+use std::ffi::CStr;
+use std::fmt::{Debug, Display, Formatter};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Maximum age of exported state unless changed with [`StateKey::set_max_age`]
+///
+/// This is also the window in which an exported state can be replayed, see the
+/// [module documentation](self).
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60);
+
+const VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+
+/// Properties that are exported along with the mechanism state
+///
+/// Secrets like the password are left out on purpose.
+pub(crate) const EXPORTED_PROPERTIES: [Property; 8] = [
+    AUTHID,
+    AUTHZID,
+    ANONYMOUS_TOKEN,
+    REALM,
+    SERVICE,
+    HOSTNAME,
+    SCRAM_ITER,
+    SCRAM_SALT,
+];
+
+#[derive(Debug)]
+pub enum StateError {
+    /// The mechanism can't export its state at this point of the exchange, e.g. once it completed
+    Unsupported,
+    /// Sessions of the mechanism can't be exported on this side at all
+    UnsupportedMechanism(MechanismArray),
+    /// The mechanism of the exported state is not available in this provider
+    UnknownMechanism(MechanismArray),
+    /// The state was not exported with this key, was modified or is malformed
+    Invalid,
+    /// The state is older than the maximum age of the key
+    Expired,
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsupported => f.write_str("the mechanism can't export its state at this point"),
+            Self::UnsupportedMechanism(mechanism) => {
+                write!(
+                    f,
+                    "mechanism {} does not support exporting its state",
+                    mechanism
+                )
+            }
+            Self::UnknownMechanism(mechanism) => {
+                write!(f, "mechanism {} of the state is not available", mechanism)
+            }
+            Self::Invalid => f.write_str("the session state is invalid"),
+            Self::Expired => f.write_str("the session state has expired"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// Key protecting exported session state
+pub struct StateKey {
+    cipher: ChaCha20Poly1305,
+    max_age: Duration,
+}
+
+impl StateKey {
+    /// Derive the key from an application secret
+    ///
+    /// The secret should contain at least 32 random bytes and be known to every process that
+    /// exports or resumes sessions.
+    pub fn new(secret: &[u8]) -> Self {
+        let mut mac =
+            <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC can take a key of any size");
+        mac.update(b"rsasl session state");
+        let key = mac.finalize().into_bytes();
+        Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+
+    /// Set how long after exporting a state it can be resumed
+    pub fn set_max_age(&mut self, max_age: Duration) {
+        self.max_age = max_age;
+    }
+
+    /// How long after exporting a state it can be resumed
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    /// Identifier that is unique to each exported state, e.g. to reject states resumed before
+    ///
+    /// Returns `None` if the state is malformed. The identifier is only meaningful once the state
+    /// was successfully resumed, as anyone can make up a malformed state with a given identifier.
+    pub fn state_id(state: &[u8]) -> Option<[u8; NONCE_LEN]> {
+        match state {
+            [VERSION, rest @ ..] if rest.len() >= NONCE_LEN => rest[..NONCE_LEN].try_into().ok(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn seal(&self, state: StateWriter) -> Vec<u8> {
+        let mut plaintext = Vec::with_capacity(state.buf.len() + 8);
+        plaintext.extend_from_slice(&now().to_be_bytes());
+        plaintext.extend_from_slice(&state.buf);
+
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &[VERSION],
+                },
+            )
+            .expect("session state exceeds the ChaCha20-Poly1305 message size limit");
+
+        let mut blob = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        blob.push(VERSION);
+        blob.extend_from_slice(&nonce);
+        blob.extend_from_slice(&ciphertext);
+        blob
+    }
+
+    pub(crate) fn open(&self, blob: &[u8]) -> Result<Vec<u8>, StateError> {
+        let (nonce, ciphertext) = match blob {
+            [VERSION, rest @ ..] if rest.len() >= NONCE_LEN => rest.split_at(NONCE_LEN),
+            _ => return Err(StateError::Invalid),
+        };
+        let mut plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &[VERSION],
+                },
+            )
+            .map_err(|_| StateError::Invalid)?;
+
+        let issued = StateReader::new(&plaintext).get_u64()?;
+        if now().saturating_sub(issued) > self.max_age.as_secs() {
+            return Err(StateError::Expired);
+        }
+        plaintext.drain(..8);
+        Ok(plaintext)
+    }
+}
+
+impl Debug for StateKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateKey")
+            .field("max_age", &self.max_age)
+            .finish()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Default)]
+/// Buffer mechanisms write their state to
+pub struct StateWriter {
+    buf: Vec<u8>,
+}
+
+impl StateWriter {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn put_u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    /// Write length-prefixed bytes
+    pub fn put_bytes(&mut self, value: &[u8]) {
+        self.put_u64(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    pub fn put_str(&mut self, value: &str) {
+        self.put_bytes(value.as_bytes());
+    }
+
+    /// Write bytes that may be absent, e.g. a field not yet set in this step
+    pub fn put_optional(&mut self, value: Option<&[u8]>) {
+        match value {
+            Some(value) => {
+                self.put_u8(1);
+                self.put_bytes(value);
+            }
+            None => self.put_u8(0),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Reader over state written with a [`StateWriter`]
+///
+/// All methods return [`StateError::Invalid`] if the state is too short or malformed.
+pub struct StateReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.buf.len() < len {
+            return Err(StateError::Invalid);
+        }
+        let (value, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(value)
+    }
+
+    pub fn get_u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn get_u64(&mut self) -> Result<u64, StateError> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn get_bytes(&mut self) -> Result<&'a [u8], StateError> {
+        let len = self.get_u64()?;
+        let len = usize::try_from(len).map_err(|_| StateError::Invalid)?;
+        self.take(len)
+    }
+
+    pub fn get_str(&mut self) -> Result<&'a str, StateError> {
+        std::str::from_utf8(self.get_bytes()?).map_err(|_| StateError::Invalid)
+    }
+
+    pub fn get_optional(&mut self) -> Result<Option<&'a [u8]>, StateError> {
+        match self.get_u8()? {
+            0 => Ok(None),
+            1 => self.get_bytes().map(Some),
+            _ => Err(StateError::Invalid),
+        }
+    }
+}
+
+// Helpers for the mechanisms implemented in C
+impl StateWriter {
+    /// Write an optional NUL-terminated string
+    pub(crate) unsafe fn put_c_str(&mut self, value: *const libc::c_char) {
+        if value.is_null() {
+            self.put_optional(None);
+        } else {
+            self.put_optional(Some(CStr::from_ptr(value).to_bytes()));
+        }
+    }
+}
+
+impl StateReader<'_> {
+    /// Read an optional string into a NUL-terminated buffer allocated with `malloc`
+    pub(crate) fn get_c_string(&mut self) -> Result<*mut libc::c_char, StateError> {
+        match self.get_optional()? {
+            None => Ok(std::ptr::null_mut()),
+            Some(value) if value.contains(&0) => Err(StateError::Invalid),
+            Some(value) => Ok(malloc_copy(value)),
+        }
+    }
+}
+
+/// Copy the value into a buffer allocated with `malloc`, NUL-terminated for good measure
+pub(crate) fn malloc_copy(value: &[u8]) -> *mut libc::c_char {
+    unsafe {
+        let buf = libc::malloc(value.len() + 1) as *mut libc::c_char;
+        if buf.is_null() {
+            handle_alloc_error(Layout::array::<u8>(value.len() + 1).unwrap());
+        }
+        std::ptr::copy_nonoverlapping(value.as_ptr().cast(), buf, value.len());
+        *buf.add(value.len()) = 0;
+        buf
+    }
+}
+
+/// Check that sessions of the mechanism can be exported on the given side
+pub(crate) fn check_exportable(mechanism: &Mechname, side: Side) -> Result<(), StateError> {
+    match (side, mechanism.as_str()) {
+        (Side::Server, "SCRAM-SHA-1" | "SCRAM-SHA-256" | "CRAM-MD5" | "LOGIN") => Ok(()),
+        _ => Err(StateError::UnsupportedMechanism(MechanismArray::new(
+            mechanism,
+        ))),
+    }
+}
+
+/// Construct a mechanism from its exported state
+pub(crate) fn import_mechanism(
+    mechanism: &Mechname,
+    side: Side,
+    reader: &mut StateReader,
+) -> Result<Box<dyn Authentication>, StateError> {
+    check_exportable(mechanism, side)?;
+    match mechanism.as_str() {
+        #[cfg(feature = "scram-sha-1")]
+        "SCRAM-SHA-1" => {
+            use crate::gsasl::mechtools::GSASL_HASH_SHA1;
+            crate::mechanisms::scram::state::import_server(GSASL_HASH_SHA1, reader)
+        }
+        #[cfg(feature = "scram-sha-2")]
+        "SCRAM-SHA-256" => {
+            use crate::gsasl::mechtools::GSASL_HASH_SHA256;
+            crate::mechanisms::scram::state::import_server(GSASL_HASH_SHA256, reader)
+        }
+        #[cfg(feature = "cram-md5")]
+        "CRAM-MD5" => crate::mechanisms::cram_md5::state::import_server(reader),
+        #[cfg(feature = "login")]
+        "LOGIN" => crate::mechanisms::login::state::import_server(reader),
+        _ => Err(StateError::UnsupportedMechanism(MechanismArray::new(
+            mechanism,
+        ))),
+    }
+}
+
+impl SASL {
+    /// Resume a session from state exported with [`Session::export_state`]
+    ///
+    /// *requires feature `session_state`*
+    ///
+    /// The mechanism of the session has to be available in this provider. The callback of this
+    /// provider is installed in the resumed session.
+    pub fn resume_session(&self, key: &StateKey, state: &[u8]) -> Result<Session, StateError> {
+        let plaintext = key.open(state)?;
+        let mut reader = StateReader::new(&plaintext);
+
+        let side = match reader.get_u8()? {
+            0 => Side::Client,
+            1 => Side::Server,
+            _ => return Err(StateError::Invalid),
+        };
+        let name = Mechname::new(reader.get_bytes()?).map_err(|_| StateError::Invalid)?;
+        let mechanism = match side {
+            Side::Client => self
+                .client_mech_list()
+                .into_iter()
+                .find(|mechanism| mechanism.mechanism == name),
+            Side::Server => self
+                .server_mech_list()
+                .into_iter()
+                .find(|mechanism| mechanism.mechanism == name),
+        }
+        .ok_or_else(|| StateError::UnknownMechanism(MechanismArray::new(name)))?;
+
+        let session = Session::import_state(self.callback.clone(), mechanism, side, &mut reader)?;
+        if reader.is_empty() {
+            Ok(session)
+        } else {
+            Err(StateError::Invalid)
+        }
+    }
+}
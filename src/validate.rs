@@ -59,11 +59,41 @@ pub mod validations {
         "validate the users saml token",
     ));
 
+    /// SecurID validation
+    ///
+    /// Issued by the `SECURID` server with [`AuthId`], [`AuthzId`] (if not empty), [`Passcode`]
+    /// and [`Pin`] (if the client sent one) set. An application MUST check the passcode and PIN
+    /// and signal how to continue by setting the [`SecuridValidation`] property. Returning an
+    /// error rejects the client, as do [`SecuridOutcome::Reject`] and not setting the property.
+    ///
+    /// [`AuthId`]: crate::property::AuthId
+    /// [`AuthzId`]: crate::property::AuthzId
+    /// [`Passcode`]: crate::property::Passcode
+    /// [`Pin`]: crate::property::Pin
+    /// [`SecuridValidation`]: crate::property::SecuridValidation
+    /// [`SecuridOutcome::Reject`]: crate::property::SecuridOutcome::Reject
     pub const SECURID: Validation = Validation::new(&ValidationDefinition::new(
         "securid",
         "validate the user using SecurID",
     ));
 
+    /// One-time password validation
+    ///
+    /// Issued by the `OTP` server after the response was verified against the stored sequence,
+    /// with [`AuthId`], [`AuthzId`] (if not empty) and the updated [`OtpState`] set, and likewise
+    /// by the `X-OATH` server with the updated [`OathState`]. An application MUST store the
+    /// updated entry in place of the one it provided, failing if that one was changed in the
+    /// meantime, so the same password can't be used twice.
+    ///
+    /// [`AuthId`]: crate::property::AuthId
+    /// [`AuthzId`]: crate::property::AuthzId
+    /// [`OtpState`]: crate::property::OtpState
+    /// [`OathState`]: crate::property::OathState
+    pub const OTP: Validation = Validation::new(&ValidationDefinition::new(
+        "otp",
+        "store the updated one-time password sequence",
+    ));
+
     /// GSSAPI validation
     ///
     /// This validation is called at the end of a GSSAPI validation. The properties available depend
@@ -78,16 +108,55 @@ pub mod validations {
     ///
     /// The anonymous authentication allows clients to specify a "token" of 0-255 utf-8 code points
     /// to be provided to the server. This token can be accessed using the [`AnonymousToken`] property.
+    /// The property is not set if the client didn't send a token.
+    ///
+    /// Only issued if the token satisfied the [`AnonymousPolicy`] set by the application, if any.
+    ///
+    /// [`AnonymousPolicy`]: crate::property::AnonymousPolicy
     pub const ANONYMOUS: Validation = Validation::new(&ValidationDefinition::new(
         "anonymous",
         "validate the provided anonymous token",
     ));
 
+    /// Hashed token validation
+    ///
+    /// Issued by the `HT-*` mechanisms with the [`AuthId`] sent by the client. The callback MUST
+    /// look up the token issued to this user for the given mechanism and provide it by setting the
+    /// [`HtToken`] property, or return an error if there is no such token or it has expired. The
+    /// mechanism then checks the hashed token sent by the client against it.
+    pub const HT: Validation = Validation::new(&ValidationDefinition::new(
+        "ht",
+        "look up the token issued to the user",
+    ));
+
+    /// GS2 validation
+    ///
+    /// Issued by `GS2-*` mechanisms once the GSS-API security context is established. Which
+    /// properties name the authenticated peer depends on the GSS-API mechanism, usually its
+    /// context sets [`AuthId`]. [`AuthzId`] is set if the client requested to act as a specific
+    /// authorization identity and the application MUST check that the peer is allowed to do so.
+    ///
+    /// [`AuthId`]: crate::property::AuthId
+    /// [`AuthzId`]: crate::property::AuthzId
+    pub const GS2: Validation = Validation::new(&ValidationDefinition::new(
+        "gs2",
+        "validate the peer of an established GSS-API context",
+    ));
+
     /// External validation
     ///
     /// This validation relies on external information outside the protocol connection itself, e.g.
-    /// TLS client certificates, originating UID/GID of an UNIX socket connection, or source IP. No
-    /// properties are provided.
+    /// TLS client certificates, originating UID/GID of an UNIX socket connection, or source IP.
+    /// [`AuthzId`] is set if the client requested to act as a specific authorization identity,
+    /// otherwise the identity derived from the external information is to be used.
+    ///
+    /// The mechanism itself has no access to that information. Servers can attach it to the
+    /// session before the exchange with the [`TlsClientIdentity`] and [`PeerCredentials`]
+    /// properties for the validation to inspect.
+    ///
+    /// [`AuthzId`]: crate::property::AuthzId
+    /// [`TlsClientIdentity`]: crate::property::TlsClientIdentity
+    /// [`PeerCredentials`]: crate::property::PeerCredentials
     pub const EXTERNAL: Validation = Validation::new(&ValidationDefinition::new(
         "external",
         "validate the connection using External information",
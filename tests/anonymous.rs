@@ -0,0 +1,129 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{
+    properties, AnonymousAccess, AnonymousOutcome, AnonymousPolicy, AnonymousToken,
+    TraceRequirement,
+};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::validate::validations::ANONYMOUS;
+use rsasl::validate::Validation;
+use rsasl::{Property, SASL};
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Applies the policy, if any, and turns away `banned@example.org`
+struct Archive(Option<Arc<AnonymousPolicy>>);
+impl Callback for Archive {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match (property, &self.0) {
+            (properties::ANONYMOUS_ACCESS, Some(policy)) => {
+                session.set_property::<AnonymousAccess>(policy.clone());
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+
+    fn validate(
+        &self,
+        session: &mut SessionData,
+        validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        assert_eq!(validation, ANONYMOUS);
+        let token = session.get_property::<AnonymousToken>();
+        if token.as_deref().map(String::as_str) == Some("banned@example.org") {
+            return Err(SessionError::AuthenticationFailure);
+        }
+        Ok(())
+    }
+}
+
+fn server(policy: Option<&Arc<AnonymousPolicy>>) -> Session {
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(Archive(policy.cloned())));
+    sasl.server_start(Mechname::new(b"ANONYMOUS").unwrap())
+        .unwrap()
+}
+
+fn login(policy: Option<&Arc<AnonymousPolicy>>, trace: &[u8]) -> Result<Outcome, SessionError> {
+    server(policy).step_outcome(Some(trace))
+}
+
+#[test]
+fn optional_trace() {
+    let sasl = SASL::new();
+    let mut client = sasl
+        .client_start(Mechname::new(b"ANONYMOUS").unwrap())
+        .unwrap();
+    let response = match client.step_outcome(None).unwrap() {
+        Outcome::Final(Some(response)) => response,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    assert!(response.is_empty());
+
+    let mut server = server(None);
+    assert_eq!(
+        server.step_outcome(Some(&response)).unwrap(),
+        Outcome::Success(None)
+    );
+    assert!(server.get_property::<AnonymousToken>().is_none());
+}
+
+#[test]
+fn multibyte_trace() {
+    // 255 characters, but 1020 bytes
+    let trace = "\u{1F4E7}".repeat(255);
+    assert!(login(None, trace.as_bytes()).is_ok());
+    let trace = "\u{1F4E7}".repeat(256);
+    assert!(login(None, trace.as_bytes()).is_err());
+}
+
+#[test]
+fn policy() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let audit_log = log.clone();
+    let policy = Arc::new(
+        AnonymousPolicy::new()
+            .require(TraceRequirement::Email)
+            .rate_limit(2, Duration::from_secs(3600))
+            .audit(move |attempt| {
+                let trace = attempt.trace.map(str::to_string);
+                audit_log.lock().unwrap().push((trace, attempt.outcome));
+            }),
+    );
+
+    assert!(login(Some(&policy), b"reader@example.org").is_ok());
+    assert!(login(Some(&policy), b"reader").is_err());
+    assert!(login(Some(&policy), b"").is_err());
+    assert!(login(Some(&policy), b"\xff@").is_err());
+    assert!(login(Some(&policy), b"reader@example.org").is_ok());
+    assert!(matches!(
+        login(Some(&policy), b"reader@example.org"),
+        Err(SessionError::AuthenticationFailure)
+    ));
+    assert!(login(Some(&policy), b"banned@example.org").is_err());
+
+    let reader = Some("reader@example.org".to_string());
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            (reader.clone(), AnonymousOutcome::Accepted),
+            (Some("reader".to_string()), AnonymousOutcome::Disallowed),
+            (None, AnonymousOutcome::Disallowed),
+            (Some("\u{FFFD}@".to_string()), AnonymousOutcome::Malformed),
+            (reader.clone(), AnonymousOutcome::Accepted),
+            (reader, AnonymousOutcome::RateLimited),
+            (
+                Some("banned@example.org".to_string()),
+                AnonymousOutcome::Rejected
+            ),
+        ]
+    );
+}
@@ -0,0 +1,142 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechanisms::browser_redirect::BrowserRedirect;
+use rsasl::mechname::Mechname;
+use rsasl::property::{
+    properties, AuthId, OpenID20RedirectUrl, SAML20IDPIdentifier, SAML20RedirectUrl,
+};
+use rsasl::session::{Outcome, SessionData};
+use rsasl::validate::Validation;
+use rsasl::{Property, SASL};
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Sends the user to the given URL, which is the loopback listener of the client itself
+struct Server(String);
+impl Callback for Server {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::SAML20_REDIRECT_URL => {
+                session.set_property::<SAML20RedirectUrl>(Arc::new(self.0.clone()));
+                Ok(())
+            }
+            properties::OPENID20_REDIRECT_URL => {
+                session.set_property::<OpenID20RedirectUrl>(Arc::new(self.0.clone()));
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+
+    fn validate(
+        &self,
+        _session: &mut SessionData,
+        _validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        Ok(())
+    }
+}
+
+/// Request `path` from the listener at `url` like a browser would, returning the response
+fn get(url: &str, path: &str) -> io::Result<String> {
+    let address = url
+        .strip_prefix("http://")
+        .and_then(|url| url.strip_suffix('/'))
+        .unwrap();
+    let mut stream = TcpStream::connect(address)?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: text/html\r\n\r\n",
+        path, address
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+/// A browser that first requests a favicon and then follows the redirect
+fn browser(url: &str) -> io::Result<()> {
+    let url = url.to_string();
+    thread::spawn(move || {
+        let favicon = get(&url, "/favicon.ico").unwrap();
+        assert!(favicon.starts_with("HTTP/1.1 404"));
+        let page = get(&url, "/?SAMLResponse=PHNhbWxwOlJlc3BvbnNl").unwrap();
+        assert!(page.starts_with("HTTP/1.1 200 OK"));
+        assert!(page.contains("Authentication complete"));
+    });
+    Ok(())
+}
+
+#[test]
+fn saml20() {
+    let redirect = BrowserRedirect::bind(browser).unwrap();
+    let url = redirect.url().to_string();
+    let mechanism = Mechname::new(b"SAML20").unwrap();
+
+    let mut client_sasl = SASL::new();
+    client_sasl.install_callback(Arc::new(redirect));
+    let mut client = client_sasl.client_start(mechanism).unwrap();
+    client.set_property::<SAML20IDPIdentifier>(Arc::new("example.org".to_string()));
+
+    let mut server_sasl = SASL::new();
+    server_sasl.install_callback(Arc::new(Server(url.clone())));
+    let mut server = server_sasl.server_start(mechanism).unwrap();
+
+    let initial = match client.step_outcome(None).unwrap() {
+        Outcome::Continue(Some(initial)) => initial,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    let challenge = match server.step_outcome(Some(&initial)).unwrap() {
+        Outcome::Continue(Some(challenge)) => challenge,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    assert_eq!(challenge, url.as_bytes());
+    let response = match client.step_outcome(Some(&challenge)).unwrap() {
+        Outcome::Final(Some(response)) => response,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    assert_eq!(
+        server.step_outcome(Some(&response)).unwrap(),
+        Outcome::Success(None)
+    );
+}
+
+#[test]
+fn openid20_timeout() {
+    let redirect = BrowserRedirect::bind(|_url| Ok(()))
+        .unwrap()
+        .with_timeout(Duration::from_millis(200));
+    let url = redirect.url().to_string();
+    let mechanism = Mechname::new(b"OPENID20").unwrap();
+
+    let mut client_sasl = SASL::new();
+    client_sasl.install_callback(Arc::new(redirect));
+    let mut client = client_sasl.client_start(mechanism).unwrap();
+    client.set_property::<AuthId>(Arc::new("https://openid.example/user".to_string()));
+
+    let mut server_sasl = SASL::new();
+    server_sasl.install_callback(Arc::new(Server(url)));
+    let mut server = server_sasl.server_start(mechanism).unwrap();
+
+    let initial = match client.step_outcome(None).unwrap() {
+        Outcome::Continue(Some(initial)) => initial,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    let challenge = match server.step_outcome(Some(&initial)).unwrap() {
+        Outcome::Continue(Some(challenge)) => challenge,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    match client.step_outcome(Some(&challenge)) {
+        Err(SessionError::Io { source }) => assert_eq!(source.kind(), io::ErrorKind::TimedOut),
+        other => panic!("unexpected client outcome {:?}", other),
+    }
+}
@@ -0,0 +1,130 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{properties, AuthId, CramMd5Secret, HmacMd5Context, Hostname, Password};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::{Property, SASL};
+
+use std::ffi::CString;
+use std::sync::Arc;
+
+/// How the server stores the password of `testuser`
+#[derive(Clone)]
+enum Stored {
+    Plaintext(&'static str),
+    Secret(HmacMd5Context),
+}
+
+struct CB(Stored);
+impl Callback for CB {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        let authid = session.get_property::<AuthId>();
+        if authid.as_deref().map(String::as_str) != Some("testuser") {
+            return Err(SessionError::NoCallback { property });
+        }
+        match (property, &self.0) {
+            (properties::PASSWORD, Stored::Plaintext(password)) => {
+                session.set_property::<Password>(Arc::new(password.to_string()));
+                Ok(())
+            }
+            (properties::CRAM_MD5_SECRET, Stored::Secret(secret)) => {
+                session.set_property::<CramMd5Secret>(Arc::new(*secret));
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+}
+
+fn sessions(stored: Stored) -> (Session, Session) {
+    let mechanism = Mechname::new(b"CRAM-MD5").unwrap();
+    let mut client = SASL::new().client_start(mechanism).unwrap();
+    client.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    client.set_property::<Password>(Arc::new("secret".to_string()));
+
+    let mut server_sasl = SASL::new();
+    server_sasl.install_callback(Arc::new(CB(stored)));
+    let server = server_sasl.server_start(mechanism).unwrap();
+    (client, server)
+}
+
+/// Run the exchange, returning the challenge the server sent
+fn exchange(client: &mut Session, server: &mut Session) -> Result<Vec<u8>, SessionError> {
+    let challenge = match server.step_outcome(None)? {
+        Outcome::Continue(Some(challenge)) => challenge,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    let response = match client.step_outcome(Some(&challenge))? {
+        Outcome::Final(Some(response)) => response,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    assert!(response.starts_with(b"testuser "));
+    assert_eq!(
+        server.step_outcome(Some(&response))?,
+        Outcome::Success(None)
+    );
+    Ok(challenge)
+}
+
+#[test]
+fn plaintext_password() {
+    let (mut client, mut server) = sessions(Stored::Plaintext("secret"));
+    let challenge = exchange(&mut client, &mut server).unwrap();
+    assert!(challenge.starts_with(b"<"));
+    assert!(challenge.ends_with(b"@localhost>"));
+    assert_eq!(
+        server
+            .get_property::<AuthId>()
+            .as_deref()
+            .map(String::as_str),
+        Some("testuser")
+    );
+}
+
+#[test]
+fn wrong_password() {
+    let (mut client, mut server) = sessions(Stored::Plaintext("wrong"));
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn dovecot_secret() {
+    let stored = HmacMd5Context::from_password(b"secret").to_dovecot();
+    let secret = HmacMd5Context::from_dovecot(&stored).unwrap();
+    let (mut client, mut server) = sessions(Stored::Secret(secret));
+    exchange(&mut client, &mut server).unwrap();
+
+    let wrong = HmacMd5Context::from_password(b"wrong");
+    let (mut client, mut server) = sessions(Stored::Secret(wrong));
+    assert!(exchange(&mut client, &mut server).is_err());
+}
+
+#[test]
+fn cyrus_secret() {
+    let stored = HmacMd5Context::from_password(b"secret").to_cyrus();
+    let secret = HmacMd5Context::from_cyrus(&stored).unwrap();
+    let (mut client, mut server) = sessions(Stored::Secret(secret));
+    exchange(&mut client, &mut server).unwrap();
+}
+
+#[test]
+fn challenge_hostname() {
+    let (mut client, mut server) = sessions(Stored::Plaintext("secret"));
+    server.set_property::<Hostname>(Arc::new(CString::new("mail.example.org").unwrap()));
+    let challenge = exchange(&mut client, &mut server).unwrap();
+    assert!(challenge.ends_with(b"@mail.example.org>"));
+}
+
+#[test]
+fn malformed_response() {
+    let (_, mut server) = sessions(Stored::Plaintext("secret"));
+    server.step_outcome(None).unwrap();
+    assert!(server.step_outcome(Some(b"testuser")).is_err());
+}
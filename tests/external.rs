@@ -0,0 +1,148 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{AuthzId, CertificateIdentity, SubjectAltName, TlsClientIdentity};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::validate::validations::EXTERNAL;
+use rsasl::validate::Validation;
+use rsasl::SASL;
+
+use std::sync::Arc;
+
+/// Lets `backup.example.org` act as `backup` and nothing else
+struct Certificates;
+impl Callback for Certificates {
+    fn validate(
+        &self,
+        session: &mut SessionData,
+        validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        assert_eq!(validation, EXTERNAL);
+        let identity = session
+            .get_property::<TlsClientIdentity>()
+            .ok_or(SessionError::AuthenticationFailure)?;
+        let backup = SubjectAltName::Dns("backup.example.org".to_string());
+        if !identity.subject_alt_names.contains(&backup) {
+            return Err(SessionError::AuthenticationFailure);
+        }
+        match session
+            .get_property::<AuthzId>()
+            .as_deref()
+            .map(String::as_str)
+        {
+            None | Some("backup") => Ok(()),
+            Some(_) => Err(SessionError::AuthenticationFailure),
+        }
+    }
+}
+
+fn server_for(dns: &str) -> Session {
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(Certificates));
+    let mut server = sasl
+        .server_start(Mechname::new(b"EXTERNAL").unwrap())
+        .unwrap();
+    server.set_property::<TlsClientIdentity>(Arc::new(CertificateIdentity {
+        subject: format!("CN={}", dns),
+        subject_alt_names: vec![SubjectAltName::Dns(dns.to_string())],
+    }));
+    server
+}
+
+fn client(authzid: Option<&str>) -> Vec<u8> {
+    let sasl = SASL::new();
+    let mut client = sasl
+        .client_start(Mechname::new(b"EXTERNAL").unwrap())
+        .unwrap();
+    if let Some(authzid) = authzid {
+        client.set_property::<AuthzId>(Arc::new(authzid.to_string()));
+    }
+    match client.step_outcome(None).unwrap() {
+        Outcome::Final(Some(response)) => response,
+        other => panic!("unexpected client outcome {:?}", other),
+    }
+}
+
+#[test]
+fn sends_authzid() {
+    assert_eq!(client(Some("backup")), b"backup");
+    assert_eq!(client(None), b"");
+}
+
+#[test]
+fn certificate() {
+    let mut server = server_for("backup.example.org");
+    assert_eq!(
+        server.step_outcome(Some(&client(None))).unwrap(),
+        Outcome::Success(None)
+    );
+    assert!(server.get_property::<AuthzId>().is_none());
+
+    let mut server = server_for("backup.example.org");
+    assert_eq!(
+        server.step_outcome(Some(&client(Some("backup")))).unwrap(),
+        Outcome::Success(None)
+    );
+}
+
+#[test]
+fn rejected() {
+    let mut server = server_for("backup.example.org");
+    assert!(matches!(
+        server.step_outcome(Some(&client(Some("root")))),
+        Err(SessionError::AuthenticationFailure)
+    ));
+
+    let mut server = server_for("web.example.org");
+    assert!(matches!(
+        server.step_outcome(Some(b"")),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn waits_for_response() {
+    let mut server = server_for("backup.example.org");
+    assert_eq!(server.step_outcome(None).unwrap(), Outcome::Continue(None));
+    assert!(server.step_outcome(Some(b"\xff")).is_err());
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[test]
+fn peer_credentials() {
+    use rsasl::property::{PeerCredentials, UnixCredentials};
+    use std::os::unix::net::UnixStream;
+
+    struct LocalAdmin;
+    impl Callback for LocalAdmin {
+        fn validate(
+            &self,
+            session: &mut SessionData,
+            _validation: Validation,
+            _mechanism: &Mechname,
+        ) -> Result<(), SessionError> {
+            let credentials = session.get_property::<PeerCredentials>().unwrap();
+            if credentials.uid != unsafe { libc::getuid() } {
+                return Err(SessionError::AuthenticationFailure);
+            }
+            Ok(())
+        }
+    }
+
+    let (socket, _peer) = UnixStream::pair().unwrap();
+    let credentials = UnixCredentials::of(&socket).unwrap();
+    #[cfg(target_os = "linux")]
+    assert_eq!(credentials.pid, Some(std::process::id() as i32));
+
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(LocalAdmin));
+    let mut server = sasl
+        .server_start(Mechname::new(b"EXTERNAL").unwrap())
+        .unwrap();
+    server.set_property::<PeerCredentials>(Arc::new(credentials));
+    assert_eq!(
+        server.step_outcome(Some(b"")).unwrap(),
+        Outcome::Success(None)
+    );
+}
@@ -0,0 +1,285 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechanisms::gs2::context::{GssContext, GssMechanism, GssStep};
+use rsasl::mechanisms::gs2::mechinfo::{mechanism_name, register};
+use rsasl::mechname::Mechname;
+use rsasl::property::{AuthId, AuthzId};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::validate::{validations, Validation};
+use rsasl::SASL;
+
+use std::sync::Arc;
+
+/// A toy GSS-API mechanism
+///
+/// The initiator sends its name, the acceptor a nonce, the initiator the nonce with the channel
+/// bindings and the acceptor confirms with a final token. `STANDARD` selects whether the initial
+/// token has the standard header.
+struct Toy<const STANDARD: bool>;
+
+const STANDARD_OID: &[u8] = b"\x2b\x06\x01\x04\x01\x82\x37\x02";
+const NONSTD_OID: &[u8] = b"\x2b\x06\x01\x04\x01\x82\x37\x03";
+
+fn initial_token(standard: bool, name: &[u8]) -> Vec<u8> {
+    if standard {
+        let mut token = vec![0x60, (2 + STANDARD_OID.len() + name.len()) as u8, 0x06];
+        token.push(STANDARD_OID.len() as u8);
+        token.extend_from_slice(STANDARD_OID);
+        token.extend_from_slice(name);
+        token
+    } else {
+        name.to_vec()
+    }
+}
+
+struct Initiator {
+    standard: bool,
+    channel_bindings: Vec<u8>,
+    step: usize,
+}
+
+impl GssContext for Initiator {
+    fn step(
+        &mut self,
+        _session: &mut SessionData,
+        token: Option<&[u8]>,
+    ) -> Result<GssStep, SessionError> {
+        self.step += 1;
+        match (self.step, token) {
+            (1, None) => Ok(GssStep::Continue(initial_token(self.standard, b"alice"))),
+            (2, Some(nonce)) => {
+                let mut token = nonce.to_vec();
+                token.extend_from_slice(&self.channel_bindings);
+                Ok(GssStep::Continue(token))
+            }
+            (3, Some(b"ok")) => Ok(GssStep::Complete(None)),
+            _ => Err(SessionError::AuthenticationFailure),
+        }
+    }
+}
+
+struct Acceptor {
+    standard: bool,
+    channel_bindings: Vec<u8>,
+    name: Option<String>,
+}
+
+impl GssContext for Acceptor {
+    fn step(
+        &mut self,
+        session: &mut SessionData,
+        token: Option<&[u8]>,
+    ) -> Result<GssStep, SessionError> {
+        let token = token.ok_or(SessionError::AuthenticationFailure)?;
+        match self.name.take() {
+            None => {
+                let name = if self.standard {
+                    // The header restored by the server must match what the client created
+                    let framing = initial_token(true, b"");
+                    let name = &token[framing.len().min(token.len())..];
+                    if token != initial_token(true, name) {
+                        return Err(SessionError::AuthenticationFailure);
+                    }
+                    name
+                } else {
+                    token
+                };
+                self.name = Some(String::from_utf8(name.to_vec()).unwrap());
+                Ok(GssStep::Continue(b"nonce".to_vec()))
+            }
+            Some(name) => {
+                let mut expected = b"nonce".to_vec();
+                expected.extend_from_slice(&self.channel_bindings);
+                if token != expected {
+                    return Err(SessionError::AuthenticationFailure);
+                }
+                session.set_property::<AuthId>(Arc::new(name));
+                Ok(GssStep::Complete(Some(b"ok".to_vec())))
+            }
+        }
+    }
+}
+
+impl<const STANDARD: bool> GssMechanism for Toy<STANDARD> {
+    const OID: &'static [u8] = if STANDARD { STANDARD_OID } else { NONSTD_OID };
+    type Client = Initiator;
+    type Server = Acceptor;
+
+    fn client(
+        _session: &mut SessionData,
+        channel_bindings: &[u8],
+    ) -> Result<Initiator, SessionError> {
+        Ok(Initiator {
+            standard: STANDARD,
+            channel_bindings: channel_bindings.to_vec(),
+            step: 0,
+        })
+    }
+
+    fn server(
+        _session: &mut SessionData,
+        channel_bindings: &[u8],
+    ) -> Result<Acceptor, SessionError> {
+        Ok(Acceptor {
+            standard: STANDARD,
+            channel_bindings: channel_bindings.to_vec(),
+            name: None,
+        })
+    }
+}
+
+struct CB;
+impl Callback for CB {
+    fn validate(
+        &self,
+        session: &mut SessionData,
+        validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        let authid = session.get_property::<AuthId>();
+        let authzid = session.get_property::<AuthzId>();
+        match (validation, authid.as_deref(), authzid.as_deref()) {
+            (validations::GS2, Some(authid), None) if authid == "alice" => Ok(()),
+            (validations::GS2, Some(authid), Some(authzid))
+                if authid == "alice" && authzid == "admin,ops" =>
+            {
+                Ok(())
+            }
+            _ => Err(SessionError::AuthenticationFailure),
+        }
+    }
+}
+
+fn sessions<M: GssMechanism>(plus: bool) -> (Session, Session) {
+    let name = mechanism_name(M::OID);
+    let name = if plus { format!("{}-PLUS", name) } else { name };
+    let mechanism = Mechname::new(name.as_bytes()).unwrap();
+
+    let mut sasl = SASL::new();
+    register::<M>(&mut sasl);
+    let client = sasl.client_start(mechanism).unwrap();
+    sasl.install_callback(Arc::new(CB));
+    let server = sasl.server_start(mechanism).unwrap();
+    (client, server)
+}
+
+/// Run the exchange, returning the client's first message
+fn exchange(client: &mut Session, server: &mut Session) -> Result<Vec<u8>, SessionError> {
+    let first = match client.step_outcome(None)? {
+        Outcome::Continue(Some(first)) => first,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    let nonce = match server.step_outcome(Some(&first))? {
+        Outcome::Continue(Some(nonce)) => nonce,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    let response = match client.step_outcome(Some(&nonce))? {
+        Outcome::Continue(Some(response)) => response,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    let ok = match server.step_outcome(Some(&response))? {
+        Outcome::Success(Some(ok)) => ok,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    assert_eq!(client.step_outcome(Some(&ok))?, Outcome::Final(None));
+    Ok(first)
+}
+
+#[test]
+fn names() {
+    let mut sasl = SASL::new();
+    register::<Toy<true>>(&mut sasl);
+    let name = mechanism_name(STANDARD_OID);
+    assert_eq!(name.len(), 15);
+    assert!(sasl.client_supports(Mechname::new(name.as_bytes()).unwrap()));
+    let plus = format!("{}-PLUS", name);
+    assert!(sasl.server_supports(Mechname::new(plus.as_bytes()).unwrap()));
+    let other = mechanism_name(NONSTD_OID);
+    assert!(!sasl.client_supports(Mechname::new(other.as_bytes()).unwrap()));
+}
+
+#[test]
+fn standard_token() {
+    let (mut client, mut server) = sessions::<Toy<true>>(false);
+    let first = exchange(&mut client, &mut server).unwrap();
+    assert_eq!(first, b"n,,alice");
+    assert_eq!(
+        server
+            .get_property::<AuthId>()
+            .as_deref()
+            .map(String::as_str),
+        Some("alice")
+    );
+}
+
+#[test]
+fn nonstandard_token() {
+    let (mut client, mut server) = sessions::<Toy<false>>(false);
+    let first = exchange(&mut client, &mut server).unwrap();
+    assert_eq!(first, b"F,n,,alice");
+}
+
+#[test]
+fn authzid() {
+    let (mut client, mut server) = sessions::<Toy<true>>(false);
+    client.set_property::<AuthzId>(Arc::new("admin,ops".to_string()));
+    let first = exchange(&mut client, &mut server).unwrap();
+    assert_eq!(first, b"n,a=admin=2Cops,alice");
+    assert_eq!(
+        server
+            .get_property::<AuthzId>()
+            .as_deref()
+            .map(String::as_str),
+        Some("admin,ops")
+    );
+
+    let (mut client, mut server) = sessions::<Toy<true>>(false);
+    client.set_property::<AuthzId>(Arc::new("root".to_string()));
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn channel_binding() {
+    let (mut client, mut server) = sessions::<Toy<false>>(true);
+    client.set_channel_binding_data("tls-exporter", Box::new([1; 32]));
+    server.set_channel_binding_data("tls-exporter", Box::new([1; 32]));
+    let first = exchange(&mut client, &mut server).unwrap();
+    assert_eq!(first, b"F,p=tls-exporter,,alice");
+
+    let (mut client, mut server) = sessions::<Toy<false>>(true);
+    client.set_channel_binding_data("tls-exporter", Box::new([1; 32]));
+    server.set_channel_binding_data("tls-exporter", Box::new([2; 32]));
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::AuthenticationFailure)
+    ));
+
+    let (mut client, mut server) = sessions::<Toy<false>>(true);
+    client.set_channel_binding_data("tls-exporter", Box::new([1; 32]));
+    server.set_channel_binding_data("tls-server-end-point", Box::new([1; 32]));
+    let error = exchange(&mut client, &mut server).unwrap_err();
+    assert!(error.is_mechanism_error());
+
+    let (mut client, _) = sessions::<Toy<false>>(true);
+    let error = client.step_outcome(None).unwrap_err();
+    assert!(error.is_mechanism_error());
+}
+
+#[test]
+fn downgrade() {
+    // The client supports channel binding but the server didn't seem to
+    let (mut client, mut server) = sessions::<Toy<true>>(false);
+    client.set_channel_binding_data("tls-exporter", Box::new([1; 32]));
+    let first = exchange(&mut client, &mut server).unwrap();
+    assert_eq!(first, b"y,,alice");
+
+    // But it did, so an attacker must have removed the -PLUS variant
+    let (mut client, mut server) = sessions::<Toy<true>>(false);
+    client.set_channel_binding_data("tls-exporter", Box::new([1; 32]));
+    server.set_channel_binding_data("tls-exporter", Box::new([1; 32]));
+    let error = exchange(&mut client, &mut server).unwrap_err();
+    assert!(error.is_mechanism_error());
+}
@@ -0,0 +1,103 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{AuthId, HtToken};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::validate::validations::HT;
+use rsasl::validate::Validation;
+use rsasl::SASL;
+
+use std::sync::Arc;
+
+struct TokenStore;
+impl Callback for TokenStore {
+    fn validate(
+        &self,
+        session: &mut SessionData,
+        validation: Validation,
+        mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        assert_eq!(validation, HT);
+        assert!(mechanism.as_str().starts_with("HT-SHA-256-"));
+        match session.get_property::<AuthId>() {
+            Some(authid) if authid.as_str() == "testuser" => {
+                session.set_property::<HtToken>(Arc::new("s3cr3tt0k3n".to_string()));
+                Ok(())
+            }
+            _ => Err(SessionError::AuthenticationFailure),
+        }
+    }
+}
+
+fn sessions(mechanism: &str, token: &str) -> (Session, Session) {
+    let client_sasl = SASL::new();
+    let mut server_sasl = SASL::new();
+    server_sasl.install_callback(Arc::new(TokenStore));
+    let mechanism = Mechname::new(mechanism.as_bytes()).unwrap();
+
+    let mut client = client_sasl.client_start(mechanism).unwrap();
+    client.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    client.set_property::<HtToken>(Arc::new(token.to_string()));
+    let server = server_sasl.server_start(mechanism).unwrap();
+    (client, server)
+}
+
+/// Run the single round trip exchange, returning the server outcome and the client result of
+/// processing the additional data
+fn exchange(
+    client: &mut Session,
+    server: &mut Session,
+) -> Result<Result<(), SessionError>, SessionError> {
+    let initial = match client.step_outcome(None).unwrap() {
+        Outcome::Continue(Some(initial)) => initial,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    assert!(initial.starts_with(b"testuser\0"));
+    assert_eq!(initial.len(), b"testuser\0".len() + 32);
+
+    let additional_data = match server.step_outcome(Some(&initial))? {
+        Outcome::Success(Some(data)) => data,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    Ok(client
+        .step_outcome(Some(&additional_data))
+        .map(|outcome| assert_eq!(outcome, Outcome::Final(None))))
+}
+
+#[test]
+fn ht_sha_256_none() {
+    let (mut client, mut server) = sessions("HT-SHA-256-NONE", "s3cr3tt0k3n");
+    exchange(&mut client, &mut server).unwrap().unwrap();
+}
+
+#[test]
+fn ht_sha_256_wrong_token() {
+    let (mut client, mut server) = sessions("HT-SHA-256-NONE", "wrong");
+    assert!(exchange(&mut client, &mut server).is_err());
+}
+
+#[test]
+fn ht_sha_256_forged_server() {
+    let (mut client, _) = sessions("HT-SHA-256-NONE", "s3cr3tt0k3n");
+    client.step_outcome(None).unwrap();
+    assert!(client.step_outcome(Some(&[0; 32])).is_err());
+}
+
+#[test]
+fn ht_sha_256_channel_binding() {
+    let (mut client, mut server) = sessions("HT-SHA-256-EXPR", "s3cr3tt0k3n");
+    client.set_channel_binding_data("tls-exporter", Box::new([1, 2, 3]));
+    server.set_channel_binding_data("tls-exporter", Box::new([1, 2, 3]));
+    exchange(&mut client, &mut server).unwrap().unwrap();
+
+    // Different channels, e.g. because of a MITM
+    let (mut client, mut server) = sessions("HT-SHA-256-EXPR", "s3cr3tt0k3n");
+    client.set_channel_binding_data("tls-exporter", Box::new([1, 2, 3]));
+    server.set_channel_binding_data("tls-exporter", Box::new([4, 5, 6]));
+    assert!(exchange(&mut client, &mut server).is_err());
+
+    // Channel binding data of the wrong type
+    let (mut client, _) = sessions("HT-SHA-256-ENDP", "s3cr3tt0k3n");
+    client.set_channel_binding_data("tls-unique", Box::new([1, 2, 3]));
+    assert!(client.step_outcome(None).is_err());
+}
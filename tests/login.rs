@@ -0,0 +1,145 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{properties, AuthId, LoginPromptText, LoginPrompts, Password};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::validate::{validations, Validation};
+use rsasl::{Property, SASL};
+
+use std::sync::Arc;
+
+/// Accepts `testuser` with the password `secret`, asking with the prompts given if any
+struct CB(Option<Arc<LoginPromptText>>);
+impl Callback for CB {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match (property, &self.0) {
+            (properties::LOGIN_PROMPTS, Some(prompts)) => {
+                session.set_property::<LoginPrompts>(prompts.clone());
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+
+    fn validate(
+        &self,
+        session: &mut SessionData,
+        validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        assert_eq!(validation, validations::SIMPLE);
+        let authid = session.get_property::<AuthId>();
+        let password = session.get_property::<Password>();
+        match (authid.as_deref(), password.as_deref()) {
+            (Some(authid), Some(password)) if authid == "testuser" && password == "secret" => {
+                Ok(())
+            }
+            _ => Err(SessionError::AuthenticationFailure),
+        }
+    }
+}
+
+fn client(password: &str) -> Session {
+    let mut client = SASL::new()
+        .client_start(Mechname::new(b"LOGIN").unwrap())
+        .unwrap();
+    client.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    client.set_property::<Password>(Arc::new(password.to_string()));
+    client
+}
+
+fn server(prompts: Option<LoginPromptText>) -> Session {
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(CB(prompts.map(Arc::new))));
+    sasl.server_start(Mechname::new(b"LOGIN").unwrap()).unwrap()
+}
+
+/// Run the exchange, returning the prompts the server sent
+fn exchange(client: &mut Session, server: &mut Session) -> Result<Vec<Vec<u8>>, SessionError> {
+    let mut prompts = Vec::new();
+    let mut response = None;
+    loop {
+        match server.step_outcome(response.as_deref())? {
+            Outcome::Continue(Some(prompt)) => {
+                response = match client.step_outcome(Some(&prompt))? {
+                    Outcome::Continue(data) | Outcome::Final(data) => data,
+                    other => panic!("unexpected client outcome {:?}", other),
+                };
+                prompts.push(prompt);
+            }
+            Outcome::Success(None) => return Ok(prompts),
+            other => panic!("unexpected server outcome {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn default_prompts() {
+    let mut server = server(None);
+    let prompts = exchange(&mut client("secret"), &mut server).unwrap();
+    assert_eq!(prompts, [&b"Username:"[..], b"Password:"]);
+    assert_eq!(
+        server
+            .get_property::<AuthId>()
+            .as_deref()
+            .map(String::as_str),
+        Some("testuser")
+    );
+}
+
+#[test]
+fn configured_prompts() {
+    let prompts = LoginPromptText::new("Benutzername:", "Passwort:").unwrap();
+    let sent = exchange(&mut client("secret"), &mut server(Some(prompts))).unwrap();
+    assert_eq!(sent, [&b"Benutzername:"[..], b"Passwort:"]);
+}
+
+#[test]
+fn wrong_password() {
+    assert!(matches!(
+        exchange(&mut client("hunter2"), &mut server(None)),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn interprets_prompts() {
+    let mut client = client("secret");
+    // A server asking for the password first
+    assert_eq!(
+        client.step_outcome(Some(b"Password")).unwrap(),
+        Outcome::Continue(Some(b"secret".to_vec()))
+    );
+    assert_eq!(
+        client.step_outcome(Some(b"User Name")).unwrap(),
+        Outcome::Final(Some(b"testuser".to_vec()))
+    );
+
+    // Prompts not recognized are answered in the usual order
+    let mut client = self::client("secret");
+    assert_eq!(
+        client.step_outcome(Some(b"?")).unwrap(),
+        Outcome::Continue(Some(b"testuser".to_vec()))
+    );
+    assert_eq!(
+        client.step_outcome(Some(b"?")).unwrap(),
+        Outcome::Final(Some(b"secret".to_vec()))
+    );
+}
+
+#[test]
+fn initial_response() {
+    let mut server = server(None);
+    assert_eq!(
+        server.step_outcome(Some(b"testuser")).unwrap(),
+        Outcome::Continue(Some(b"Password:".to_vec()))
+    );
+    assert_eq!(
+        server.step_outcome(Some(b"secret")).unwrap(),
+        Outcome::Success(None)
+    );
+}
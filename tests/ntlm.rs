@@ -0,0 +1,151 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{properties, AuthId, NtHash, NtlmSecret, Password, Realm};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::{Property, SASL};
+
+use std::sync::Arc;
+
+/// How the server stores the password of `EXAMPLE\testuser`
+#[derive(Copy, Clone)]
+enum Stored {
+    Plaintext(&'static str),
+    Hash(NtHash),
+}
+
+struct CB(Stored);
+impl Callback for CB {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        let authid = session.get_property::<AuthId>();
+        let realm = session.get_property::<Realm>();
+        if authid.as_deref().map(String::as_str) != Some("testuser")
+            || realm.as_deref().and_then(|realm| realm.to_str().ok()) != Some("EXAMPLE")
+        {
+            return Err(SessionError::NoCallback { property });
+        }
+        match (property, self.0) {
+            (properties::PASSWORD, Stored::Plaintext(password)) => {
+                session.set_property::<Password>(Arc::new(password.to_string()));
+                Ok(())
+            }
+            (properties::NTLM_SECRET, Stored::Hash(hash)) => {
+                session.set_property::<NtlmSecret>(Arc::new(hash));
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+}
+
+fn sessions(stored: Stored, password: &str) -> (Session, Session) {
+    let mechanism = Mechname::new(b"NTLM").unwrap();
+    let mut client = SASL::new().client_start(mechanism).unwrap();
+    client.set_property::<AuthId>(Arc::new("EXAMPLE\\testuser".to_string()));
+    client.set_property::<Password>(Arc::new(password.to_string()));
+
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(CB(stored)));
+    let server = sasl.server_start(mechanism).unwrap();
+    (client, server)
+}
+
+fn exchange(client: &mut Session, server: &mut Session) -> Result<(), SessionError> {
+    let negotiate = match client.step_outcome(None)? {
+        Outcome::Continue(Some(negotiate)) => negotiate,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    let challenge = match server.step_outcome(Some(&negotiate))? {
+        Outcome::Continue(Some(challenge)) => challenge,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    let authenticate = match client.step_outcome(Some(&challenge))? {
+        Outcome::Final(Some(authenticate)) => authenticate,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    assert_eq!(
+        server.step_outcome(Some(&authenticate))?,
+        Outcome::Success(None)
+    );
+    Ok(())
+}
+
+#[test]
+fn password() {
+    let (mut client, mut server) = sessions(Stored::Plaintext("secret"), "secret");
+    exchange(&mut client, &mut server).unwrap();
+    assert_eq!(
+        server
+            .get_property::<AuthId>()
+            .as_deref()
+            .map(String::as_str),
+        Some("testuser")
+    );
+}
+
+#[test]
+fn nt_hash() {
+    let hash = NtHash::from_password("secret");
+    let (mut client, mut server) = sessions(Stored::Hash(hash), "secret");
+    exchange(&mut client, &mut server).unwrap();
+
+    let (mut client, mut server) = sessions(Stored::Hash(hash), "Secret");
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn wrong_password() {
+    let (mut client, mut server) = sessions(Stored::Plaintext("secret"), "hunter2");
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn channel_binding() {
+    let (mut client, mut server) = sessions(Stored::Plaintext("secret"), "secret");
+    client.set_channel_binding_data("tls-server-end-point", Box::new([1; 32]));
+    server.set_channel_binding_data("tls-server-end-point", Box::new([1; 32]));
+    exchange(&mut client, &mut server).unwrap();
+
+    let (mut client, mut server) = sessions(Stored::Plaintext("secret"), "secret");
+    client.set_channel_binding_data("tls-server-end-point", Box::new([2; 32]));
+    server.set_channel_binding_data("tls-server-end-point", Box::new([1; 32]));
+    let error = exchange(&mut client, &mut server).unwrap_err();
+    assert!(error.is_mechanism_error());
+
+    let (mut client, mut server) = sessions(Stored::Plaintext("secret"), "secret");
+    server.set_channel_binding_data("tls-server-end-point", Box::new([1; 32]));
+    let error = exchange(&mut client, &mut server).unwrap_err();
+    assert!(error.is_mechanism_error());
+}
+
+#[test]
+fn tampered_negotiate() {
+    let (mut client, mut server) = sessions(Stored::Plaintext("secret"), "secret");
+    let mut negotiate = match client.step_outcome(None).unwrap() {
+        Outcome::Continue(Some(negotiate)) => negotiate,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    // Change the version, which the MIC covers
+    let last = negotiate.len() - 1;
+    negotiate[last] ^= 1;
+    let challenge = match server.step_outcome(Some(&negotiate)).unwrap() {
+        Outcome::Continue(Some(challenge)) => challenge,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    let authenticate = match client.step_outcome(Some(&challenge)).unwrap() {
+        Outcome::Final(Some(authenticate)) => authenticate,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    let error = server.step_outcome(Some(&authenticate)).unwrap_err();
+    assert!(error.is_mechanism_error());
+}
@@ -0,0 +1,182 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{properties, AuthId, OpenID20OutcomeData, OpenID20RedirectUrl};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::validate::validations::OPENID20;
+use rsasl::validate::Validation;
+use rsasl::{Property, SASL};
+
+use std::sync::Arc;
+
+const REDIRECT: &str = "https://openid.example/login?openid.mode=checkid_setup";
+const OUTCOME: &str = "openid.sreg.email=user@example.org";
+
+/// Accepts the identifier `https://openid.example/user`
+struct Server;
+impl Callback for Server {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::OPENID20_REDIRECT_URL => {
+                session.set_property::<OpenID20RedirectUrl>(Arc::new(REDIRECT.to_string()));
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+
+    fn validate(
+        &self,
+        session: &mut SessionData,
+        validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        assert_eq!(validation, OPENID20);
+        let authid = session.get_property::<AuthId>().unwrap();
+        if authid.as_str() != "https://openid.example/user" {
+            return Err(SessionError::AuthenticationFailure);
+        }
+        session.set_property::<OpenID20OutcomeData>(Arc::new(OUTCOME.to_string()));
+        Ok(())
+    }
+}
+
+struct Browser;
+impl Callback for Browser {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::OPENID20_AUTHENTICATE_IN_BROWSER => {
+                let url = session.get_property::<OpenID20RedirectUrl>().unwrap();
+                assert_eq!(url.as_str(), REDIRECT);
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+}
+
+fn sessions(identifier: &str) -> (Session, Session) {
+    let mechanism = Mechname::new(b"OPENID20").unwrap();
+    let mut client_sasl = SASL::new();
+    client_sasl.install_callback(Arc::new(Browser));
+    let mut client = client_sasl.client_start(mechanism).unwrap();
+    client.set_property::<AuthId>(Arc::new(identifier.to_string()));
+
+    let mut server_sasl = SASL::new();
+    server_sasl.install_callback(Arc::new(Server));
+    let server = server_sasl.server_start(mechanism).unwrap();
+    (client, server)
+}
+
+/// Run the exchange up to the client signalling it completed authentication in the browser
+fn authenticate(client: &mut Session, server: &mut Session) {
+    let initial = match client.step_outcome(None).unwrap() {
+        Outcome::Continue(Some(initial)) => initial,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    let redirect = match server.step_outcome(Some(&initial)).unwrap() {
+        Outcome::Continue(Some(redirect)) => redirect,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    assert_eq!(redirect, REDIRECT.as_bytes());
+    assert_eq!(
+        client.step_outcome(Some(&redirect)).unwrap(),
+        Outcome::Continue(Some(b"=".to_vec()))
+    );
+}
+
+#[test]
+fn outcome_data() {
+    let (mut client, mut server) = sessions("https://openid.example/user");
+    authenticate(&mut client, &mut server);
+    let outcome = match server.step_outcome(Some(b"=")).unwrap() {
+        Outcome::Success(Some(outcome)) => outcome,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    assert_eq!(outcome, OUTCOME.as_bytes());
+    assert_eq!(
+        client.step_outcome(Some(&outcome)).unwrap(),
+        Outcome::Final(None)
+    );
+    assert_eq!(
+        client
+            .get_property::<OpenID20OutcomeData>()
+            .as_deref()
+            .map(String::as_str),
+        Some(OUTCOME)
+    );
+}
+
+#[test]
+fn error_challenge() {
+    let (mut client, mut server) = sessions("https://openid.example/someone");
+    authenticate(&mut client, &mut server);
+    let error = match server.step_outcome(Some(b"=")).unwrap() {
+        Outcome::Continue(Some(error)) => error,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    assert_eq!(error, b"openid.error=fail");
+    let response = match client.step_outcome(Some(&error)).unwrap() {
+        Outcome::Continue(Some(response)) => response,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    assert!(matches!(
+        server.step_outcome(Some(&response)),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn missing_identifier() {
+    let (mut client, _) = sessions("");
+    assert!(matches!(
+        client.step_outcome(None),
+        Err(SessionError::NoProperty {
+            property: properties::AUTHID
+        })
+    ));
+}
+
+#[cfg(feature = "protocol_imap")]
+#[test]
+fn over_imap() {
+    use rsasl::protocols::imap;
+    use rsasl::protocols::line::{ClientAction, ServerAction};
+
+    for (identifier, success) in &[
+        ("https://openid.example/user", true),
+        ("https://openid.example/someone", false),
+    ] {
+        let (client, server) = sessions(identifier);
+        let mut client = imap::client(client, "A1", true);
+        let command = client.start().unwrap();
+        let parsed = imap::parse_authenticate(&command).unwrap();
+        let mut server = imap::server(server, parsed.tag.unwrap());
+
+        let mut action = server.start(parsed.initial_response);
+        let client_action = loop {
+            match action {
+                ServerAction::Continue(line) => {
+                    let response = match client.handle_line(&line).unwrap() {
+                        ClientAction::Respond(response) => response,
+                        other => panic!("client did not respond to a challenge: {:?}", other),
+                    };
+                    action = server.handle_line(&response);
+                }
+                ServerAction::Success(line) | ServerAction::Failure(line, _) => {
+                    break client.handle_line(&line).unwrap();
+                }
+            }
+        };
+        assert_eq!(matches!(client_action, ClientAction::Success), *success);
+        assert_eq!(matches!(client_action, ClientAction::Failure(_)), !*success);
+    }
+}
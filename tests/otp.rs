@@ -0,0 +1,342 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechanisms::otp::oath::{hotp, to_digits, totp};
+use rsasl::mechname::Mechname;
+use rsasl::property::{
+    properties, AuthId, OathAlgorithm, OathCounter, OathEntry, OathState, OtpAlgorithm, OtpEntry,
+    OtpInit, OtpInitParameters, OtpState, Passcode, Password,
+};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::validate::validations::OTP;
+use rsasl::validate::Validation;
+use rsasl::{Property, SASL};
+
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+const PASSPHRASE: &str = "This is a test.";
+
+/// Keeps the sequence of `testuser`, replacing it only if it wasn't changed concurrently
+struct Store(Mutex<OtpEntry>);
+impl Callback for Store {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::OTP_STATE
+                if session
+                    .get_property::<AuthId>()
+                    .as_deref()
+                    .map(String::as_str)
+                    == Some("testuser") =>
+            {
+                let entry = self.0.lock().unwrap().clone();
+                session.set_property::<OtpState>(Arc::new(entry));
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+
+    fn validate(
+        &self,
+        session: &mut SessionData,
+        validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        assert_eq!(validation, OTP);
+        let updated = session.get_property::<OtpState>().unwrap();
+        let mut entry = self.0.lock().unwrap();
+        if updated.sequence >= entry.sequence && updated.seed == entry.seed {
+            return Err(SessionError::AuthenticationFailure);
+        }
+        *entry = updated.as_ref().clone();
+        Ok(())
+    }
+}
+
+fn store(sequence: u32) -> Arc<Store> {
+    let entry = OtpEntry::new(OtpAlgorithm::Md5, PASSPHRASE, "ke1234", sequence);
+    Arc::new(Store(Mutex::new(entry)))
+}
+
+fn sessions(store: &Arc<Store>, password: &str) -> (Session, Session) {
+    let mechanism = Mechname::new(b"OTP").unwrap();
+    let mut client = SASL::new().client_start(mechanism).unwrap();
+    client.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    client.set_property::<Password>(Arc::new(password.to_string()));
+
+    let mut server_sasl = SASL::new();
+    server_sasl.install_callback(store.clone());
+    let server = server_sasl.server_start(mechanism).unwrap();
+    (client, server)
+}
+
+/// Run the exchange, returning the challenge and response
+fn exchange(client: &mut Session, server: &mut Session) -> Result<(String, String), SessionError> {
+    let authid = match client.step_outcome(None)? {
+        Outcome::Continue(Some(authid)) => authid,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    assert_eq!(authid, b"\0testuser");
+    let challenge = match server.step_outcome(Some(&authid))? {
+        Outcome::Continue(Some(challenge)) => challenge,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    let response = match client.step_outcome(Some(&challenge))? {
+        Outcome::Final(Some(response)) => response,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    assert_eq!(
+        server.step_outcome(Some(&response))?,
+        Outcome::Success(None)
+    );
+    Ok((
+        String::from_utf8(challenge).unwrap(),
+        String::from_utf8(response).unwrap(),
+    ))
+}
+
+#[test]
+fn counts_down() {
+    let store = store(500);
+    let (mut client, mut server) = sessions(&store, PASSPHRASE);
+    let (challenge, response) = exchange(&mut client, &mut server).unwrap();
+    assert_eq!(challenge, "otp-md5 499 ke1234 ext");
+    assert!(response.starts_with("hex:"));
+    assert_eq!(store.0.lock().unwrap().sequence, 499);
+
+    let (mut client, mut server) = sessions(&store, PASSPHRASE);
+    let (challenge, _) = exchange(&mut client, &mut server).unwrap();
+    assert_eq!(challenge, "otp-md5 498 ke1234 ext");
+}
+
+#[test]
+fn replayed_response() {
+    let store = store(500);
+    let (mut client, mut server) = sessions(&store, PASSPHRASE);
+    let (_, response) = exchange(&mut client, &mut server).unwrap();
+
+    let (_, mut server) = sessions(&store, PASSPHRASE);
+    server.step_outcome(Some(b"\0testuser")).unwrap();
+    assert!(matches!(
+        server.step_outcome(Some(response.as_bytes())),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn word_response() {
+    // RFC 2289 test vector for md5, sequence 99
+    let entry = OtpEntry::new(OtpAlgorithm::Md5, PASSPHRASE, "TeSt", 100);
+    let store = Arc::new(Store(Mutex::new(entry)));
+    let (_, mut server) = sessions(&store, PASSPHRASE);
+    server.step_outcome(Some(b"\0testuser")).unwrap();
+    let outcome = server
+        .step_outcome(Some(b"word:bail tuft bits gang chef thy"))
+        .unwrap();
+    assert_eq!(outcome, Outcome::Success(None));
+}
+
+/// Re-initializes the sequence with the given parameters
+struct Init(OtpInitParameters);
+impl Callback for Init {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::OTP_INIT => {
+                session.set_property::<OtpInit>(Arc::new(self.0.clone()));
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+}
+
+fn init_client(sequence: u32, seed: &str) -> Session {
+    let mut client_sasl = SASL::new();
+    client_sasl.install_callback(Arc::new(Init(OtpInitParameters {
+        algorithm: OtpAlgorithm::Sha1,
+        sequence,
+        seed: seed.to_string(),
+        passphrase: Some("A new pass phrase".to_string()),
+    })));
+    let mut client = client_sasl
+        .client_start(Mechname::new(b"OTP").unwrap())
+        .unwrap();
+    client.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    client.set_property::<Password>(Arc::new(PASSPHRASE.to_string()));
+    client
+}
+
+#[test]
+fn reinitialize() {
+    let store = store(2);
+    let (_, mut server) = sessions(&store, PASSPHRASE);
+    let mut client = init_client(1000, "ke1235");
+    let (_, response) = exchange(&mut client, &mut server).unwrap();
+    assert!(response.starts_with("init-hex:"));
+    assert_eq!(
+        *store.0.lock().unwrap(),
+        OtpEntry::new(OtpAlgorithm::Sha1, "A new pass phrase", "ke1235", 1000)
+    );
+
+    let (mut client, mut server) = sessions(&store, "A new pass phrase");
+    let (challenge, _) = exchange(&mut client, &mut server).unwrap();
+    assert_eq!(challenge, "otp-sha1 999 ke1235 ext");
+}
+
+#[test]
+fn reinitialize_requires_new_sequence() {
+    let store = store(500);
+    for (sequence, seed) in [(1000, "KE1234"), (0, "ke1235")].iter() {
+        let (_, mut server) = sessions(&store, PASSPHRASE);
+        let mut client = init_client(*sequence, seed);
+        assert!(matches!(
+            exchange(&mut client, &mut server),
+            Err(SessionError::MechanismError(_))
+        ));
+    }
+    assert_eq!(store.0.lock().unwrap().sequence, 500);
+}
+
+#[test]
+fn wrong_passphrase() {
+    let store = store(500);
+    let (mut client, mut server) = sessions(&store, "Not the pass phrase");
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::AuthenticationFailure)
+    ));
+    assert_eq!(store.0.lock().unwrap().sequence, 500);
+}
+
+#[test]
+fn empty_first_message() {
+    let store = store(500);
+    let (_, mut server) = sessions(&store, PASSPHRASE);
+    assert!(matches!(
+        server.step_outcome(Some(b"")),
+        Err(SessionError::MechanismError(_))
+    ));
+}
+
+#[test]
+fn exhausted_sequence() {
+    let store = store(0);
+    let (_, mut server) = sessions(&store, PASSPHRASE);
+    assert!(matches!(
+        server.step_outcome(Some(b"\0testuser")),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+const SECRET: &[u8] = b"12345678901234567890";
+
+/// Keeps the HOTP or TOTP secret of `testuser`
+struct OathStore(Mutex<OathEntry>);
+impl Callback for OathStore {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::OATH_STATE => {
+                let entry = self.0.lock().unwrap().clone();
+                session.set_property::<OathState>(Arc::new(entry));
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+
+    fn validate(
+        &self,
+        session: &mut SessionData,
+        validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        assert_eq!(validation, OTP);
+        let updated = session.get_property::<OathState>().unwrap();
+        *self.0.lock().unwrap() = updated.as_ref().clone();
+        Ok(())
+    }
+}
+
+fn oath_sessions(store: &Arc<OathStore>, passcode: String) -> (Session, Session) {
+    let mechanism = Mechname::new(b"X-OATH").unwrap();
+    let mut client = SASL::new().client_start(mechanism).unwrap();
+    client.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    client.set_property::<Passcode>(Arc::new(passcode));
+
+    let mut server_sasl = SASL::new();
+    server_sasl.install_callback(store.clone());
+    let server = server_sasl.server_start(mechanism).unwrap();
+    (client, server)
+}
+
+#[test]
+fn totp_passcode() {
+    let entry = OathEntry::totp(OathAlgorithm::Sha1, SECRET, 6, 30);
+    let store = Arc::new(OathStore(Mutex::new(entry)));
+    let passcode = to_digits(
+        totp(OathAlgorithm::Sha1, SECRET, 6, 30, SystemTime::now()),
+        6,
+    );
+
+    let (mut client, mut server) = oath_sessions(&store, passcode.clone());
+    let (challenge, response) = exchange(&mut client, &mut server).unwrap();
+    assert!(challenge.starts_with("otp-totp "));
+    assert_eq!(response, format!("oath:{}", passcode));
+    assert!(matches!(
+        store.0.lock().unwrap().counter,
+        OathCounter::Totp { last: Some(_), .. }
+    ));
+
+    // The same passcode can't be used twice
+    let (mut client, mut server) = oath_sessions(&store, passcode);
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn hotp_passcode() {
+    let entry = OathEntry::hotp(OathAlgorithm::Sha1, SECRET, 6);
+    let store = Arc::new(OathStore(Mutex::new(entry)));
+    let passcode = |counter| to_digits(hotp(OathAlgorithm::Sha1, SECRET, 6, counter), 6);
+
+    // The server resynchronizes to a counter ahead of its own
+    let (mut client, mut server) = oath_sessions(&store, passcode(2));
+    let (challenge, _) = exchange(&mut client, &mut server).unwrap();
+    assert_eq!(challenge, "otp-hotp 0");
+    assert_eq!(
+        store.0.lock().unwrap().counter,
+        OathCounter::Hotp { next: 3 }
+    );
+
+    let (mut client, mut server) = oath_sessions(&store, passcode(1));
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn otp_does_not_use_oath_state() {
+    let entry = OathEntry::hotp(OathAlgorithm::Sha1, SECRET, 6);
+    let store = Arc::new(OathStore(Mutex::new(entry)));
+    let mut server_sasl = SASL::new();
+    server_sasl.install_callback(store);
+    let mut server = server_sasl
+        .server_start(Mechname::new(b"OTP").unwrap())
+        .unwrap();
+    assert!(server.step_outcome(Some(b"\0testuser")).is_err());
+}
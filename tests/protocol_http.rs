@@ -0,0 +1,172 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{properties, AuthId, Password};
+#[cfg(feature = "session_state")]
+use rsasl::protocols::http::EncryptedStore;
+use rsasl::protocols::http::{
+    AuthenticationInfo, Challenge, Client, Credentials, MemoryStore, Server, ServerStep, StateStore,
+};
+use rsasl::session::SessionData;
+#[cfg(feature = "session_state")]
+use rsasl::state::StateKey;
+use rsasl::{Property, SASL};
+
+use std::sync::Arc;
+
+struct CB(&'static str);
+impl Callback for CB {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::PASSWORD => {
+                session.set_property::<Password>(Arc::new(self.0.to_string()));
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+}
+
+fn server<S: StateStore>(store: S) -> Server<S> {
+    let mut server = Server::new(store, vec![Mechname::new(b"SCRAM-SHA-256").unwrap()]);
+    server.set_realm("api@example.org".to_string());
+    server
+}
+
+/// Run a SCRAM-SHA-256 exchange, passing every header through its serialized form. Each round
+/// uses a fresh `SASL` on the server side like separate requests would.
+fn exchange<S: StateStore>(
+    server: Server<S>,
+    server_password: &'static str,
+) -> Result<(usize, Client), Challenge> {
+    let server_sasl = || {
+        let mut sasl = SASL::new();
+        sasl.install_callback(Arc::new(CB(server_password)));
+        sasl
+    };
+
+    let challenge = Challenge::parse(&server.challenge().to_string()).unwrap();
+    let mut session = SASL::new()
+        .client_start_suggested(challenge.mechanisms())
+        .unwrap();
+    session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    session.set_property::<Password>(Arc::new("secret".to_string()));
+    let mut client = Client::new(session);
+
+    let mut requests = 1;
+    let mut credentials = client.credentials(&challenge).unwrap();
+    loop {
+        let credentials_header = credentials.to_string();
+        assert!(credentials_header.contains("realm=\"api@example.org\""));
+        let parsed = Credentials::parse(&credentials_header).unwrap();
+        match server.handle(&server_sasl(), &parsed) {
+            ServerStep::Continue(challenge) => {
+                let challenge = Challenge::parse(&challenge.to_string()).unwrap();
+                assert!(challenge.s2s.is_some());
+                credentials = client.handle_challenge(&challenge).unwrap();
+                requests += 1;
+            }
+            ServerStep::Authenticated { info, .. } => {
+                let info = AuthenticationInfo::parse(&info.to_string()).unwrap();
+                client.handle_success(Some(&info)).unwrap();
+                return Ok((requests, client));
+            }
+            ServerStep::Failure { challenge, .. } => return Err(challenge),
+        }
+    }
+}
+
+#[test]
+fn scram_over_requests() {
+    let (requests, _) = exchange(server(MemoryStore::new()), "secret").unwrap();
+    assert_eq!(requests, 2);
+}
+
+#[cfg(feature = "session_state")]
+#[test]
+fn scram_with_encrypted_store() {
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(CB("secret")));
+    let store = EncryptedStore::new(
+        Arc::new(sasl),
+        StateKey::new(b"a secret shared by all servers!!"),
+    );
+    let (requests, _) = exchange(server(store), "secret").unwrap();
+    assert_eq!(requests, 2);
+}
+
+#[cfg(feature = "session_state")]
+#[test]
+fn encrypted_store_rejects_replayed_state() {
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(CB("secret")));
+    let sasl = Arc::new(sasl);
+    let store = EncryptedStore::new(
+        sasl.clone(),
+        StateKey::new(b"a secret shared by all servers!!"),
+    );
+    let server = server(store);
+
+    let challenge = server.challenge();
+    let mut session = sasl.client_start_suggested(challenge.mechanisms()).unwrap();
+    session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    session.set_property::<Password>(Arc::new("secret".to_string()));
+    let mut client = Client::new(session);
+    let credentials = client.credentials(&challenge).unwrap();
+    let challenge = match server.handle(&sasl, &credentials) {
+        ServerStep::Continue(challenge) => challenge,
+        _ => panic!("expected a challenge"),
+    };
+    let credentials = client.handle_challenge(&challenge).unwrap();
+    assert!(matches!(
+        server.handle(&sasl, &credentials),
+        ServerStep::Authenticated { .. }
+    ));
+    assert!(matches!(
+        server.handle(&sasl, &credentials),
+        ServerStep::Failure { error: None, .. }
+    ));
+}
+
+#[test]
+fn scram_bad_password() {
+    let challenge = match exchange(server(MemoryStore::new()), "wrong") {
+        Err(challenge) => challenge,
+        Ok(_) => panic!("authenticated with a wrong password"),
+    };
+    // The failure starts over with the full list of mechanisms
+    assert_eq!(challenge.mechanisms, vec!["SCRAM-SHA-256"]);
+    assert_eq!(challenge.s2s, None);
+}
+
+#[test]
+fn unknown_state_token() {
+    let server = server(MemoryStore::new());
+    let credentials = Credentials {
+        realm: None,
+        mechanism: "SCRAM-SHA-256".to_string(),
+        c2s: Some("Yz1iaXdz".to_string()),
+        s2s: Some("forged".to_string()),
+    };
+    assert!(matches!(
+        server.handle(&SASL::new(), &credentials),
+        ServerStep::Failure { error: None, .. }
+    ));
+}
+
+#[test]
+fn success_without_server_proof_is_rejected() {
+    let challenge = server(MemoryStore::new()).challenge();
+    let mut session = SASL::new()
+        .client_start_suggested(challenge.mechanisms())
+        .unwrap();
+    session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    session.set_property::<Password>(Arc::new("secret".to_string()));
+    let mut client = Client::new(session);
+    client.credentials(&challenge).unwrap();
+    assert!(client.handle_success(None).is_err());
+}
@@ -0,0 +1,91 @@
+use rsasl::mechname::Mechname;
+use rsasl::property::{AuthId, Password};
+use rsasl::protocols::imap;
+use rsasl::protocols::line::{ClientAction, ServerAction};
+use rsasl::SASL;
+
+use std::sync::Arc;
+
+fn scram_exchange(sasl_ir: bool, server_password: &str) -> (Vec<String>, ServerAction) {
+    let sasl = SASL::new();
+    let mechanism = Mechname::new(b"SCRAM-SHA-256").unwrap();
+
+    let mut client_session = sasl.client_start(mechanism).unwrap();
+    client_session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    client_session.set_property::<Password>(Arc::new("secret".to_string()));
+    let mut client = imap::client(client_session, "A1", sasl_ir);
+
+    let command = client.start().unwrap();
+    let parsed = imap::parse_authenticate(&command).unwrap();
+    let mut server_session = sasl
+        .server_start(Mechname::new(parsed.mechanism.as_bytes()).unwrap())
+        .unwrap();
+    server_session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    server_session.set_property::<Password>(Arc::new(server_password.to_string()));
+    let mut server = imap::server(server_session, parsed.tag.unwrap());
+
+    let mut transcript = vec![command.clone()];
+    let mut action = server.start(parsed.initial_response);
+    loop {
+        match action {
+            ServerAction::Continue(line) => {
+                transcript.push(line.clone());
+                let response = match client.handle_line(&line).unwrap() {
+                    ClientAction::Respond(response) => response,
+                    other => panic!("client did not respond to a challenge: {:?}", other),
+                };
+                transcript.push(response.clone());
+                action = server.handle_line(&response);
+            }
+            ServerAction::Success(ref line) => {
+                transcript.push(line.clone());
+                assert!(matches!(
+                    client.handle_line(line).unwrap(),
+                    ClientAction::Success
+                ));
+                return (transcript, action);
+            }
+            ServerAction::Failure(ref line, _) => {
+                transcript.push(line.clone());
+                assert!(matches!(
+                    client.handle_line(line).unwrap(),
+                    ClientAction::Failure(_)
+                ));
+                return (transcript, action);
+            }
+        }
+    }
+}
+
+#[test]
+fn scram_with_sasl_ir() {
+    let (transcript, outcome) = scram_exchange(true, "secret");
+    assert!(matches!(outcome, ServerAction::Success(_)));
+    // Initial response, server-first, client-final, server-final as additional data and the
+    // empty response confirming it, and finally the tagged OK
+    assert_eq!(transcript.len(), 6);
+    assert!(transcript[0].starts_with("A1 AUTHENTICATE SCRAM-SHA-256 "));
+    assert_eq!(transcript[4], "");
+    assert_eq!(transcript[5], "A1 OK AUTHENTICATE completed");
+}
+
+#[test]
+fn scram_without_sasl_ir() {
+    let (transcript, outcome) = scram_exchange(false, "secret");
+    assert!(matches!(outcome, ServerAction::Success(_)));
+    assert_eq!(transcript[0], "A1 AUTHENTICATE SCRAM-SHA-256");
+    assert_eq!(transcript[1], "+ ");
+    assert_eq!(transcript.len(), 8);
+}
+
+#[test]
+fn scram_bad_password() {
+    let (_, outcome) = scram_exchange(true, "wrong");
+    match outcome {
+        ServerAction::Failure(line, reason) => {
+            assert!(!reason.is_temporary());
+            assert!(line.starts_with("A1 NO "));
+        }
+        other => panic!("expected failure, got {:?}", other),
+    }
+}
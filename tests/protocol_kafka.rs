@@ -0,0 +1,169 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{AuthId, Password};
+use rsasl::protocols::kafka::{
+    decode_token, encode_token, Client, ClientStep, KafkaError, SaslAuthenticateRequest,
+    SaslAuthenticateResponse, SaslHandshakeRequest, SaslHandshakeResponse, Server, ServerStep,
+    DEFAULT_TOKEN_LIMIT, SASL_AUTHENTICATION_FAILED, UNSUPPORTED_SASL_MECHANISM,
+};
+use rsasl::session::SessionData;
+use rsasl::validate::{validations, Validation};
+use rsasl::SASL;
+
+use std::sync::Arc;
+
+struct CB;
+impl Callback for CB {
+    fn validate(
+        &self,
+        session: &mut SessionData,
+        validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        match validation {
+            validations::SIMPLE => {
+                let password = session
+                    .get_property::<Password>()
+                    .ok_or_else(SessionError::no_property::<Password>)?;
+                if password.as_str() == "secret" {
+                    Ok(())
+                } else {
+                    Err(SessionError::AuthenticationFailure)
+                }
+            }
+            _ => Err(SessionError::NoValidate { validation }),
+        }
+    }
+}
+
+fn sasl() -> SASL {
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(CB));
+    sasl
+}
+
+fn enabled() -> Vec<&'static Mechname> {
+    vec![
+        Mechname::new(b"SCRAM-SHA-256").unwrap(),
+        Mechname::new(b"PLAIN").unwrap(),
+    ]
+}
+
+fn client(sasl: &SASL, mechanism: &[u8]) -> Client {
+    let mut session = sasl
+        .client_start(Mechname::new(mechanism).unwrap())
+        .unwrap();
+    session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    session.set_property::<Password>(Arc::new("secret".to_string()));
+    Client::new(session)
+}
+
+/// Run a SaslAuthenticate v1 exchange, passing every body through its encoded form
+fn exchange(mechanism: &[u8], password: &str) -> Result<i64, KafkaError> {
+    let sasl = sasl();
+    let mut client = client(&sasl, mechanism);
+
+    let handshake = SaslHandshakeRequest::decode(&client.handshake().encode()).unwrap();
+    let (response, server) = Server::handshake(&sasl, enabled(), &handshake);
+    let mut server = server.unwrap();
+    if mechanism != b"PLAIN" {
+        server
+            .session_mut()
+            .set_property::<AuthId>(Arc::new("testuser".to_string()));
+        server
+            .session_mut()
+            .set_property::<Password>(Arc::new(password.to_string()));
+    }
+    server.set_session_lifetime_ms(3_600_000);
+
+    let response = SaslHandshakeResponse::decode(&response.encode()).unwrap();
+    let mut request = client.handle_handshake(&response)?;
+    loop {
+        let request_body = SaslAuthenticateRequest::decode(&request.encode()).unwrap();
+        let response = match server.handle_authenticate(&request_body) {
+            ServerStep::Continue(response) | ServerStep::Authenticated(response) => response,
+            ServerStep::Failure { response, .. } => response,
+        };
+        let response = SaslAuthenticateResponse::decode(&response.encode(1).unwrap(), 1).unwrap();
+        match client.handle_authenticate(&response)? {
+            ClientStep::Send(next) => request = next,
+            ClientStep::Authenticated {
+                session_lifetime_ms,
+            } => return Ok(session_lifetime_ms),
+        }
+    }
+}
+
+#[test]
+fn scram_success_with_session_lifetime() {
+    assert_eq!(exchange(b"SCRAM-SHA-256", "secret").unwrap(), 3_600_000);
+}
+
+#[test]
+fn plain_success() {
+    assert_eq!(exchange(b"PLAIN", "secret").unwrap(), 3_600_000);
+}
+
+#[test]
+fn scram_bad_password() {
+    match exchange(b"SCRAM-SHA-256", "wrong") {
+        Err(KafkaError::Rejected { error_code, .. }) => {
+            assert_eq!(error_code, SASL_AUTHENTICATION_FAILED)
+        }
+        other => panic!("expected rejection, got {:?}", other),
+    }
+}
+
+#[test]
+fn unsupported_mechanism() {
+    let sasl = sasl();
+    let mut client = client(&sasl, b"LOGIN");
+    let (response, server) = Server::handshake(&sasl, enabled(), &client.handshake());
+    assert!(server.is_none());
+    assert_eq!(response.error_code, UNSUPPORTED_SASL_MECHANISM);
+    assert_eq!(response.mechanisms, vec!["SCRAM-SHA-256", "PLAIN"]);
+    assert!(matches!(
+        client.handle_handshake(&response),
+        Err(KafkaError::Rejected {
+            error_code: UNSUPPORTED_SASL_MECHANISM,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn legacy_plain() {
+    // After a version 0 handshake tokens are only prefixed by their length
+    let sasl = sasl();
+    let mut client = client(&sasl, b"PLAIN");
+    let request = SaslHandshakeRequest {
+        mechanism: "PLAIN".to_string(),
+    };
+    let mut server = Server::handshake(&sasl, enabled(), &request).1.unwrap();
+
+    let wire = encode_token(&client.initial_request().unwrap().auth_bytes);
+    let (token, len) = decode_token(&wire, DEFAULT_TOKEN_LIMIT).unwrap().unwrap();
+    assert_eq!(len, wire.len());
+    let response = match server.handle_authenticate(&SaslAuthenticateRequest {
+        auth_bytes: token.to_vec(),
+    }) {
+        ServerStep::Authenticated(response) => response,
+        other => panic!("expected success, got {:?}", other),
+    };
+    let wire = encode_token(&response.auth_bytes);
+    let (token, _) = decode_token(&wire, DEFAULT_TOKEN_LIMIT).unwrap().unwrap();
+    assert_eq!(
+        client
+            .handle_authenticate(&SaslAuthenticateResponse {
+                error_code: 0,
+                error_message: None,
+                auth_bytes: token.to_vec(),
+                session_lifetime_ms: 0,
+            })
+            .unwrap(),
+        ClientStep::Authenticated {
+            session_lifetime_ms: 0
+        }
+    );
+}
@@ -0,0 +1,186 @@
+use rsasl::mechname::Mechname;
+use rsasl::property::{AuthId, Password};
+use rsasl::protocols::ldap::{
+    BindRequest, Client, ClientStep, LdapError, ResultCode, Server, ServerStep,
+};
+use rsasl::SASL;
+
+use std::sync::Arc;
+
+fn client(sasl: &SASL) -> Client {
+    let mut session = sasl
+        .client_start(Mechname::new(b"SCRAM-SHA-256").unwrap())
+        .unwrap();
+    session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    session.set_property::<Password>(Arc::new("secret".to_string()));
+    Client::new(session)
+}
+
+/// Run a SCRAM-SHA-256 bind, returning the number of bind requests
+fn bind(server_password: &str) -> (Client, Server, Result<usize, LdapError>) {
+    let sasl = SASL::new();
+    let mut client = client(&sasl);
+    let mut request = client.bind_request().unwrap();
+    let mut server = Server::start(&sasl, &request).unwrap();
+    server
+        .session_mut()
+        .set_property::<AuthId>(Arc::new("testuser".to_string()));
+    server
+        .session_mut()
+        .set_property::<Password>(Arc::new(server_password.to_string()));
+
+    let mut requests = 1;
+    loop {
+        let response = match server.handle_bind_request(&request) {
+            ServerStep::InProgress(response) => {
+                assert_eq!(response.result_code, ResultCode::SASL_BIND_IN_PROGRESS);
+                response
+            }
+            ServerStep::Bound(response) => {
+                // The server-final message is sent along with the success
+                assert!(response.server_sasl_creds.is_some());
+                response
+            }
+            ServerStep::Failure { response, .. } => response,
+            ServerStep::Aborted => panic!("bind aborted"),
+        };
+        match client.handle_bind_response(&response) {
+            Ok(ClientStep::Bind(next)) => {
+                requests += 1;
+                request = next;
+            }
+            Ok(ClientStep::Bound) => return (client, server, Ok(requests)),
+            Err(error) => return (client, server, Err(error)),
+        }
+    }
+}
+
+#[test]
+fn scram_bind() {
+    let (_, _, result) = bind("secret");
+    assert_eq!(result.unwrap(), 2);
+}
+
+#[test]
+fn scram_invalid_credentials() {
+    let (_, _, result) = bind("wrong");
+    match result {
+        Err(LdapError::Rejected { result_code, .. }) => {
+            assert_eq!(result_code, ResultCode::INVALID_CREDENTIALS)
+        }
+        other => panic!("expected rejection, got {:?}", other),
+    }
+}
+
+#[test]
+fn scram_has_no_security_layer() {
+    let (client, server, result) = bind("secret");
+    result.unwrap();
+    assert!(!client.has_security_layer());
+    assert!(client.into_security_layer().is_none());
+    assert!(!server.has_security_layer());
+    assert!(server.into_security_layer().is_none());
+}
+
+#[cfg(feature = "srp")]
+#[test]
+fn srp_security_layer() {
+    use rsasl::callback::Callback;
+    use rsasl::error::SessionError;
+    use rsasl::property::{
+        properties, SrpDigest, SrpGroup, SrpLayer, SrpSecret, SrpSecurityLayer, SrpVerifier,
+    };
+    use rsasl::session::SessionData;
+    use rsasl::Property;
+
+    struct CB(SrpVerifier);
+    impl Callback for CB {
+        fn provide_prop(
+            &self,
+            session: &mut SessionData,
+            property: Property,
+        ) -> Result<(), SessionError> {
+            match property {
+                properties::SRP_SECRET => {
+                    session.set_property::<SrpSecret>(Arc::new(self.0.clone()));
+                    Ok(())
+                }
+                _ => Err(SessionError::NoCallback { property }),
+            }
+        }
+    }
+
+    let mechanism = Mechname::new(b"SRP").unwrap();
+    let mut session = SASL::new().client_start(mechanism).unwrap();
+    session.set_property::<AuthId>(Arc::new("alice".to_string()));
+    session.set_property::<Password>(Arc::new("password123".to_string()));
+    session.set_property::<SrpSecurityLayer>(Arc::new(SrpLayer::Confidentiality));
+    let mut client = Client::new(session);
+
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(CB(SrpVerifier::new(
+        "alice",
+        "password123",
+        SrpGroup::Bits2048,
+        SrpDigest::Sha256,
+    ))));
+    let mut request = client.bind_request().unwrap();
+    let mut server = Server::start(&sasl, &request).unwrap();
+    loop {
+        let response = match server.handle_bind_request(&request) {
+            ServerStep::InProgress(response) | ServerStep::Bound(response) => response,
+            other => panic!("unexpected server step {:?}", other),
+        };
+        match client.handle_bind_response(&response).unwrap() {
+            ClientStep::Bind(next) => request = next,
+            ClientStep::Bound => break,
+        }
+    }
+
+    assert!(client.has_security_layer());
+    assert!(server.has_security_layer());
+    let mut client = client.into_security_layer().unwrap();
+    let mut server = server.into_security_layer().unwrap();
+    let message = b"0\x05\x02\x01\x02B\x00";
+    let wrapped = client.wrap(message).unwrap();
+    // Feed the buffer in two parts, as it may be split across reads
+    server.push(&wrapped[..3]);
+    assert_eq!(server.unwrap_next().unwrap(), None);
+    server.push(&wrapped[3..]);
+    assert_eq!(server.unwrap_next().unwrap().as_deref(), Some(&message[..]));
+}
+
+#[test]
+fn unsupported_mechanism() {
+    let sasl = SASL::new();
+    let request = BindRequest {
+        name: String::new(),
+        mechanism: "X-UNKNOWN".to_string(),
+        credentials: None,
+    };
+    let response = Server::start(&sasl, &request).err().unwrap();
+    assert_eq!(response.result_code, ResultCode::AUTH_METHOD_NOT_SUPPORTED);
+}
+
+#[test]
+fn different_mechanism_aborts() {
+    let sasl = SASL::new();
+    let mut client = client(&sasl);
+    let request = client.bind_request().unwrap();
+    let mut server = Server::start(&sasl, &request).unwrap();
+    server
+        .session_mut()
+        .set_property::<Password>(Arc::new("secret".to_string()));
+    assert!(matches!(
+        server.handle_bind_request(&request),
+        ServerStep::InProgress(_)
+    ));
+    let request = BindRequest {
+        mechanism: "PLAIN".to_string(),
+        ..request
+    };
+    assert!(matches!(
+        server.handle_bind_request(&request),
+        ServerStep::Aborted
+    ));
+}
@@ -0,0 +1,111 @@
+use rsasl::mechname::Mechname;
+use rsasl::property::{AuthId, Password};
+use rsasl::protocols::mongodb::{
+    Client, ClientStep, CommandError, SaslContinue, SaslReply, Server, ServerStep,
+    AUTHENTICATION_FAILED,
+};
+use rsasl::protocols::ProtocolError;
+use rsasl::SASL;
+
+use std::sync::Arc;
+
+fn client(sasl: &SASL) -> Client {
+    let mut session = sasl
+        .client_start(Mechname::new(b"SCRAM-SHA-256").unwrap())
+        .unwrap();
+    session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    session.set_property::<Password>(Arc::new("secret".to_string()));
+    Client::new(session)
+}
+
+/// Run a SCRAM-SHA-256 conversation, returning the number of round trips
+fn conversation(skip_empty_exchange: bool, server_password: &str) -> Result<usize, CommandError> {
+    let sasl = SASL::new();
+    let mut client = client(&sasl);
+    let start = client.sasl_start(skip_empty_exchange).unwrap();
+
+    let mut server_session = sasl
+        .server_start(Mechname::new(start.mechanism.as_bytes()).unwrap())
+        .unwrap();
+    server_session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    server_session.set_property::<Password>(Arc::new(server_password.to_string()));
+    let mut server = Server::new(server_session, 1);
+
+    let mut round_trips = 1;
+    let mut step = server.handle_start(&start);
+    loop {
+        let reply = match step {
+            ServerStep::Continue(reply) => reply,
+            ServerStep::Authenticated(reply) => {
+                assert!(reply.done);
+                assert_eq!(
+                    client.handle_reply(&reply).unwrap(),
+                    ClientStep::Authenticated
+                );
+                return Ok(round_trips);
+            }
+            ServerStep::Failure { reply, .. } => return Err(reply),
+        };
+        match client.handle_reply(&reply).unwrap() {
+            ClientStep::Continue(command) => {
+                assert_eq!(command.conversation_id, 1);
+                round_trips += 1;
+                step = server.handle_continue(&command);
+            }
+            ClientStep::Authenticated => panic!("client done before the server"),
+        }
+    }
+}
+
+#[test]
+fn scram_with_empty_exchange() {
+    // saslStart, saslContinue with client-final and the empty saslContinue
+    assert_eq!(conversation(false, "secret").unwrap(), 3);
+}
+
+#[test]
+fn scram_skip_empty_exchange() {
+    assert_eq!(conversation(true, "secret").unwrap(), 2);
+}
+
+#[test]
+fn scram_bad_password() {
+    assert_eq!(
+        conversation(true, "wrong").unwrap_err().code,
+        AUTHENTICATION_FAILED
+    );
+}
+
+#[test]
+fn done_before_server_final_is_rejected() {
+    let sasl = SASL::new();
+    let mut client = client(&sasl);
+    client.sasl_start(true).unwrap();
+    let reply = SaslReply {
+        conversation_id: 1,
+        done: true,
+        payload: Vec::new(),
+    };
+    assert!(matches!(
+        client.handle_reply(&reply),
+        Err(ProtocolError::Session(_)) | Err(ProtocolError::PrematureSuccess)
+    ));
+}
+
+#[test]
+fn wrong_conversation_id() {
+    let sasl = SASL::new();
+    let mut server_session = sasl
+        .server_start(Mechname::new(b"SCRAM-SHA-256").unwrap())
+        .unwrap();
+    server_session.set_property::<Password>(Arc::new("secret".to_string()));
+    let mut server = Server::new(server_session, 1);
+    let command = SaslContinue {
+        conversation_id: 2,
+        payload: Vec::new(),
+    };
+    assert!(matches!(
+        server.handle_continue(&command),
+        ServerStep::Failure { error: None, .. }
+    ));
+}
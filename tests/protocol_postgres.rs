@@ -0,0 +1,108 @@
+use rsasl::mechname::Mechname;
+use rsasl::property::{AuthId, Password};
+use rsasl::protocols::postgres::{
+    AuthenticationOk, AuthenticationSasl, BackendMessage, Client, ErrorResponse,
+    SaslInitialResponse, SaslResponse, Server, ServerStep, INVALID_PASSWORD,
+};
+use rsasl::protocols::ProtocolError;
+use rsasl::SASL;
+
+use std::sync::Arc;
+
+fn decode_backend(buf: &[u8]) -> BackendMessage {
+    BackendMessage::decode(buf).unwrap()
+}
+
+/// Run a SCRAM-SHA-256 exchange, passing every message through its encoded form
+fn scram_exchange(server_password: &str) -> (usize, Result<(), ErrorResponse>) {
+    let sasl = SASL::new();
+    // Servers only offer the PLUS variant on TLS connections
+    let offered = [Mechname::new(b"SCRAM-SHA-256").unwrap()];
+    let advertisement =
+        match decode_backend(&AuthenticationSasl::new(offered.iter().copied()).encode()) {
+            BackendMessage::AuthenticationSasl(advertisement) => advertisement,
+            other => panic!("expected AuthenticationSASL, got {:?}", other),
+        };
+
+    let mut client_session = sasl
+        .client_start_suggested(advertisement.mechanisms())
+        .unwrap();
+    client_session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    client_session.set_property::<Password>(Arc::new("secret".to_string()));
+    let mut client = Client::new(client_session);
+
+    let initial =
+        SaslInitialResponse::decode(&client.initial_response().unwrap().encode()).unwrap();
+    assert_eq!(initial.mechanism, "SCRAM-SHA-256");
+    let mut server_session = sasl
+        .server_start(Mechname::new(initial.mechanism.as_bytes()).unwrap())
+        .unwrap();
+    server_session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    server_session.set_property::<Password>(Arc::new(server_password.to_string()));
+    let mut server = Server::new(server_session);
+
+    let mut messages = 1;
+    let mut step = server.handle_initial_response(&initial);
+    loop {
+        match step {
+            ServerStep::Continue(challenge) => {
+                let challenge = match decode_backend(&challenge.encode()) {
+                    BackendMessage::AuthenticationSaslContinue(challenge) => challenge,
+                    other => panic!("expected AuthenticationSASLContinue, got {:?}", other),
+                };
+                let response = client.handle_continue(&challenge).unwrap().encode();
+                let response = SaslResponse::decode(&response).unwrap();
+                messages += 2;
+                step = server.handle_response(&response);
+            }
+            ServerStep::Authenticated { final_message } => {
+                let final_message = match decode_backend(&final_message.unwrap().encode()) {
+                    BackendMessage::AuthenticationSaslFinal(message) => message,
+                    other => panic!("expected AuthenticationSASLFinal, got {:?}", other),
+                };
+                client.handle_final(&final_message).unwrap();
+                client.handle_ok(&AuthenticationOk).unwrap();
+                return (messages + 2, Ok(()));
+            }
+            ServerStep::Failure { response, .. } => {
+                return match decode_backend(&response.encode()) {
+                    BackendMessage::ErrorResponse(response) => (messages + 1, Err(response)),
+                    other => panic!("expected ErrorResponse, got {:?}", other),
+                };
+            }
+        }
+    }
+}
+
+#[test]
+fn scram_success_with_final_message() {
+    let (messages, outcome) = scram_exchange("secret");
+    outcome.unwrap();
+    // SASLInitialResponse, AuthenticationSASLContinue, SASLResponse, AuthenticationSASLFinal and
+    // AuthenticationOk
+    assert_eq!(messages, 5);
+}
+
+#[test]
+fn scram_bad_password() {
+    let (_, outcome) = scram_exchange("wrong");
+    let response = outcome.unwrap_err();
+    assert_eq!(response.severity, "FATAL");
+    assert_eq!(response.code, INVALID_PASSWORD);
+}
+
+#[test]
+fn ok_without_final_is_rejected() {
+    let sasl = SASL::new();
+    let mut session = sasl
+        .client_start(Mechname::new(b"SCRAM-SHA-256").unwrap())
+        .unwrap();
+    session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    session.set_property::<Password>(Arc::new("secret".to_string()));
+    let mut client = Client::new(session);
+    client.initial_response().unwrap();
+    assert!(matches!(
+        client.handle_ok(&AuthenticationOk),
+        Err(ProtocolError::PrematureSuccess)
+    ));
+}
@@ -0,0 +1,93 @@
+use rsasl::mechname::Mechname;
+use rsasl::property::{AuthId, Password};
+use rsasl::protocols::xmpp::{Client, Condition, Server, ServerStep, Stanza, Success};
+use rsasl::SASL;
+
+use std::sync::Arc;
+
+/// Run a SCRAM-SHA-256 exchange, passing every stanza through its serialized form
+fn scram_exchange(server_password: &str) -> (Vec<String>, Result<Success, Condition>) {
+    let sasl = SASL::new();
+    let mechanism = Mechname::new(b"SCRAM-SHA-256").unwrap();
+
+    let mut client_session = sasl.client_start(mechanism).unwrap();
+    client_session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    client_session.set_property::<Password>(Arc::new("secret".to_string()));
+    let mut client = Client::new(client_session);
+
+    let mut transcript = vec![client.authenticate().unwrap().to_string()];
+    let authenticate = match Stanza::parse(&transcript[0]).unwrap() {
+        Stanza::Authenticate(authenticate) => authenticate,
+        other => panic!("expected authenticate, got {:?}", other),
+    };
+    let mut server_session = sasl
+        .server_start(Mechname::new(authenticate.mechanism.as_bytes()).unwrap())
+        .unwrap();
+    server_session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    server_session.set_property::<Password>(Arc::new(server_password.to_string()));
+    let mut server = Server::new(server_session);
+
+    let mut step = server.handle_authenticate(&authenticate);
+    loop {
+        match step {
+            ServerStep::Challenge(challenge) => {
+                transcript.push(challenge.to_string());
+                let challenge = match Stanza::parse(transcript.last().unwrap()).unwrap() {
+                    Stanza::Challenge(challenge) => challenge,
+                    other => panic!("expected challenge, got {:?}", other),
+                };
+                transcript.push(client.handle_challenge(&challenge).unwrap().to_string());
+                let response = match Stanza::parse(transcript.last().unwrap()).unwrap() {
+                    Stanza::Response(response) => response,
+                    other => panic!("expected response, got {:?}", other),
+                };
+                step = server.handle_response(&response);
+            }
+            ServerStep::Authenticated { additional_data } => {
+                let success = Success::new(additional_data, "testuser@example.org".to_string());
+                transcript.push(success.to_string());
+                client.handle_success(&success).unwrap();
+                return (transcript, Ok(success));
+            }
+            ServerStep::Failure { failure, .. } => {
+                transcript.push(failure.to_string());
+                return (transcript, Err(failure.condition));
+            }
+        }
+    }
+}
+
+#[test]
+fn scram_success_carries_additional_data() {
+    let (transcript, outcome) = scram_exchange("secret");
+    let success = outcome.unwrap();
+    assert!(success.additional_data.unwrap().starts_with(b"v="));
+    // Authenticate with initial response, server-first, client-final and the success with the
+    // server-final message as additional data instead of a separate challenge
+    assert_eq!(transcript.len(), 4);
+    assert!(transcript[0].contains("<initial-response>"));
+}
+
+#[test]
+fn scram_bad_password() {
+    let (transcript, outcome) = scram_exchange("wrong");
+    assert_eq!(outcome, Err(Condition::NotAuthorized));
+    assert!(transcript
+        .last()
+        .unwrap()
+        .contains("<not-authorized xmlns='urn:ietf:params:xml:ns:xmpp-sasl'/>"));
+}
+
+#[test]
+fn forged_success_is_rejected() {
+    let sasl = SASL::new();
+    let mut session = sasl
+        .client_start(Mechname::new(b"SCRAM-SHA-256").unwrap())
+        .unwrap();
+    session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    session.set_property::<Password>(Arc::new("secret".to_string()));
+    let mut client = Client::new(session);
+    client.authenticate().unwrap();
+    let success = Success::new(None, "testuser@example.org".to_string());
+    assert!(client.handle_success(&success).is_err());
+}
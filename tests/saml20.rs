@@ -0,0 +1,149 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{properties, AuthzId, SAML20IDPIdentifier, SAML20RedirectUrl};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::validate::validations::SAML20;
+use rsasl::validate::Validation;
+use rsasl::{Property, SASL};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const REDIRECT: &str = "https://idp.example.org/sso?SAMLRequest=fVJNT8MwDL0j8R+q3Lu2";
+
+/// Redirects users of `example.org` to their IdP and accepts every assertion
+struct Server;
+impl Callback for Server {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::SAML20_REDIRECT_URL
+                if session
+                    .get_property::<SAML20IDPIdentifier>()
+                    .as_deref()
+                    .map(String::as_str)
+                    == Some("example.org") =>
+            {
+                session.set_property::<SAML20RedirectUrl>(Arc::new(REDIRECT.to_string()));
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+
+    fn validate(
+        &self,
+        _session: &mut SessionData,
+        validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        assert_eq!(validation, SAML20);
+        Ok(())
+    }
+}
+
+/// Counts how often the user was sent to their browser
+struct Browser(AtomicUsize);
+impl Callback for Browser {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::SAML20_AUTHENTICATE_IN_BROWSER => {
+                let url = session.get_property::<SAML20RedirectUrl>().unwrap();
+                assert_eq!(url.as_str(), REDIRECT);
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+}
+
+fn sessions(browser: &Arc<Browser>, idp: Option<&str>) -> (Session, Session) {
+    let mechanism = Mechname::new(b"SAML20").unwrap();
+    let mut client_sasl = SASL::new();
+    client_sasl.install_callback(browser.clone());
+    let mut client = client_sasl.client_start(mechanism).unwrap();
+    if let Some(idp) = idp {
+        client.set_property::<SAML20IDPIdentifier>(Arc::new(idp.to_string()));
+    }
+
+    let mut server_sasl = SASL::new();
+    server_sasl.install_callback(Arc::new(Server));
+    let server = server_sasl.server_start(mechanism).unwrap();
+    (client, server)
+}
+
+#[test]
+fn exchange() {
+    let browser = Arc::new(Browser(AtomicUsize::new(0)));
+    let (mut client, mut server) = sessions(&browser, Some("example.org"));
+    client.set_property::<AuthzId>(Arc::new("admin,ops=1".to_string()));
+
+    let initial = match client.step_outcome(None).unwrap() {
+        Outcome::Continue(Some(initial)) => initial,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    assert_eq!(initial, b"n,a=admin=2Cops=3D1,example.org");
+    let redirect = match server.step_outcome(Some(&initial)).unwrap() {
+        Outcome::Continue(Some(redirect)) => redirect,
+        other => panic!("unexpected server outcome {:?}", other),
+    };
+    assert_eq!(redirect, REDIRECT.as_bytes());
+    assert_eq!(
+        client.step_outcome(Some(&redirect)).unwrap(),
+        Outcome::Final(Some(b"=".to_vec()))
+    );
+    assert_eq!(browser.0.load(Ordering::Relaxed), 1);
+    assert_eq!(
+        server.step_outcome(Some(b"=")).unwrap(),
+        Outcome::Success(None)
+    );
+    assert_eq!(
+        server
+            .get_property::<AuthzId>()
+            .as_deref()
+            .map(String::as_str),
+        Some("admin,ops=1")
+    );
+}
+
+#[test]
+fn missing_idp_identifier() {
+    let browser = Arc::new(Browser(AtomicUsize::new(0)));
+    let (mut client, _) = sessions(&browser, None);
+    assert!(matches!(
+        client.step_outcome(None),
+        Err(SessionError::NoProperty {
+            property: properties::SAML20_IDP_IDENTIFIER
+        })
+    ));
+}
+
+#[test]
+fn unknown_idp() {
+    let browser = Arc::new(Browser(AtomicUsize::new(0)));
+    let (_, mut server) = sessions(&browser, None);
+    assert!(server.step_outcome(Some(b"n,,example.com")).is_err());
+}
+
+#[test]
+fn malformed_messages() {
+    let browser = Arc::new(Browser(AtomicUsize::new(0)));
+    let (_, mut server) = sessions(&browser, None);
+    assert!(server
+        .step_outcome(Some(b"p=tls-unique,,example.org"))
+        .is_err());
+    assert!(server.step_outcome(Some(b"n,,")).is_err());
+
+    let (_, mut server) = sessions(&browser, None);
+    server.step_outcome(Some(b"n,,example.org")).unwrap();
+    assert!(server.step_outcome(Some(b"done")).is_err());
+}
@@ -0,0 +1,140 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{properties, Advertised, AdvertisedMechanisms, AuthId, Password, Property};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::SASL;
+
+use std::sync::Arc;
+
+const ADVERTISED: [&str; 3] = ["PLAIN", "SCRAM-SHA-1", "SCRAM-SHA-256"];
+
+/// Start a client the way a protocol implementation would, from the advertisement it received
+fn client(mechanisms: &[&str], channel_bindings: &[&str]) -> Session {
+    let sasl = SASL::new();
+    let mut session = sasl
+        .client_start_advertised(
+            mechanisms
+                .iter()
+                .map(|name| Mechname::new(name.as_bytes()).unwrap()),
+            channel_bindings.iter().copied(),
+        )
+        .unwrap();
+    session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    session.set_property::<Password>(Arc::new("secret".to_string()));
+    session
+}
+
+fn server(mechanism: &Mechname, advertised: Option<Advertised>) -> Session {
+    let sasl = SASL::new();
+    let mut session = sasl.server_start(mechanism).unwrap();
+    session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    session.set_property::<Password>(Arc::new("secret".to_string()));
+    if let Some(advertised) = advertised {
+        session.set_property::<AdvertisedMechanisms>(Arc::new(advertised));
+    }
+    session
+}
+
+/// Run the exchange, returning which side failed if any
+fn exchange(mut client: Session, advertised: Option<Advertised>) -> Result<(), &'static str> {
+    let mut server = server(client.get_mechname(), advertised);
+    let mut data = match client.step_outcome(None).unwrap() {
+        Outcome::Continue(data) => data,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    loop {
+        match server.step_outcome(data.as_deref()) {
+            Ok(Outcome::Continue(challenge)) => match client.step_outcome(challenge.as_deref()) {
+                Ok(Outcome::Continue(response)) | Ok(Outcome::Final(response)) => data = response,
+                Ok(Outcome::Success(_)) => unreachable!(),
+                Err(_) => return Err("client"),
+            },
+            Ok(Outcome::Success(additional_data)) => {
+                return client
+                    .step_outcome(additional_data.as_deref())
+                    .map(|_| ())
+                    .map_err(|_| "client");
+            }
+            Ok(Outcome::Final(_)) => unreachable!(),
+            Err(_) => return Err("server"),
+        }
+    }
+}
+
+#[test]
+fn matching_advertisement() {
+    let client = client(&ADVERTISED, &["tls-exporter"]);
+    assert_eq!(client.get_mechname().as_str(), "SCRAM-SHA-256");
+    let advertised = Advertised::new(ADVERTISED, ["tls-exporter"]);
+    assert_eq!(exchange(client, Some(advertised)), Ok(()));
+}
+
+#[test]
+fn stripped_mechanism() {
+    // An attacker removing SCRAM-SHA-256 makes the client fall back to SCRAM-SHA-1
+    let client = client(&["PLAIN", "SCRAM-SHA-1"], &["tls-exporter"]);
+    assert_eq!(client.get_mechname().as_str(), "SCRAM-SHA-1");
+    let advertised = Advertised::new(ADVERTISED, ["tls-exporter"]);
+    assert_eq!(exchange(client, Some(advertised)), Err("client"));
+}
+
+#[test]
+fn stripped_channel_binding() {
+    let client = client(&ADVERTISED, &["tls-server-end-point"]);
+    let advertised = Advertised::new(ADVERTISED, ["tls-exporter", "tls-server-end-point"]);
+    assert_eq!(exchange(client, Some(advertised)), Err("client"));
+}
+
+#[test]
+fn server_without_downgrade_protection() {
+    // The attribute may have been stripped as well
+    let client = client(&ADVERTISED, &["tls-exporter"]);
+    assert_eq!(exchange(client, None), Err("client"));
+}
+
+/// Answers the password and, like most callbacks, `NoProperty` for everything else
+struct PasswordCallback;
+impl Callback for PasswordCallback {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::PASSWORD => {
+                session.set_property::<Password>(Arc::new("secret".to_string()));
+                Ok(())
+            }
+            _ => Err(SessionError::NoProperty { property }),
+        }
+    }
+}
+
+#[test]
+fn callback_without_advertisement() {
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(PasswordCallback));
+    let mechanism = Mechname::new(b"SCRAM-SHA-256").unwrap();
+
+    let mut client = sasl.client_start(mechanism).unwrap();
+    client.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    let mut server = sasl.server_start(mechanism).unwrap();
+    server.set_property::<AuthId>(Arc::new("testuser".to_string()));
+
+    let mut data = match client.step_outcome(None).unwrap() {
+        Outcome::Continue(data) => data,
+        other => panic!("unexpected client outcome {:?}", other),
+    };
+    let additional_data = loop {
+        match server.step_outcome(data.as_deref()).unwrap() {
+            Outcome::Continue(challenge) => match client.step_outcome(challenge.as_deref()) {
+                Ok(Outcome::Continue(response)) | Ok(Outcome::Final(response)) => data = response,
+                other => panic!("unexpected client outcome {:?}", other),
+            },
+            Outcome::Success(additional_data) => break additional_data,
+            Outcome::Final(_) => unreachable!(),
+        }
+    };
+    client.step_outcome(additional_data.as_deref()).unwrap();
+}
@@ -0,0 +1,178 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{
+    properties, AuthId, Passcode, Pin, SecuridOutcome, SecuridValidation, SuggestedPin,
+};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::validate::validations::SECURID;
+use rsasl::validate::Validation;
+use rsasl::{Property, SASL};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A token that is in next token mode and requires a new PIN to be set
+struct Validator;
+impl Callback for Validator {
+    fn validate(
+        &self,
+        session: &mut SessionData,
+        validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        assert_eq!(validation, SECURID);
+        if session
+            .get_property::<AuthId>()
+            .as_deref()
+            .map(String::as_str)
+            != Some("testuser")
+        {
+            return Err(SessionError::AuthenticationFailure);
+        }
+        let passcode = session.get_property::<Passcode>();
+        let pin = session.get_property::<Pin>();
+        let outcome = match (passcode.as_deref().map(String::as_str), pin.as_deref()) {
+            (Some("111111"), _) => SecuridOutcome::NextTokenCode,
+            (Some("222222"), None) => SecuridOutcome::NewPin(Some("1234".to_string())),
+            (Some("222222"), Some(pin)) if pin == "1234" => SecuridOutcome::Accept,
+            _ => SecuridOutcome::Reject,
+        };
+        session.set_property::<SecuridValidation>(Arc::new(outcome));
+        Ok(())
+    }
+}
+
+/// Hands out consecutive token codes and accepts the suggested PIN
+struct Token(AtomicUsize);
+impl Callback for Token {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::PASSCODE => {
+                let codes = ["111111", "222222"];
+                let code = codes[self.0.fetch_add(1, Ordering::Relaxed).min(1)];
+                session.set_property::<Passcode>(Arc::new(code.to_string()));
+                Ok(())
+            }
+            properties::PIN => {
+                let suggested = session
+                    .get_property::<SuggestedPin>()
+                    .expect("no PIN suggested");
+                session.set_property::<Pin>(suggested);
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+}
+
+fn sessions(authid: &str) -> (Session, Session) {
+    let mechanism = Mechname::new(b"SECURID").unwrap();
+    let mut client_sasl = SASL::new();
+    client_sasl.install_callback(Arc::new(Token(AtomicUsize::new(0))));
+    let mut client = client_sasl.client_start(mechanism).unwrap();
+    client.set_property::<AuthId>(Arc::new(authid.to_string()));
+
+    let mut server_sasl = SASL::new();
+    server_sasl.install_callback(Arc::new(Validator));
+    let server = server_sasl.server_start(mechanism).unwrap();
+    (client, server)
+}
+
+/// Run the exchange, returning the challenges the server sent
+fn exchange(client: &mut Session, server: &mut Session) -> Result<Vec<Vec<u8>>, SessionError> {
+    let mut challenges = Vec::new();
+    let mut input = None;
+    loop {
+        let response = match client.step_outcome(input.as_deref())? {
+            Outcome::Final(Some(response)) => response,
+            other => panic!("unexpected client outcome {:?}", other),
+        };
+        match server.step_outcome(Some(&response))? {
+            Outcome::Continue(Some(challenge)) => {
+                challenges.push(challenge.clone());
+                input = Some(challenge);
+            }
+            Outcome::Success(None) => return Ok(challenges),
+            other => panic!("unexpected server outcome {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn next_token_code_and_new_pin() {
+    let (mut client, mut server) = sessions("testuser");
+    let challenges = exchange(&mut client, &mut server).unwrap();
+    assert_eq!(challenges, vec![b"passcode".to_vec(), b"pin1234".to_vec()]);
+    assert_eq!(
+        server.get_property::<Pin>().as_deref().map(String::as_str),
+        Some("1234")
+    );
+}
+
+#[test]
+fn rejected() {
+    let (mut client, mut server) = sessions("someone");
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn rejected_by_outcome() {
+    let (mut client, mut server) = sessions("testuser");
+    client.set_property::<Passcode>(Arc::new("333333".to_string()));
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn malformed_messages() {
+    let (mut client, mut server) = sessions("testuser");
+    assert!(server.step_outcome(Some(b"\0testuser")).is_err());
+    assert!(server
+        .step_outcome(Some(b"\x00testuser\x00111111\x00\x00\x00"))
+        .is_err());
+    client.step_outcome(None).unwrap();
+    assert!(client.step_outcome(Some(b"unknown")).is_err());
+}
+
+#[test]
+fn no_outcome_is_rejected() {
+    /// Handles the validation without setting an outcome
+    struct Silent;
+    impl Callback for Silent {
+        fn validate(
+            &self,
+            _session: &mut SessionData,
+            _validation: Validation,
+            _mechanism: &Mechname,
+        ) -> Result<(), SessionError> {
+            Ok(())
+        }
+    }
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(Silent));
+    let mut server = sasl
+        .server_start(Mechname::new(b"SECURID").unwrap())
+        .unwrap();
+    assert!(matches!(
+        server.step_outcome(Some(b"\0testuser\x00222222\0")),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+#[test]
+fn empty_challenge_is_rejected() {
+    let (mut client, _) = sessions("testuser");
+    client.step_outcome(None).unwrap();
+    assert!(client.step_outcome(Some(b"")).is_err());
+    assert!(client.step_outcome(None).is_err());
+}
@@ -0,0 +1,200 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{properties, AuthId, Password};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::state::{StateError, StateKey};
+use rsasl::validate::{validations, Validation};
+use rsasl::{Property, SASL};
+
+use std::sync::Arc;
+
+const SECRET: &[u8] = b"a secret shared by all servers!!";
+
+/// Provides the password for `testuser` and validates it for simple mechanisms
+struct CB;
+impl Callback for CB {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::PASSWORD
+                if session
+                    .get_property::<AuthId>()
+                    .as_deref()
+                    .map(String::as_str)
+                    == Some("testuser") =>
+            {
+                session.set_property::<Password>(Arc::new("secret".to_string()));
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+
+    fn validate(
+        &self,
+        session: &mut SessionData,
+        validation: Validation,
+        _mechanism: &Mechname,
+    ) -> Result<(), SessionError> {
+        if validation != validations::SIMPLE {
+            return Err(SessionError::no_validate(validation));
+        }
+        let authid = session.get_property::<AuthId>();
+        let password = session.get_property::<Password>();
+        match (authid.as_deref(), password.as_deref()) {
+            (Some(authid), Some(password)) if authid == "testuser" && password == "secret" => {
+                Ok(())
+            }
+            _ => Err(SessionError::AuthenticationFailure),
+        }
+    }
+}
+
+/// A provider as a separate server process would have it
+fn server_sasl() -> SASL {
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(CB));
+    sasl
+}
+
+fn client(sasl: &SASL, mechanism: &[u8]) -> Session {
+    let mut session = sasl
+        .client_start(Mechname::new(mechanism).unwrap())
+        .unwrap();
+    session.set_property::<AuthId>(Arc::new("testuser".to_string()));
+    session.set_property::<Password>(Arc::new("secret".to_string()));
+    session
+}
+
+/// Run the exchange, exporting the server session after every step and resuming it in a fresh
+/// provider for the next one
+fn exchange(mechanism: &[u8]) -> Result<(), SessionError> {
+    let key = StateKey::new(SECRET);
+    let mut client = client(&SASL::new(), mechanism);
+    let mut server = server_sasl()
+        .server_start(Mechname::new(mechanism).unwrap())
+        .unwrap();
+
+    let mut to_server = if client.are_we_first() {
+        match client.step_outcome(None)? {
+            Outcome::Continue(data) => data,
+            outcome => panic!("unexpected client outcome {:?}", outcome),
+        }
+    } else {
+        None
+    };
+    loop {
+        match server.step_outcome(to_server.as_deref())? {
+            Outcome::Continue(data) => {
+                let state = server.export_state(&key).unwrap();
+                server = server_sasl().resume_session(&key, &state).unwrap();
+                match client.step_outcome(data.as_deref())? {
+                    Outcome::Continue(data) | Outcome::Final(data) => to_server = data,
+                    outcome => panic!("unexpected client outcome {:?}", outcome),
+                }
+            }
+            Outcome::Success(data) => {
+                if let Some(data) = data {
+                    client.step_outcome(Some(&data))?;
+                }
+                return Ok(());
+            }
+            Outcome::Final(_) => panic!("server returned a client outcome"),
+        }
+    }
+}
+
+#[test]
+fn scram_resumed() {
+    exchange(b"SCRAM-SHA-256").unwrap();
+}
+
+#[test]
+fn cram_md5_resumed() {
+    exchange(b"CRAM-MD5").unwrap();
+}
+
+#[test]
+fn login_resumed() {
+    exchange(b"LOGIN").unwrap();
+}
+
+/// Start a SCRAM exchange and export the server state after the server-first message
+fn scram_state(key: &StateKey) -> Vec<u8> {
+    let mut client = client(&SASL::new(), b"SCRAM-SHA-256");
+    let mut server = server_sasl()
+        .server_start(Mechname::new(b"SCRAM-SHA-256").unwrap())
+        .unwrap();
+    let client_first = match client.step_outcome(None).unwrap() {
+        Outcome::Continue(data) => data,
+        outcome => panic!("unexpected client outcome {:?}", outcome),
+    };
+    server.step_outcome(client_first.as_deref()).unwrap();
+    server.export_state(key).unwrap()
+}
+
+#[test]
+fn resumed_properties() {
+    let key = StateKey::new(SECRET);
+    let mut server = server_sasl()
+        .resume_session(&key, &scram_state(&key))
+        .unwrap();
+    assert_eq!(server.get_mechname().as_str(), "SCRAM-SHA-256");
+    assert_eq!(
+        server
+            .get_property::<AuthId>()
+            .as_deref()
+            .map(String::as_str),
+        Some("testuser")
+    );
+    // Secrets are never exported
+    assert!(server.get_property::<Password>().is_none());
+}
+
+#[test]
+fn tampered_state() {
+    let key = StateKey::new(SECRET);
+    let mut state = scram_state(&key);
+    let last = state.len() - 1;
+    state[last] ^= 1;
+    assert!(matches!(
+        server_sasl().resume_session(&key, &state),
+        Err(StateError::Invalid)
+    ));
+}
+
+#[test]
+fn wrong_key() {
+    let state = scram_state(&StateKey::new(SECRET));
+    assert!(matches!(
+        server_sasl().resume_session(&StateKey::new(b"some other secret"), &state),
+        Err(StateError::Invalid)
+    ));
+}
+
+#[test]
+fn unsupported_mechanism() {
+    let session = server_sasl()
+        .server_start(Mechname::new(b"PLAIN").unwrap())
+        .unwrap();
+    assert!(matches!(
+        session.export_state(&StateKey::new(SECRET)),
+        Err(StateError::UnsupportedMechanism(_))
+    ));
+}
+
+#[test]
+fn channel_binding_is_not_exported() {
+    let mut session = server_sasl()
+        .server_start(Mechname::new(b"SCRAM-SHA-256-PLUS").unwrap())
+        .unwrap();
+    session.set_channel_binding_data("tls-exporter", Box::new([0; 32]));
+    assert!(matches!(
+        session.export_state(&StateKey::new(SECRET)),
+        Err(StateError::UnsupportedMechanism(_))
+    ));
+}
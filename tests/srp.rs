@@ -0,0 +1,226 @@
+use rsasl::callback::Callback;
+use rsasl::error::SessionError;
+use rsasl::mechname::Mechname;
+use rsasl::property::{
+    properties, AuthId, AuthzId, Password, SrpDigest, SrpGroup, SrpLayer, SrpSecret,
+    SrpSecurityLayer, SrpVerifier,
+};
+use rsasl::session::{Outcome, Session, SessionData};
+use rsasl::{Property, SASL};
+
+use std::sync::Arc;
+
+struct CB {
+    verifier: SrpVerifier,
+    required: Option<SrpLayer>,
+}
+impl Callback for CB {
+    fn provide_prop(
+        &self,
+        session: &mut SessionData,
+        property: Property,
+    ) -> Result<(), SessionError> {
+        match property {
+            properties::SRP_SECRET
+                if session
+                    .get_property::<AuthId>()
+                    .as_deref()
+                    .map(String::as_str)
+                    == Some("alice") =>
+            {
+                session.set_property::<SrpSecret>(Arc::new(self.verifier.clone()));
+                Ok(())
+            }
+            properties::SRP_SECURITY_LAYER if self.required.is_some() => {
+                session.set_property::<SrpSecurityLayer>(Arc::new(self.required.unwrap()));
+                Ok(())
+            }
+            _ => Err(SessionError::NoCallback { property }),
+        }
+    }
+}
+
+fn sessions(
+    verifier: SrpVerifier,
+    password: &str,
+    requested: Option<SrpLayer>,
+    required: Option<SrpLayer>,
+) -> (Session, Session) {
+    let mechanism = Mechname::new(b"SRP").unwrap();
+    let mut client = SASL::new().client_start(mechanism).unwrap();
+    client.set_property::<AuthId>(Arc::new("alice".to_string()));
+    client.set_property::<Password>(Arc::new(password.to_string()));
+    if let Some(layer) = requested {
+        client.set_property::<SrpSecurityLayer>(Arc::new(layer));
+    }
+
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(CB { verifier, required }));
+    let server = sasl.server_start(mechanism).unwrap();
+    (client, server)
+}
+
+fn exchange(client: &mut Session, server: &mut Session) -> Result<(), SessionError> {
+    let mut input = None;
+    for _ in 0..2 {
+        let output = match client.step_outcome(input.as_deref())? {
+            Outcome::Continue(Some(output)) => output,
+            other => panic!("unexpected client outcome {:?}", other),
+        };
+        input = match server.step_outcome(Some(&output))? {
+            Outcome::Continue(Some(output)) | Outcome::Success(Some(output)) => Some(output),
+            other => panic!("unexpected server outcome {:?}", other),
+        };
+    }
+    assert_eq!(
+        client.step_outcome(input.as_deref())?,
+        Outcome::Final(None)
+    );
+    Ok(())
+}
+
+fn verifier(group: SrpGroup, digest: SrpDigest) -> SrpVerifier {
+    SrpVerifier::new("alice", "password123", group, digest)
+}
+
+#[test]
+fn success() {
+    for &(group, digest) in &[
+        (SrpGroup::Bits1024, SrpDigest::Sha1),
+        (SrpGroup::Bits2048, SrpDigest::Sha256),
+        (SrpGroup::Bits3072, SrpDigest::Sha256),
+    ] {
+        let (mut client, mut server) = sessions(verifier(group, digest), "password123", None, None);
+        exchange(&mut client, &mut server).unwrap();
+        assert!(!client.has_security_layer());
+        assert!(!server.has_security_layer());
+        assert!(matches!(
+            client.encode(b"data"),
+            Err(SessionError::NoSecurityLayer)
+        ));
+    }
+}
+
+#[test]
+fn authzid() {
+    let (mut client, mut server) = sessions(
+        verifier(SrpGroup::Bits2048, SrpDigest::Sha256),
+        "password123",
+        None,
+        None,
+    );
+    client.set_property::<AuthzId>(Arc::new("bob".to_string()));
+    exchange(&mut client, &mut server).unwrap();
+    assert_eq!(
+        server
+            .get_property::<AuthzId>()
+            .as_deref()
+            .map(String::as_str),
+        Some("bob")
+    );
+}
+
+#[test]
+fn wrong_password() {
+    let (mut client, mut server) = sessions(
+        verifier(SrpGroup::Bits2048, SrpDigest::Sha256),
+        "password124",
+        None,
+        None,
+    );
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::AuthenticationFailure)
+    ));
+}
+
+fn round_trip(client: &mut Session, server: &mut Session) {
+    for message in &[&b"hello"[..], b"", &[7; 100]] {
+        let encoded = client.encode(message).unwrap();
+        assert_eq!(&server.decode(&encoded).unwrap()[..], *message);
+        let encoded = server.encode(message).unwrap();
+        assert_eq!(&client.decode(&encoded).unwrap()[..], *message);
+    }
+}
+
+#[test]
+fn integrity() {
+    let (mut client, mut server) = sessions(
+        verifier(SrpGroup::Bits2048, SrpDigest::Sha256),
+        "password123",
+        Some(SrpLayer::Integrity),
+        None,
+    );
+    exchange(&mut client, &mut server).unwrap();
+    assert!(client.has_security_layer());
+    assert!(server.has_security_layer());
+    round_trip(&mut client, &mut server);
+
+    let encoded = client.encode(b"hello").unwrap();
+    assert_eq!(&encoded[..5], b"hello");
+    let mut tampered = encoded.to_vec();
+    tampered[0] ^= 1;
+    assert!(server.decode(&tampered).unwrap_err().is_mechanism_error());
+
+    // Replaying a buffer fails as the sequence number differs
+    server.decode(&encoded).unwrap();
+    assert!(server.decode(&encoded).unwrap_err().is_mechanism_error());
+}
+
+#[test]
+fn confidentiality() {
+    let (mut client, mut server) = sessions(
+        verifier(SrpGroup::Bits2048, SrpDigest::Sha1),
+        "password123",
+        Some(SrpLayer::Confidentiality),
+        None,
+    );
+    exchange(&mut client, &mut server).unwrap();
+    round_trip(&mut client, &mut server);
+
+    let encoded = client.encode(b"secret message").unwrap();
+    assert!(!encoded
+        .windows(b"secret".len())
+        .any(|window| window == b"secret"));
+    assert_eq!(&server.decode(&encoded).unwrap()[..], b"secret message");
+}
+
+#[test]
+fn mandatory_layer() {
+    let (mut client, mut server) = sessions(
+        verifier(SrpGroup::Bits2048, SrpDigest::Sha256),
+        "password123",
+        None,
+        Some(SrpLayer::Confidentiality),
+    );
+    exchange(&mut client, &mut server).unwrap();
+    round_trip(&mut client, &mut server);
+}
+
+#[test]
+fn unknown_user() {
+    let mechanism = Mechname::new(b"SRP").unwrap();
+    let mut client = SASL::new().client_start(mechanism).unwrap();
+    client.set_property::<AuthId>(Arc::new("mallory".to_string()));
+    client.set_property::<Password>(Arc::new("password123".to_string()));
+    let mut sasl = SASL::new();
+    sasl.install_callback(Arc::new(CB {
+        verifier: verifier(SrpGroup::Bits1024, SrpDigest::Sha1),
+        required: None,
+    }));
+    let mut server = sasl.server_start(mechanism).unwrap();
+    assert!(matches!(
+        exchange(&mut client, &mut server),
+        Err(SessionError::NoProperty { .. })
+    ));
+}
+
+#[test]
+fn security_filter() {
+    let mut sasl = SASL::new();
+    sasl.set_filter(|mechanism| mechanism.security.mutual && mechanism.security.max_ssf > 0);
+
+    assert!(sasl.server_supports(Mechname::new(b"SRP").unwrap()));
+    assert!(!sasl.server_supports(Mechname::new(b"PLAIN").unwrap()));
+    assert!(!sasl.server_supports(Mechname::new(b"NTLM").unwrap()));
+}